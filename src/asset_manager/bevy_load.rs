@@ -0,0 +1,269 @@
+use crate::asset_manager::error::AssetError;
+use crate::asset_manager::{load, load_obj};
+use crate::game_objects::render_object::ObjectId;
+use crate::game_objects::scene::Scene;
+use crate::game_objects::transform::Transform;
+use crate::winit_app::winit_render_app::AppData;
+use bevy::tasks::{AsyncComputeTaskPool, Task, TaskPool, futures::check_ready};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use vulkanalia::{Device, Instance};
+
+/// the cpu side of an asset, read on a worker thread and uploaded on the main thread.
+#[derive(Debug)]
+pub enum AssetSource {
+    Gltf(Box<load::GltfSource>),
+    Obj(load_obj::ObjSource),
+}
+impl AssetSource {
+    /// parses the file and decodes its images, the loader is picked by the file extension.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, AssetError> {
+        let path = path.as_ref();
+        if path.extension().is_some_and(|ext| ext == "obj") {
+            load_obj::read_source(path).map(Self::Obj)
+        } else {
+            load::read(path).map(|source| Self::Gltf(Box::new(source)))
+        }
+    }
+    pub fn upload(
+        self,
+        instance: &Instance,
+        device: &Device,
+        data: &mut AppData,
+        scene: &mut Scene,
+    ) -> Result<Vec<ObjectId>, AssetError> {
+        match self {
+            Self::Gltf(source) => load::upload(instance, device, data, scene, *source),
+            Self::Obj(source) => load_obj::upload(instance, device, data, scene, source),
+        }
+    }
+    /// adds the primitives of the file to existing objects instead of creating new ones.
+    pub fn attach(
+        self,
+        instance: &Instance,
+        device: &Device,
+        data: &mut AppData,
+        scene: &mut Scene,
+        bindings: &[AssetBinding],
+    ) -> Result<Vec<ObjectId>, AssetError> {
+        match self {
+            Self::Gltf(source) => load::attach(instance, device, data, scene, *source, bindings),
+            Self::Obj(source) => load_obj::attach(instance, device, data, scene, source, bindings),
+        }
+    }
+    /// swaps the new resources into every object loaded from the same file.
+    pub fn reload(
+        self,
+        instance: &Instance,
+        device: &Device,
+        data: &mut AppData,
+        scene: &mut Scene,
+    ) -> Result<(), AssetError> {
+        match self {
+            Self::Gltf(source) => load::reload(instance, device, data, scene, *source),
+            Self::Obj(source) => load_obj::reload(instance, device, data, scene, source),
+        }
+    }
+}
+
+/// node `node` of a file bound to an existing object once the file is read. obj models are
+/// nodes with a single primitive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AssetBinding {
+    pub object: ObjectId,
+    pub node: usize,
+    /// a primitive of the node added to the render objects of `object`. `None` makes `object`
+    /// the node itself, which skins and animations of the file target.
+    pub primitive: Option<usize>,
+    /// objects made from the same node are told apart by their copy, every copy is tracked as
+    /// its own load of the file. the primitives are shared by every copy.
+    pub copy: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LoadId(pub usize);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoadState {
+    /// parsing and decoding on a worker thread.
+    Reading,
+    /// uploaded, the ids are the root objects of the file or the objects it was attached to.
+    Loaded(Vec<ObjectId>),
+    /// the full error was reported to the scene.
+    Failed(String),
+}
+
+/// an asset whose worker thread finished, waiting for the upload.
+#[derive(Debug)]
+pub struct ReadAsset {
+    pub id: LoadId,
+    pub path: PathBuf,
+    /// applied to every root object once uploaded.
+    pub transform: Option<Transform>,
+    /// replaces the resources of the objects already loaded from the file.
+    pub reload: bool,
+    /// adds the primitives to these objects instead of creating objects, see `attach`.
+    pub bindings: Vec<AssetBinding>,
+    pub source: Result<AssetSource, AssetError>,
+}
+
+#[derive(Debug)]
+struct PendingLoad {
+    id: LoadId,
+    path: PathBuf,
+    transform: Option<Transform>,
+    reload: bool,
+    bindings: Vec<AssetBinding>,
+    task: Task<Result<AssetSource, AssetError>>,
+}
+
+/// assets being read on the `AsyncComputeTaskPool`. reading is spread over the workers, the
+/// upload needs the device and happens in `upload_read_assets`.
+#[derive(Debug, Default)]
+pub struct AssetLoads {
+    next_id: usize,
+    pending: Vec<PendingLoad>,
+    states: HashMap<LoadId, LoadState>,
+    /// loads started since the queue was last empty, the total of the progress bar.
+    started: usize,
+}
+impl AssetLoads {
+    /// starts reading `path` on a worker thread.
+    pub fn load(&mut self, path: impl Into<PathBuf>, transform: Option<Transform>) -> LoadId {
+        self.spawn(path.into(), transform, false, vec![])
+    }
+    /// reads `path` again for the objects that were loaded from it, see `hot_reload`.
+    pub fn reload(&mut self, path: impl Into<PathBuf>) -> LoadId {
+        self.spawn(path.into(), None, true, vec![])
+    }
+    /// reads `path` and adds its primitives to the objects of `bindings`, used to restore
+    /// saved scenes whose objects already exist.
+    pub fn attach(&mut self, path: impl Into<PathBuf>, bindings: Vec<AssetBinding>) -> LoadId {
+        self.spawn(path.into(), None, false, bindings)
+    }
+    fn spawn(
+        &mut self,
+        path: PathBuf,
+        transform: Option<Transform>,
+        reload: bool,
+        bindings: Vec<AssetBinding>,
+    ) -> LoadId {
+        let id = LoadId(self.next_id);
+        self.next_id += 1;
+        // the task pool plugin creates the pool, the winit app and tests have none
+        let pool = AsyncComputeTaskPool::get_or_init(TaskPool::default);
+        let task = pool.spawn({
+            let path = path.clone();
+            async move { AssetSource::read(path) }
+        });
+        self.pending.push(PendingLoad {
+            id,
+            path,
+            transform,
+            reload,
+            bindings,
+            task,
+        });
+        self.states.insert(id, LoadState::Reading);
+        self.started += 1;
+        id
+    }
+    /// removes the loads whose worker is done, in the order they were started.
+    pub fn take_read(&mut self) -> Vec<ReadAsset> {
+        let mut read = vec![];
+        let mut pending = vec![];
+        for mut load in self.pending.drain(..) {
+            match check_ready(&mut load.task) {
+                Some(source) => {
+                    // nothing waits for a reload, its errors are reported to the scene
+                    if load.reload {
+                        self.states.remove(&load.id);
+                    }
+                    read.push(ReadAsset {
+                        id: load.id,
+                        path: load.path,
+                        transform: load.transform,
+                        reload: load.reload,
+                        bindings: load.bindings,
+                        source,
+                    })
+                }
+                None => pending.push(load),
+            }
+        }
+        self.pending = pending;
+        if self.pending.is_empty() {
+            self.started = 0;
+        }
+        read
+    }
+    /// records the outcome of the upload of a load returned by `take_read`.
+    pub fn finish(&mut self, id: LoadId, state: LoadState) {
+        if let Some(entry) = self.states.get_mut(&id) {
+            *entry = state;
+        }
+    }
+    pub fn state(&self, id: LoadId) -> Option<&LoadState> {
+        self.states.get(&id)
+    }
+    /// like `state`, but a load that is done is forgotten once its outcome was handed out.
+    pub fn take_state(&mut self, id: LoadId) -> Option<LoadState> {
+        match self.states.get(&id)? {
+            LoadState::Reading => Some(LoadState::Reading),
+            _ => self.states.remove(&id),
+        }
+    }
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_empty()
+    }
+    /// paths still being read.
+    pub fn pending(&self) -> impl Iterator<Item = &Path> {
+        self.pending.iter().map(|load| load.path.as_path())
+    }
+    /// fraction of the loads started since the queue was last empty that are done reading.
+    pub fn progress(&self) -> f32 {
+        if self.started == 0 {
+            return 1.0;
+        }
+        (self.started - self.pending.len()) as f32 / self.started as f32
+    }
+}
+
+/// uploads every asset whose worker finished, must run on the thread owning the device queue.
+/// failures are reported to the scene instead of aborting.
+pub fn upload_read_assets(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    scene: &mut Scene,
+) {
+    for read in scene.loads.take_read() {
+        let result = read.source.and_then(|source| {
+            if read.reload {
+                source
+                    .reload(instance, device, data, scene)
+                    .map(|()| vec![])
+            } else if !read.bindings.is_empty() {
+                source.attach(instance, device, data, scene, &read.bindings)
+            } else {
+                source.upload(instance, device, data, scene)
+            }
+        });
+        let state = match result {
+            Ok(roots) => {
+                if let Some(transform) = &read.transform {
+                    for root in &roots {
+                        let _ = scene.transform_object(*root, transform.clone());
+                    }
+                }
+                LoadState::Loaded(roots)
+            }
+            Err(error) => {
+                let message = error.to_string();
+                scene.report_asset_error(error);
+                LoadState::Failed(message)
+            }
+        };
+        scene.loads.finish(read.id, state);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::asset_manager::load::AccessorError;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// the file an error happened in, and the node and mesh that were being loaded if any.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AssetLocation {
+    pub path: PathBuf,
+    pub node: Option<String>,
+    pub mesh: Option<String>,
+}
+impl AssetLocation {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            node: None,
+            mesh: None,
+        }
+    }
+    pub fn node(&self, name: impl Into<String>) -> Self {
+        Self {
+            node: Some(name.into()),
+            ..self.clone()
+        }
+    }
+    pub fn mesh(&self, name: impl Into<String>) -> Self {
+        Self {
+            mesh: Some(name.into()),
+            ..self.clone()
+        }
+    }
+}
+impl fmt::Display for AssetLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(node) = &self.node {
+            write!(f, ", node \"{node}\"")?;
+        }
+        if let Some(mesh) = &self.mesh {
+            write!(f, ", mesh \"{mesh}\"")?;
+        }
+        Ok(())
+    }
+}
+
+/// the file was read but its content is malformed.
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error(transparent)]
+    Gltf(Box<gltf::Error>),
+    #[error(transparent)]
+    Obj(#[from] tobj::LoadError),
+    #[error(transparent)]
+    Png(#[from] png::DecodingError),
+    #[error(transparent)]
+    Image(Box<image::ImageError>),
+    #[error(transparent)]
+    Accessor(#[from] AccessorError),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+impl From<image::ImageError> for ParseError {
+    fn from(error: image::ImageError) -> Self {
+        Self::Image(Box::new(error))
+    }
+}
+
+/// why an asset could not be loaded, reported to the gui instead of aborting.
+#[derive(Debug, Error)]
+pub enum AssetError {
+    #[error("{location}: could not read file: {source}")]
+    Io {
+        location: AssetLocation,
+        source: std::io::Error,
+    },
+    #[error("{location}: could not parse: {source}")]
+    Parse {
+        location: AssetLocation,
+        source: ParseError,
+    },
+    #[error("{location}: unsupported format: {what}")]
+    Unsupported {
+        location: AssetLocation,
+        what: String,
+    },
+    #[error("{location}: gpu upload failed: {source}")]
+    Upload {
+        location: AssetLocation,
+        source: anyhow::Error,
+    },
+}
+impl AssetError {
+    pub fn io(location: &AssetLocation, source: std::io::Error) -> Self {
+        Self::Io {
+            location: location.clone(),
+            source,
+        }
+    }
+    pub fn parse(location: &AssetLocation, source: impl Into<ParseError>) -> Self {
+        Self::Parse {
+            location: location.clone(),
+            source: source.into(),
+        }
+    }
+    pub fn invalid(location: &AssetLocation, message: impl Into<String>) -> Self {
+        Self::parse(location, ParseError::Invalid(message.into()))
+    }
+    pub fn unsupported(location: &AssetLocation, what: impl Into<String>) -> Self {
+        Self::Unsupported {
+            location: location.clone(),
+            what: what.into(),
+        }
+    }
+    pub fn upload(location: &AssetLocation, source: impl Into<anyhow::Error>) -> Self {
+        Self::Upload {
+            location: location.clone(),
+            source: source.into(),
+        }
+    }
+
+    /// io errors from inside gltf are reported as such, everything else is a parse error.
+    pub fn gltf(location: &AssetLocation, error: gltf::Error) -> Self {
+        match error {
+            gltf::Error::Io(source) => Self::io(location, source),
+            gltf::Error::UnsupportedScheme
+            | gltf::Error::UnsupportedImageEncoding
+            | gltf::Error::UnsupportedImageFormat(_) => {
+                Self::unsupported(location, error.to_string())
+            }
+            error => Self::parse(location, ParseError::Gltf(Box::new(error))),
+        }
+    }
+    /// same as `gltf` for errors of the obj loader.
+    pub fn obj(location: &AssetLocation, error: tobj::LoadError) -> Self {
+        match error {
+            tobj::LoadError::OpenFileFailed | tobj::LoadError::ReadError => {
+                Self::io(location, std::io::Error::other(error))
+            }
+            error => Self::parse(location, error),
+        }
+    }
+    /// unsupported primitive modes are a format limitation, not a broken file.
+    pub fn accessor(location: &AssetLocation, error: AccessorError) -> Self {
+        match error {
+            AccessorError::Mode(_) => Self::unsupported(location, error.to_string()),
+            error => Self::parse(location, error),
+        }
+    }
+
+    pub fn location(&self) -> &AssetLocation {
+        match self {
+            Self::Io { location, .. }
+            | Self::Parse { location, .. }
+            | Self::Unsupported { location, .. }
+            | Self::Upload { location, .. } => location,
+        }
+    }
+}
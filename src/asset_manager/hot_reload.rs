@@ -0,0 +1,283 @@
+use crate::asset_manager::registry::{self, Garbage};
+use crate::game_objects::render_object::{ObjectId, RenderId, RenderObject};
+use crate::game_objects::scene::Scene;
+use crate::vulkan::vertexbuffer_util::VertexPbr;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// polls the modification time of files. a change is only reported once the file has not
+/// changed for `settle`, exporters write large files in several steps.
+#[derive(Debug)]
+pub struct FileWatcher {
+    pub settle: Duration,
+    files: HashMap<PathBuf, WatchedFile>,
+}
+#[derive(Debug)]
+struct WatchedFile {
+    modified: Option<SystemTime>,
+    changed_at: Option<Instant>,
+}
+impl FileWatcher {
+    pub fn new(settle: Duration) -> Self {
+        Self {
+            settle,
+            files: HashMap::new(),
+        }
+    }
+    /// starts watching `path`, changes made before this call are not reported.
+    pub fn watch(&mut self, path: impl AsRef<Path>) {
+        let path = registry::source(path);
+        let modified = modified(&path);
+        self.files.entry(path).or_insert(WatchedFile {
+            modified,
+            changed_at: None,
+        });
+    }
+    pub fn unwatch(&mut self, path: impl AsRef<Path>) {
+        self.files.remove(&registry::source(path));
+    }
+    pub fn is_watched(&self, path: impl AsRef<Path>) -> bool {
+        self.files.contains_key(&registry::source(path))
+    }
+    /// files that changed and then stayed untouched for `settle` until `now`. a deleted file
+    /// counts as changed, so the reload reports the missing file.
+    pub fn poll(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut changed = vec![];
+        for (path, file) in &mut self.files {
+            let modified = modified(path);
+            if modified != file.modified {
+                file.modified = modified;
+                file.changed_at = Some(now);
+            } else if let Some(changed_at) = file.changed_at
+                && now.saturating_duration_since(changed_at) >= self.settle
+            {
+                file.changed_at = None;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// the objects one load of a file created, matched against the file again when it changes.
+#[derive(Clone, Debug, Default)]
+pub struct LoadedFile {
+    /// gltf node index, or obj model index, to the object made from it.
+    pub nodes: HashMap<usize, ObjectId>,
+    /// render objects by node and primitive index.
+    pub primitives: HashMap<(usize, usize), RenderId>,
+}
+
+/// reloads loaded files when they, or the buffers and images they refer to, change on disk.
+#[derive(Debug)]
+pub struct HotReload {
+    /// on by default in debug builds.
+    pub enabled: bool,
+    /// time between two polls of the watcher.
+    pub interval: Duration,
+    pub watcher: FileWatcher,
+    /// every load of a file, keyed by `registry::source`.
+    files: HashMap<PathBuf, Vec<LoadedFile>>,
+    /// watched files to the loaded files that depend on them.
+    dependents: HashMap<PathBuf, HashSet<PathBuf>>,
+    last_poll: Option<Instant>,
+}
+impl Default for HotReload {
+    fn default() -> Self {
+        Self {
+            enabled: cfg!(debug_assertions),
+            interval: Duration::from_millis(250),
+            watcher: FileWatcher::new(Duration::from_millis(300)),
+            files: HashMap::new(),
+            dependents: HashMap::new(),
+            last_poll: None,
+        }
+    }
+}
+impl HotReload {
+    /// remembers what a load of `path` created. `dependencies` are the files it was read
+    /// from, including `path` itself.
+    pub fn track(
+        &mut self,
+        path: impl AsRef<Path>,
+        loaded: LoadedFile,
+        dependencies: impl IntoIterator<Item = PathBuf>,
+    ) {
+        let path = registry::source(path);
+        for dependency in dependencies {
+            self.watcher.watch(&dependency);
+            self.dependents
+                .entry(registry::source(dependency))
+                .or_default()
+                .insert(path.clone());
+        }
+        self.files.entry(path).or_default().push(loaded);
+    }
+    /// every load of `path`, removed until they are tracked again.
+    pub fn take_loads(&mut self, path: impl AsRef<Path>) -> Vec<LoadedFile> {
+        self.files
+            .remove(&registry::source(path))
+            .unwrap_or_default()
+    }
+    /// the files the loads of `path` were read from, see `track`.
+    pub fn dependencies(&self, path: impl AsRef<Path>) -> Vec<PathBuf> {
+        let path = registry::source(path);
+        self.dependents
+            .iter()
+            .filter(|(_, files)| files.contains(&path))
+            .map(|(dependency, _)| dependency.clone())
+            .collect()
+    }
+    pub fn loads(&self, path: impl AsRef<Path>) -> &[LoadedFile] {
+        self.files
+            .get(&registry::source(path))
+            .map_or(&[], Vec::as_slice)
+    }
+    /// every loaded file with its loads.
+    pub fn files(&self) -> impl Iterator<Item = (&Path, &[LoadedFile])> {
+        self.files
+            .iter()
+            .map(|(path, loads)| (path.as_path(), loads.as_slice()))
+    }
+    /// the file an object was made from, the index of the load in `loads` and the node index.
+    pub fn node_source(&self, id: ObjectId) -> Option<(&Path, usize, usize)> {
+        self.files.iter().find_map(|(path, loads)| {
+            loads.iter().enumerate().find_map(|(load, loaded)| {
+                loaded
+                    .nodes
+                    .iter()
+                    .find(|(_, object_id)| **object_id == id)
+                    .map(|(node, _)| (path.as_path(), load, *node))
+            })
+        })
+    }
+    /// the file, node and primitive index a render object was loaded from.
+    pub fn primitive_source(&self, id: RenderId) -> Option<(&Path, usize, usize)> {
+        self.files.iter().find_map(|(path, loads)| {
+            loads
+                .iter()
+                .flat_map(|loaded| &loaded.primitives)
+                .find(|(_, render_id)| **render_id == id)
+                .map(|((node, primitive), _)| (path.as_path(), *node, *primitive))
+        })
+    }
+    /// the loaded files to read again, at most once every `interval`.
+    pub fn changed_files(&mut self, now: Instant) -> Vec<PathBuf> {
+        if !self.enabled
+            || self
+                .last_poll
+                .is_some_and(|last| now.saturating_duration_since(last) < self.interval)
+        {
+            return vec![];
+        }
+        self.last_poll = Some(now);
+        let mut files: Vec<PathBuf> = vec![];
+        for changed in self.watcher.poll(now) {
+            for file in self.dependents.get(&changed).into_iter().flatten() {
+                if !files.contains(file) {
+                    files.push(file.clone());
+                }
+            }
+        }
+        files
+    }
+}
+
+/// starts reading every changed file in the background, `bevy_load::upload_read_assets`
+/// swaps the new resources in once they are read.
+pub fn queue_changed_files(scene: &mut Scene) {
+    for path in scene.hot_reload.changed_files(Instant::now()) {
+        log::info!("reloading {}", path.display());
+        scene.loads.reload(path);
+    }
+}
+
+/// puts the new render objects of a node in place of the old ones. render ids and instance
+/// lists stay the same, the old objects are retired until no frame in flight uses them.
+/// primitives the node gained are added to its object, the ones it lost are removed.
+pub fn replace_render_objects(
+    scene: &mut Scene,
+    loaded: &mut LoadedFile,
+    node: usize,
+    object_id: ObjectId,
+    render_objects: Vec<RenderObject<VertexPbr>>,
+    frames: usize,
+) {
+    let count = render_objects.len();
+    for (primitive, mut render_object) in render_objects.into_iter().enumerate() {
+        let existing = loaded
+            .primitives
+            .get(&(node, primitive))
+            .copied()
+            .filter(|id| scene.render_objects.get(*id).is_some());
+        match existing {
+            Some(render_id) => {
+                let Some(slot) = scene.render_objects.get_mut(render_id) else {
+                    continue;
+                };
+                render_object.instances = std::mem::take(&mut slot.instances);
+                render_object.pin = std::mem::take(&mut slot.pin);
+                let instances = render_object.instances.clone();
+                let old = std::mem::replace(slot, render_object);
+                for instance in instances {
+                    scene.mark_moved(instance);
+                }
+                scene
+                    .assets
+                    .retire(Garbage::RenderObject(Box::new(old)), frames);
+            }
+            None => {
+                render_object.instances.push(object_id);
+                let render_id = scene.render_objects.insert(render_object);
+                if let Some(object) = scene.objects.get_mut(object_id) {
+                    object.render_objects.push(render_id);
+                }
+                scene.mark_moved(object_id);
+                loaded.primitives.insert((node, primitive), render_id);
+            }
+        }
+    }
+    let removed: Vec<(usize, usize)> = loaded
+        .primitives
+        .keys()
+        .filter(|(n, p)| *n == node && *p >= count)
+        .copied()
+        .collect();
+    for key in removed {
+        let Some(render_id) = loaded.primitives.remove(&key) else {
+            continue;
+        };
+        let Some(old) = scene.render_objects.remove(render_id) else {
+            continue;
+        };
+        for instance in &old.instances {
+            if let Some(object) = scene.objects.get_mut(*instance) {
+                object.render_objects.retain(|id| *id != render_id);
+            }
+            scene.mark_moved(*instance);
+        }
+        scene
+            .assets
+            .retire(Garbage::RenderObject(Box::new(old)), frames);
+    }
+}
+
+/// removes the objects and render objects of a load that failed partway. render objects are
+/// retired rather than destroyed, as the frames in flight may already draw them.
+pub fn discard(scene: &mut Scene, loaded: LoadedFile, frames: usize) {
+    for object_id in loaded.nodes.into_values() {
+        scene.remove_linked(object_id);
+    }
+    for render_id in loaded.primitives.into_values() {
+        if let Some(old) = scene.render_objects.remove(render_id) {
+            scene
+                .assets
+                .retire(Garbage::RenderObject(Box::new(old)), frames);
+        }
+    }
+}
@@ -1,31 +1,62 @@
+use crate::asset_manager::bevy_load::AssetBinding;
+use crate::asset_manager::error::{AssetError, AssetLocation};
+use crate::asset_manager::hot_reload::{self, LoadedFile};
+use crate::asset_manager::load_obj;
+use crate::asset_manager::registry::{AssetRegistry, MeshKey, TextureKey};
 use crate::winit_app::winit_render_app::AppData;
 use crate::{
     game_objects::{
-        render_object::{ObjectId, PBR, RenderId, RenderObject},
+        animation::{
+            Animatable, AnimationClip, AnimationPlayer, Channel, Interpolation, Property, Sampler,
+            Skin,
+        },
+        light::Light,
+        morph::{MorphMesh, MorphTargets},
+        render_object::{Deformation, ObjectId, PBR, RenderId, RenderObject, SkinnedMesh},
         scene::{GameObject, Scene},
         transform::Transform,
     },
-    vulkan::{
-        image_util::TextureData,
-        vertexbuffer_util::{VertexData, VertexPbr},
-    },
+    vulkan::vertexbuffer_util::{VertexPbr, VertexSkin},
 };
 
-use glam::{Vec2, Vec3, Vec4};
+use anyhow::anyhow;
+use glam::{Mat4, Quat, UVec4, Vec2, Vec3, Vec4};
 use gltf::json::accessor::{ComponentType, Type};
+use gltf::khr_lights_punctual::Kind;
 use gltf::{
     Accessor, Node, Semantic,
     buffer::{self},
     image,
 };
-use log::info;
-use std::{collections::HashMap, path::Path};
-use terrors::OneOf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use vulkanalia::{Device, Instance};
 
-const DEFAULT_TEXTURE: [u8; 16] = [
-    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
-];
+const DEFAULT_TEXTURE: [u8; 4] = [255, 255, 255, 255];
+/// flat tangent space normal.
+const DEFAULT_NORMAL_TEXTURE: [u8; 4] = [128, 128, 255, 255];
+/// a parsed gltf file with its buffers and decoded images, nothing is on the gpu yet.
+#[derive(Debug)]
+pub struct GltfSource {
+    pub location: AssetLocation,
+    pub document: gltf::Document,
+    pub buffers: Vec<buffer::Data>,
+    pub images: Vec<image::Data>,
+}
+
+///parses a gltf file and decodes its images, does not need the gpu so it can run on a worker.
+pub fn read(path: impl AsRef<Path>) -> Result<GltfSource, AssetError> {
+    let location = AssetLocation::new(path.as_ref());
+    let (document, buffers, images) =
+        gltf::import(path).map_err(|e| AssetError::gltf(&location, e))?;
+    Ok(GltfSource {
+        location,
+        document,
+        buffers,
+        images,
+    })
+}
+
 ///loads a single gltf scene.
 pub fn scene(
     instance: &Instance,
@@ -33,138 +64,519 @@ pub fn scene(
     data: &mut AppData,
     scene: &mut Scene,
     path: impl AsRef<Path>,
-) -> Result<Vec<ObjectId>, OneOf<(gltf::Error, String, anyhow::Error)>> {
-    let (document, buffers, images) = gltf::import(path).unwrap();
+) -> Result<Vec<ObjectId>, AssetError> {
+    upload(instance, device, data, scene, read(path)?)
+}
+
+///creates the objects of a gltf file returned by `read`, uploading its meshes and textures.
+pub fn upload(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    scene: &mut Scene,
+    source: GltfSource,
+) -> Result<Vec<ObjectId>, AssetError> {
+    let mut loaded = LoadedFile::default();
+    let game_objects = match upload_nodes(instance, device, data, scene, &source, &mut loaded) {
+        Ok(game_objects) => game_objects,
+        Err(e) => {
+            hot_reload::discard(scene, loaded, data.swapchain_images.len());
+            return Err(e);
+        }
+    };
+    scene
+        .hot_reload
+        .track(&source.location.path, loaded, dependencies(&source));
+
+    Ok(game_objects)
+}
+
+fn upload_nodes(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    scene: &mut Scene,
+    source: &GltfSource,
+    loaded: &mut LoadedFile,
+) -> Result<Vec<ObjectId>, AssetError> {
     let mut game_objects: Vec<ObjectId> = vec![];
-    for gltf_scene in document.scenes() {
+    for gltf_scene in source.document.scenes() {
         for node in gltf_scene.nodes() {
-            let object_id = load_node(
-                instance, device, data, scene, &node, &buffers, &images, None,
-            )?;
+            let object_id = load_node(instance, device, data, scene, source, &node, None, loaded)?;
 
-            game_objects.push(object_id.unwrap())
+            game_objects.push(object_id)
         }
     }
-
+    load_skins(scene, source, &loaded.nodes)?;
+    let clips = load_clips(source, &loaded.nodes)?;
+    // clips target objects by id, so a single player on the first root drives the whole file.
+    if !clips.is_empty()
+        && let Some(root) = game_objects.first()
+        && let Some(object) = scene.objects.get_mut(*root)
+    {
+        object.animation = Some(autoplay(clips));
+    }
     Ok(game_objects)
 }
 
-fn load_node(
+///adds the nodes and primitives of a gltf file to existing objects, see `AssetLoads::attach`.
+///skins and clips target the objects bound to the nodes of the same copy, the clips are played
+///by the object of the first root node.
+pub fn attach(
     instance: &Instance,
     device: &Device,
     data: &mut AppData,
     scene: &mut Scene,
-    node: &Node,
-    buffers: &Vec<buffer::Data>,
-    images: &Vec<image::Data>,
-    parent: Option<ObjectId>,
-) -> Result<Option<ObjectId>, OneOf<(gltf::Error, String, anyhow::Error)>> {
-    //let mut render_object_ids: Vec<RenderId> = vec![];
-    let mut render_ids: Vec<RenderId> = vec![];
-    //let mut maybe_object_id: Option<ObjectId> = None;
-    println!("node name: {:?}", node.name());
-    if let Some(mesh) = node.mesh() {
-        println!("mesh primitives: {:?}", mesh.primitives().count());
+    source: GltfSource,
+    bindings: &[AssetBinding],
+) -> Result<Vec<ObjectId>, AssetError> {
+    let mut copies = BTreeMap::new();
+    let result = attach_bindings(
+        instance,
+        device,
+        data,
+        scene,
+        &source,
+        bindings,
+        &mut copies,
+    );
+    // whatever was attached before an error is tracked, so it reloads with the file
+    for loaded in copies.into_values() {
+        scene
+            .hot_reload
+            .track(&source.location.path, loaded, dependencies(&source));
+    }
+    result
+}
 
-        for prim in mesh.primitives() {
-            let _mode = prim.mode();
-            let mut attr_map = HashMap::new();
-            for attr in prim.attributes() {
-                let accessor = prim.get(&attr.0).unwrap();
-                let view = accessor.view().unwrap();
-                drop(view);
-                let slice = get_buffer_slice(&accessor, buffers);
-                attr_map.insert(attr.0, slice);
+fn attach_bindings(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    scene: &mut Scene,
+    source: &GltfSource,
+    bindings: &[AssetBinding],
+    copies: &mut BTreeMap<usize, LoadedFile>,
+) -> Result<Vec<ObjectId>, AssetError> {
+    let mut objects = vec![];
+    let mut primitives = HashMap::new();
+    let result = bindings.iter().try_for_each(|binding| {
+        let node = source.document.nodes().nth(binding.node).ok_or_else(|| {
+            AssetError::invalid(&source.location, format!("no node {}", binding.node))
+        })?;
+        let loaded = copies.entry(binding.copy).or_default();
+        let Some(primitive) = binding.primitive else {
+            loaded.nodes.insert(binding.node, binding.object);
+            return Ok(());
+        };
+        let render_id = match primitives.get(&(binding.node, primitive)) {
+            Some(render_id) => *render_id,
+            None => {
+                let prim = node
+                    .mesh()
+                    .and_then(|mesh| mesh.primitives().nth(primitive))
+                    .ok_or_else(|| {
+                        AssetError::invalid(
+                            &source.location.node(node.name().unwrap_or("unnamed")),
+                            format!("no primitive {primitive}"),
+                        )
+                    })?;
+                let render_object =
+                    load_primitive(instance, device, data, scene, source, &node, &prim)?;
+                let render_id = scene.render_objects.insert(render_object);
+                primitives.insert((binding.node, primitive), render_id);
+                render_id
             }
+        };
+        if scene.attach_render_object(binding.object, render_id)
+            && !objects.contains(&binding.object)
+        {
+            objects.push(binding.object);
+        }
+        Ok(())
+    });
+    // every copy swaps the shared render objects when the file is reloaded
+    for loaded in copies.values_mut() {
+        loaded.primitives = primitives.clone();
+    }
+    result?;
+    for loaded in copies.values() {
+        load_skins(scene, source, &loaded.nodes)?;
+        let clips = load_clips(source, &loaded.nodes)?;
+        let root = source
+            .document
+            .scenes()
+            .flat_map(|gltf_scene| gltf_scene.nodes())
+            .find_map(|node| loaded.nodes.get(&node.index()));
+        if !clips.is_empty()
+            && let Some(root) = root
+            && let Some(object) = scene.objects.get_mut(*root)
+        {
+            object.animation = Some(autoplay(clips));
+        }
+    }
+    Ok(objects)
+}
 
-            let vertices = intersperse_vertex_data(&attr_map);
-            let index_acc = prim.indices().unwrap();
-            let index_slice = get_buffer_slice(&index_acc, buffers);
-            let indices: Vec<u32> = match index_acc.data_type() {
-                gltf::accessor::DataType::U8 => {
-                    println!("index data type: u8");
-                    let indices = index_slice.to_vec();
-                    indices.iter().map(|u| *u as u32).collect()
-                }
-                gltf::accessor::DataType::U16 => {
-                    assert_eq!(
-                        index_slice.len() % 2,
-                        0,
-                        "index list must be divisible by 2"
-                    );
-                    println!("index data type: u16");
-                    let indices: &[u16] = unsafe { std::mem::transmute(index_slice) };
-                    let mut u32s: Vec<u32> = vec![];
-                    for i in 0..(indices.len() / 2) {
-                        u32s.push(indices[i] as u32);
-                    }
-                    u32s
-                }
-                gltf::accessor::DataType::U32 => {
-                    assert_eq!(
-                        index_slice.len() % 4,
-                        0,
-                        "index list must be divisible by 4"
-                    );
-                    println!("index data type: u32");
-                    let indices: &[u32] = unsafe { std::mem::transmute(index_slice) };
-                    let mut new_indices: Vec<u32> = vec![];
-                    for i in 0..indices.len() / 4 {
-                        new_indices.push(indices[i]);
-                    }
-                    new_indices
-                }
-                _ => panic!("should be unsigned integer type"),
-            };
+///rebuilds the render objects, skins and clips of every load of the file in place. objects
+///keep their transforms and instances, nodes that were added to the file are not loaded.
+pub fn reload(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    scene: &mut Scene,
+    source: GltfSource,
+) -> Result<(), AssetError> {
+    let frames = data.swapchain_images.len();
+    scene.assets.forget(&source.location.path, frames);
+    for mut loaded in scene.hot_reload.take_loads(&source.location.path) {
+        let result = reload_file(instance, device, data, scene, &source, &mut loaded);
+        // whatever was replaced before an error is tracked again
+        scene
+            .hot_reload
+            .track(&source.location.path, loaded, dependencies(&source));
+        result?;
+    }
+    Ok(())
+}
 
-            let pbr = prim.material().pbr_metallic_roughness();
-            let base = Vec4::from(pbr.base_color_factor());
-            let (pixels, width, height) = match pbr.base_color_texture() {
-                None => {
-                    println!("using default texture");
-                    (Vec::from(DEFAULT_TEXTURE), 1, 1)
-                }
-                Some(texture) => {
-                    let image = &images[texture.texture().index()];
-                    let mut pixels = image.pixels.clone();
-                    match image.format {
-                        image::Format::R8G8B8 => pixels = interleave_alpha_channel(&pixels, 255),
-                        image::Format::R8G8B8A8 => (),
-                        format => {
-                            return Err(OneOf::new(format!("weird texture format: {:?}", format)));
-                        }
-                    }
-                    (pixels, image.width, image.height)
-                }
-            };
+fn reload_file(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    scene: &mut Scene,
+    source: &GltfSource,
+    loaded: &mut LoadedFile,
+) -> Result<(), AssetError> {
+    let frames = data.swapchain_images.len();
+    for node in source.document.nodes() {
+        let Some(object_id) = loaded.nodes.get(&node.index()).copied() else {
+            continue;
+        };
+        let mut render_objects = vec![];
+        if let Some(mesh) = node.mesh() {
+            for prim in mesh.primitives() {
+                render_objects.push(load_primitive(
+                    instance, device, data, scene, source, &node, &prim,
+                )?);
+            }
+        }
+        if let Some(object) = scene.objects.get_mut(object_id) {
+            object.light = read_light(&node);
+        }
+        hot_reload::replace_render_objects(
+            scene,
+            loaded,
+            node.index(),
+            object_id,
+            render_objects,
+            frames,
+        );
+    }
+    load_skins(scene, source, &loaded.nodes)?;
+    let clips = load_clips(source, &loaded.nodes)?;
+    for object_id in loaded.nodes.values() {
+        if let Some(object) = scene.objects.get_mut(*object_id)
+            && let Some(player) = &mut object.animation
+        {
+            player.clips = clips.clone();
+            if player
+                .active
+                .is_some_and(|index| index >= player.clips.len())
+            {
+                player.stop();
+            }
+            player.seek(player.time);
+        }
+    }
+    Ok(())
+}
+
+/// the file and the external buffers and images it refers to.
+fn dependencies(source: &GltfSource) -> Vec<PathBuf> {
+    let directory = source.location.path.parent().unwrap_or(Path::new(""));
+    let buffers = source
+        .document
+        .buffers()
+        .filter_map(|buffer| match buffer.source() {
+            buffer::Source::Uri(uri) => Some(uri),
+            buffer::Source::Bin => None,
+        });
+    let images = source
+        .document
+        .images()
+        .filter_map(|image| match image.source() {
+            image::Source::Uri { uri, .. } => Some(uri),
+            image::Source::View { .. } => None,
+        });
+    let files = buffers
+        .chain(images)
+        .filter(|uri| !uri.starts_with("data:"))
+        .map(|uri| directory.join(uri));
+    std::iter::once(source.location.path.clone())
+        .chain(files)
+        .collect()
+}
 
-            let vertex_data = unsafe {
-                VertexData::create_vertex_data(instance, device, data, vertices, indices, false)
+// joints may be loaded after the skinned node, so skins are resolved once every node exists.
+fn load_skins(
+    scene: &mut Scene,
+    source: &GltfSource,
+    node_ids: &HashMap<usize, ObjectId>,
+) -> Result<(), AssetError> {
+    for node in source.document.nodes() {
+        if let Some(skin) = node.skin()
+            && let Some(object_id) = node_ids.get(&node.index())
+        {
+            let location = source.location.node(node.name().unwrap_or("unnamed"));
+            let skin = load_skin(&skin, &source.buffers, node_ids, &location)?;
+            if let Some(object) = scene.objects.get_mut(*object_id) {
+                object.skin = Some(skin);
             }
-            .map_err(OneOf::new)?;
-            let texture_data = unsafe {
-                TextureData::create_texture_from_data(
-                    instance,
-                    device,
-                    data,
-                    pixels,
-                    (width, height),
+        }
+    }
+    Ok(())
+}
+
+fn load_clips(
+    source: &GltfSource,
+    node_ids: &HashMap<usize, ObjectId>,
+) -> Result<Vec<AnimationClip>, AssetError> {
+    source
+        .document
+        .animations()
+        .map(|animation| load_animation(&animation, &source.buffers, node_ids, &source.location))
+        .collect()
+}
+
+fn load_skin(
+    skin: &gltf::Skin,
+    buffers: &[buffer::Data],
+    node_ids: &HashMap<usize, ObjectId>,
+    location: &AssetLocation,
+) -> Result<Skin, AssetError> {
+    let joints: Vec<ObjectId> = skin
+        .joints()
+        .map(|joint| {
+            node_ids.get(&joint.index()).copied().ok_or_else(|| {
+                AssetError::invalid(
+                    location,
+                    format!("joint node {} is not part of the scene", joint.index()),
                 )
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    let inverse_bind_matrices: Vec<Mat4> = match skin.inverse_bind_matrices() {
+        Some(accessor) => read_floats::<16>(&accessor, buffers)
+            .map_err(|e| AssetError::accessor(location, e))?
+            .iter()
+            .map(Mat4::from_cols_array)
+            .collect(),
+        None => vec![Mat4::IDENTITY; joints.len()],
+    };
+    if inverse_bind_matrices.len() != joints.len() {
+        return Err(AssetError::invalid(
+            location,
+            format!(
+                "skin has {} joints but {} inverse bind matrices",
+                joints.len(),
+                inverse_bind_matrices.len()
+            ),
+        ));
+    }
+    Ok(Skin {
+        joints,
+        inverse_bind_matrices,
+    })
+}
+
+/// a player that starts the first clip of the file, as viewers of the file would expect.
+fn autoplay(clips: Vec<AnimationClip>) -> AnimationPlayer {
+    let mut player = AnimationPlayer::new(clips);
+    player.play(0);
+    player
+}
+
+/// reads the channels of `animation` that target objects of `node_ids`, the others are left out.
+pub fn load_animation(
+    animation: &gltf::Animation,
+    buffers: &[buffer::Data],
+    node_ids: &HashMap<usize, ObjectId>,
+    location: &AssetLocation,
+) -> Result<AnimationClip, AssetError> {
+    let location = location.node(format!(
+        "animation {}",
+        animation.name().unwrap_or("unnamed")
+    ));
+    let mut channels = vec![];
+    for channel in animation.channels() {
+        let Some(target) = node_ids.get(&channel.target().node().index()) else {
+            continue;
+        };
+        let interpolation = match channel.sampler().interpolation() {
+            gltf::animation::Interpolation::Step => Interpolation::Step,
+            gltf::animation::Interpolation::Linear => Interpolation::Linear,
+            gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+        };
+        let read = |accessor: &Accessor| read_floats::<1>(accessor, buffers);
+        let times: Vec<f32> = read(&channel.sampler().input())
+            .map_err(|e| AssetError::accessor(&location, e))?
+            .into_iter()
+            .map(|[time]| time)
+            .collect();
+        let output = channel.sampler().output();
+        let property = match channel.target().property() {
+            gltf::animation::Property::Translation => Property::Translation(sampler(
+                &location,
+                interpolation,
+                times,
+                read_floats::<3>(&output, buffers)
+                    .map_err(|e| AssetError::accessor(&location, e))?
+                    .into_iter()
+                    .map(Vec3::from)
+                    .collect(),
+            )?),
+            gltf::animation::Property::Rotation => Property::Rotation(sampler(
+                &location,
+                interpolation,
+                times,
+                read_floats::<4>(&output, buffers)
+                    .map_err(|e| AssetError::accessor(&location, e))?
+                    .into_iter()
+                    .map(Quat::from_array)
+                    .collect(),
+            )?),
+            gltf::animation::Property::Scale => Property::Scale(sampler(
+                &location,
+                interpolation,
+                times,
+                read_floats::<3>(&output, buffers)
+                    .map_err(|e| AssetError::accessor(&location, e))?
+                    .into_iter()
+                    .map(Vec3::from)
+                    .collect(),
+            )?),
+            gltf::animation::Property::MorphTargetWeights => {
+                let values: Vec<f32> = read(&output)
+                    .map_err(|e| AssetError::accessor(&location, e))?
+                    .into_iter()
+                    .map(|[weight]| weight)
+                    .collect();
+                let per_key = match interpolation {
+                    Interpolation::CubicSpline => 3,
+                    _ => 1,
+                };
+                let elements = times.len() * per_key;
+                if elements == 0 {
+                    continue;
+                }
+                let target_count = values.len() / elements;
+                // outputs hold the weight of every target for each element
+                let samplers = (0..target_count)
+                    .map(|target| {
+                        let target_values = (0..elements)
+                            .map(|element| values[element * target_count + target])
+                            .collect();
+                        sampler(&location, interpolation, times.clone(), target_values)
+                    })
+                    .collect::<Result<_, _>>()?;
+                Property::Weights(samplers)
             }
-            .map_err(OneOf::new)?;
-            let render_object = unsafe {
-                RenderObject::create_render_object(
-                    instance,
-                    device,
-                    data,
-                    vertex_data,
-                    PBR { texture_data, base },
-                    &mut scene.sun,
-                )
+        };
+        channels.push(Channel {
+            target: *target,
+            property,
+        });
+    }
+    Ok(AnimationClip::new(
+        animation.name().unwrap_or("unnamed"),
+        channels,
+    ))
+}
+
+/// `Sampler::new` without the panic, the value count is checked against the keyframes.
+fn sampler<T: Animatable>(
+    location: &AssetLocation,
+    interpolation: Interpolation,
+    times: Vec<f32>,
+    values: Vec<T>,
+) -> Result<Sampler<T>, AssetError> {
+    let per_key = match interpolation {
+        Interpolation::CubicSpline => 3,
+        _ => 1,
+    };
+    if times.is_empty() || times.len() * per_key != values.len() {
+        return Err(AssetError::invalid(
+            location,
+            format!(
+                "animation sampler has {} keyframes but {} values",
+                times.len(),
+                values.len()
+            ),
+        ));
+    }
+    Ok(Sampler::new(interpolation, times, values))
+}
+
+/// reads the position and normal deltas of every morph target, missing deltas are zero. every
+/// target has to cover all vertices of the primitive.
+pub fn read_morph_targets(
+    prim: &gltf::Primitive,
+    buffers: &[buffer::Data],
+    vertex_count: usize,
+) -> Result<MorphTargets, AccessorError> {
+    let mut targets = MorphTargets {
+        vertex_count,
+        ..Default::default()
+    };
+    let deltas =
+        |accessor: Option<Accessor>, semantic: &'static str| -> Result<Vec<Vec3>, AccessorError> {
+            let Some(accessor) = accessor else {
+                return Ok(vec![Vec3::ZERO; vertex_count]);
+            };
+            let values = read_floats::<3>(&accessor, buffers)?;
+            if values.len() != vertex_count {
+                return Err(AccessorError::AttributeCount {
+                    semantic,
+                    expected: vertex_count,
+                    found: values.len(),
+                });
             }
-            .map_err(OneOf::broaden)?;
+            Ok(values.into_iter().map(Vec3::from).collect())
+        };
+    for target in prim.morph_targets() {
+        targets
+            .position_deltas
+            .push(deltas(target.positions(), "POSITION")?);
+        targets
+            .normal_deltas
+            .push(deltas(target.normals(), "NORMAL")?);
+    }
+    Ok(targets)
+}
+
+fn load_node(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    scene: &mut Scene,
+    source: &GltfSource,
+    node: &Node,
+    parent: Option<ObjectId>,
+    loaded: &mut LoadedFile,
+) -> Result<ObjectId, AssetError> {
+    let location = source.location.node(node.name().unwrap_or("unnamed"));
+    let mut render_ids: Vec<RenderId> = vec![];
+    println!("node name: {:?}", node.name());
+    if let Some(mesh) = node.mesh() {
+        println!("mesh primitives: {:?}", mesh.primitives().count());
+        for prim in mesh.primitives() {
+            let render_object = load_primitive(instance, device, data, scene, source, node, &prim)?;
             let render_key = scene.render_objects.insert(render_object);
-            render_ids.push(render_key.clone());
+            loaded
+                .primitives
+                .insert((node.index(), prim.index()), render_key);
+            render_ids.push(render_key);
         }
     }
     let transform = glam::Mat4::from_cols_array_2d(&node.transform().matrix());
@@ -175,107 +587,592 @@ fn load_node(
         scale,
         rotation,
     };
-    let game_object = GameObject {
-        name: node.name().unwrap_or("unnamed").to_string(),
+    let mut game_object = GameObject::new(
+        node.name().unwrap_or("unnamed"),
         transform,
-        children: vec![],
-        render_objects: render_ids.clone(),
-        parent,
-    };
-    let maybe_object_id = scene.insert_instance(game_object);
+        render_ids.clone(),
+    );
+    game_object.parent = parent;
+    game_object.light = read_light(node);
+    if let Some(mesh) = node.mesh() {
+        let target_count = mesh
+            .primitives()
+            .map(|prim| prim.morph_targets().count())
+            .max()
+            .unwrap_or(0);
+        game_object.morph_weights = match node.weights().or(mesh.weights()) {
+            Some(weights) => weights.to_vec(),
+            None => vec![0.0; target_count],
+        };
+    }
+    let object_id = scene.insert_instance(game_object).ok_or_else(|| {
+        AssetError::upload(&location, anyhow!("render object of the node is missing"))
+    })?;
+    loaded.nodes.insert(node.index(), object_id);
     let mut children = vec![];
     for child in node.children() {
-        let object_id = load_node(
+        let child_id = load_node(
             instance,
             device,
             data,
             scene,
+            source,
             &child,
-            buffers,
-            images,
-            maybe_object_id,
+            Some(object_id),
+            loaded,
         )?;
 
-        children.push(object_id.unwrap());
+        children.push(child_id);
     }
 
-    let game_object = scene.objects.get_mut(maybe_object_id.unwrap()).unwrap();
-    game_object.children = children;
-    Ok(maybe_object_id)
+    if let Some(game_object) = scene.objects.get_mut(object_id) {
+        game_object.children = children;
+    }
+    Ok(object_id)
 }
 
-fn get_buffer_slice<'a>(accessor: &Accessor, buffers: &'a Vec<buffer::Data>) -> &'a [u8] {
-    let view = accessor.view().unwrap();
-    let index = view.buffer().index();
-    info!(
-        "buffer index : {index:?}, length : {:?}",
-        view.buffer().length()
-    );
-
-    let buffer = &buffers[index];
-    let type_size = match accessor.data_type() {
-        ComponentType::I8 => 1,
-        ComponentType::U8 => 1,
-        ComponentType::I16 => 2,
-        ComponentType::U16 => 2,
-        ComponentType::U32 => 4,
-        ComponentType::F32 => 4,
+/// the point or spot light of a node, directional lights are left to the `Sun`.
+pub fn read_light(node: &Node) -> Option<Light> {
+    let light = node.light()?;
+    let color = Vec3::from(light.color());
+    let mut read = match light.kind() {
+        Kind::Directional => return None,
+        Kind::Point => Light::point(color, light.intensity()),
+        Kind::Spot {
+            inner_cone_angle,
+            outer_cone_angle,
+        } => Light::spot(color, light.intensity(), inner_cone_angle, outer_cone_angle),
     };
-    let dimension = match accessor.dimensions() {
-        Type::Scalar => 1,
-        Type::Vec2 => 2,
-        Type::Vec3 => 3,
-        Type::Vec4 => 4,
-        Type::Mat2 => 4,
-        Type::Mat3 => 9,
-        Type::Mat4 => 16,
+    read.range = light.range();
+    Some(read)
+}
+
+/// uploads one primitive of the mesh of `node`, used by the first load and by reloads.
+fn load_primitive(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    scene: &mut Scene,
+    source: &GltfSource,
+    node: &Node,
+    prim: &gltf::Primitive,
+) -> Result<RenderObject<VertexPbr>, AssetError> {
+    let location = source.location.node(node.name().unwrap_or("unnamed"));
+    let mesh = node
+        .mesh()
+        .ok_or_else(|| AssetError::invalid(&location, "primitive of a node without mesh"))?;
+    let location = location.mesh(mesh.name().unwrap_or("unnamed"));
+    let buffers = &source.buffers;
+    let key = MeshKey::new(&location.path, mesh.index(), prim.index());
+    let vertices = || read_primitive(prim, buffers).map_err(|e| AssetError::accessor(&location, e));
+    let vertex_data = unsafe {
+        scene
+            .assets
+            .mesh(instance, device, data, key, vertices, &location)
+    }?;
+    let morph_targets = read_morph_targets(prim, buffers, vertex_data.vertices.len())
+        .map_err(|e| AssetError::accessor(&location, e))?;
+    let pbr = load_material(
+        instance,
+        device,
+        data,
+        &mut scene.assets,
+        &prim.material(),
+        &source.images,
+        &location,
+    )?;
+    let skin = match node.skin() {
+        Some(skin) => {
+            let skin_vertices = read_skin(
+                prim,
+                buffers,
+                vertex_data.vertices.len(),
+                skin.joints().count(),
+            )
+            .map_err(|e| AssetError::accessor(&location, e))?;
+            match skin_vertices {
+                Some(skin_vertices) => {
+                    let skinned_mesh = unsafe {
+                        SkinnedMesh::create(
+                            instance,
+                            device,
+                            data,
+                            &skin_vertices,
+                            skin.joints().count(),
+                        )
+                    }
+                    .map_err(|e| AssetError::upload(&location, e))?;
+                    Some(skinned_mesh)
+                }
+                None => None,
+            }
+        }
+        None => None,
     };
-    &buffer.0[accessor.offset() + view.offset()
-        ..accessor.offset() + view.offset() + accessor.count() * type_size * dimension]
-}
-
-fn intersperse_vertex_data(map: &HashMap<Semantic, &[u8]>) -> Vec<VertexPbr> {
-    let positions = *map.get(&Semantic::Positions).unwrap();
-    assert_eq!(positions.len() % 12, 0, "positions must be divisible by 12");
-    let positions: &[Vec3] = unsafe { std::mem::transmute(positions) };
-
-    let normals = *map.get(&Semantic::Normals).unwrap();
-    let normals: &[Vec3] = unsafe { std::mem::transmute(normals) };
-    let coords = map.get(&Semantic::TexCoords(0));
-    let coord_flag = coords.is_some();
-    let coords: &[Vec2] = if coord_flag {
-        let coords = *coords.unwrap();
-        assert_eq!(coords.len() % 8, 0, "coords must be divisible by 8");
-        let coords: &[Vec2] = unsafe { std::mem::transmute(coords) };
-        println!("coords: {:?}", coords.len() / 8);
-        assert_eq!(
-            coords.len() / 2,
-            positions.len() / 3,
-            "attribute lists must be of the same length,
-        but is {} and {}",
-            coords.len() / 2,
-            positions.len() / 3
-        );
-        coords
+    // the skinned pipeline blends morph targets too, so skinned primitives always get one
+    let morph = if morph_targets.target_count() > 0 || skin.is_some() {
+        let morph_mesh = unsafe { MorphMesh::create(instance, device, data, morph_targets) }
+            .map_err(|e| AssetError::upload(&location, e))?;
+        Some(morph_mesh)
     } else {
-        &[]
+        None
+    };
+    unsafe {
+        RenderObject::create_render_object(
+            instance,
+            device,
+            data,
+            vertex_data,
+            pbr,
+            Deformation { skin, morph },
+            &mut scene.sun,
+        )
+    }
+    .map_err(|e| AssetError::upload(&location, e))
+}
+
+/// uploads every texture of a gltf material, slots without a texture get a default.
+fn load_material(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    assets: &mut AssetRegistry,
+    material: &gltf::Material,
+    images: &[image::Data],
+    location: &AssetLocation,
+) -> Result<PBR, AssetError> {
+    let pbr = material.pbr_metallic_roughness();
+    let base_color = pbr
+        .base_color_texture()
+        .map(|info| info.texture().source().index());
+    let metallic_roughness = pbr
+        .metallic_roughness_texture()
+        .map(|info| info.texture().source().index());
+    let normal = material.normal_texture();
+    let occlusion = material.occlusion_texture();
+    let emissive = material
+        .emissive_texture()
+        .map(|info| info.texture().source().index());
+
+    let mut texture = |index: Option<usize>, default: [u8; 4], linear: bool| {
+        let key = match index {
+            Some(index) => TextureKey::image(&location.path, index, linear),
+            None => TextureKey::Solid {
+                color: default,
+                linear,
+            },
+        };
+        let pixels = || texture_pixels(images, index, default, location);
+        unsafe { assets.texture(instance, device, data, key, pixels, location) }
+    };
+    let texture_data = texture(base_color, DEFAULT_TEXTURE, false)?;
+    let normal_texture = texture(
+        normal.as_ref().map(|n| n.texture().source().index()),
+        DEFAULT_NORMAL_TEXTURE,
+        true,
+    )?;
+    let metallic_roughness_texture = texture(metallic_roughness, DEFAULT_TEXTURE, true)?;
+    let occlusion_texture = texture(
+        occlusion.as_ref().map(|o| o.texture().source().index()),
+        DEFAULT_TEXTURE,
+        true,
+    )?;
+    let emissive_texture = texture(emissive, DEFAULT_TEXTURE, false)?;
+
+    Ok(PBR {
+        texture_data,
+        base: Vec4::from(pbr.base_color_factor()),
+        normal_texture,
+        normal_scale: normal.map_or(1.0, |n| n.scale()),
+        metallic_roughness_texture,
+        metallic: pbr.metallic_factor(),
+        roughness: pbr.roughness_factor(),
+        occlusion_texture,
+        occlusion_strength: occlusion.map_or(1.0, |o| o.strength()),
+        emissive_texture,
+        emissive: Vec3::from(material.emissive_factor()),
+    })
+}
+
+/// rgba8 pixels of the image at `index`, or the 1x1 `default` if the slot is empty.
+fn texture_pixels(
+    images: &[image::Data],
+    index: Option<usize>,
+    default: [u8; 4],
+    location: &AssetLocation,
+) -> Result<(Vec<u8>, (u32, u32)), AssetError> {
+    let Some(index) = index else {
+        return Ok((Vec::from(default), (1, 1)));
+    };
+    let image = images
+        .get(index)
+        .ok_or_else(|| AssetError::invalid(location, format!("image {index} was not loaded")))?;
+    let pixels = match image.format {
+        image::Format::R8 => image.pixels.iter().flat_map(|&r| [r, r, r, 255]).collect(),
+        image::Format::R8G8 => image
+            .pixels
+            .chunks_exact(2)
+            .flat_map(|rg| [rg[0], rg[1], 0, 255])
+            .collect(),
+        image::Format::R8G8B8 => interleave_alpha_channel(&image.pixels, 255),
+        image::Format::R8G8B8A8 => image.pixels.clone(),
+        format => {
+            return Err(AssetError::unsupported(
+                location,
+                format!("texture format {format:?}"),
+            ));
+        }
+    };
+    Ok((pixels, (image.width, image.height)))
+}
+
+/// everything that can go wrong while reading vertex data out of a gltf buffer.
+#[derive(Debug, thiserror::Error)]
+pub enum AccessorError {
+    #[error("primitive has no {0} attribute")]
+    MissingAttribute(&'static str),
+    #[error("accessor {accessor} has {found} components per element, expected {expected}")]
+    Dimensions {
+        accessor: usize,
+        expected: usize,
+        found: usize,
+    },
+    #[error("accessor {accessor} has component type {found:?}, which is not allowed here")]
+    ComponentType {
+        accessor: usize,
+        found: ComponentType,
+    },
+    #[error("accessor {accessor} references buffer {buffer}, which was not loaded")]
+    MissingBuffer { accessor: usize, buffer: usize },
+    #[error("accessor {accessor} reads past the end of its buffer view")]
+    OutOfBounds { accessor: usize },
+    #[error("sparse index {index} of accessor {accessor} is past its {count} elements")]
+    SparseIndex {
+        accessor: usize,
+        index: usize,
+        count: usize,
+    },
+    #[error("attribute {semantic} has {found} elements, but the primitive has {expected} vertices")]
+    AttributeCount {
+        semantic: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    #[error("vertex index {index} is out of range for {vertex_count} vertices")]
+    IndexOutOfRange { index: u32, vertex_count: usize },
+    #[error("joint {joint} is out of range for a skin of {joint_count} joints")]
+    JointOutOfRange { joint: u32, joint_count: usize },
+    #[error("primitive mode {0:?} is not supported, only triangles are")]
+    Mode(gltf::mesh::Mode),
+}
+
+/// reads the vertices and triangle list indices of a primitive. normals are generated if the
+/// primitive has none, and non indexed primitives get sequential indices.
+pub fn read_primitive(
+    prim: &gltf::Primitive,
+    buffers: &[buffer::Data],
+) -> Result<(Vec<VertexPbr>, Vec<u32>), AccessorError> {
+    let positions = prim
+        .get(&Semantic::Positions)
+        .ok_or(AccessorError::MissingAttribute("POSITION"))?;
+    let positions = read_floats::<3>(&positions, buffers)?;
+    let vertex_count = positions.len();
+    let normals = read_attribute::<3>(prim, Semantic::Normals, "NORMAL", vertex_count, buffers)?;
+    let coords = read_attribute::<2>(
+        prim,
+        Semantic::TexCoords(0),
+        "TEXCOORD_0",
+        vertex_count,
+        buffers,
+    )?;
+
+    let indices: Vec<u32> = match prim.indices() {
+        Some(accessor) => read_integers::<1>(&accessor, buffers)?
+            .into_iter()
+            .map(|[i]| i)
+            .collect(),
+        None => (0..vertex_count as u32).collect(),
+    };
+    if let Some(index) = indices.iter().find(|i| **i as usize >= vertex_count) {
+        return Err(AccessorError::IndexOutOfRange {
+            index: *index,
+            vertex_count,
+        });
+    }
+    let indices = triangle_list(prim.mode(), indices)?;
+
+    let mut vertices: Vec<VertexPbr> = positions
+        .iter()
+        .enumerate()
+        .map(|(i, pos)| {
+            let normal = normals.as_ref().map_or(Vec3::ZERO, |n| Vec3::from(n[i]));
+            let tex_coord = coords.as_ref().map_or(Vec2::ZERO, |c| Vec2::from(c[i]));
+            VertexPbr::new(Vec3::from(*pos), normal, tex_coord)
+        })
+        .collect();
+    if normals.is_none() {
+        load_obj::generate_normals(&mut vertices, &indices);
+    }
+    Ok((vertices, indices))
+}
+
+/// reads an optional vertex attribute, which must have an element for every vertex.
+fn read_attribute<const N: usize>(
+    prim: &gltf::Primitive,
+    semantic: Semantic,
+    name: &'static str,
+    vertex_count: usize,
+    buffers: &[buffer::Data],
+) -> Result<Option<Vec<[f32; N]>>, AccessorError> {
+    let Some(accessor) = prim.get(&semantic) else {
+        return Ok(None);
     };
+    let values = read_floats(&accessor, buffers)?;
+    if values.len() != vertex_count {
+        return Err(AccessorError::AttributeCount {
+            semantic: name,
+            expected: vertex_count,
+            found: values.len(),
+        });
+    }
+    Ok(Some(values))
+}
+
+/// reads the joints and weights of a skinned primitive, `None` if it has neither. both have to
+/// cover every vertex and joints have to index into the skin.
+pub fn read_skin(
+    prim: &gltf::Primitive,
+    buffers: &[buffer::Data],
+    vertex_count: usize,
+    joint_count: usize,
+) -> Result<Option<Vec<VertexSkin>>, AccessorError> {
+    let (Some(joints), Some(weights)) = (
+        prim.get(&Semantic::Joints(0)),
+        prim.get(&Semantic::Weights(0)),
+    ) else {
+        return Ok(None);
+    };
+    let joints = read_integers::<4>(&joints, buffers)?;
+    let weights = read_floats::<4>(&weights, buffers)?;
+    for (semantic, found) in [("JOINTS_0", joints.len()), ("WEIGHTS_0", weights.len())] {
+        if found != vertex_count {
+            return Err(AccessorError::AttributeCount {
+                semantic,
+                expected: vertex_count,
+                found,
+            });
+        }
+    }
+    if let Some(joint) = joints
+        .iter()
+        .flatten()
+        .find(|j| **j as usize >= joint_count)
+    {
+        return Err(AccessorError::JointOutOfRange {
+            joint: *joint,
+            joint_count,
+        });
+    }
+    Ok(Some(
+        joints
+            .into_iter()
+            .zip(weights)
+            .map(|(joints, weights)| VertexSkin {
+                joints: UVec4::from_array(joints),
+                weights: Vec4::from_array(weights),
+            })
+            .collect(),
+    ))
+}
+
+/// unrolls strips and fans into a plain triangle list, keeping the winding of every triangle.
+fn triangle_list(mode: gltf::mesh::Mode, indices: Vec<u32>) -> Result<Vec<u32>, AccessorError> {
+    use gltf::mesh::Mode;
+    match mode {
+        Mode::Triangles => Ok(indices),
+        Mode::TriangleStrip => Ok(indices
+            .windows(3)
+            .enumerate()
+            .flat_map(|(i, w)| {
+                if i % 2 == 0 {
+                    [w[0], w[1], w[2]]
+                } else {
+                    [w[1], w[0], w[2]]
+                }
+            })
+            .collect()),
+        Mode::TriangleFan => Ok(indices
+            .windows(2)
+            .skip(1)
+            .flat_map(|w| [indices[0], w[0], w[1]])
+            .collect()),
+        mode => Err(AccessorError::Mode(mode)),
+    }
+}
 
-    let mut vertices = vec![];
+/// reads an accessor with `N` components per element as floats. normalized integers are mapped
+/// to [0, 1] or [-1, 1], other integers are converted as is.
+pub fn read_floats<const N: usize>(
+    accessor: &Accessor,
+    buffers: &[buffer::Data],
+) -> Result<Vec<[f32; N]>, AccessorError> {
+    let normalized = accessor.normalized();
+    read_elements(accessor, buffers, |bytes, component_type| {
+        Ok(match component_type {
+            ComponentType::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            ComponentType::U8 if normalized => bytes[0] as f32 / u8::MAX as f32,
+            ComponentType::I8 if normalized => (bytes[0] as i8 as f32 / i8::MAX as f32).max(-1.0),
+            ComponentType::U16 if normalized => {
+                u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / u16::MAX as f32
+            }
+            ComponentType::I16 if normalized => {
+                (i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / i16::MAX as f32).max(-1.0)
+            }
+            ComponentType::U8 => bytes[0] as f32,
+            ComponentType::I8 => bytes[0] as i8 as f32,
+            ComponentType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+            ComponentType::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+            ComponentType::U32 => {
+                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32
+            }
+        })
+    })
+}
 
-    for i in 0..positions.len() / 12 {
-        let pos = positions[i];
-        let tex_coord = if coord_flag { coords[i] } else { Vec2::ZERO };
-        let normal = normals[i];
-        vertices.push(VertexPbr {
-            pos,
-            normal,
-            tex_coord,
+/// reads an accessor of unsigned integers with `N` components per element, used for indices
+/// and joints.
+pub fn read_integers<const N: usize>(
+    accessor: &Accessor,
+    buffers: &[buffer::Data],
+) -> Result<Vec<[u32; N]>, AccessorError> {
+    read_elements(
+        accessor,
+        buffers,
+        |bytes, component_type| match component_type {
+            ComponentType::U8 => Ok(bytes[0] as u32),
+            ComponentType::U16 => Ok(u16::from_le_bytes([bytes[0], bytes[1]]) as u32),
+            ComponentType::U32 => Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            found => Err(AccessorError::ComponentType {
+                accessor: accessor.index(),
+                found,
+            }),
+        },
+    )
+}
+
+/// reads every element of an accessor, `component` converts the little endian bytes of a
+/// single component. handles byte strides, padded matrix columns and sparse substitution.
+fn read_elements<T, const N: usize>(
+    accessor: &Accessor,
+    buffers: &[buffer::Data],
+    component: impl Fn(&[u8], ComponentType) -> Result<T, AccessorError>,
+) -> Result<Vec<[T; N]>, AccessorError>
+where
+    T: Copy + Default,
+{
+    let index = accessor.index();
+    let found = accessor.dimensions().multiplicity();
+    if found != N {
+        return Err(AccessorError::Dimensions {
+            accessor: index,
+            expected: N,
+            found,
         });
     }
-    println!("vertex count : {:?}", vertices.len());
-    vertices
+    let component_type = accessor.data_type();
+    let component_size = component_type.size();
+    let rows = match accessor.dimensions() {
+        Type::Mat2 => 2,
+        Type::Mat3 => 3,
+        Type::Mat4 => 4,
+        _ => N,
+    };
+    // matrix columns start on 4 byte boundaries
+    let column_stride = match accessor.dimensions() {
+        Type::Mat2 | Type::Mat3 | Type::Mat4 => (rows * component_size).next_multiple_of(4),
+        _ => rows * component_size,
+    };
+    let element_size = column_stride * (N / rows);
+    let element = |bytes: &[u8]| -> Result<[T; N], AccessorError> {
+        let mut element = [T::default(); N];
+        for (c, value) in element.iter_mut().enumerate() {
+            let offset = (c / rows) * column_stride + (c % rows) * component_size;
+            *value = component(&bytes[offset..offset + component_size], component_type)?;
+        }
+        Ok(element)
+    };
+
+    let count = accessor.count();
+    let mut elements = match accessor.view() {
+        Some(view) => {
+            let stride = view.stride().unwrap_or(element_size);
+            let bytes = view_bytes(index, &view, buffers)?;
+            let start = accessor.offset();
+            let end = match count {
+                0 => start,
+                _ => start + stride * (count - 1) + element_size,
+            };
+            if end > bytes.len() {
+                return Err(AccessorError::OutOfBounds { accessor: index });
+            }
+            (0..count)
+                .map(|i| element(&bytes[start + i * stride..]))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+        // sparse accessors without a view start out as zeros
+        None => vec![[T::default(); N]; count],
+    };
+
+    if let Some(sparse) = accessor.sparse() {
+        let indices = sparse.indices();
+        let index_size = match indices.index_type() {
+            gltf::accessor::sparse::IndexType::U8 => 1,
+            gltf::accessor::sparse::IndexType::U16 => 2,
+            gltf::accessor::sparse::IndexType::U32 => 4,
+        };
+        let index_bytes = view_bytes(index, &indices.view(), buffers)?;
+        let values = sparse.values();
+        let value_bytes = view_bytes(index, &values.view(), buffers)?;
+        let sparse_count = sparse.count();
+        if indices.offset() + sparse_count * index_size > index_bytes.len()
+            || values.offset() + sparse_count * element_size > value_bytes.len()
+        {
+            return Err(AccessorError::OutOfBounds { accessor: index });
+        }
+        for i in 0..sparse_count {
+            let bytes = &index_bytes[indices.offset() + i * index_size..];
+            let target = match index_size {
+                1 => bytes[0] as usize,
+                2 => u16::from_le_bytes([bytes[0], bytes[1]]) as usize,
+                _ => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize,
+            };
+            if target >= count {
+                return Err(AccessorError::SparseIndex {
+                    accessor: index,
+                    index: target,
+                    count,
+                });
+            }
+            elements[target] = element(&value_bytes[values.offset() + i * element_size..])?;
+        }
+    }
+    Ok(elements)
 }
+
+/// the bytes covered by a buffer view.
+fn view_bytes<'a>(
+    accessor: usize,
+    view: &buffer::View,
+    buffers: &'a [buffer::Data],
+) -> Result<&'a [u8], AccessorError> {
+    let buffer = view.buffer().index();
+    let data = buffers
+        .get(buffer)
+        .ok_or(AccessorError::MissingBuffer { accessor, buffer })?;
+    data.get(view.offset()..view.offset() + view.length())
+        .ok_or(AccessorError::OutOfBounds { accessor })
+}
+
 /// turns images with type r8g8b8 to r8g8b8a8.
 fn interleave_alpha_channel(rgbs: &Vec<u8>, alpha_val: u8) -> Vec<u8> {
     let a = alpha_val;
@@ -0,0 +1,506 @@
+use crate::asset_manager::bevy_load::AssetBinding;
+use crate::asset_manager::error::{AssetError, AssetLocation};
+use crate::asset_manager::hot_reload::{self, LoadedFile};
+use crate::asset_manager::registry::{AssetRegistry, MeshKey, TextureKey};
+use crate::winit_app::winit_render_app::AppData;
+use crate::{
+    game_objects::{
+        render_object::{Deformation, ObjectId, PBR, RenderObject},
+        scene::{GameObject, Scene},
+        transform::Transform,
+    },
+    vulkan::vertexbuffer_util::VertexPbr,
+};
+use glam::{Quat, Vec2, Vec3, Vec4};
+use log::warn;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use vulkanalia::{Device, Instance};
+
+const DEFAULT_TEXTURE: [u8; 4] = [255, 255, 255, 255];
+/// flat tangent space normal.
+const DEFAULT_NORMAL_TEXTURE: [u8; 4] = [128, 128, 255, 255];
+
+/// an obj model converted to engine vertices, ready for upload.
+#[derive(Clone, Debug)]
+pub struct ObjMesh {
+    pub name: String,
+    pub vertices: Vec<VertexPbr>,
+    pub indices: Vec<u32>,
+    pub material: ObjMaterial,
+}
+
+/// the parts of an mtl material that map onto `PBR`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjMaterial {
+    pub base: Vec4,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: Vec3,
+    pub diffuse_texture: Option<PathBuf>,
+    pub normal_texture: Option<PathBuf>,
+}
+impl Default for ObjMaterial {
+    fn default() -> Self {
+        Self {
+            base: Vec4::ONE,
+            metallic: 0.0,
+            roughness: 1.0,
+            emissive: Vec3::ZERO,
+            diffuse_texture: None,
+            normal_texture: None,
+        }
+    }
+}
+impl ObjMaterial {
+    /// textures are resolved relative to `directory`, the folder of the obj file.
+    pub fn from_mtl(material: &tobj::Material, directory: &Path) -> Self {
+        let texture = |name: &str| (!name.is_empty()).then(|| directory.join(name));
+        let param = |key: &str| {
+            material.unknown_param.get(key).map(|value| {
+                value
+                    .split_whitespace()
+                    .filter_map(|v| v.parse::<f32>().ok())
+            })
+        };
+        // pbr extension to mtl, falls back to converting the phong exponent
+        let roughness = param("Pr")
+            .and_then(|mut values| values.next())
+            .unwrap_or((2.0 / (material.shininess + 2.0)).sqrt());
+        let metallic = param("Pm")
+            .and_then(|mut values| values.next())
+            .unwrap_or(0.0);
+        let emissive = param("Ke")
+            .map(|values| values.collect::<Vec<f32>>())
+            .filter(|values| values.len() == 3)
+            .map_or(Vec3::ZERO, |values| Vec3::from_slice(&values));
+        Self {
+            base: Vec3::from(material.diffuse).extend(material.dissolve),
+            metallic,
+            roughness: roughness.clamp(0.0, 1.0),
+            emissive,
+            diffuse_texture: texture(&material.diffuse_texture),
+            normal_texture: texture(&material.normal_texture),
+        }
+    }
+}
+
+/// an obj file with its decoded textures, nothing is on the gpu yet.
+#[derive(Clone, Debug)]
+pub struct ObjSource {
+    pub path: PathBuf,
+    pub meshes: Vec<ObjMesh>,
+    /// rgba8 pixels and size of every texture the materials refer to.
+    pub textures: HashMap<PathBuf, (Vec<u8>, (u32, u32))>,
+}
+
+///reads an obj file and decodes its textures, does not need the gpu so it can run on a worker.
+pub fn read_source(path: impl AsRef<Path>) -> Result<ObjSource, AssetError> {
+    let path = path.as_ref();
+    let mut meshes = read(path)?;
+    let mut textures = HashMap::new();
+    let mut missing = HashSet::new();
+    for mesh in &mut meshes {
+        let location = AssetLocation::new(path).mesh(&mesh.name);
+        let material = &mut mesh.material;
+        // like a missing mtl file, a texture that can not be read only loses the texture
+        for texture in [&mut material.diffuse_texture, &mut material.normal_texture] {
+            let Some(file) = texture.clone() else {
+                continue;
+            };
+            if !textures.contains_key(&file) && !missing.contains(&file) {
+                match texture_pixels(&file, &location) {
+                    Ok(pixels) => {
+                        textures.insert(file.clone(), pixels);
+                    }
+                    Err(e) => {
+                        warn!("{e}, using the default texture");
+                        missing.insert(file.clone());
+                    }
+                }
+            }
+            if missing.contains(&file) {
+                *texture = None;
+            }
+        }
+    }
+    Ok(ObjSource {
+        path: path.to_path_buf(),
+        meshes,
+        textures,
+    })
+}
+
+///reads every model of an obj file and its mtl materials, without touching the gpu.
+pub fn read(path: impl AsRef<Path>) -> Result<Vec<ObjMesh>, AssetError> {
+    let path = path.as_ref();
+    let location = AssetLocation::new(path);
+    let (models, materials) =
+        tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS).map_err(|e| AssetError::obj(&location, e))?;
+    // a missing or broken mtl file only loses the materials, not the geometry
+    let materials = materials.unwrap_or_default();
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    let mut meshes = vec![];
+    for model in models {
+        let (vertices, indices) = mesh_vertices(&model.mesh)
+            .map_err(|e| AssetError::invalid(&location.mesh(&model.name), e))?;
+        let material = model
+            .mesh
+            .material_id
+            .and_then(|id| materials.get(id))
+            .map_or_else(ObjMaterial::default, |m| {
+                ObjMaterial::from_mtl(m, directory)
+            });
+        meshes.push(ObjMesh {
+            name: model.name,
+            vertices,
+            indices,
+            material,
+        });
+    }
+    Ok(meshes)
+}
+
+///turns a single indexed tobj mesh into `VertexPbr`s, generating normals if the file has none.
+pub fn mesh_vertices(mesh: &tobj::Mesh) -> Result<(Vec<VertexPbr>, Vec<u32>), String> {
+    let count = mesh.positions.len() / 3;
+    if mesh.indices.iter().any(|i| *i as usize >= count) {
+        return Err("obj index out of range".to_string());
+    }
+    let has_normals = mesh.normals.len() == mesh.positions.len();
+    let has_coords = mesh.texcoords.len() / 2 == count;
+    let mut vertices: Vec<VertexPbr> = (0..count)
+        .map(|i| {
+            let pos = Vec3::from_slice(&mesh.positions[3 * i..3 * i + 3]);
+            let normal = if has_normals {
+                Vec3::from_slice(&mesh.normals[3 * i..3 * i + 3])
+            } else {
+                Vec3::ZERO
+            };
+            // obj puts the origin of the texture in the bottom left corner
+            let tex_coord = if has_coords {
+                Vec2::new(mesh.texcoords[2 * i], 1.0 - mesh.texcoords[2 * i + 1])
+            } else {
+                Vec2::ZERO
+            };
+            VertexPbr::new(pos, normal, tex_coord)
+        })
+        .collect();
+    if !has_normals {
+        generate_normals(&mut vertices, &mesh.indices);
+    }
+    Ok((vertices, mesh.indices.clone()))
+}
+
+///smooth normals, every face adds its area weighted normal to its corners.
+pub fn generate_normals(vertices: &mut [VertexPbr], indices: &[u32]) {
+    for vertex in vertices.iter_mut() {
+        vertex.normal = Vec3::ZERO;
+    }
+    for face in indices.chunks_exact(3) {
+        let [a, b, c] = [face[0], face[1], face[2]].map(|i| i as usize);
+        let normal = (vertices[b].pos - vertices[a].pos).cross(vertices[c].pos - vertices[a].pos);
+        vertices[a].normal += normal;
+        vertices[b].normal += normal;
+        vertices[c].normal += normal;
+    }
+    for vertex in vertices.iter_mut() {
+        vertex.normal = vertex.normal.normalize_or(Vec3::Y);
+    }
+}
+
+///loads every model of an obj file as its own root `GameObject`.
+pub fn scene(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    scene: &mut Scene,
+    path: impl AsRef<Path>,
+) -> Result<Vec<ObjectId>, AssetError> {
+    upload(instance, device, data, scene, read_source(path)?)
+}
+
+///creates the objects of an obj file returned by `read_source`, uploading meshes and textures.
+pub fn upload(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    scene: &mut Scene,
+    source: ObjSource,
+) -> Result<Vec<ObjectId>, AssetError> {
+    let mut game_objects = vec![];
+    let mut loaded = LoadedFile::default();
+    for (index, mesh) in source.meshes.iter().enumerate() {
+        let render_object = match load_mesh(instance, device, data, scene, &source, index) {
+            Ok(render_object) => render_object,
+            Err(e) => {
+                hot_reload::discard(scene, loaded, data.swapchain_images.len());
+                return Err(e);
+            }
+        };
+        let render_id = scene.render_objects.insert(render_object);
+        let transform = Transform::new(Vec3::ZERO, Vec3::ONE, Quat::IDENTITY);
+        let object = GameObject::new(&mesh.name, transform, vec![render_id]);
+        if let Some(object_id) = scene.insert_instance(object) {
+            game_objects.push(object_id);
+            loaded.nodes.insert(index, object_id);
+            loaded.primitives.insert((index, 0), render_id);
+        }
+    }
+    scene
+        .hot_reload
+        .track(&source.path, loaded, dependencies(&source));
+    Ok(game_objects)
+}
+
+///adds the models of an obj file to existing objects, see `load::attach`.
+pub fn attach(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    scene: &mut Scene,
+    source: ObjSource,
+    bindings: &[AssetBinding],
+) -> Result<Vec<ObjectId>, AssetError> {
+    let mut copies = BTreeMap::new();
+    let result = attach_bindings(
+        instance,
+        device,
+        data,
+        scene,
+        &source,
+        bindings,
+        &mut copies,
+    );
+    for loaded in copies.into_values() {
+        scene
+            .hot_reload
+            .track(&source.path, loaded, dependencies(&source));
+    }
+    result
+}
+
+fn attach_bindings(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    scene: &mut Scene,
+    source: &ObjSource,
+    bindings: &[AssetBinding],
+    copies: &mut BTreeMap<usize, LoadedFile>,
+) -> Result<Vec<ObjectId>, AssetError> {
+    let mut objects = vec![];
+    let mut primitives = HashMap::new();
+    let result = bindings.iter().try_for_each(|binding| {
+        if binding.node >= source.meshes.len() {
+            return Err(AssetError::invalid(
+                &AssetLocation::new(&source.path),
+                format!("no model {}", binding.node),
+            ));
+        }
+        let loaded = copies.entry(binding.copy).or_default();
+        if binding.primitive.is_none() {
+            loaded.nodes.insert(binding.node, binding.object);
+            return Ok(());
+        }
+        let render_id = match primitives.get(&(binding.node, 0)) {
+            Some(render_id) => *render_id,
+            None => {
+                let render_object = load_mesh(instance, device, data, scene, source, binding.node)?;
+                let render_id = scene.render_objects.insert(render_object);
+                primitives.insert((binding.node, 0), render_id);
+                render_id
+            }
+        };
+        if scene.attach_render_object(binding.object, render_id)
+            && !objects.contains(&binding.object)
+        {
+            objects.push(binding.object);
+        }
+        Ok(())
+    });
+    for loaded in copies.values_mut() {
+        loaded.primitives = primitives.clone();
+    }
+    result.map(|()| objects)
+}
+
+///swaps in the new meshes and materials of every load of the file, see `load::reload`.
+pub fn reload(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    scene: &mut Scene,
+    source: ObjSource,
+) -> Result<(), AssetError> {
+    let frames = data.swapchain_images.len();
+    scene.assets.forget(&source.path, frames);
+    for mut loaded in scene.hot_reload.take_loads(&source.path) {
+        let mut result = Ok(());
+        for index in 0..source.meshes.len() {
+            let Some(object_id) = loaded.nodes.get(&index).copied() else {
+                continue;
+            };
+            match load_mesh(instance, device, data, scene, &source, index) {
+                Ok(render_object) => hot_reload::replace_render_objects(
+                    scene,
+                    &mut loaded,
+                    index,
+                    object_id,
+                    vec![render_object],
+                    frames,
+                ),
+                Err(error) => {
+                    result = Err(error);
+                    break;
+                }
+            }
+        }
+        scene
+            .hot_reload
+            .track(&source.path, loaded, dependencies(&source));
+        result?;
+    }
+    Ok(())
+}
+
+/// the obj file and its textures.
+fn dependencies(source: &ObjSource) -> Vec<PathBuf> {
+    std::iter::once(source.path.clone())
+        .chain(source.textures.keys().cloned())
+        .collect()
+}
+
+/// uploads model `index` of the file, used by the first load and by reloads.
+fn load_mesh(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    scene: &mut Scene,
+    source: &ObjSource,
+    index: usize,
+) -> Result<RenderObject<VertexPbr>, AssetError> {
+    let mesh = &source.meshes[index];
+    let location = AssetLocation::new(&source.path).mesh(&mesh.name);
+    let pbr = load_material(
+        instance,
+        device,
+        data,
+        &mut scene.assets,
+        &mesh.material,
+        &source.textures,
+        &location,
+    )?;
+    let key = MeshKey::new(&source.path, index, 0);
+    let vertices = || Ok((mesh.vertices.clone(), mesh.indices.clone()));
+    let vertex_data = unsafe {
+        scene
+            .assets
+            .mesh(instance, device, data, key, vertices, &location)
+    }?;
+    unsafe {
+        RenderObject::create_render_object(
+            instance,
+            device,
+            data,
+            vertex_data,
+            pbr,
+            Deformation::default(),
+            &mut scene.sun,
+        )
+    }
+    .map_err(|e| AssetError::upload(&location, e))
+}
+
+fn load_material(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    assets: &mut AssetRegistry,
+    material: &ObjMaterial,
+    textures: &HashMap<PathBuf, (Vec<u8>, (u32, u32))>,
+    location: &AssetLocation,
+) -> Result<PBR, AssetError> {
+    let mut texture = |path: Option<&Path>, default: [u8; 4], linear: bool| {
+        let key = match path {
+            Some(path) => TextureKey::image(path, 0, linear),
+            None => TextureKey::Solid {
+                color: default,
+                linear,
+            },
+        };
+        let pixels = || match path {
+            Some(path) => textures.get(path).cloned().ok_or_else(|| {
+                AssetError::invalid(location, format!("{} was not decoded", path.display()))
+            }),
+            None => Ok((Vec::from(default), (1, 1))),
+        };
+        unsafe { assets.texture(instance, device, data, key, pixels, location) }
+    };
+    let texture_data = texture(material.diffuse_texture.as_deref(), DEFAULT_TEXTURE, false)?;
+    let normal_texture = texture(
+        material.normal_texture.as_deref(),
+        DEFAULT_NORMAL_TEXTURE,
+        true,
+    )?;
+    let metallic_roughness_texture = texture(None, DEFAULT_TEXTURE, true)?;
+    let occlusion_texture = texture(None, DEFAULT_TEXTURE, true)?;
+    let emissive_texture = texture(None, DEFAULT_TEXTURE, false)?;
+    Ok(PBR {
+        texture_data,
+        base: material.base,
+        normal_texture,
+        normal_scale: 1.0,
+        metallic_roughness_texture,
+        metallic: material.metallic,
+        roughness: material.roughness,
+        occlusion_texture,
+        occlusion_strength: 1.0,
+        emissive_texture,
+        emissive: material.emissive,
+    })
+}
+
+/// rgba8 pixels of a png file.
+fn texture_pixels(
+    path: &Path,
+    location: &AssetLocation,
+) -> Result<(Vec<u8>, (u32, u32)), AssetError> {
+    // errors point at the texture, the mesh tells which material used it
+    let location = AssetLocation {
+        path: path.to_path_buf(),
+        ..location.clone()
+    };
+    let file = File::open(path).map_err(|e| AssetError::io(&location, e))?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| AssetError::parse(&location, e))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|e| AssetError::parse(&location, e))?;
+    buffer.truncate(info.buffer_size());
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks_exact(2)
+            .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+            .collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        color_type => {
+            return Err(AssetError::unsupported(
+                &location,
+                format!("png color type {color_type:?}"),
+            ));
+        }
+    };
+    Ok((pixels, (info.width, info.height)))
+}
@@ -0,0 +1,375 @@
+use crate::asset_manager::error::{AssetError, AssetLocation};
+use glam::{Vec2, Vec3};
+use image::codecs::hdr::HdrDecoder;
+use image::codecs::openexr::OpenExrDecoder;
+use image::{DynamicImage, ImageDecoder};
+use std::f32::consts::PI;
+use std::io::BufReader;
+use std::path::Path;
+
+/// how the six faces of a sky are stored in a single image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SkyLayout {
+    /// a 2:1 longitude-latitude panorama.
+    Equirectangular,
+    /// 4:3, the +y face above and the -y face below the row -x, +z, +x, -z.
+    HorizontalCross,
+    /// 3:4, the row -x, +z, +x between +y and -y, the -z face at the bottom upside down.
+    VerticalCross,
+}
+impl SkyLayout {
+    /// guessed from the aspect ratio, the crosses need square cells.
+    pub fn detect(width: u32, height: u32) -> Option<Self> {
+        if width == 2 * height {
+            Some(Self::Equirectangular)
+        } else if width.is_multiple_of(4) && width / 4 * 3 == height {
+            Some(Self::HorizontalCross)
+        } else if width.is_multiple_of(3) && width / 3 * 4 == height {
+            Some(Self::VerticalCross)
+        } else {
+            None
+        }
+    }
+}
+
+/// linear rgb radiance, rows from top to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Vec3>,
+}
+impl HdrImage {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Vec3::ZERO; width as usize * height as usize],
+        }
+    }
+    pub fn get(&self, x: u32, y: u32) -> Vec3 {
+        self.pixels[(y * self.width + x) as usize]
+    }
+    pub fn set(&mut self, x: u32, y: u32, color: Vec3) {
+        self.pixels[(y * self.width + x) as usize] = color;
+    }
+    /// bilinear lookup of a panorama, `u` wraps around the horizon and `v` is clamped at the
+    /// poles.
+    pub fn sample_equirectangular(&self, uv: Vec2) -> Vec3 {
+        let x = uv.x * self.width as f32 - 0.5;
+        let y = (uv.y * self.height as f32 - 0.5).clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let column = |x: f32| (x as i64).rem_euclid(self.width as i64) as u32;
+        let (x0, x1) = (column(x0), column(x0 + 1.0));
+        let (y0, y1) = (y0 as u32, (y0 as u32 + 1).min(self.height - 1));
+        let top = self.get(x0, y0).lerp(self.get(x1, y0), tx);
+        let bottom = self.get(x0, y1).lerp(self.get(x1, y1), tx);
+        top.lerp(bottom, ty)
+    }
+}
+
+/// six square faces in the layer order of a vulkan cube map: +x, -x, +y, -y, +z, -z.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HdrCubemap {
+    pub size: u32,
+    pub faces: Vec<HdrImage>,
+}
+impl HdrCubemap {
+    /// resamples a panorama, the top row is +y and the center column looks down +z.
+    pub fn from_equirectangular(image: &HdrImage, size: u32) -> Self {
+        let faces = (0..6)
+            .map(|face| {
+                let mut pixels = HdrImage::new(size, size);
+                for y in 0..size {
+                    for x in 0..size {
+                        let direction = face_direction(face, size, x, y);
+                        pixels.set(
+                            x,
+                            y,
+                            image.sample_equirectangular(equirectangular_uv(direction)),
+                        );
+                    }
+                }
+                pixels
+            })
+            .collect();
+        Self { size, faces }
+    }
+    /// cuts the faces out of a cross, `None` for panoramas or images that are not a cross.
+    pub fn from_cross(image: &HdrImage, layout: SkyLayout) -> Option<Self> {
+        if SkyLayout::detect(image.width, image.height) != Some(layout) {
+            return None;
+        }
+        // cell of every face, and whether it is stored upside down
+        let (size, cells) = match layout {
+            SkyLayout::Equirectangular => return None,
+            SkyLayout::HorizontalCross => (
+                image.width / 4,
+                [
+                    (2, 1, false),
+                    (0, 1, false),
+                    (1, 0, false),
+                    (1, 2, false),
+                    (1, 1, false),
+                    (3, 1, false),
+                ],
+            ),
+            SkyLayout::VerticalCross => (
+                image.width / 3,
+                [
+                    (2, 1, false),
+                    (0, 1, false),
+                    (1, 0, false),
+                    (1, 2, false),
+                    (1, 1, false),
+                    (1, 3, true),
+                ],
+            ),
+        };
+        let faces = cells
+            .into_iter()
+            .map(|(column, row, flipped)| {
+                let mut face = HdrImage::new(size, size);
+                for y in 0..size {
+                    for x in 0..size {
+                        let (fx, fy) = if flipped {
+                            (size - 1 - x, size - 1 - y)
+                        } else {
+                            (x, y)
+                        };
+                        face.set(x, y, image.get(column * size + fx, row * size + fy));
+                    }
+                }
+                face
+            })
+            .collect();
+        Some(Self { size, faces })
+    }
+    /// converts the `R8G8B8A8_SRGB` faces of `SkyBox::load` to linear radiance.
+    pub fn from_rgba8_srgb(pixels: &[u8], size: u32) -> Self {
+        let texels = (size * size) as usize;
+        let faces = pixels
+            .chunks_exact(4 * texels)
+            .take(6)
+            .map(|face| HdrImage {
+                width: size,
+                height: size,
+                pixels: face
+                    .chunks_exact(4)
+                    .map(|rgba| {
+                        Vec3::new(
+                            srgb_to_linear(rgba[0]),
+                            srgb_to_linear(rgba[1]),
+                            srgb_to_linear(rgba[2]),
+                        )
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self { size, faces }
+    }
+    /// every face filled with the same radiance.
+    pub fn uniform(size: u32, color: Vec3) -> Self {
+        let mut face = HdrImage::new(size, size);
+        face.pixels.fill(color);
+        Self {
+            size,
+            faces: vec![face; 6],
+        }
+    }
+    /// half the size, every texel the average of the four it covers. odd sizes drop the last
+    /// row and column.
+    pub fn downsample(&self) -> Self {
+        let size = (self.size / 2).max(1);
+        let faces = self
+            .faces
+            .iter()
+            .map(|face| {
+                let mut half = HdrImage::new(size, size);
+                for y in 0..size {
+                    for x in 0..size {
+                        let (x0, y0) = (2 * x, 2 * y);
+                        let (x1, y1) = ((x0 + 1).min(self.size - 1), (y0 + 1).min(self.size - 1));
+                        let sum = face.get(x0, y0)
+                            + face.get(x1, y0)
+                            + face.get(x0, y1)
+                            + face.get(x1, y1);
+                        half.set(x, y, sum / 4.0);
+                    }
+                }
+                half
+            })
+            .collect();
+        Self { size, faces }
+    }
+    /// downsamples until the faces are at most `max_size` wide.
+    pub fn downsample_to(&self, max_size: u32) -> Self {
+        let mut cubemap = self.clone();
+        while cubemap.size > max_size.max(1) {
+            cubemap = cubemap.downsample();
+        }
+        cubemap
+    }
+    /// bilinear lookup in the face `direction` points at, clamped at the face edges.
+    pub fn sample(&self, direction: Vec3) -> Vec3 {
+        let (face, uv) = face_uv(direction);
+        let face = &self.faces[face];
+        let max = (self.size - 1) as f32;
+        let x = (uv.x * self.size as f32 - 0.5).clamp(0.0, max);
+        let y = (uv.y * self.size as f32 - 0.5).clamp(0.0, max);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as u32, y0 as u32);
+        let (x1, y1) = ((x0 + 1).min(self.size - 1), (y0 + 1).min(self.size - 1));
+        let top = face.get(x0, y0).lerp(face.get(x1, y0), tx);
+        let bottom = face.get(x0, y1).lerp(face.get(x1, y1), tx);
+        top.lerp(bottom, ty)
+    }
+    /// the texels of all faces as `R16G16B16A16_SFLOAT`, ready for
+    /// `TextureData::create_cubemap_from_data`.
+    pub fn to_rgba16f(&self) -> Vec<u8> {
+        self.faces
+            .iter()
+            .flat_map(|face| &face.pixels)
+            .flat_map(|color| color.extend(1.0).to_array())
+            .flat_map(|channel| half::f16::from_f32(channel).to_le_bytes())
+            .collect()
+    }
+}
+
+/// the direction through the center of texel `x`, `y` of a cube face, following the face
+/// orientation of the vulkan spec. not normalized.
+pub fn face_direction(face: usize, size: u32, x: u32, y: u32) -> Vec3 {
+    let u = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+    let v = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+    match face {
+        0 => Vec3::new(1.0, -v, -u),
+        1 => Vec3::new(-1.0, -v, u),
+        2 => Vec3::new(u, 1.0, v),
+        3 => Vec3::new(u, -1.0, -v),
+        4 => Vec3::new(u, -v, 1.0),
+        _ => Vec3::new(-u, -v, -1.0),
+    }
+}
+
+/// the face a direction points at and the texture coordinates on it, the inverse of
+/// `face_direction`.
+pub fn face_uv(direction: Vec3) -> (usize, Vec2) {
+    let Vec3 { x, y, z } = direction;
+    let (face, sc, tc, major) = if x.abs() >= y.abs() && x.abs() >= z.abs() {
+        if x >= 0.0 {
+            (0, -z, -y, x)
+        } else {
+            (1, z, -y, -x)
+        }
+    } else if y.abs() >= z.abs() {
+        if y >= 0.0 {
+            (2, x, z, y)
+        } else {
+            (3, x, -z, -y)
+        }
+    } else if z >= 0.0 {
+        (4, x, -y, z)
+    } else {
+        (5, -x, -y, -z)
+    };
+    let major = major.max(f32::MIN_POSITIVE);
+    (
+        face,
+        Vec2::new(0.5 * (sc / major + 1.0), 0.5 * (tc / major + 1.0)),
+    )
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let c = value as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// where a direction lands in a panorama, `u` is the longitude and `v` the latitude.
+pub fn equirectangular_uv(direction: Vec3) -> Vec2 {
+    let direction = direction.normalize();
+    Vec2::new(
+        0.5 + direction.x.atan2(direction.z) / (2.0 * PI),
+        direction.y.clamp(-1.0, 1.0).acos() / PI,
+    )
+}
+
+/// decodes a radiance .hdr file.
+pub fn read_hdr(path: impl AsRef<Path>) -> Result<HdrImage, AssetError> {
+    let location = AssetLocation::new(&path);
+    let file = std::fs::File::open(&path).map_err(|e| AssetError::io(&location, e))?;
+    let decoder =
+        HdrDecoder::new(BufReader::new(file)).map_err(|e| AssetError::parse(&location, e))?;
+    let (width, height) = decoder.dimensions();
+    let mut bytes = vec![0; decoder.total_bytes() as usize];
+    decoder
+        .read_image(&mut bytes)
+        .map_err(|e| AssetError::parse(&location, e))?;
+    let pixels = bytes
+        .chunks_exact(12)
+        .map(|rgb| {
+            let channel = |i: usize| f32::from_ne_bytes(rgb[i..i + 4].try_into().unwrap());
+            Vec3::new(channel(0), channel(4), channel(8))
+        })
+        .collect();
+    Ok(HdrImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// decodes the color channels of an openexr file, alpha is dropped.
+pub fn read_exr(path: impl AsRef<Path>) -> Result<HdrImage, AssetError> {
+    let location = AssetLocation::new(&path);
+    let file = std::fs::File::open(&path).map_err(|e| AssetError::io(&location, e))?;
+    let decoder =
+        OpenExrDecoder::new(BufReader::new(file)).map_err(|e| AssetError::parse(&location, e))?;
+    let image = DynamicImage::from_decoder(decoder)
+        .map_err(|e| AssetError::parse(&location, e))?
+        .into_rgb32f();
+    Ok(HdrImage {
+        width: image.width(),
+        height: image.height(),
+        pixels: image.pixels().map(|rgb| Vec3::from(rgb.0)).collect(),
+    })
+}
+
+/// reads a .hdr or .exr sky in any `SkyLayout`. panoramas are projected to faces a quarter of
+/// their width wide.
+pub fn read_environment(path: impl AsRef<Path>) -> Result<HdrCubemap, AssetError> {
+    let location = AssetLocation::new(&path);
+    let image = if path
+        .as_ref()
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("exr"))
+    {
+        read_exr(&path)?
+    } else {
+        read_hdr(&path)?
+    };
+    match SkyLayout::detect(image.width, image.height) {
+        Some(SkyLayout::Equirectangular) => Ok(HdrCubemap::from_equirectangular(
+            &image,
+            (image.width / 4).max(1),
+        )),
+        Some(layout) => HdrCubemap::from_cross(&image, layout).ok_or_else(|| {
+            AssetError::invalid(
+                &location,
+                format!("{}x{} is not a cross", image.width, image.height),
+            )
+        }),
+        None => Err(AssetError::invalid(
+            &location,
+            format!(
+                "{}x{} is neither a 2:1 panorama nor a cross",
+                image.width, image.height
+            ),
+        )),
+    }
+}
@@ -1,2 +1,7 @@
+pub mod bevy_load;
+pub mod error;
+pub mod hot_reload;
 pub mod load;
+pub mod load_obj;
 pub mod load_skybox;
+pub mod registry;
@@ -0,0 +1,262 @@
+#![allow(unsafe_op_in_unsafe_fn, clippy::missing_safety_doc)]
+use crate::asset_manager::error::{AssetError, AssetLocation};
+use crate::game_objects::render_object::RenderObject;
+use crate::vulkan::image_util::TextureData;
+use crate::vulkan::vertexbuffer_util::{VertexData, VertexPbr};
+use crate::winit_app::winit_render_app::AppData;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use vulkanalia::vk::{self, DeviceV1_0};
+use vulkanalia::{Device, Instance};
+
+/// identifies a texture upload. srgb and linear uploads of the same image are different
+/// textures, since the image format differs.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TextureKey {
+    /// image `index` of the file at `path`, always 0 for single image files.
+    Image {
+        path: PathBuf,
+        index: usize,
+        linear: bool,
+    },
+    /// 1x1 texture used for empty material slots.
+    Solid { color: [u8; 4], linear: bool },
+}
+impl TextureKey {
+    pub fn image(path: impl AsRef<Path>, index: usize, linear: bool) -> Self {
+        Self::Image {
+            path: source(path),
+            index,
+            linear,
+        }
+    }
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::Image { path, .. } => Some(path),
+            Self::Solid { .. } => None,
+        }
+    }
+}
+
+/// identifies the vertex and index buffers of primitive `primitive` of mesh `mesh`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MeshKey {
+    pub path: PathBuf,
+    pub mesh: usize,
+    pub primitive: usize,
+}
+impl MeshKey {
+    pub fn new(path: impl AsRef<Path>, mesh: usize, primitive: usize) -> Self {
+        Self {
+            path: source(path),
+            mesh,
+            primitive,
+        }
+    }
+}
+
+/// the same file reached through different relative paths maps to one key.
+pub fn source(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// reference counted values by key. the cache holds one reference itself, so an entry is
+/// unused once its strong count drops to 1.
+#[derive(Debug)]
+pub struct Cache<K, T> {
+    entries: HashMap<K, Arc<T>>,
+}
+impl<K, T> Default for Cache<K, T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+impl<K, T> Cache<K, T>
+where
+    K: Clone + Eq + Hash,
+{
+    /// the cached value for `key`, `create` is only called on a miss.
+    pub fn get_or_try_insert<E>(
+        &mut self,
+        key: K,
+        create: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Arc<T>, E> {
+        if let Some(value) = self.entries.get(&key) {
+            return Ok(value.clone());
+        }
+        let value = Arc::new(create()?);
+        self.entries.insert(key, value.clone());
+        Ok(value)
+    }
+    pub fn get(&self, key: &K) -> Option<&Arc<T>> {
+        self.entries.get(key)
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// removes and returns the entries nobody but the cache refers to.
+    pub fn take_unused(&mut self) -> Vec<Arc<T>> {
+        self.take_where(|_, value| Arc::strong_count(value) == 1)
+    }
+    /// removes and returns the entries `remove` returns true for.
+    pub fn take_where(&mut self, mut remove: impl FnMut(&K, &Arc<T>) -> bool) -> Vec<Arc<T>> {
+        let keys: Vec<K> = self
+            .entries
+            .iter()
+            .filter(|(key, value)| remove(key, value))
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.into_iter()
+            .filter_map(|key| self.entries.remove(&key))
+            .collect()
+    }
+    /// removes every entry, whether it is still referenced or not.
+    pub fn take_all(&mut self) -> Vec<Arc<T>> {
+        self.entries.drain().map(|(_, value)| value).collect()
+    }
+}
+
+/// gpu resources that were replaced while a frame in flight may still read them.
+#[derive(Debug)]
+pub enum Garbage {
+    RenderObject(Box<RenderObject<VertexPbr>>),
+    Texture(Arc<TextureData>),
+    Mesh(Arc<VertexData<VertexPbr>>),
+}
+
+/// shared gpu textures and vertex buffers of every loaded asset, loading a file twice or
+/// reusing an image across primitives uploads it once.
+#[derive(Debug, Default)]
+pub struct AssetRegistry {
+    pub textures: Cache<TextureKey, TextureData>,
+    pub meshes: Cache<MeshKey, VertexData<VertexPbr>>,
+    /// replaced resources and the number of frames left until they may be destroyed.
+    pub garbage: Vec<(usize, Garbage)>,
+}
+impl AssetRegistry {
+    /// the texture for `key`, `pixels` is only decoded and uploaded on a miss.
+    pub unsafe fn texture(
+        &mut self,
+        instance: &Instance,
+        device: &Device,
+        data: &mut AppData,
+        key: TextureKey,
+        pixels: impl FnOnce() -> Result<(Vec<u8>, (u32, u32)), AssetError>,
+        location: &AssetLocation,
+    ) -> Result<Arc<TextureData>, AssetError> {
+        let linear = match &key {
+            TextureKey::Image { linear, .. } | TextureKey::Solid { linear, .. } => *linear,
+        };
+        self.textures.get_or_try_insert(key, || {
+            let (pixels, size) = pixels()?;
+            let texture = if linear {
+                TextureData::create_linear_texture_from_data(instance, device, data, pixels, size)
+            } else {
+                TextureData::create_texture_from_data(instance, device, data, pixels, size)
+            };
+            texture.map_err(|e| AssetError::upload(location, e))
+        })
+    }
+    /// the vertex and index buffers for `key`, `vertices` is only read and uploaded on a miss.
+    pub unsafe fn mesh(
+        &mut self,
+        instance: &Instance,
+        device: &Device,
+        data: &mut AppData,
+        key: MeshKey,
+        vertices: impl FnOnce() -> Result<(Vec<VertexPbr>, Vec<u32>), AssetError>,
+        location: &AssetLocation,
+    ) -> Result<Arc<VertexData<VertexPbr>>, AssetError> {
+        self.meshes.get_or_try_insert(key, || {
+            let (vertices, indices) = vertices()?;
+            VertexData::create_vertex_data(instance, device, data, vertices, indices, false)
+                .map_err(|e| AssetError::upload(location, e))
+        })
+    }
+    /// removes every texture and mesh read from `path` from the caches, so that the next load
+    /// uploads them again. the old ones are destroyed once no render object uses them anymore
+    /// and `frames` frames have passed.
+    pub fn forget(&mut self, path: &Path, frames: usize) {
+        let path = source(path);
+        for texture in self
+            .textures
+            .take_where(|key, _| key.path() == Some(path.as_path()))
+        {
+            self.retire(Garbage::Texture(texture), frames);
+        }
+        for mesh in self.meshes.take_where(|key, _| key.path == path) {
+            self.retire(Garbage::Mesh(mesh), frames);
+        }
+    }
+    /// destroys `garbage` after `frames` calls to `collect_garbage`.
+    pub fn retire(&mut self, garbage: Garbage, frames: usize) {
+        self.garbage.push((frames, garbage));
+    }
+    /// call once per frame after waiting for its fence. render objects go first, they hold
+    /// the last references to retired textures and meshes.
+    pub unsafe fn collect_garbage(&mut self, device: &Device, descriptor_pool: vk::DescriptorPool) {
+        for (frames, _) in &mut self.garbage {
+            *frames = frames.saturating_sub(1);
+        }
+        self.garbage.retain(|(frames, garbage)| match garbage {
+            Garbage::RenderObject(object) if *frames == 0 => {
+                let _ = device.free_descriptor_sets(descriptor_pool, &object.descriptor_sets);
+                object.destroy(device);
+                false
+            }
+            _ => true,
+        });
+        self.garbage.retain(|(frames, garbage)| match garbage {
+            Garbage::Texture(texture) if *frames == 0 && Arc::strong_count(texture) == 1 => {
+                texture.destroy_image(device);
+                false
+            }
+            Garbage::Mesh(mesh) if *frames == 0 && Arc::strong_count(mesh) == 1 => {
+                mesh.destroy(device);
+                false
+            }
+            _ => true,
+        });
+    }
+    /// destroys the garbage without waiting for frames, the device must be idle.
+    pub unsafe fn destroy_garbage(&mut self, device: &Device, descriptor_pool: vk::DescriptorPool) {
+        for (frames, _) in &mut self.garbage {
+            *frames = 1;
+        }
+        self.collect_garbage(device, descriptor_pool);
+    }
+    /// destroys textures and buffers that no render object uses anymore. the caller must make
+    /// sure no frame in flight still reads them.
+    pub unsafe fn release_unused(&mut self, device: &Device) {
+        for texture in self.textures.take_unused() {
+            texture.destroy_image(device);
+        }
+        for mesh in self.meshes.take_unused() {
+            mesh.destroy(device);
+        }
+    }
+    /// destroys everything, used on shutdown once the device is idle.
+    pub unsafe fn destroy(&mut self, device: &Device) {
+        for (_, garbage) in self.garbage.drain(..) {
+            match garbage {
+                Garbage::RenderObject(object) => object.destroy(device),
+                Garbage::Texture(texture) => texture.destroy_image(device),
+                Garbage::Mesh(mesh) => mesh.destroy(device),
+            }
+        }
+        for texture in self.textures.take_all() {
+            texture.destroy_image(device);
+        }
+        for mesh in self.meshes.take_all() {
+            mesh.destroy(device);
+        }
+    }
+}
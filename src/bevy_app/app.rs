@@ -1,6 +1,7 @@
 #![allow(unsafe_op_in_unsafe_fn, clippy::missing_safety_doc)]
 
 use crate::{
+    asset_manager::{bevy_load::upload_read_assets, hot_reload::queue_changed_files},
     bevy_app::render::{VulkanApp, create_vulkan_resources, destroy, render},
     game_objects::scene::Scene,
     gui::gui::{Gui, create_gui_from_window},
@@ -35,7 +36,7 @@ use bevy::{
         message::{Message, MessageReader, MessageWriter},
         query::With,
         resource::Resource,
-        system::{Commands, NonSendMut, Query, Res, ResMut},
+        system::{Commands, NonSendMarker, NonSendMut, Query, Res, ResMut},
     },
     image::ImagePlugin,
     input::InputPlugin,
@@ -93,7 +94,11 @@ impl Plugin for VulkanDefault {
         app.add_systems(Startup, (create_vulkan_resources, create_gui_from_window));
         app.add_systems(
             Update,
-            (process_raw_winit_events, redraw /*destroy_renderer*/),
+            (
+                process_raw_winit_events,
+                upload_loaded_assets,
+                redraw, /*destroy_renderer*/
+            ),
         );
         //app.add_systems(Update, update_camera_and_gui);
         app.add_systems(
@@ -154,12 +159,25 @@ pub fn process_raw_winit_events(
                 input_state.read_event(&event.event);
             }
             gui.set_enabled(&mut input_state);
+            gui.pick_object(&mut scene, &data, &mut input_state);
             scene.update(time.delta_secs(), &input_state);
             input_state.reset_mouse_delta();
         }
     });
 }
 
+/// moves the assets read by the task pool onto the gpu, pinned to the main thread like the
+/// rest of the vulkan work. changed files are queued for a reload first.
+pub fn upload_loaded_assets(
+    app: Res<VulkanApp>,
+    mut data: ResMut<AppData>,
+    mut scene: ResMut<Scene>,
+    _main_thread: NonSendMarker,
+) {
+    queue_changed_files(&mut scene);
+    upload_read_assets(&app.instance, &app.device, &mut data, &mut scene);
+}
+
 pub fn destroy_renderer(
     gui: NonSendMut<Gui>,
     mut app: ResMut<VulkanApp>,
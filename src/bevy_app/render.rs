@@ -1,7 +1,9 @@
 #![allow(unsafe_op_in_unsafe_fn, clippy::missing_safety_doc)]
 use crate::winit_app::winit_render_app::{self, AppData, FrameInfo};
 use crate::{
-    game_objects::scene::Scene,
+    game_objects::{
+        bounds::Frustum, environment::update_environment, light::update_lights, scene::Scene,
+    },
     gui::gui::{Gui, create_gui_descriptor_sets},
     vulkan::{
         MAX_FRAMES_IN_FLIGHT, VALIDATION_ENABLED,
@@ -16,8 +18,10 @@ use crate::{
         device_util::{create_logical_device, pick_physical_device},
         framebuffer_util::{create_depth_objects, create_framebuffers},
         instance_util::create_instance,
+        light_clusters::{ClusterGrid, assign_lights, update_clusters},
         pipeline_util::{create_pbr_pipeline, gui_pipeline, skybox_pipeline},
         render_pass_util::create_render_pass,
+        shadow_map::{ShadowMap, shadow_map_pipelines, update_cascades},
         swapchain_util::{create_swapchain, create_swapchain_image_views},
         sync_util::create_sync_objects,
         uniform_buffer_object::{
@@ -40,7 +44,6 @@ use bevy::{
 use std::intrinsics::copy_nonoverlapping as memcpy;
 
 use egui::FullOutput;
-use glam::Mat4;
 use tracing::{error, info};
 use vulkanalia::{
     Device, Entry, Instance,
@@ -101,6 +104,8 @@ pub fn create_vulkan_resources(
                 gui_pipeline(&device, &mut data, 0).unwrap();
                 create_pbr_pipeline(&device, &mut data, 1).unwrap();
                 skybox_pipeline(&device, &mut data, 2).unwrap();
+                data.shadow_map = ShadowMap::create(&instance, &device, &data).unwrap();
+                shadow_map_pipelines(&device, &mut data).unwrap();
                 create_color_objects(&instance, &device, &mut data).unwrap();
                 create_depth_objects(&instance, &device, &mut data).unwrap();
                 create_framebuffers(&device, &mut data).unwrap();
@@ -108,10 +113,11 @@ pub fn create_vulkan_resources(
                 create_transient_command_pool(&instance, &device, &mut data).unwrap();
                 create_descriptor_pool(&device, &mut data, 30).unwrap();
 
-                create_command_buffers(&device, &mut scene, &mut data, window, None).unwrap();
+                create_command_buffers(&device, &mut scene, &mut data, None).unwrap();
                 create_sync_objects(&device, &mut data).unwrap();
 
                 create_global_buffers(&instance, &device, &mut data, &mut scene).unwrap();
+                update_environment(&instance, &device, &mut data, &mut scene).unwrap();
 
                 commands.insert_resource(scene);
                 commands.insert_resource(data);
@@ -140,6 +146,8 @@ pub fn recreate_swapchain(
         println!("here");
         device.device_wait_idle().unwrap();
         println!("device");
+        // retired objects hold descriptor sets of the pool that is about to be destroyed
+        scene.assets.destroy_garbage(device, data.descriptor_pool);
         destroy_swapchain(data, device, scene);
         println!("after destroy swap");
         if let Err(e) = create_swapchain(window, instance, device, data) {
@@ -152,6 +160,7 @@ pub fn recreate_swapchain(
         create_descriptor_pool(&device, data, 30).unwrap();
         skybox_pipeline(&device, data, 2).unwrap();
         create_pbr_pipeline(&device, data, 1).unwrap();
+        shadow_map_pipelines(device, data).unwrap();
         gui_pipeline(&device, data, 0).unwrap();
         println!("beree");
         create_color_objects(&instance, &device, data).unwrap();
@@ -184,7 +193,7 @@ pub fn recreate_swapchain(
             }
         }
 
-        create_command_buffers(&device, scene, data, window, Some(gui)).unwrap();
+        create_command_buffers(&device, scene, data, Some(gui)).unwrap();
         data.recreated = true;
         info!("recreated swapchain");
     }
@@ -192,6 +201,7 @@ pub fn recreate_swapchain(
 
 pub fn update_uniform_buffer(
     image_index: usize,
+    instance: &Instance,
     device: &Device,
     scene: &mut Scene,
     data: &mut AppData,
@@ -206,18 +216,23 @@ pub fn update_uniform_buffer(
     data.pbr_push_contant = PbrPushConstant {
         proj_inv_view: (view.inverse()),
     };
-    for (_i, object) in scene.render_objects.iter() {
-        let mut model = [Mat4::default(); 10];
-        for (index, instance_index) in object.instances.iter().enumerate() {
-            let instance = scene.objects.get(*instance_index).unwrap();
-            model[index] = instance.global_matrix(&scene);
-        }
-        let ubo = PbrUniform {
-            model,
-            base: object.pbr.base,
-        };
-        unsafe { ubo.map_memory(device, object.uniform_buffers_memory[image_index]) }.unwrap();
+    let frustum = Frustum::new(&scene.camera, proj);
+    scene
+        .update_render_objects(instance, device, data, image_index, &frustum)
+        .unwrap();
+    let lights = scene.lights();
+    unsafe { update_lights(device, data.light_buffer_memory[image_index], &lights) }.unwrap();
+    let grid = ClusterGrid::new(&scene.camera, proj);
+    let clusters = assign_lights(&grid, &lights);
+    unsafe {
+        update_clusters(
+            device,
+            data.cluster_buffer_memory[image_index],
+            &grid,
+            &clusters,
+        )
     }
+    .unwrap();
     if scene.skybox.is_some() {
         let scale = window.scale_factor() as f32;
         let ubo = GlobalUniform {
@@ -228,6 +243,8 @@ pub fn update_uniform_buffer(
         };
         unsafe { ubo.map_memory(&device, data.global_buffer_memory[image_index]) }.unwrap();
     }
+    let aspect = scene.camera.aspect(data, gui);
+    update_cascades(&mut scene.sun, &scene.camera, aspect);
     let memory = unsafe {
         device.map_memory(
             scene.sun.memory[image_index],
@@ -253,6 +270,14 @@ pub unsafe fn render(
     app.device
         .wait_for_fences(&[data.in_flight_fences[app.frame]], true, u64::MAX)
         .unwrap();
+    scene
+        .assets
+        .collect_garbage(&app.device, data.descriptor_pool);
+    // textures and meshes of the render objects destroyed above
+    scene.assets.release_unused(&app.device);
+    if let Err(e) = update_environment(&app.instance, &app.device, data, scene) {
+        error!("could not update the environment maps: {e:?}");
+    }
 
     let result = app.device.acquire_next_image_khr(
         data.swapchain,
@@ -313,11 +338,19 @@ pub unsafe fn render(
         .unwrap();
         gui.needs_redraw = false;
     }
-    update_uniform_buffer(image_index, &app.device, scene, data, window, gui);
+    update_uniform_buffer(
+        image_index,
+        &app.instance,
+        &app.device,
+        scene,
+        data,
+        window,
+        gui,
+    );
 
     let wait_semaphores = &[data.image_available_semaphores[app.frame]];
     let wait_stages = &[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
-    create_command_buffer(&app.device, scene, data, window, Some(gui), image_index).unwrap();
+    create_command_buffer(&app.device, scene, data, Some(gui), image_index).unwrap();
     let command_buffers = &[data.command_centers[image_index].command_buffers[0]];
     let signal_semaphores = &[data.render_finished_semaphores[app.frame]];
     let submit_info = vk::SubmitInfo::builder()
@@ -372,6 +405,9 @@ pub(crate) unsafe fn destroy(
 ) {
     unsafe {
         app.device.device_wait_idle().unwrap();
+        scene
+            .assets
+            .destroy_garbage(&app.device, data.descriptor_pool);
         destroy_swapchain(data, &app.device, scene);
         app.device
             .destroy_descriptor_set_layout(data.gui_descriptor_layout, None);
@@ -384,15 +420,13 @@ pub(crate) unsafe fn destroy(
             app.device.free_memory(data.image_memory, None);
         }
         for (_i, object) in scene.render_objects.iter() {
-            app.device
-                .destroy_sampler(object.pbr.texture_data.sampler, None);
-            app.device
-                .destroy_image_view(object.pbr.texture_data.image_view, None);
-
-            app.device
-                .destroy_image(object.pbr.texture_data.image, None);
-            app.device
-                .free_memory(object.pbr.texture_data.image_memory, None);
+            if let Some(skin) = &object.skin {
+                skin.destroy(&app.device);
+            }
+            if let Some(morph) = &object.morph {
+                morph.destroy(&app.device);
+            }
+            object.instance_buffers.destroy(&app.device);
         }
 
         app.device
@@ -430,19 +464,12 @@ pub(crate) unsafe fn destroy(
                 }
             }
         }
-        for (_i, object) in scene.render_objects.iter() {
-            app.device
-                .free_memory(object.vertex_data.vertex_buffer_memory, None);
-            app.device
-                .destroy_buffer(object.vertex_data.vertex_buffer, None);
-            app.device
-                .free_memory(object.vertex_data.index_buffer_memory, None);
-            app.device
-                .destroy_buffer(object.vertex_data.index_buffer, None);
-        }
+        scene.assets.destroy(&app.device);
         if let Some(skybox) = &scene.skybox {
             skybox.texture_data.destroy_image(&app.device)
         }
+        data.environment.destroy(&app.device);
+        data.shadow_map.destroy(&app.device);
         for center in &data.command_centers {
             app.device.destroy_command_pool(center.command_pool, None);
         }
@@ -485,6 +512,18 @@ fn destroy_swapchain(data: &mut AppData, device: &Device, scene: &mut Scene) {
         data.global_buffer_memory
             .iter()
             .for_each(|m| device.free_memory(*m, None));
+        data.light_buffer
+            .iter()
+            .for_each(|b| device.destroy_buffer(*b, None));
+        data.light_buffer_memory
+            .iter()
+            .for_each(|m| device.free_memory(*m, None));
+        data.cluster_buffer
+            .iter()
+            .for_each(|b| device.destroy_buffer(*b, None));
+        data.cluster_buffer_memory
+            .iter()
+            .for_each(|m| device.free_memory(*m, None));
         scene
             .sun
             .buffer
@@ -511,6 +550,9 @@ fn destroy_swapchain(data: &mut AppData, device: &Device, scene: &mut Scene) {
             device.free_command_buffers(center.command_pool, &center.command_buffers);
         }
         device.destroy_pipeline(data.pbr_pipeline, None);
+        device.destroy_pipeline(data.skinned_pbr_pipeline, None);
+        device.destroy_pipeline(data.morph_pbr_pipeline, None);
+        data.shadow_map.destroy_pipelines(device);
         device.destroy_pipeline_layout(data.pbr_pipeline_layout, None);
         device.destroy_pipeline(data.skybox_pipeline, None);
         device.destroy_pipeline_layout(data.skybox_pipeline_layout, None);
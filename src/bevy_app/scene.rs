@@ -1,9 +1,23 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
 use bevy::math::Vec4;
+use glam::Vec3;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::{
-    game_objects::material::{Material, MaterialInstance},
-    vulkan::vertexbuffer_util::Vertex,
+    asset_manager::{bevy_load::AssetBinding, registry},
+    game_objects::{
+        camera::Camera,
+        light::Light,
+        material::{Material, MaterialInstance},
+        render_object::{MaterialOverride, ObjectId},
+        scene::{self, GameObject, Sun},
+        skybox::SkyBoxSource,
+        transform::Transform,
+    },
+    vulkan::{shadow_map::CascadeSettings, vertexbuffer_util::Vertex},
 };
 pub struct UnstagedPbrData {
     base_image: Option<Vec<u8>>,
@@ -15,6 +29,321 @@ pub struct UnstagedRenderObject<V: Vertex, M: MaterialInstance> {
     material: M,
 }
 
-//scene should be used to save and load scenes,
-#[derive(Serialize, Deserialize)]
-pub struct Scene {}
+/// a scene as saved to disk. objects refer to each other by their index in `objects`, render
+/// objects by the file, node and primitive they were loaded from.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scene {
+    pub camera: Camera,
+    pub sun: SavedSun,
+    pub skybox: Option<SkyBoxSource>,
+    pub objects: Vec<SavedObject>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedSun {
+    /// points towards the light.
+    pub direction: Vec3,
+    pub color: glam::Vec4,
+    pub cascades: CascadeSettings,
+}
+impl Default for SavedSun {
+    fn default() -> Self {
+        let sun = Sun::default();
+        Self {
+            direction: sun.omnidirectional_light.direction.truncate(),
+            color: sun.omnidirectional_light.color,
+            cascades: sun.cascades,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedObject {
+    pub name: String,
+    pub transform: Transform,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    /// the node of a file the object was made from, skins and animations of the file target it.
+    pub node: Option<NodeRef>,
+    /// the primitive every render object of the object was loaded from.
+    pub primitives: Vec<PrimitiveRef>,
+    pub light: Option<Light>,
+    pub material_override: MaterialOverride,
+    pub morph_weights: Vec<f32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeRef {
+    pub path: PathBuf,
+    pub node: usize,
+    /// tells apart objects made from the same node, like the copies of a `Prefab`.
+    #[serde(default)]
+    pub copy: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PrimitiveRef {
+    pub path: PathBuf,
+    pub node: usize,
+    pub primitive: usize,
+}
+
+#[derive(Debug, Error)]
+pub enum SceneError {
+    #[error("{}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("could not write the scene: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("could not parse the scene: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("object {object} links to object {link}, which does not link back")]
+    Link { object: usize, link: usize },
+    #[error("object {object} is its own ancestor")]
+    Cycle { object: usize },
+}
+
+impl Scene {
+    /// the saved form of `scene`. render objects that were not loaded from a file are left out.
+    pub fn capture(scene: &scene::Scene) -> Self {
+        let indices: HashMap<ObjectId, usize> = scene
+            .objects
+            .iter()
+            .enumerate()
+            .map(|(index, (id, _))| (id, index))
+            .collect();
+        let objects = scene
+            .objects
+            .iter()
+            .map(|(id, object)| SavedObject {
+                name: object.name.clone(),
+                transform: object.transform.clone(),
+                parent: object
+                    .parent
+                    .and_then(|parent| indices.get(&parent).copied()),
+                children: object
+                    .children
+                    .iter()
+                    .filter_map(|child| indices.get(child).copied())
+                    .collect(),
+                node: scene
+                    .hot_reload
+                    .node_source(id)
+                    .map(|(path, copy, node)| NodeRef {
+                        path: path.to_path_buf(),
+                        node,
+                        copy,
+                    }),
+                primitives: object
+                    .render_objects
+                    .iter()
+                    .filter_map(|render_id| scene.hot_reload.primitive_source(*render_id))
+                    .map(|(path, node, primitive)| PrimitiveRef {
+                        path: path.to_path_buf(),
+                        node,
+                        primitive,
+                    })
+                    .collect(),
+                light: object.light.clone(),
+                material_override: object.material_override,
+                morph_weights: object.morph_weights.clone(),
+            })
+            .collect();
+        let light = &scene.sun.omnidirectional_light;
+        Self {
+            camera: scene.camera.clone(),
+            sun: SavedSun {
+                direction: light.direction.truncate(),
+                color: light.color,
+                cascades: scene.sun.cascades.clone(),
+            },
+            skybox: scene.skybox.as_ref().map(|skybox| skybox.source.clone()),
+            objects,
+        }
+    }
+
+    pub fn to_ron(&self) -> Result<String, SceneError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, SceneError> {
+        Ok(ron::from_str(text)?)
+    }
+
+    /// writes the scene to `path`, files inside of its directory are saved relative to it.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        let path = path.as_ref();
+        let directory = directory(path);
+        let mut scene = self.clone();
+        scene.map_paths(|file| file.strip_prefix(&directory).unwrap_or(file).to_path_buf());
+        std::fs::write(path, scene.to_ron()?).map_err(|source| SceneError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// reads a scene written by `save`, relative paths are resolved against its directory.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|source| SceneError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut scene = Self::from_ron(&text)?;
+        let directory = directory(path);
+        scene.map_paths(|file| directory.join(file));
+        Ok(scene)
+    }
+
+    /// every file path of the scene replaced by `map`.
+    pub fn map_paths(&mut self, map: impl Fn(&Path) -> PathBuf) {
+        for object in &mut self.objects {
+            if let Some(node) = &mut object.node {
+                node.path = map(&node.path);
+            }
+            for primitive in &mut object.primitives {
+                primitive.path = map(&primitive.path);
+            }
+        }
+        if let Some(skybox) = &mut self.skybox {
+            for path in skybox.paths_mut() {
+                *path = map(path);
+            }
+        }
+    }
+
+    /// checks that parents and children link to each other and that no object is its own
+    /// ancestor.
+    pub fn validate(&self) -> Result<(), SceneError> {
+        for (index, object) in self.objects.iter().enumerate() {
+            if let Some(parent) = object.parent
+                && !self
+                    .objects
+                    .get(parent)
+                    .is_some_and(|parent| parent.children.contains(&index))
+            {
+                return Err(SceneError::Link {
+                    object: index,
+                    link: parent,
+                });
+            }
+            for child in &object.children {
+                if self
+                    .objects
+                    .get(*child)
+                    .is_none_or(|child| child.parent != Some(index))
+                {
+                    return Err(SceneError::Link {
+                        object: index,
+                        link: *child,
+                    });
+                }
+            }
+        }
+        for index in 0..self.objects.len() {
+            let mut ancestor = self.objects[index].parent;
+            for _ in 0..self.objects.len() {
+                match ancestor {
+                    Some(parent) if parent == index => {
+                        return Err(SceneError::Cycle { object: index });
+                    }
+                    Some(parent) => ancestor = self.objects[parent].parent,
+                    None => break,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// the nodes and primitives to attach to the objects `ids` of `objects` once their files
+    /// are read, by file in the order the files first appear.
+    pub fn bindings(&self, ids: &[ObjectId]) -> Vec<(PathBuf, Vec<AssetBinding>)> {
+        let mut files: Vec<(PathBuf, Vec<AssetBinding>)> = vec![];
+        let mut bind = |path: &Path, binding: AssetBinding| match files
+            .iter_mut()
+            .find(|(file, _)| file == path)
+        {
+            Some((_, bindings)) => bindings.push(binding),
+            None => files.push((path.to_path_buf(), vec![binding])),
+        };
+        for (object, id) in self.objects.iter().zip(ids) {
+            if let Some(node) = &object.node {
+                bind(
+                    &node.path,
+                    AssetBinding {
+                        object: *id,
+                        node: node.node,
+                        primitive: None,
+                        copy: node.copy,
+                    },
+                );
+            }
+            for primitive in &object.primitives {
+                bind(
+                    &primitive.path,
+                    AssetBinding {
+                        object: *id,
+                        node: primitive.node,
+                        primitive: Some(primitive.primitive),
+                        copy: 0,
+                    },
+                );
+            }
+        }
+        files
+    }
+
+    /// replaces the objects, camera and sun of `scene` with the saved ones and starts reading
+    /// the files of their render objects, see `AssetLoads::attach`. the skybox needs the device
+    /// and is left to the caller, see `SkyBoxSource::load`. returns the ids of `objects`.
+    pub fn restore(&self, scene: &mut scene::Scene) -> Result<Vec<ObjectId>, SceneError> {
+        self.validate()?;
+        for id in scene.objects.ids() {
+            scene.remove_instance(id);
+        }
+        let ids: Vec<ObjectId> = self
+            .objects
+            .iter()
+            .map(|saved| {
+                let mut object = GameObject::new(&saved.name, saved.transform.clone(), vec![]);
+                object.light = saved.light.clone();
+                object.material_override = saved.material_override;
+                object.morph_weights = saved.morph_weights.clone();
+                scene.objects.insert(object)
+            })
+            .collect();
+        for (saved, id) in self.objects.iter().zip(&ids) {
+            if let Some(object) = scene.objects.get_mut(*id) {
+                object.parent = saved.parent.map(|parent| ids[parent]);
+                object.children = saved.children.iter().map(|child| ids[*child]).collect();
+            }
+            scene.mark_moved(*id);
+        }
+        scene.camera = self.camera.clone();
+        scene.sun.omnidirectional_light.direction = self.sun.direction.extend(0.0);
+        scene.sun.omnidirectional_light.color = self.sun.color;
+        scene.sun.cascades = self.sun.cascades.clone();
+        scene.selected_object = ids.first().copied();
+        for (path, bindings) in self.bindings(&ids) {
+            scene.loads.attach(path, bindings);
+        }
+        scene.refit_bvh();
+        Ok(ids)
+    }
+}
+
+/// the directory of a scene file, relative file names are in the working directory.
+fn directory(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => registry::source(parent),
+        _ => registry::source("."),
+    }
+}
@@ -1,10 +1,9 @@
 use std::f32::consts::PI;
 
 use VulcanEngine_0::{
-    asset_manager,
     bevy_app::{self, render::VulkanApp},
     game_objects::{scene::Scene, skybox::SkyBox, transform::Transform},
-    vulkan::winit_render_app::AppData,
+    winit_app::winit_render_app::AppData,
 };
 use bevy::{
     app::{App, PostStartup},
@@ -29,26 +28,15 @@ fn add_objects(mut app: ResMut<VulkanApp>, mut data: ResMut<AppData>, mut scene:
         "assets/LittleMan.glb",
         "assets/PlatformerCharacter.glb",
     ];
-    let guy = asset_manager::load::scene(
-        &app.instance,
-        &app.device,
-        &mut data,
-        &mut scene,
+    // both models are read on worker threads and show up once uploaded
+    scene.loads.load(
         "assets/PlatformerCharacter.glb",
-    )
-    .unwrap();
-    for g in guy {
-        scene
-            .transform_object(
-                g,
-                Transform {
-                    position: Vec3::new(7.0, -2.0, 6.0),
-                    scale: 4.0 * Vec3::ONE,
-                    rotation: Quat::from_rotation_y(-PI / 2.0),
-                },
-            )
-            .unwrap();
-    }
+        Some(Transform {
+            position: Vec3::new(7.0, -2.0, 6.0),
+            scale: 4.0 * Vec3::ONE,
+            rotation: Quat::from_rotation_y(-PI / 2.0),
+        }),
+    );
     /*let ashtray = app.add_object("assets/living_room/Chair.glb").unwrap();
     for a in ashtray {
         app.scene
@@ -62,25 +50,14 @@ fn add_objects(mut app: ResMut<VulkanApp>, mut data: ResMut<AppData>, mut scene:
             )
             .unwrap();
     }*/
-    let man = asset_manager::load::scene(
-        &app.instance,
-        &app.device,
-        &mut data,
-        &mut scene,
+    scene.loads.load(
         "assets/LittleMan.glb",
-    )
-    .unwrap();
-
-    scene
-        .transform_object(
-            man[0],
-            Transform {
-                position: Vec3::new(-0.0, -2.0, 8.0),
-                scale: 8.0 * Vec3::ONE,
-                rotation: Quat::default(),
-            },
-        )
-        .unwrap();
+        Some(Transform {
+            position: Vec3::new(-0.0, -2.0, 8.0),
+            scale: 8.0 * Vec3::ONE,
+            rotation: Quat::default(),
+        }),
+    );
     //let building = app.add_object("assets/city_building.glb").unwrap();
 
     scene.skybox = Some(
@@ -27,7 +27,7 @@ fn main() -> Result<(), OneOf<(OsError, anyhow::Error, EventLoopError, ErrorCode
                 "assets/PlatformerCharacter.glb",
             ];
 
-            let guy = app.add_object("assets/PlatformerCharacter.glb").unwrap();
+            let guy = app.add_object("assets/PlatformerCharacter.glb");
             for g in guy {
                 app.scene
                     .transform_object(
@@ -40,7 +40,7 @@ fn main() -> Result<(), OneOf<(OsError, anyhow::Error, EventLoopError, ErrorCode
                     )
                     .unwrap();
             }
-            let ashtray = app.add_object("assets/living_room/Chair.glb").unwrap();
+            let ashtray = app.add_object("assets/living_room/Chair.glb");
             for a in ashtray {
                 app.scene
                     .transform_object(
@@ -53,19 +53,19 @@ fn main() -> Result<(), OneOf<(OsError, anyhow::Error, EventLoopError, ErrorCode
                     )
                     .unwrap();
             }
-            let man = app.add_object("assets/LittleMan.glb").unwrap();
-            let man = man.iter().next().unwrap();
-
-            app.scene
-                .transform_object(
-                    *man,
-                    Transform {
-                        position: Vec3::new(-0.0, -2.0, 8.0),
-                        scale: 8.0 * Vec3::ONE,
-                        rotation: Quat::default(),
-                    },
-                )
-                .unwrap();
+            let man = app.add_object("assets/LittleMan.glb");
+            if let Some(man) = man.first() {
+                app.scene
+                    .transform_object(
+                        *man,
+                        Transform {
+                            position: Vec3::new(-0.0, -2.0, 8.0),
+                            scale: 8.0 * Vec3::ONE,
+                            rotation: Quat::default(),
+                        },
+                    )
+                    .unwrap();
+            }
             //let building = app.add_object("assets/city_building.glb").unwrap();
             app.scene.skybox = Some(
                 SkyBox::load(
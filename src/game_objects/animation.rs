@@ -0,0 +1,334 @@
+use crate::game_objects::render_object::ObjectId;
+use crate::game_objects::scene::{GameObject, Scene};
+use glam::{Mat4, Quat, Vec3};
+use std::ops::{Add, Mul};
+
+/// how values between two keyframes are computed, matches the gltf sampler modes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Step,
+    Linear,
+    /// hermite spline, every keyframe stores an in-tangent, a value and an out-tangent.
+    CubicSpline,
+}
+
+/// values that can be keyframed.
+pub trait Animatable: Copy + Add<Output = Self> + Mul<f32, Output = Self> {
+    fn interpolate(a: Self, b: Self, s: f32) -> Self;
+    /// applied to every sampled value, used to keep rotations unit length.
+    fn normalize_sample(self) -> Self {
+        self
+    }
+}
+impl Animatable for Vec3 {
+    fn interpolate(a: Self, b: Self, s: f32) -> Self {
+        a.lerp(b, s)
+    }
+}
+impl Animatable for f32 {
+    fn interpolate(a: Self, b: Self, s: f32) -> Self {
+        a + (b - a) * s
+    }
+}
+impl Animatable for Quat {
+    fn interpolate(a: Self, b: Self, s: f32) -> Self {
+        a.slerp(b, s)
+    }
+    fn normalize_sample(self) -> Self {
+        self.normalize()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Sampler<T> {
+    pub interpolation: Interpolation,
+    pub times: Vec<f32>,
+    /// one value per keyframe, or three (in-tangent, value, out-tangent) for cubic splines.
+    pub values: Vec<T>,
+}
+
+impl<T> Sampler<T>
+where
+    T: Animatable,
+{
+    pub fn new(interpolation: Interpolation, times: Vec<f32>, values: Vec<T>) -> Self {
+        let per_key = if interpolation == Interpolation::CubicSpline {
+            3
+        } else {
+            1
+        };
+        assert_eq!(
+            times.len() * per_key,
+            values.len(),
+            "sampler needs {per_key} value(s) per keyframe"
+        );
+        Self {
+            interpolation,
+            times,
+            values,
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.times.last().copied().unwrap_or(0.0)
+    }
+
+    fn value(&self, key: usize) -> T {
+        match self.interpolation {
+            Interpolation::CubicSpline => self.values[3 * key + 1],
+            _ => self.values[key],
+        }
+    }
+
+    /// samples the curve at `time`, times outside the keyframes are clamped.
+    pub fn sample(&self, time: f32) -> T {
+        let last = self.times.len() - 1;
+        if time <= self.times[0] {
+            return self.value(0).normalize_sample();
+        }
+        if time >= self.times[last] {
+            return self.value(last).normalize_sample();
+        }
+        let next = self.times.partition_point(|&t| t <= time);
+        let key = next - 1;
+        let delta = self.times[next] - self.times[key];
+        let s = (time - self.times[key]) / delta;
+        let value = match self.interpolation {
+            Interpolation::Step => self.value(key),
+            Interpolation::Linear => T::interpolate(self.value(key), self.value(next), s),
+            Interpolation::CubicSpline => {
+                let s2 = s * s;
+                let s3 = s2 * s;
+                let v0 = self.values[3 * key + 1];
+                let b0 = self.values[3 * key + 2];
+                let a1 = self.values[3 * next];
+                let v1 = self.values[3 * next + 1];
+                v0 * (2.0 * s3 - 3.0 * s2 + 1.0)
+                    + b0 * (delta * (s3 - 2.0 * s2 + s))
+                    + v1 * (-2.0 * s3 + 3.0 * s2)
+                    + a1 * (delta * (s3 - s2))
+            }
+        };
+        value.normalize_sample()
+    }
+}
+
+/// the transform component or morph weights a channel animates.
+#[derive(Clone, Debug)]
+pub enum Property {
+    Translation(Sampler<Vec3>),
+    Rotation(Sampler<Quat>),
+    Scale(Sampler<Vec3>),
+    /// one sampler per morph target.
+    Weights(Vec<Sampler<f32>>),
+}
+
+/// a single sampled value, ready to be written into an object.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pose {
+    Translation(Vec3),
+    Rotation(Quat),
+    Scale(Vec3),
+    Weights(Vec<f32>),
+}
+impl Pose {
+    pub fn apply(&self, object: &mut GameObject) {
+        let transform = &mut object.transform;
+        match self {
+            Pose::Translation(position) => transform.position = *position,
+            Pose::Rotation(rotation) => transform.rotation = *rotation,
+            Pose::Scale(scale) => transform.scale = *scale,
+            Pose::Weights(weights) => object.morph_weights.clone_from(weights),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Channel {
+    pub target: ObjectId,
+    pub property: Property,
+}
+impl Channel {
+    pub fn duration(&self) -> f32 {
+        match &self.property {
+            Property::Translation(sampler) | Property::Scale(sampler) => sampler.duration(),
+            Property::Rotation(sampler) => sampler.duration(),
+            Property::Weights(samplers) => {
+                samplers.iter().map(Sampler::duration).fold(0.0, f32::max)
+            }
+        }
+    }
+    pub fn sample(&self, time: f32) -> Pose {
+        match &self.property {
+            Property::Translation(sampler) => Pose::Translation(sampler.sample(time)),
+            Property::Rotation(sampler) => Pose::Rotation(sampler.sample(time)),
+            Property::Scale(sampler) => Pose::Scale(sampler.sample(time)),
+            Property::Weights(samplers) => {
+                Pose::Weights(samplers.iter().map(|s| s.sample(time)).collect())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AnimationClip {
+    pub name: String,
+    pub channels: Vec<Channel>,
+    pub duration: f32,
+}
+impl AnimationClip {
+    pub fn new(name: impl Into<String>, channels: Vec<Channel>) -> Self {
+        let duration = channels.iter().map(Channel::duration).fold(0.0, f32::max);
+        Self {
+            name: name.into(),
+            channels,
+            duration,
+        }
+    }
+    /// samples every channel of the clip at `time`.
+    pub fn sample(&self, time: f32) -> Vec<(ObjectId, Pose)> {
+        self.channels
+            .iter()
+            .map(|channel| (channel.target, channel.sample(time)))
+            .collect()
+    }
+}
+
+/// plays one clip at a time, the clips may target any object in the scene.
+#[derive(Clone, Debug)]
+pub struct AnimationPlayer {
+    pub clips: Vec<AnimationClip>,
+    pub active: Option<usize>,
+    pub time: f32,
+    /// playback rate, negative values play the clip backwards.
+    pub speed: f32,
+    /// wrap around at the ends of the clip instead of holding the last pose.
+    pub looping: bool,
+    pub paused: bool,
+}
+impl Default for AnimationPlayer {
+    fn default() -> Self {
+        Self::new(vec![])
+    }
+}
+impl AnimationPlayer {
+    pub fn new(clips: Vec<AnimationClip>) -> Self {
+        Self {
+            clips,
+            active: None,
+            time: 0.0,
+            speed: 1.0,
+            looping: true,
+            paused: false,
+        }
+    }
+    pub fn clip_names(&self) -> impl Iterator<Item = &str> {
+        self.clips.iter().map(|clip| clip.name.as_str())
+    }
+    /// starts the clip at `index` from the beginning.
+    pub fn play(&mut self, index: usize) -> bool {
+        if index >= self.clips.len() {
+            return false;
+        }
+        self.active = Some(index);
+        self.time = if self.speed < 0.0 {
+            self.clips[index].duration
+        } else {
+            0.0
+        };
+        self.paused = false;
+        true
+    }
+    pub fn play_by_name(&mut self, name: &str) -> bool {
+        match self.clips.iter().position(|clip| clip.name == name) {
+            Some(index) => self.play(index),
+            None => false,
+        }
+    }
+    pub fn stop(&mut self) {
+        self.active = None;
+        self.time = 0.0;
+    }
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+    pub fn is_playing(&self) -> bool {
+        self.active.is_some() && !self.paused && !self.is_finished()
+    }
+    /// true once a non looping clip has reached its end.
+    pub fn is_finished(&self) -> bool {
+        let Some(clip) = self.active_clip() else {
+            return false;
+        };
+        !self.looping
+            && ((self.speed >= 0.0 && self.time >= clip.duration)
+                || (self.speed < 0.0 && self.time <= 0.0))
+    }
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+    /// moves the playhead to `time`, clamped to the active clip.
+    pub fn seek(&mut self, time: f32) {
+        let duration = self.active_clip().map_or(0.0, |clip| clip.duration);
+        self.time = time.clamp(0.0, duration);
+    }
+    pub fn active_clip(&self) -> Option<&AnimationClip> {
+        self.clips.get(self.active?)
+    }
+    /// moves the playhead by `delta * speed`, wrapping or clamping at the ends of the clip.
+    pub fn advance(&mut self, delta: f32) {
+        if self.paused {
+            return;
+        }
+        let Some(clip) = self.active_clip() else {
+            return;
+        };
+        let duration = clip.duration;
+        let time = self.time + delta * self.speed;
+        self.time = if duration <= 0.0 {
+            0.0
+        } else if self.looping {
+            time.rem_euclid(duration)
+        } else {
+            time.clamp(0.0, duration)
+        };
+    }
+    /// the pose of the active clip at the current playhead.
+    pub fn pose(&self) -> Vec<(ObjectId, Pose)> {
+        match self.active_clip() {
+            Some(clip) => clip.sample(self.time),
+            None => vec![],
+        }
+    }
+}
+
+/// the joints of a skinned mesh, `joints[i]` is bound by `inverse_bind_matrices[i]`.
+#[derive(Clone, Debug)]
+pub struct Skin {
+    pub joints: Vec<ObjectId>,
+    pub inverse_bind_matrices: Vec<Mat4>,
+}
+impl Skin {
+    /// joint matrices in the model space of `instance`, the mesh is still transformed by the
+    /// instance's own model matrix afterwards.
+    pub fn joint_matrices(&self, scene: &Scene, instance: &GameObject) -> Vec<Mat4> {
+        let inverse_model = instance.global_matrix(scene).inverse();
+        self.joints
+            .iter()
+            .zip(&self.inverse_bind_matrices)
+            .map(|(joint, inverse_bind)| {
+                let joint_matrix = scene
+                    .objects
+                    .get(*joint)
+                    .map_or(Mat4::IDENTITY, |joint| joint.global_matrix(scene));
+                inverse_model * joint_matrix * *inverse_bind
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,236 @@
+use glam::{Mat3, Mat4, Vec3, Vec4, Vec4Swizzles};
+
+use crate::game_objects::camera::Camera;
+use crate::game_objects::render_object::ObjectId;
+use crate::game_objects::scene::Scene;
+
+/// axis aligned bounding box.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+/// the bounding volumes of a mesh in its own space, computed once from its vertices.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Bounds {
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+}
+
+/// the six planes of a view frustum facing inwards, a point `p` is inside a plane when
+/// `plane.xyz.dot(p) + plane.w >= 0`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    pub planes: [Vec4; 6],
+}
+
+/// a half line from `origin`, `direction` does not have to be normalized but distances along
+/// the ray are in multiples of it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+/// instances drawn and culled in the last frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CullStats {
+    pub draw_calls: usize,
+    pub drawn: usize,
+    pub culled: usize,
+}
+
+impl Aabb {
+    /// the box around `points`, an empty box at the origin when there are none.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Self {
+        let mut points = points.into_iter();
+        let Some(first) = points.next() else {
+            return Self::default();
+        };
+        points.fold(
+            Self {
+                min: first,
+                max: first,
+            },
+            |aabb, point| Self {
+                min: aabb.min.min(point),
+                max: aabb.max.max(point),
+            },
+        )
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    /// the smallest box holding both boxes.
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// the box grown by `margin` on every side.
+    pub fn expand(&self, margin: f32) -> Self {
+        Self {
+            min: self.min - margin,
+            max: self.max + margin,
+        }
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
+    pub fn contains(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.min).all() && other.max.cmple(self.max).all()
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let size = self.max - self.min;
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    /// squared distance from `point` to the closest point of the box, 0 inside of it.
+    pub fn distance_squared(&self, point: Vec3) -> f32 {
+        point.clamp(self.min, self.max).distance_squared(point)
+    }
+
+    /// the distance along `ray` at which it enters the box, 0 when it starts inside. misses
+    /// and boxes farther than `max_distance` are `None`.
+    pub fn ray_distance(&self, ray: &Ray, max_distance: f32) -> Option<f32> {
+        let inverse = ray.direction.recip();
+        let a = (self.min - ray.origin) * inverse;
+        let b = (self.max - ray.origin) * inverse;
+        let near = a.min(b).max_element().max(0.0);
+        let far = a.max(b).min_element().min(max_distance);
+        (near <= far).then_some(near)
+    }
+
+    /// the world space box around this box placed by `matrix`.
+    pub fn transform(&self, matrix: Mat4) -> Self {
+        let center = matrix.transform_point3(self.center());
+        let axes = Mat3::from_mat4(matrix);
+        let extents = Mat3::from_cols(axes.x_axis.abs(), axes.y_axis.abs(), axes.z_axis.abs())
+            * self.half_extents();
+        Self {
+            min: center - extents,
+            max: center + extents,
+        }
+    }
+}
+
+impl BoundingSphere {
+    /// the sphere around this sphere placed by `matrix`, non uniform scales grow the radius by
+    /// the largest one.
+    pub fn transform(&self, matrix: Mat4) -> Self {
+        let scale = matrix
+            .x_axis
+            .xyz()
+            .length()
+            .max(matrix.y_axis.xyz().length())
+            .max(matrix.z_axis.xyz().length());
+        Self {
+            center: matrix.transform_point3(self.center),
+            radius: self.radius * scale,
+        }
+    }
+}
+
+impl Bounds {
+    /// the box around `points` and the sphere around its center reaching the farthest point.
+    pub fn from_points(points: &[Vec3]) -> Self {
+        let aabb = Aabb::from_points(points.iter().copied());
+        let center = aabb.center();
+        let radius = points
+            .iter()
+            .map(|point| point.distance(center))
+            .fold(0.0, f32::max);
+        Self {
+            aabb,
+            sphere: BoundingSphere { center, radius },
+        }
+    }
+}
+
+impl Frustum {
+    /// the frustum of `camera` seen through `proj`, the near and far plane are taken from
+    /// the camera so they do not depend on the depth range of the projection.
+    pub fn new(camera: &Camera, proj: Mat4) -> Self {
+        let camera_matrix = camera.transform.matrix();
+        let clip = proj * camera_matrix.inverse();
+        let (x, y, w) = (clip.row(0), clip.row(1), clip.row(3));
+        let eye = camera_matrix.transform_point3(Vec3::ZERO);
+        let forward = camera_matrix
+            .transform_vector3(Vec3::NEG_Z)
+            .normalize_or_zero();
+        let near = forward.extend(-forward.dot(eye) - camera.near_field);
+        let far = (-forward).extend(forward.dot(eye) + camera.far_field);
+        Self::from_planes([w + x, w - x, w + y, w - y, near, far])
+    }
+
+    /// normalizes `planes` so their distances are in world units.
+    pub fn from_planes(planes: [Vec4; 6]) -> Self {
+        Self {
+            planes: planes.map(|plane| plane / plane.xyz().length().max(f32::MIN_POSITIVE)),
+        }
+    }
+
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.xyz().dot(sphere.center) + plane.w >= -sphere.radius)
+    }
+
+    /// false only when the whole box is behind one plane, boxes near the corners of the
+    /// frustum may pass without touching it.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner farthest along the plane normal
+            let corner = Vec3::select(plane.xyz().cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            plane.xyz().dot(corner) + plane.w >= 0.0
+        })
+    }
+
+    /// whether a mesh with `bounds` placed by `matrix` may be visible, the sphere is tested
+    /// first since it is cheaper.
+    pub fn intersects(&self, bounds: &Bounds, matrix: Mat4) -> bool {
+        self.intersects_sphere(&bounds.sphere.transform(matrix))
+            && self.intersects_aabb(&bounds.aabb.transform(matrix))
+    }
+}
+
+impl Scene {
+    /// `instances` of a mesh with `bounds` reordered so the ones in `frustum` come first,
+    /// and their count. missing instances are placed at the origin like in `instance_data`.
+    pub fn cull_instances(
+        &self,
+        bounds: &Bounds,
+        instances: &[ObjectId],
+        frustum: &Frustum,
+    ) -> (Vec<ObjectId>, usize) {
+        let (mut visible, culled): (Vec<ObjectId>, Vec<ObjectId>) =
+            instances.iter().partition(|id| {
+                let matrix = match self.objects.get(**id) {
+                    Some(object) => object.global_matrix(self),
+                    None => Mat4::IDENTITY,
+                };
+                frustum.intersects(bounds, matrix)
+            });
+        let count = visible.len();
+        visible.extend(culled);
+        (visible, count)
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::{HashMap, HashSet};
+
+use glam::Vec3;
+
+use crate::game_objects::bounds::{Aabb, Frustum, Ray};
+use crate::game_objects::render_object::ObjectId;
+use crate::game_objects::scene::Scene;
+
+/// leaves are stored grown by this much, so objects moving less than it are only refit.
+pub const BVH_MARGIN: f32 = 0.1;
+
+#[derive(Clone, Debug)]
+struct Node {
+    /// grown by `BVH_MARGIN` for leaves, the union of the children for branches.
+    aabb: Aabb,
+    parent: Option<usize>,
+    /// 0 for leaves, one more than the higher child for branches.
+    height: usize,
+    kind: NodeKind,
+}
+
+#[derive(Clone, Debug)]
+enum NodeKind {
+    Leaf { object: ObjectId, tight: Aabb },
+    Branch { children: [usize; 2] },
+    Free,
+}
+
+/// a dynamic bounding volume hierarchy over world space boxes of scene objects. leaves are
+/// inserted next to the sibling that grows the surface area of the tree the least and only
+/// moved when their object leaves the grown box, queries test the exact boxes.
+#[derive(Clone, Debug, Default)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    root: Option<usize>,
+    leaves: HashMap<ObjectId, usize>,
+}
+
+impl Bvh {
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn contains(&self, object: ObjectId) -> bool {
+        self.leaves.contains_key(&object)
+    }
+
+    /// the box `object` was last inserted or updated with.
+    pub fn bounds(&self, object: ObjectId) -> Option<Aabb> {
+        let leaf = *self.leaves.get(&object)?;
+        match self.nodes[leaf].kind {
+            NodeKind::Leaf { tight, .. } => Some(tight),
+            _ => None,
+        }
+    }
+
+    pub fn objects(&self) -> impl Iterator<Item = ObjectId> + '_ {
+        self.leaves.keys().copied()
+    }
+
+    /// the longest path from the root to a leaf, 0 for an empty tree.
+    pub fn depth(&self) -> usize {
+        let mut depth = 0;
+        let mut stack: Vec<(usize, usize)> = self.root.map(|root| (root, 1)).into_iter().collect();
+        while let Some((index, level)) = stack.pop() {
+            depth = depth.max(level);
+            if let NodeKind::Branch { children } = self.nodes[index].kind {
+                stack.extend(children.map(|child| (child, level + 1)));
+            }
+        }
+        depth
+    }
+
+    /// adds `object` with the world space box `aabb`, an object already in the tree is updated.
+    pub fn insert(&mut self, object: ObjectId, aabb: Aabb) {
+        self.update(object, aabb);
+    }
+
+    /// moves `object` to `aabb`. returns true when its leaf was reinserted, false when the box
+    /// still fits into the grown box of the leaf and only the exact box changed.
+    pub fn update(&mut self, object: ObjectId, aabb: Aabb) -> bool {
+        let Some(&leaf) = self.leaves.get(&object) else {
+            let leaf = self.allocate(Node {
+                aabb: aabb.expand(BVH_MARGIN),
+                parent: None,
+                height: 0,
+                kind: NodeKind::Leaf {
+                    object,
+                    tight: aabb,
+                },
+            });
+            self.leaves.insert(object, leaf);
+            self.insert_leaf(leaf);
+            return true;
+        };
+        self.nodes[leaf].kind = NodeKind::Leaf {
+            object,
+            tight: aabb,
+        };
+        if self.nodes[leaf].aabb.contains(&aabb) {
+            return false;
+        }
+        self.remove_leaf(leaf);
+        self.nodes[leaf].aabb = aabb.expand(BVH_MARGIN);
+        self.insert_leaf(leaf);
+        true
+    }
+
+    /// returns false when `object` was not in the tree.
+    pub fn remove(&mut self, object: ObjectId) -> bool {
+        let Some(leaf) = self.leaves.remove(&object) else {
+            return false;
+        };
+        self.remove_leaf(leaf);
+        self.release(leaf);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// every object whose box overlaps `aabb`.
+    pub fn query_aabb(&self, aabb: &Aabb) -> Vec<ObjectId> {
+        let mut found = vec![];
+        self.traverse(
+            |node| node.overlaps(aabb),
+            |object, tight| {
+                if tight.overlaps(aabb) {
+                    found.push(object);
+                }
+            },
+        );
+        found
+    }
+
+    /// every object whose box is not entirely behind a plane of `frustum`, see
+    /// `Frustum::intersects_aabb`.
+    pub fn query_frustum(&self, frustum: &Frustum) -> Vec<ObjectId> {
+        let mut found = vec![];
+        self.traverse(
+            |node| frustum.intersects_aabb(node),
+            |object, tight| {
+                if frustum.intersects_aabb(tight) {
+                    found.push(object);
+                }
+            },
+        );
+        found
+    }
+
+    /// every object whose box is hit by `ray` before `max_distance`, sorted by the distance at
+    /// which the ray enters the box.
+    pub fn ray_hits(&self, ray: &Ray, max_distance: f32) -> Vec<(ObjectId, f32)> {
+        let mut hits = vec![];
+        self.traverse(
+            |node| node.ray_distance(ray, max_distance).is_some(),
+            |object, tight| {
+                if let Some(distance) = tight.ray_distance(ray, max_distance) {
+                    hits.push((object, distance));
+                }
+            },
+        );
+        hits.sort_by(|a, b| a.1.total_cmp(&b.1));
+        hits
+    }
+
+    /// the object whose box `ray` enters first.
+    pub fn ray_cast(&self, ray: &Ray, max_distance: f32) -> Option<(ObjectId, f32)> {
+        self.closest(
+            |aabb, best| aabb.ray_distance(ray, best.min(max_distance)),
+            |aabb| aabb.ray_distance(ray, max_distance),
+        )
+    }
+
+    /// the object whose box is closest to `point` and the distance to it, 0 inside of it.
+    pub fn nearest(&self, point: Vec3) -> Option<(ObjectId, f32)> {
+        let distance = |aabb: &Aabb| Some(aabb.distance_squared(point).sqrt());
+        self.closest(
+            |aabb, best| distance(aabb).filter(|distance| *distance <= best),
+            distance,
+        )
+    }
+
+    /// visits the leaves below the nodes passing `enter`.
+    fn traverse(&self, enter: impl Fn(&Aabb) -> bool, mut visit: impl FnMut(ObjectId, &Aabb)) {
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !enter(&node.aabb) {
+                continue;
+            }
+            match &node.kind {
+                NodeKind::Leaf { object, tight } => visit(*object, tight),
+                NodeKind::Branch { children } => stack.extend(children),
+                NodeKind::Free => {}
+            }
+        }
+    }
+
+    /// the leaf with the smallest `leaf_distance`, branches are skipped once `node_distance`,
+    /// a lower bound given the best distance so far, is `None`.
+    fn closest(
+        &self,
+        node_distance: impl Fn(&Aabb, f32) -> Option<f32>,
+        leaf_distance: impl Fn(&Aabb) -> Option<f32>,
+    ) -> Option<(ObjectId, f32)> {
+        let mut best: Option<(ObjectId, f32)> = None;
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let bound = best.map_or(f32::INFINITY, |(_, distance)| distance);
+            if node_distance(&node.aabb, bound).is_none() {
+                continue;
+            }
+            match &node.kind {
+                NodeKind::Leaf { object, tight } => {
+                    if let Some(distance) = leaf_distance(tight)
+                        && distance < bound
+                    {
+                        best = Some((*object, distance));
+                    }
+                }
+                NodeKind::Branch { children } => {
+                    // the nearer child is visited first so the farther one is more likely skipped
+                    let mut children = children.map(|child| {
+                        let distance = node_distance(&self.nodes[child].aabb, bound);
+                        (child, distance.unwrap_or(f32::INFINITY))
+                    });
+                    children.sort_by(|a, b| b.1.total_cmp(&a.1));
+                    stack.extend(children.map(|(child, _)| child));
+                }
+                NodeKind::Free => {}
+            }
+        }
+        best
+    }
+
+    fn allocate(&mut self, node: Node) -> usize {
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn release(&mut self, index: usize) {
+        self.nodes[index].kind = NodeKind::Free;
+        self.free.push(index);
+    }
+
+    fn children(&self, index: usize) -> Option<[usize; 2]> {
+        match self.nodes[index].kind {
+            NodeKind::Branch { children } => Some(children),
+            _ => None,
+        }
+    }
+
+    fn insert_leaf(&mut self, leaf: usize) {
+        let Some(root) = self.root else {
+            self.nodes[leaf].parent = None;
+            self.root = Some(leaf);
+            return;
+        };
+        let aabb = self.nodes[leaf].aabb;
+        // walk down to the sibling whose branch costs the least surface area
+        let mut sibling = root;
+        while let Some(children) = self.children(sibling) {
+            let node = self.nodes[sibling].aabb;
+            let combined = node.union(&aabb).surface_area();
+            let cost = 2.0 * combined;
+            let inherited = 2.0 * (combined - node.surface_area());
+            let child_cost = |child: usize| {
+                let child_aabb = self.nodes[child].aabb;
+                let grown = child_aabb.union(&aabb).surface_area();
+                match self.nodes[child].kind {
+                    NodeKind::Branch { .. } => grown - child_aabb.surface_area() + inherited,
+                    _ => grown + inherited,
+                }
+            };
+            let costs = children.map(child_cost);
+            if cost < costs[0] && cost < costs[1] {
+                break;
+            }
+            sibling = if costs[0] <= costs[1] {
+                children[0]
+            } else {
+                children[1]
+            };
+        }
+
+        let parent = self.nodes[sibling].parent;
+        let branch = self.allocate(Node {
+            aabb: self.nodes[sibling].aabb.union(&aabb),
+            parent,
+            height: 0,
+            kind: NodeKind::Branch {
+                children: [sibling, leaf],
+            },
+        });
+        self.nodes[sibling].parent = Some(branch);
+        self.nodes[leaf].parent = Some(branch);
+        match parent {
+            Some(parent) => self.replace_child(parent, sibling, branch),
+            None => self.root = Some(branch),
+        }
+        self.refit(branch);
+    }
+
+    fn remove_leaf(&mut self, leaf: usize) {
+        if self.root == Some(leaf) {
+            self.root = None;
+            return;
+        }
+        let Some(parent) = self.nodes[leaf].parent else {
+            return;
+        };
+        let Some(children) = self.children(parent) else {
+            return;
+        };
+        let sibling = if children[0] == leaf {
+            children[1]
+        } else {
+            children[0]
+        };
+        let grandparent = self.nodes[parent].parent;
+        self.nodes[sibling].parent = grandparent;
+        match grandparent {
+            Some(grandparent) => {
+                self.replace_child(grandparent, parent, sibling);
+                self.refit(grandparent);
+            }
+            None => self.root = Some(sibling),
+        }
+        self.nodes[leaf].parent = None;
+        self.release(parent);
+    }
+
+    fn replace_child(&mut self, parent: usize, old: usize, new: usize) {
+        if let NodeKind::Branch { children } = &mut self.nodes[parent].kind {
+            for child in children.iter_mut().filter(|child| **child == old) {
+                *child = new;
+            }
+        }
+    }
+
+    /// recomputes the boxes and heights of `index` and its ancestors, rotating unbalanced
+    /// branches on the way up so sorted insertions do not degrade the tree into a list.
+    fn refit(&mut self, index: usize) {
+        let mut next = Some(index);
+        while let Some(index) = next {
+            let index = self.balance(index);
+            self.fit_children(index);
+            next = self.nodes[index].parent;
+        }
+    }
+
+    fn fit_children(&mut self, index: usize) {
+        if let Some([a, b]) = self.children(index) {
+            self.nodes[index].aabb = self.nodes[a].aabb.union(&self.nodes[b].aabb);
+            self.nodes[index].height = 1 + self.nodes[a].height.max(self.nodes[b].height);
+        }
+    }
+
+    /// rotates the higher child of a branch up when the heights of its children differ by
+    /// more than one, returns the node now in the place of `index`.
+    fn balance(&mut self, index: usize) -> usize {
+        let Some([b, c]) = self.children(index) else {
+            return index;
+        };
+        let (b, c) = (self.nodes[b].height, self.nodes[c].height);
+        if c > b + 1 {
+            self.rotate_up(index, 1)
+        } else if b > c + 1 {
+            self.rotate_up(index, 0)
+        } else {
+            index
+        }
+    }
+
+    /// moves child `side` of `index` into its place, `index` becomes a child of it and takes
+    /// over the lower of its children.
+    fn rotate_up(&mut self, index: usize, side: usize) -> usize {
+        let Some(children) = self.children(index) else {
+            return index;
+        };
+        let (up, stay) = (children[side], children[1 - side]);
+        let Some([f, g]) = self.children(up) else {
+            return index;
+        };
+        let parent = self.nodes[index].parent;
+        self.nodes[up].parent = parent;
+        match parent {
+            Some(parent) => self.replace_child(parent, index, up),
+            None => self.root = Some(up),
+        }
+        let (high, low) = if self.nodes[f].height > self.nodes[g].height {
+            (f, g)
+        } else {
+            (g, f)
+        };
+        self.nodes[index].parent = Some(up);
+        self.nodes[index].kind = NodeKind::Branch {
+            children: [stay, low],
+        };
+        self.nodes[low].parent = Some(index);
+        self.nodes[up].kind = NodeKind::Branch {
+            children: [index, high],
+        };
+        self.fit_children(index);
+        self.fit_children(up);
+        up
+    }
+}
+
+impl Scene {
+    /// the world space box around the render objects of an object, a point at its origin
+    /// when it has none.
+    pub fn world_bounds(&self, id: ObjectId) -> Option<Aabb> {
+        let object = self.objects.get(id)?;
+        let matrix = object.global_matrix(self);
+        let aabb = object
+            .render_objects
+            .iter()
+            .filter_map(|render_id| self.render_objects.get(*render_id))
+            .map(|render_object| render_object.vertex_data.bounds.aabb.transform(matrix))
+            .reduce(|a, b| a.union(&b));
+        Some(aabb.unwrap_or_else(|| {
+            let origin = matrix.transform_point3(Vec3::ZERO);
+            Aabb {
+                min: origin,
+                max: origin,
+            }
+        }))
+    }
+
+    /// the bounds of `id` and every object below it changed, they are refit by the next
+    /// `refit_bvh`. the scene marks its own changes, code writing transforms directly calls this.
+    pub fn mark_moved(&mut self, id: ObjectId) {
+        self.moved.insert(id);
+    }
+
+    /// moves the objects marked by `mark_moved` and everything below them to their current
+    /// world bounds, objects that were not moved keep their leaves.
+    pub fn refit_bvh(&mut self) {
+        let mut refit = HashSet::new();
+        for id in std::mem::take(&mut self.moved) {
+            for id in self.subtree(id) {
+                if refit.insert(id)
+                    && let Some(aabb) = self.world_bounds(id)
+                {
+                    self.bvh.update(id, aabb);
+                }
+            }
+        }
+    }
+}
@@ -7,9 +7,11 @@ use bevy::input::keyboard::{KeyCode, KeyboardInput};
 use bevy::input::mouse::{MouseButton, MouseButtonInput, MouseMotion};
 use bevy::reflect::Enum;
 use bevy::time::Time;
-use glam::{Mat4, Quat, Vec3};
+use glam::{Mat4, Quat, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
 use winit::event::MouseScrollDelta;
 
+use crate::game_objects::bounds::Ray;
 use crate::game_objects::transform::{self, Transform};
 use crate::gui::gui::Gui;
 use crate::vulkan::input_state::InputState;
@@ -18,7 +20,7 @@ use crate::winit_app::winit_render_app::{self, AppData};
 use std::cmp::PartialEq;
 use std::f32::consts::PI;
 
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub struct Camera {
     pub movement_speed: f32,
     pub rotation_speed: f32,
@@ -128,16 +130,37 @@ impl Camera {
         self.move_to_target();
     }
 
-    pub fn projection_matrix(&self, data: &AppData, gui: &Gui) -> Mat4 {
-        let aspect = if gui.enabled {
+    /// the aspect ratio of the gui viewport, or of the whole window without the gui.
+    pub fn aspect(&self, data: &AppData, gui: &Gui) -> f32 {
+        if gui.enabled {
             (gui.callback.max.x - gui.callback.min.x) / (gui.callback.max.y - gui.callback.min.y)
         } else {
             data.swapchain_extent.width as f32 / data.swapchain_extent.height as f32
-        };
+        }
+    }
+
+    /// the world space ray through a point of the screen in normalized device coordinates of
+    /// `proj`, starting on the near plane. the direction is normalized.
+    pub fn ray_through(&self, proj: Mat4, ndc: Vec2) -> Ray {
+        let camera = self.transform.matrix();
+        let eye = camera.transform_point3(Vec3::ZERO);
+        let forward = camera.transform_vector3(Vec3::NEG_Z).normalize_or_zero();
+        // any depth inside of the frustum lies on the ray through the eye
+        let point = (proj * camera.inverse())
+            .inverse()
+            .project_point3(ndc.extend(0.9));
+        let direction = (point - eye).normalize_or(forward);
+        Ray {
+            origin: eye + direction * (self.near_field / direction.dot(forward).max(1e-6)),
+            direction,
+        }
+    }
+
+    pub fn projection_matrix(&self, data: &AppData, gui: &Gui) -> Mat4 {
         CORRECTION
             * Mat4::perspective_rh(
                 self.fov * (PI / 180.0),
-                aspect,
+                self.aspect(data, gui),
                 self.near_field,
                 self.far_field,
             )
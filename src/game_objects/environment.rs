@@ -0,0 +1,316 @@
+#![allow(unsafe_op_in_unsafe_fn, clippy::missing_safety_doc)]
+use crate::asset_manager::load_skybox::{HdrCubemap, HdrImage, face_direction};
+use crate::game_objects::render_object::{ENVIRONMENT_TEXTURE_COUNT, Renderable};
+use crate::game_objects::scene::Scene;
+use crate::game_objects::skybox::SkyBox;
+use crate::vulkan::image_util::TextureData;
+use crate::winit_app::winit_render_app::AppData;
+use glam::{Vec2, Vec3};
+use std::f32::consts::PI;
+use vulkanalia::vk::{self, DeviceV1_0, Handle};
+use vulkanalia::{Device, Instance};
+
+/// faces of the skybox copy the maps are made from, see `SkyBox::radiance`.
+pub const RADIANCE_SIZE: u32 = 128;
+pub const IRRADIANCE_SIZE: u32 = 16;
+/// the irradiance is smooth enough to be integrated from a small copy of the sky.
+const IRRADIANCE_SOURCE_SIZE: u32 = 16;
+/// size of the sharpest level of the prefiltered map, roughness goes from 0 at the first
+/// level to 1 at the last.
+pub const PREFILTER_SIZE: u32 = 64;
+pub const PREFILTER_LEVELS: u32 = 5;
+const PREFILTER_SAMPLES: u32 = 64;
+pub const BRDF_LUT_SIZE: u32 = 64;
+const BRDF_LUT_SAMPLES: u32 = 256;
+/// radiance of the uniform environment used while there is no skybox.
+pub const DEFAULT_AMBIENT: Vec3 = Vec3::splat(0.03);
+
+/// the cpu side of the image based lighting of a sky.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvironmentMaps {
+    /// cosine weighted average of the incoming radiance around every normal, divided by pi
+    /// so a uniform sky keeps its value.
+    pub irradiance: HdrCubemap,
+    /// the sky blurred with a ggx lobe, one level per roughness step.
+    pub prefiltered: Vec<HdrCubemap>,
+}
+impl EnvironmentMaps {
+    pub fn from_radiance(radiance: &HdrCubemap) -> Self {
+        Self {
+            irradiance: irradiance(radiance, IRRADIANCE_SIZE),
+            prefiltered: prefilter(
+                radiance,
+                PREFILTER_SIZE,
+                PREFILTER_LEVELS,
+                PREFILTER_SAMPLES,
+            ),
+        }
+    }
+    /// a sky of the same radiance everywhere, every map is a single texel per face.
+    pub fn uniform(radiance: Vec3) -> Self {
+        Self {
+            irradiance: HdrCubemap::uniform(1, radiance),
+            prefiltered: vec![HdrCubemap::uniform(1, radiance)],
+        }
+    }
+}
+
+/// the solid angle a texel of a cube face covers.
+pub fn texel_solid_angle(size: u32, x: u32, y: u32) -> f32 {
+    let area = |x: f32, y: f32| (x * y).atan2((x * x + y * y + 1.0).sqrt());
+    let texel = 1.0 / size as f32;
+    let u = 2.0 * (x as f32 + 0.5) * texel - 1.0;
+    let v = 2.0 * (y as f32 + 0.5) * texel - 1.0;
+    let (x0, y0, x1, y1) = (u - texel, v - texel, u + texel, v + texel);
+    area(x0, y0) - area(x0, y1) - area(x1, y0) + area(x1, y1)
+}
+
+/// integrates the sky over the hemisphere of every texel direction.
+pub fn irradiance(radiance: &HdrCubemap, size: u32) -> HdrCubemap {
+    let source = radiance.downsample_to(IRRADIANCE_SOURCE_SIZE);
+    // every source texel as a direction and its radiance times the solid angle it covers
+    let mut texels = vec![];
+    for (face, pixels) in source.faces.iter().enumerate() {
+        for y in 0..source.size {
+            for x in 0..source.size {
+                let direction = face_direction(face, source.size, x, y).normalize();
+                let weight = texel_solid_angle(source.size, x, y);
+                texels.push((direction, pixels.get(x, y) * weight));
+            }
+        }
+    }
+    map_faces(size, |normal| {
+        texels
+            .iter()
+            .map(|(direction, radiance)| *radiance * normal.dot(*direction).max(0.0))
+            .sum::<Vec3>()
+            / PI
+    })
+}
+
+/// blurs the sky with ggx lobes of increasing roughness, one level of half the size each.
+/// samples read from a blurrier copy of the sky the less likely their direction is, so a few
+/// samples per texel do not alias.
+pub fn prefilter(radiance: &HdrCubemap, size: u32, levels: u32, samples: u32) -> Vec<HdrCubemap> {
+    let mut chain = vec![radiance.downsample_to(size)];
+    while chain.last().unwrap().size > 1 {
+        chain.push(chain.last().unwrap().downsample());
+    }
+    let base = chain[0].size as f32;
+    let texel_solid_angle = 4.0 * PI / (6.0 * base * base);
+    (0..levels)
+        .map(|level| {
+            let size = (chain[0].size >> level).max(1);
+            if level == 0 || levels == 1 {
+                return chain[0].downsample_to(size);
+            }
+            let roughness = level as f32 / (levels - 1) as f32;
+            map_faces(size, |normal| {
+                // the view direction is assumed to be the normal, as usual for split sums
+                let mut color = Vec3::ZERO;
+                let mut weight = 0.0;
+                for i in 0..samples {
+                    let half = importance_sample_ggx(hammersley(i, samples), normal, roughness);
+                    let light = 2.0 * normal.dot(half) * half - normal;
+                    let n_dot_l = normal.dot(light);
+                    if n_dot_l <= 0.0 {
+                        continue;
+                    }
+                    let n_dot_h = normal.dot(half).max(0.0);
+                    let pdf = distribution_ggx(n_dot_h, roughness) / 4.0 + 1e-4;
+                    let sample_solid_angle = 1.0 / (samples as f32 * pdf + 1e-4);
+                    let lod = 0.5 * (sample_solid_angle / texel_solid_angle).log2();
+                    let lod = (lod.round().max(0.0) as usize).min(chain.len() - 1);
+                    color += chain[lod].sample(light) * n_dot_l;
+                    weight += n_dot_l;
+                }
+                color / weight.max(f32::MIN_POSITIVE)
+            })
+        })
+        .collect()
+}
+
+/// the split sum lookup table of the specular brdf. columns go from n·v 0 to 1, rows from
+/// roughness 0 to 1. the red channel scales f0, the green channel is added to it.
+pub fn brdf_lut(size: u32, samples: u32) -> Vec<Vec2> {
+    let mut lut = Vec::with_capacity((size * size) as usize);
+    for row in 0..size {
+        let roughness = (row as f32 + 0.5) / size as f32;
+        for column in 0..size {
+            let n_dot_v = (column as f32 + 0.5) / size as f32;
+            let view = Vec3::new((1.0 - n_dot_v * n_dot_v).sqrt(), 0.0, n_dot_v);
+            let mut scale_bias = Vec2::ZERO;
+            for i in 0..samples {
+                let half = importance_sample_ggx(hammersley(i, samples), Vec3::Z, roughness);
+                let light = 2.0 * view.dot(half) * half - view;
+                let n_dot_l = light.z;
+                if n_dot_l <= 0.0 {
+                    continue;
+                }
+                let n_dot_h = half.z.max(0.0);
+                let v_dot_h = view.dot(half).max(0.0);
+                let g = geometry_smith_ibl(n_dot_v, n_dot_l, roughness);
+                let visibility = g * v_dot_h / (n_dot_h * n_dot_v).max(f32::MIN_POSITIVE);
+                let fresnel = (1.0 - v_dot_h).powi(5);
+                scale_bias += Vec2::new(1.0 - fresnel, fresnel) * visibility;
+            }
+            lut.push(scale_bias / samples as f32);
+        }
+    }
+    lut
+}
+
+/// fills every texel of a new cube map with `f` of its normalized direction.
+fn map_faces(size: u32, f: impl Fn(Vec3) -> Vec3) -> HdrCubemap {
+    let faces = (0..6)
+        .map(|face| {
+            let mut pixels = HdrImage::new(size, size);
+            for y in 0..size {
+                for x in 0..size {
+                    let direction = face_direction(face, size, x, y).normalize();
+                    pixels.set(x, y, f(direction));
+                }
+            }
+            pixels
+        })
+        .collect();
+    HdrCubemap { size, faces }
+}
+
+/// low discrepancy point `i` of `count`.
+fn hammersley(i: u32, count: u32) -> Vec2 {
+    Vec2::new(
+        i as f32 / count as f32,
+        i.reverse_bits() as f32 * 2.328_306_4e-10,
+    )
+}
+
+/// a half vector around `normal` distributed like the ggx lobe of `roughness`.
+fn importance_sample_ggx(xi: Vec2, normal: Vec3, roughness: f32) -> Vec3 {
+    let a = roughness * roughness;
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = ((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y)).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+    let up = if normal.z.abs() < 0.999 {
+        Vec3::Z
+    } else {
+        Vec3::X
+    };
+    let tangent = up.cross(normal).normalize();
+    let bitangent = normal.cross(tangent);
+    (tangent * phi.cos() * sin_theta + bitangent * phi.sin() * sin_theta + normal * cos_theta)
+        .normalize()
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * denom * denom)
+}
+
+/// smith geometry term with the remapping of k used for image based lighting.
+fn geometry_smith_ibl(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = roughness * roughness / 2.0;
+    let schlick = |n_dot_x: f32| n_dot_x / (n_dot_x * (1.0 - k) + k);
+    schlick(n_dot_v) * schlick(n_dot_l)
+}
+
+/// the image based lighting bound to every pbr descriptor set, made from `scene.skybox`.
+#[derive(Clone, Debug, Default)]
+pub struct Environment {
+    pub irradiance: TextureData,
+    pub prefiltered: TextureData,
+    pub brdf_lut: TextureData,
+    /// the skybox image the maps were made from, null for the uniform environment and `None`
+    /// before the first update.
+    pub source: Option<vk::Image>,
+}
+impl Environment {
+    /// textures in the order of their descriptor bindings, starting at `ENVIRONMENT_BINDING`.
+    pub fn textures(&self) -> [&TextureData; ENVIRONMENT_TEXTURE_COUNT] {
+        [&self.irradiance, &self.prefiltered, &self.brdf_lut]
+    }
+    /// whether the maps were made from `skybox`, or from the uniform environment for `None`.
+    pub fn is_current(&self, skybox: Option<&SkyBox>) -> bool {
+        self.source == Some(skybox.map_or(vk::Image::null(), |skybox| skybox.texture_data.image))
+    }
+    pub unsafe fn destroy(&self, device: &Device) {
+        for texture in self.textures() {
+            texture.destroy_image(device);
+        }
+    }
+}
+
+/// regenerates `data.environment` when `scene.skybox` changed since the last call, and points
+/// the descriptor sets of every render object at the new maps. call before the first object
+/// is uploaded, and once per frame after waiting for its fence.
+pub unsafe fn update_environment(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    scene: &mut Scene,
+) -> anyhow::Result<()> {
+    if data.environment.is_current(scene.skybox.as_ref()) {
+        return Ok(());
+    }
+    let maps = match &scene.skybox {
+        Some(skybox) => EnvironmentMaps::from_radiance(&skybox.radiance),
+        None => EnvironmentMaps::uniform(DEFAULT_AMBIENT),
+    };
+    let format = vk::Format::R16G16B16A16_SFLOAT;
+    let irradiance = TextureData::create_cubemap_from_levels(
+        instance,
+        device,
+        data,
+        &[maps.irradiance.to_rgba16f()],
+        maps.irradiance.size,
+        format,
+    )?;
+    let levels: Vec<Vec<u8>> = maps
+        .prefiltered
+        .iter()
+        .map(HdrCubemap::to_rgba16f)
+        .collect();
+    let prefiltered = TextureData::create_cubemap_from_levels(
+        instance,
+        device,
+        data,
+        &levels,
+        maps.prefiltered[0].size,
+        format,
+    )?;
+    if data.environment.brdf_lut.image.is_null() {
+        let pixels = brdf_lut(BRDF_LUT_SIZE, BRDF_LUT_SAMPLES)
+            .into_iter()
+            .flat_map(|texel| texel.to_array())
+            .flat_map(|channel| half::f16::from_f32(channel).to_le_bytes())
+            .collect();
+        data.environment.brdf_lut = TextureData::create_lookup_texture(
+            instance,
+            device,
+            data,
+            pixels,
+            (BRDF_LUT_SIZE, BRDF_LUT_SIZE),
+            vk::Format::R16G16_SFLOAT,
+        )?;
+    }
+    // frames in flight still sample the old maps through the descriptor sets rewritten below
+    device.device_wait_idle()?;
+    data.environment.irradiance.destroy_image(device);
+    data.environment.prefiltered.destroy_image(device);
+    data.environment.irradiance = irradiance;
+    data.environment.prefiltered = prefiltered;
+    data.environment.source = Some(
+        scene
+            .skybox
+            .as_ref()
+            .map_or(vk::Image::null(), |skybox| skybox.texture_data.image),
+    );
+    for (_, object) in scene.render_objects.iter() {
+        for i in 0..object.get_descriptor_sets().len() {
+            object.init_descriptor(device, data, &mut scene.sun, i);
+        }
+    }
+    Ok(())
+}
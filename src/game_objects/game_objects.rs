@@ -4,14 +4,17 @@ use glam::Mat4;
 
 use crate::{
     game_objects::{
-        scene::{IsId, ParaSlab},
+        slabs::{IsId, ParaSlab},
         transform::Transform,
     },
     vulkan::vertexbuffer_util::Vertex,
 };
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub struct ObjectId(pub usize);
+pub struct ObjectId {
+    pub index: usize,
+    pub generation: u32,
+}
 #[derive(Debug)]
 pub struct GameObject<ObjectId, RenderId, V>
 where
@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+use crate::game_objects::render_object::ObjectId;
+use crate::game_objects::scene::{GameObject, Scene};
+use crate::game_objects::transform::Transform;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum HierarchyError {
+    #[error("no object {0:?} in the scene")]
+    Missing(ObjectId),
+    #[error("{parent:?} is {child:?} or below it")]
+    Cycle { child: ObjectId, parent: ObjectId },
+}
+
+impl Scene {
+    /// whether `id` is `ancestor` or below it. gives up on hierarchies that already loop.
+    pub fn is_descendant(&self, id: ObjectId, ancestor: ObjectId) -> bool {
+        let mut current = Some(id);
+        for _ in 0..=self.objects.len() {
+            match current {
+                Some(id) if id == ancestor => return true,
+                Some(id) => current = self.objects.get(id).and_then(|object| object.parent),
+                None => return false,
+            }
+        }
+        false
+    }
+
+    /// `id` and every object below it, parents before their children.
+    pub fn subtree(&self, id: ObjectId) -> Vec<ObjectId> {
+        let mut ids = vec![];
+        let mut visited = HashSet::new();
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let Some(object) = self.objects.get(id) else {
+                continue;
+            };
+            if !visited.insert(id) {
+                continue;
+            }
+            ids.push(id);
+            stack.extend(object.children.iter().rev());
+        }
+        ids
+    }
+
+    /// removes `id` and every object below it, parents before their children.
+    pub fn remove_subtree(&mut self, id: ObjectId) -> Vec<GameObject> {
+        let _ = self.detach(id, false);
+        self.subtree(id)
+            .into_iter()
+            .filter_map(|id| self.remove_linked(id))
+            .collect()
+    }
+
+    /// moves `child` below `parent`. with `keep_world_transform` it stays where it is,
+    /// otherwise its transform is now relative to `parent`. a parent below `child` is refused.
+    pub fn reparent(
+        &mut self,
+        child: ObjectId,
+        parent: ObjectId,
+        keep_world_transform: bool,
+    ) -> Result<(), HierarchyError> {
+        if self.objects.get(parent).is_none() {
+            return Err(HierarchyError::Missing(parent));
+        }
+        if self.objects.get(child).is_none() {
+            return Err(HierarchyError::Missing(child));
+        }
+        if self.is_descendant(parent, child) {
+            return Err(HierarchyError::Cycle { child, parent });
+        }
+        let parent_matrix = self.objects.get(parent).unwrap().global_matrix(self);
+        self.detach(child, keep_world_transform)?;
+        let object = self.objects.get_mut(child).unwrap();
+        object.parent = Some(parent);
+        if keep_world_transform {
+            object.transform =
+                Transform::from_matrix(parent_matrix.inverse() * object.transform.matrix());
+        }
+        self.objects.get_mut(parent).unwrap().children.push(child);
+        self.mark_moved(child);
+        Ok(())
+    }
+
+    /// makes `id` a root. with `keep_world_transform` it stays where it is, otherwise its
+    /// transform is now relative to the world.
+    pub fn detach(
+        &mut self,
+        id: ObjectId,
+        keep_world_transform: bool,
+    ) -> Result<(), HierarchyError> {
+        let object = self.objects.get(id).ok_or(HierarchyError::Missing(id))?;
+        let Some(parent) = object.parent else {
+            return Ok(());
+        };
+        let matrix = keep_world_transform.then(|| object.global_matrix(self));
+        if let Some(parent) = self.objects.get_mut(parent) {
+            parent.children.retain(|child| *child != id);
+        }
+        let object = self.objects.get_mut(id).unwrap();
+        object.parent = None;
+        if let Some(matrix) = matrix {
+            object.transform = Transform::from_matrix(matrix);
+        }
+        self.mark_moved(id);
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+#![allow(clippy::missing_safety_doc)]
+use std::ptr::copy_nonoverlapping as memcpy;
+
+use glam::{Mat4, UVec4, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+use vulkanalia::{
+    Device,
+    vk::{self, DeviceMemory, DeviceV1_0},
+};
+
+use crate::game_objects::scene::Scene;
+use crate::vulkan::uniform_buffer_object::PointLight;
+
+/// lights past this count are left out of the light buffer.
+pub const MAX_LIGHTS: usize = 256;
+
+/// a point or spot light attached to a `GameObject`, spot lights shine along the -z axis of
+/// the object. intensities are in candela like `KHR_lights_punctual`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Vec3,
+    pub intensity: f32,
+    /// distance at which the light has faded out, unlimited when `None`.
+    pub range: Option<f32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LightKind {
+    Point,
+    /// angles between the axis and the edge of the cone in radians, the light fades out from
+    /// the inner to the outer cone.
+    Spot {
+        inner_cone: f32,
+        outer_cone: f32,
+    },
+}
+
+impl Light {
+    pub fn point(color: Vec3, intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Point,
+            color,
+            intensity,
+            range: None,
+        }
+    }
+
+    pub fn spot(color: Vec3, intensity: f32, inner_cone: f32, outer_cone: f32) -> Self {
+        Self {
+            kind: LightKind::Spot {
+                inner_cone,
+                outer_cone,
+            },
+            ..Self::point(color, intensity)
+        }
+    }
+
+    /// the light as seen by the shader when its object is placed by `matrix`.
+    pub fn uniform(&self, matrix: Mat4) -> PointLight {
+        let position = matrix.w_axis.truncate();
+        let direction = matrix.transform_vector3(Vec3::NEG_Z).normalize_or_zero();
+        // the cone fades linearly in the cosine, a point light is never faded
+        let (scale, offset) = match self.kind {
+            LightKind::Point => (0.0, 1.0),
+            LightKind::Spot {
+                inner_cone,
+                outer_cone,
+            } => {
+                let outer = outer_cone.cos();
+                let inner = inner_cone.min(outer_cone).cos();
+                let scale = 1.0 / (inner - outer).max(0.001);
+                (scale, -outer * scale)
+            }
+        };
+        PointLight {
+            position: position.extend(self.range.unwrap_or(0.0).max(0.0)),
+            direction: direction.extend(0.0),
+            color: (self.color * self.intensity.max(0.0)).extend(1.0),
+            cone: Vec4::new(scale, offset, 0.0, 0.0),
+        }
+    }
+}
+
+/// a `uvec4` header with the light count followed by `MAX_LIGHTS` lights.
+pub fn light_buffer_size() -> u64 {
+    (size_of::<UVec4>() + size_of::<PointLight>() * MAX_LIGHTS) as u64
+}
+
+/// writes `lights` into the light buffer of a frame, see `light_buffer_size`.
+pub unsafe fn update_lights(
+    device: &Device,
+    memory: DeviceMemory,
+    lights: &[PointLight],
+) -> anyhow::Result<()> {
+    let count = lights.len().min(MAX_LIGHTS);
+    let mapped =
+        unsafe { device.map_memory(memory, 0, light_buffer_size(), vk::MemoryMapFlags::empty()) }?;
+    let header = UVec4::new(count as u32, 0, 0, 0);
+    unsafe {
+        memcpy(&header, mapped.cast(), 1);
+        let records: *mut PointLight = mapped.cast::<u8>().add(size_of::<UVec4>()).cast();
+        memcpy(lights.as_ptr(), records, count);
+        device.unmap_memory(memory);
+    }
+    Ok(())
+}
+
+impl Scene {
+    /// every light of the scene placed by the global transform of its object.
+    pub fn lights(&self) -> Vec<PointLight> {
+        self.objects
+            .iter()
+            .filter_map(|(_, object)| {
+                let light = object.light.as_ref()?;
+                Some(light.uniform(object.global_matrix(self)))
+            })
+            .collect()
+    }
+}
@@ -1,6 +1,16 @@
+pub mod animation;
+pub mod bounds;
+pub mod bvh;
 pub mod camera;
+pub mod environment;
+pub mod hierarchy;
+pub mod light;
 pub mod material;
+pub mod morph;
+pub mod picking;
+pub mod prefab;
 pub mod render_object;
 pub mod scene;
 pub mod skybox;
+pub mod slabs;
 pub mod transform;
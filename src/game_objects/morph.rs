@@ -0,0 +1,136 @@
+#![allow(unsafe_op_in_unsafe_fn, clippy::missing_safety_doc)]
+use crate::vulkan::buffer_util::{GrowableBuffers, create_buffer};
+use crate::vulkan::vertexbuffer_util::VertexPbr;
+use crate::winit_app::winit_render_app::AppData;
+use glam::{UVec4, Vec3, Vec4};
+use std::ptr::copy_nonoverlapping as memcpy;
+use vulkanalia::vk::{self, Buffer, DeviceMemory, DeviceV1_0};
+use vulkanalia::{Device, Instance};
+
+/// position and normal deltas of every morph target, `position_deltas[t][v]` moves vertex `v`
+/// of target `t`. targets without normals store zero deltas.
+#[derive(Clone, Debug, Default)]
+pub struct MorphTargets {
+    pub vertex_count: usize,
+    pub position_deltas: Vec<Vec<Vec3>>,
+    pub normal_deltas: Vec<Vec<Vec3>>,
+}
+impl MorphTargets {
+    pub fn target_count(&self) -> usize {
+        self.position_deltas.len()
+    }
+
+    /// cpu reference of the blending done in `pbr_morph.vert`.
+    pub fn blend(&self, vertices: &[VertexPbr], weights: &[f32]) -> Vec<VertexPbr> {
+        vertices
+            .iter()
+            .enumerate()
+            .map(|(v, vertex)| {
+                let mut pos = vertex.pos;
+                let mut normal = vertex.normal;
+                for (t, weight) in weights.iter().enumerate().take(self.target_count()) {
+                    pos += *weight * self.position_deltas[t][v];
+                    normal += *weight * self.normal_deltas[t][v];
+                }
+                VertexPbr::new(pos, normal.normalize_or_zero(), vertex.tex_coord)
+            })
+            .collect()
+    }
+
+    /// deltas in the layout read by the shaders, a position and a normal delta for every
+    /// vertex of every target.
+    pub fn gpu_deltas(&self) -> Vec<Vec4> {
+        let mut deltas = Vec::with_capacity(2 * self.vertex_count * self.target_count());
+        for (positions, normals) in self.position_deltas.iter().zip(&self.normal_deltas) {
+            for (position, normal) in positions.iter().zip(normals) {
+                deltas.push(position.extend(0.0));
+                deltas.push(normal.extend(0.0));
+            }
+        }
+        deltas
+    }
+}
+
+/// gpu side of a morphed primitive. the delta buffer is shared by every swapchain image,
+/// the weight buffers hold a `uvec4` header (target count, vertex count) followed by
+/// `target_count` weights for every instance.
+#[derive(Clone, Debug)]
+pub struct MorphMesh {
+    pub targets: MorphTargets,
+    pub delta_buffer: Buffer,
+    pub delta_buffer_memory: DeviceMemory,
+    pub weight_buffers: GrowableBuffers,
+}
+impl MorphMesh {
+    pub unsafe fn create(
+        instance: &Instance,
+        device: &Device,
+        data: &mut AppData,
+        targets: MorphTargets,
+    ) -> anyhow::Result<Self> {
+        let deltas = targets.gpu_deltas();
+        let size = Self::delta_buffer_size(&targets);
+        let (delta_buffer, delta_buffer_memory) = create_buffer(
+            instance,
+            device,
+            data,
+            size,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+        let mapped =
+            device.map_memory(delta_buffer_memory, 0, size, vk::MemoryMapFlags::empty())?;
+        memcpy(deltas.as_ptr(), mapped.cast(), deltas.len());
+        device.unmap_memory(delta_buffer_memory);
+
+        let weight_buffers = GrowableBuffers::create(
+            instance,
+            device,
+            data,
+            Self::weight_buffer_size(targets.target_count(), 1),
+        )?;
+        Ok(Self {
+            targets,
+            delta_buffer,
+            delta_buffer_memory,
+            weight_buffers,
+        })
+    }
+
+    pub fn delta_buffer_size(targets: &MorphTargets) -> u64 {
+        // never zero sized, vulkan does not allow empty buffers
+        (size_of::<Vec4>() * (2 * targets.vertex_count * targets.target_count()).max(1)) as u64
+    }
+
+    pub fn weight_buffer_size(target_count: usize, instance_count: usize) -> u64 {
+        (size_of::<UVec4>() + size_of::<f32>() * (target_count * instance_count).max(1)) as u64
+    }
+
+    /// writes the morph weights of every instance, `weights[i]` belongs to instance `i`. the
+    /// buffers have to hold every instance, see `RenderObject::update_instances`.
+    pub unsafe fn update_weights(
+        &self,
+        device: &Device,
+        image_index: usize,
+        weights: &[Vec<f32>],
+    ) -> anyhow::Result<()> {
+        let target_count = self.targets.target_count();
+        let header = UVec4::new(target_count as u32, self.targets.vertex_count as u32, 0, 0);
+        self.weight_buffers.write(device, image_index, |mapped| {
+            memcpy(&header, mapped.cast(), 1);
+            let slots: *mut f32 = mapped.add(size_of::<UVec4>()).cast();
+            for (instance, instance_weights) in weights.iter().enumerate() {
+                for t in 0..target_count {
+                    let weight = instance_weights.get(t).copied().unwrap_or(0.0);
+                    *slots.add(instance * target_count + t) = weight;
+                }
+            }
+        })
+    }
+
+    pub unsafe fn destroy(&self, device: &Device) {
+        device.destroy_buffer(self.delta_buffer, None);
+        device.free_memory(self.delta_buffer_memory, None);
+        self.weight_buffers.destroy(device);
+    }
+}
@@ -0,0 +1,103 @@
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::game_objects::bounds::Ray;
+use crate::game_objects::render_object::ObjectId;
+use crate::game_objects::scene::Scene;
+use crate::vulkan::vertexbuffer_util::Vertex;
+
+/// normalized device coordinates of a cursor in a viewport starting at `min` with `size`, all
+/// in pixels. `None` outside of the viewport.
+pub fn cursor_ndc(cursor: Vec2, min: Vec2, size: Vec2) -> Option<Vec2> {
+    let uv = (cursor - min) / size;
+    let inside =
+        size.cmpgt(Vec2::ZERO).all() && uv.cmpge(Vec2::ZERO).all() && uv.cmplt(Vec2::ONE).all();
+    inside.then(|| uv * 2.0 - 1.0)
+}
+
+/// the distance along `ray` to a triangle hit from either side, moller trumbore.
+pub fn ray_triangle(ray: &Ray, [a, b, c]: [Vec3; 3]) -> Option<f32> {
+    let ab = b - a;
+    let ac = c - a;
+    let p = ray.direction.cross(ac);
+    let determinant = ab.dot(p);
+    // the ray runs parallel to the triangle
+    if determinant.abs() < f32::EPSILON * ab.length() * ac.length() * ray.direction.length() {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+    let s = ray.origin - a;
+    let u = s.dot(p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(ab);
+    let v = ray.direction.dot(q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let distance = ac.dot(q) * inverse;
+    (distance >= 0.0).then_some(distance)
+}
+
+/// the distance along `ray` to the closest triangle of a mesh placed by `matrix`. the ray is
+/// moved into the space of the mesh, which keeps distances along it.
+pub fn ray_mesh<V: Vertex>(
+    ray: &Ray,
+    matrix: Mat4,
+    vertices: &[V],
+    indices: &[u32],
+) -> Option<f32> {
+    if matrix.determinant() == 0.0 {
+        return None;
+    }
+    let inverse = matrix.inverse();
+    let local = Ray {
+        origin: inverse.transform_point3(ray.origin),
+        direction: inverse.transform_vector3(ray.direction),
+    };
+    let position = |index: u32| vertices.get(index as usize)?.position();
+    indices
+        .chunks_exact(3)
+        .filter_map(|triangle| {
+            let corners = [
+                position(triangle[0])?,
+                position(triangle[1])?,
+                position(triangle[2])?,
+            ];
+            ray_triangle(&local, corners)
+        })
+        .reduce(f32::min)
+}
+
+impl Scene {
+    /// the object whose meshes `ray` hits first and the distance to the hit. the candidates
+    /// come from the bvh in the order the ray enters their boxes, skinned and morphed meshes
+    /// are tested in their rest pose.
+    pub fn pick(&self, ray: &Ray) -> Option<(ObjectId, f32)> {
+        let mut best: Option<(ObjectId, f32)> = None;
+        for (id, entry) in self.bvh.ray_hits(ray, f32::INFINITY) {
+            if best.is_some_and(|(_, distance)| distance < entry) {
+                break;
+            }
+            let Some(object) = self.objects.get(id) else {
+                continue;
+            };
+            let matrix = object.global_matrix(self);
+            let hit = object
+                .render_objects
+                .iter()
+                .filter_map(|render_id| self.render_objects.get(*render_id))
+                .filter_map(|render_object| {
+                    let mesh = &render_object.vertex_data;
+                    ray_mesh(ray, matrix, &mesh.vertices, &mesh.indices)
+                })
+                .reduce(f32::min);
+            if let Some(distance) = hit
+                && best.is_none_or(|(_, best)| distance < best)
+            {
+                best = Some((id, distance));
+            }
+        }
+        best
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::asset_manager::hot_reload::LoadedFile;
+use crate::game_objects::render_object::{MaterialOverride, ObjectId, RenderId, RenderPin};
+use crate::game_objects::scene::{GameObject, Scene};
+use crate::game_objects::transform::Transform;
+
+/// object hierarchies captured once and stamped out any number of times by
+/// `Scene::instantiate`. the copies share the render objects of the prefab, so its meshes and
+/// textures are only uploaded by the first load. the prefab pins those render objects, they
+/// stay in the scene without instances until it is dropped.
+#[derive(Clone, Debug)]
+pub struct Prefab {
+    pub name: String,
+    /// every captured object with the id it had, parents come before their children. the ids
+    /// the objects refer to each other by are replaced with the ids of the copy.
+    pub objects: Vec<(ObjectId, GameObject)>,
+    pub roots: Vec<ObjectId>,
+    /// the loads of the files the objects were made from and the buffers and images they read,
+    /// tracked again for every copy so the copies reload with the file and keep their skins
+    /// and clips in saved scenes.
+    pub loads: Vec<(PathBuf, LoadedFile, Vec<PathBuf>)>,
+    /// keep the render objects of `objects` in the scene, see `RenderPin`.
+    pub pins: Vec<RenderPin>,
+}
+impl Prefab {
+    /// the hierarchies below `roots`, `None` when one of them is missing. the roots lose their
+    /// parent, they are placed by `Scene::instantiate`.
+    pub fn capture(scene: &Scene, name: impl Into<String>, roots: &[ObjectId]) -> Option<Self> {
+        if roots.iter().any(|root| scene.objects.get(*root).is_none()) {
+            return None;
+        }
+        let mut objects: Vec<(ObjectId, GameObject)> = vec![];
+        let mut stack: Vec<ObjectId> = roots.iter().rev().copied().collect();
+        while let Some(id) = stack.pop() {
+            let Some(object) = scene.objects.get(id) else {
+                continue;
+            };
+            if objects.iter().any(|(captured, _)| *captured == id) {
+                continue;
+            }
+            let mut object = object.clone();
+            if roots.contains(&id) {
+                object.parent = None;
+            }
+            stack.extend(object.children.iter().rev());
+            objects.push((id, object));
+        }
+        let loads = scene
+            .hot_reload
+            .files()
+            .flat_map(|(path, loads)| loads.iter().map(move |loaded| (path, loaded)))
+            .filter_map(|(path, loaded)| {
+                let nodes: HashMap<usize, ObjectId> = loaded
+                    .nodes
+                    .iter()
+                    .filter(|(_, id)| objects.iter().any(|(captured, _)| captured == *id))
+                    .map(|(node, id)| (*node, *id))
+                    .collect();
+                (!nodes.is_empty()).then(|| {
+                    (
+                        path.to_path_buf(),
+                        LoadedFile {
+                            nodes,
+                            primitives: loaded.primitives.clone(),
+                        },
+                        scene.hot_reload.dependencies(path),
+                    )
+                })
+            })
+            .collect();
+        let render_ids: HashSet<RenderId> = objects
+            .iter()
+            .flat_map(|(_, object)| object.render_objects.iter().copied())
+            .collect();
+        let pins = render_ids
+            .into_iter()
+            .filter_map(|id| Some(scene.render_objects.get(id)?.pin.clone()))
+            .collect();
+        Some(Self {
+            name: name.into(),
+            objects,
+            roots: roots.to_vec(),
+            loads,
+            pins,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+/// replaces the ids `object` refers to other objects by, ids missing from `ids` are kept.
+fn remap(object: &mut GameObject, ids: &HashMap<ObjectId, ObjectId>) {
+    let map = |id: &mut ObjectId| {
+        if let Some(new) = ids.get(id) {
+            *id = *new;
+        }
+    };
+    if let Some(parent) = &mut object.parent {
+        map(parent);
+    }
+    object.children.iter_mut().for_each(map);
+    if let Some(skin) = &mut object.skin {
+        skin.joints.iter_mut().for_each(map);
+    }
+    if let Some(player) = &mut object.animation {
+        for clip in &mut player.clips {
+            for channel in &mut clip.channels {
+                map(&mut channel.target);
+            }
+        }
+    }
+}
+
+impl Scene {
+    /// adds a copy of the objects of `prefab` that shares their render objects, like
+    /// `insert_instance` does for a single object. the roots are moved by `transform` like the
+    /// roots of `AssetLoads::load` and every copy is added to the bvh. returns the new roots.
+    pub fn instantiate(&mut self, prefab: &Prefab, transform: Transform) -> Vec<ObjectId> {
+        let mut ids = HashMap::new();
+        for (id, object) in &prefab.objects {
+            let copy = self.objects.insert(object.clone());
+            for render_id in &object.render_objects {
+                if let Some(render_object) = self.render_objects.get_mut(*render_id) {
+                    render_object.instances.push(copy);
+                }
+            }
+            ids.insert(*id, copy);
+        }
+        for copy in ids.values() {
+            if let Some(object) = self.objects.get_mut(*copy) {
+                remap(object, &ids);
+            }
+        }
+        for (path, loaded, dependencies) in &prefab.loads {
+            let nodes = loaded
+                .nodes
+                .iter()
+                .filter_map(|(node, id)| Some((*node, *ids.get(id)?)))
+                .collect();
+            self.hot_reload.track(
+                path,
+                LoadedFile {
+                    nodes,
+                    primitives: loaded.primitives.clone(),
+                },
+                dependencies.iter().cloned().chain([path.clone()]),
+            );
+        }
+        let roots: Vec<ObjectId> = prefab.roots.iter().map(|root| ids[root]).collect();
+        for root in &roots {
+            let _ = self.transform_object(*root, transform.clone());
+        }
+        for copy in ids.values() {
+            if let Some(aabb) = self.world_bounds(*copy) {
+                self.bvh.insert(*copy, aabb);
+            }
+        }
+        roots
+    }
+
+    /// sets the material factors of `root` and every object below it, a tint for one copy of
+    /// a prefab.
+    pub fn set_material_override(&mut self, root: ObjectId, material: MaterialOverride) {
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            if let Some(object) = self.objects.get_mut(id) {
+                object.material_override = material;
+                stack.extend(object.children.iter().copied());
+            }
+        }
+    }
+}
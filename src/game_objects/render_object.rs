@@ -1,25 +1,220 @@
+use crate::game_objects::light::light_buffer_size;
 use crate::game_objects::material::Material;
+use crate::game_objects::morph::MorphMesh;
 use crate::game_objects::scene::Sun;
+use crate::vulkan::buffer_util::GrowableBuffers;
 use crate::vulkan::descriptor_util::{create_pbr_descriptor_sets, create_uniform_buffers};
 use crate::vulkan::image_util::TextureData;
-use crate::vulkan::uniform_buffer_object::{GlobalUniform, OrthographicLight, PbrUniform};
-use crate::vulkan::vertexbuffer_util::{Vertex, VertexData};
+use crate::vulkan::light_clusters::cluster_buffer_size;
+use crate::vulkan::uniform_buffer_object::{
+    GlobalUniform, InstanceData, OrthographicLight, PbrUniform,
+};
+use crate::vulkan::vertexbuffer_util::{Vertex, VertexData, VertexSkin};
 use crate::winit_app::winit_render_app::AppData;
-use glam::Vec4;
-use terrors::OneOf;
+use glam::{Mat4, UVec4, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+use std::ptr::copy_nonoverlapping as memcpy;
+use std::sync::Arc;
 
 use vulkanalia::vk::{self, Buffer, DescriptorSet, DeviceMemory, DeviceV1_0, HasBuilder};
 use vulkanalia::{Device, Instance};
 
-#[derive(Copy, Clone, Debug)]
-pub struct RenderId(pub usize);
-#[derive(Clone, Copy, Debug)]
-pub struct ObjectId(pub usize);
+/// a slot of a `ParaSlab` and the generation of the value in it. the id stops resolving once
+/// its value is removed, even after the slot is reused.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RenderId {
+    pub index: usize,
+    pub generation: u32,
+}
+impl RenderId {
+    pub const fn new(index: usize, generation: u32) -> Self {
+        Self { index, generation }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ObjectId {
+    pub index: usize,
+    pub generation: u32,
+}
+impl ObjectId {
+    pub const fn new(index: usize, generation: u32) -> Self {
+        Self { index, generation }
+    }
+}
 
+/// every texture slot and factor of a gltf metallic-roughness material.
+/// slots the material does not use are filled with 1x1 default textures so that
+/// every descriptor binding is always valid. textures are shared through the `AssetRegistry`,
+/// which also destroys them.
 #[derive(Clone, Debug)]
 pub struct PBR {
-    pub texture_data: TextureData,
+    /// base color texture
+    pub texture_data: Arc<TextureData>,
+    pub base: Vec4,
+    pub normal_texture: Arc<TextureData>,
+    pub normal_scale: f32,
+    /// metalness is read from the blue channel, roughness from the green channel.
+    pub metallic_roughness_texture: Arc<TextureData>,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub occlusion_texture: Arc<TextureData>,
+    pub occlusion_strength: f32,
+    pub emissive_texture: Arc<TextureData>,
+    pub emissive: Vec3,
+}
+impl PBR {
+    /// textures in the order of their descriptor bindings, starting at binding 3.
+    pub fn textures(&self) -> [&TextureData; PBR_TEXTURE_COUNT] {
+        [
+            &*self.texture_data,
+            &*self.normal_texture,
+            &*self.metallic_roughness_texture,
+            &*self.occlusion_texture,
+            &*self.emissive_texture,
+        ]
+    }
+
+    pub fn uniform(&self) -> PbrUniform {
+        PbrUniform {
+            base: self.base,
+            emissive: self.emissive.extend(1.0),
+            normal_scale: self.normal_scale,
+            occlusion_strength: self.occlusion_strength,
+            metallic: self.metallic,
+            roughness: self.roughness,
+        }
+    }
+}
+pub const PBR_TEXTURE_COUNT: usize = 5;
+/// storage buffer binding holding the joint matrices of skinned objects.
+pub const JOINT_BINDING: u32 = 3 + PBR_TEXTURE_COUNT as u32;
+/// morph target position and normal deltas.
+pub const MORPH_DELTA_BINDING: u32 = JOINT_BINDING + 1;
+/// per instance morph target weights.
+pub const MORPH_WEIGHT_BINDING: u32 = JOINT_BINDING + 2;
+/// irradiance, prefiltered specular and brdf lookup table of `AppData::environment`.
+pub const ENVIRONMENT_TEXTURE_COUNT: usize = 3;
+pub const ENVIRONMENT_BINDING: u32 = MORPH_WEIGHT_BINDING + 1;
+/// depth map of the sun, see `AppData::shadow_map`.
+pub const SHADOW_BINDING: u32 = ENVIRONMENT_BINDING + ENVIRONMENT_TEXTURE_COUNT as u32;
+/// point and spot lights of the scene, see `light::update_lights`.
+pub const LIGHT_BINDING: u32 = SHADOW_BINDING + 1;
+/// light index lists of the clusters of the view frustum, see `light_clusters`.
+pub const CLUSTER_BINDING: u32 = LIGHT_BINDING + 1;
+/// per instance model matrices and material factors, see `RenderObject::update_instances`.
+pub const INSTANCE_BINDING: u32 = CLUSTER_BINDING + 1;
+
+pub fn instance_buffer_size(instance_count: usize) -> u64 {
+    (size_of::<InstanceData>() * instance_count.max(1)) as u64
+}
+
+/// factors a game object multiplies onto the materials of its render objects.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MaterialOverride {
     pub base: Vec4,
+    pub emissive: Vec3,
+}
+impl Default for MaterialOverride {
+    fn default() -> Self {
+        Self {
+            base: Vec4::ONE,
+            emissive: Vec3::ONE,
+        }
+    }
+}
+impl MaterialOverride {
+    /// the instance data of an object placed by `model`.
+    pub fn instance(&self, model: Mat4) -> InstanceData {
+        InstanceData {
+            model,
+            normal: model.inverse().transpose(),
+            base: self.base,
+            emissive: self.emissive.extend(1.0),
+        }
+    }
+}
+
+/// gpu side of a skinned primitive. the joint buffers hold a `uvec4` header with the joint
+/// count followed by `joint_count` matrices for every instance.
+#[derive(Clone, Debug)]
+pub struct SkinnedMesh {
+    pub vertex_buffer: Buffer,
+    pub vertex_buffer_memory: DeviceMemory,
+    pub joint_count: usize,
+    pub joint_buffers: GrowableBuffers,
+}
+impl SkinnedMesh {
+    /// uploads the joints and weights of every vertex and a joint buffer per swapchain image.
+    ///
+    /// # Safety
+    /// `device` must be created from `instance`, the buffers are freed by `destroy`.
+    pub unsafe fn create(
+        instance: &Instance,
+        device: &Device,
+        data: &mut AppData,
+        vertices: &[VertexSkin],
+        joint_count: usize,
+    ) -> anyhow::Result<Self> {
+        let (vertex_buffer, vertex_buffer_memory, _) = unsafe {
+            VertexData::<VertexSkin>::create_vertex_buffer(instance, device, data, vertices, false)
+        }?;
+        let joint_buffers = unsafe {
+            GrowableBuffers::create(
+                instance,
+                device,
+                data,
+                Self::joint_buffer_size(joint_count, 1),
+            )
+        }?;
+        Ok(Self {
+            vertex_buffer,
+            vertex_buffer_memory,
+            joint_count,
+            joint_buffers,
+        })
+    }
+
+    pub fn joint_buffer_size(joint_count: usize, instance_count: usize) -> u64 {
+        (size_of::<UVec4>() + size_of::<Mat4>() * joint_count * instance_count) as u64
+    }
+
+    /// writes the joint matrices of every instance, `joint_matrices[i]` belongs to instance `i`.
+    /// the buffers have to hold every instance, see `RenderObject::update_instances`.
+    ///
+    /// # Safety
+    /// the frame that last rendered `image_index` must have finished.
+    pub unsafe fn update_joints(
+        &self,
+        device: &Device,
+        image_index: usize,
+        joint_matrices: &[Vec<Mat4>],
+    ) -> anyhow::Result<()> {
+        let header = UVec4::new(self.joint_count as u32, 0, 0, 0);
+        unsafe {
+            self.joint_buffers.write(device, image_index, |mapped| {
+                memcpy(&header, mapped.cast(), 1);
+                let matrices: *mut Mat4 = mapped.add(size_of::<UVec4>()).cast();
+                for (instance, joints) in joint_matrices.iter().enumerate() {
+                    let count = joints.len().min(self.joint_count);
+                    memcpy(
+                        joints.as_ptr(),
+                        matrices.add(instance * self.joint_count),
+                        count,
+                    );
+                }
+            })
+        }
+    }
+
+    /// # Safety
+    /// no frame in flight may still use the buffers, see `AssetRegistry::retire`.
+    pub unsafe fn destroy(&self, device: &Device) {
+        unsafe {
+            device.destroy_buffer(self.vertex_buffer, None);
+            device.free_memory(self.vertex_buffer_memory, None);
+            self.joint_buffers.destroy(device);
+        }
+    }
 }
 
 pub trait Renderable {
@@ -82,52 +277,196 @@ where
             .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
             .buffer_info(buffer_info);
 
-        let info = vk::DescriptorImageInfo::builder()
-            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-            .image_view(self.pbr.texture_data.image_view)
-            .sampler(self.pbr.texture_data.sampler);
+        let image_infos = self.pbr.textures().map(|texture| {
+            [vk::DescriptorImageInfo::builder()
+                .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .image_view(texture.image_view)
+                .sampler(texture.sampler)]
+        });
+        let joint_info = self.skin.as_ref().map(|skin| {
+            [vk::DescriptorBufferInfo::builder()
+                .buffer(skin.joint_buffers.buffers[i])
+                .offset(0)
+                .range(skin.joint_buffers.sizes[i])]
+        });
+        let morph_infos = self.morph.as_ref().map(|morph| {
+            (
+                [vk::DescriptorBufferInfo::builder()
+                    .buffer(morph.delta_buffer)
+                    .offset(0)
+                    .range(MorphMesh::delta_buffer_size(&morph.targets))],
+                [vk::DescriptorBufferInfo::builder()
+                    .buffer(morph.weight_buffers.buffers[i])
+                    .offset(0)
+                    .range(morph.weight_buffers.sizes[i])],
+            )
+        });
+        let mut writes = vec![ortho_write, global_write, ubo_write];
+        if let Some((delta_info, weight_info)) = &morph_infos {
+            for (binding, buffer_info) in [
+                (MORPH_DELTA_BINDING, delta_info),
+                (MORPH_WEIGHT_BINDING, weight_info),
+            ] {
+                writes.push(
+                    vk::WriteDescriptorSet::builder()
+                        .dst_set(self.get_descriptor_sets()[i])
+                        .dst_binding(binding)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                        .buffer_info(buffer_info),
+                );
+            }
+        }
+        if let Some(buffer_info) = &joint_info {
+            writes.push(
+                vk::WriteDescriptorSet::builder()
+                    .dst_set(self.get_descriptor_sets()[i])
+                    .dst_binding(JOINT_BINDING)
+                    .dst_array_element(0)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                    .buffer_info(buffer_info),
+            );
+        }
+        for (binding, image_info) in image_infos.iter().enumerate() {
+            writes.push(
+                vk::WriteDescriptorSet::builder()
+                    .dst_set(self.get_descriptor_sets()[i])
+                    .dst_binding(3 + binding as u32)
+                    .dst_array_element(0)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(image_info),
+            );
+        }
+        let environment_infos = data.environment.textures().map(|texture| {
+            [vk::DescriptorImageInfo::builder()
+                .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .image_view(texture.image_view)
+                .sampler(texture.sampler)]
+        });
+        for (binding, image_info) in environment_infos.iter().enumerate() {
+            writes.push(
+                vk::WriteDescriptorSet::builder()
+                    .dst_set(self.get_descriptor_sets()[i])
+                    .dst_binding(ENVIRONMENT_BINDING + binding as u32)
+                    .dst_array_element(0)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(image_info),
+            );
+        }
 
-        let image_info = &[info];
-        let sampler_write = vk::WriteDescriptorSet::builder()
-            .dst_set(self.get_descriptor_sets()[i])
-            .dst_binding(3)
-            .dst_array_element(0)
-            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-            .image_info(image_info);
+        let shadow_info = [vk::DescriptorImageInfo::builder()
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .image_view(data.shadow_map.image_view)
+            .sampler(data.shadow_map.sampler)];
+        writes.push(
+            vk::WriteDescriptorSet::builder()
+                .dst_set(self.get_descriptor_sets()[i])
+                .dst_binding(SHADOW_BINDING)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .image_info(&shadow_info),
+        );
+        let light_info = [vk::DescriptorBufferInfo::builder()
+            .buffer(data.light_buffer[i])
+            .offset(0)
+            .range(light_buffer_size())];
+        writes.push(
+            vk::WriteDescriptorSet::builder()
+                .dst_set(self.get_descriptor_sets()[i])
+                .dst_binding(LIGHT_BINDING)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .buffer_info(&light_info),
+        );
+        let cluster_info = [vk::DescriptorBufferInfo::builder()
+            .buffer(data.cluster_buffer[i])
+            .offset(0)
+            .range(cluster_buffer_size())];
+        writes.push(
+            vk::WriteDescriptorSet::builder()
+                .dst_set(self.get_descriptor_sets()[i])
+                .dst_binding(CLUSTER_BINDING)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .buffer_info(&cluster_info),
+        );
+        let instance_info = [vk::DescriptorBufferInfo::builder()
+            .buffer(self.instance_buffers.buffers[i])
+            .offset(0)
+            .range(self.instance_buffers.sizes[i])];
+        writes.push(
+            vk::WriteDescriptorSet::builder()
+                .dst_set(self.get_descriptor_sets()[i])
+                .dst_binding(INSTANCE_BINDING)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .buffer_info(&instance_info),
+        );
 
-        unsafe {
-            device.update_descriptor_sets(
-                &[ortho_write, global_write, ubo_write, sampler_write],
-                &[] as &[vk::CopyDescriptorSet],
-            )
-        };
+        unsafe { device.update_descriptor_sets(&writes, &[] as &[vk::CopyDescriptorSet]) };
+    }
+}
+/// keeps a render object in the scene after its last instance is removed, as long as a clone of
+/// it is held, see `Prefab`.
+#[derive(Clone, Debug, Default)]
+pub struct RenderPin(Arc<()>);
+impl RenderPin {
+    /// whether a clone of the pin is held outside of its render object.
+    pub fn is_held(&self) -> bool {
+        Arc::strong_count(&self.0) > 1
     }
 }
+
 #[derive(Debug, Clone)]
 pub struct RenderObject<V>
 where
     V: Vertex,
 {
-    pub vertex_data: VertexData<V>,
+    /// shared between every render object made from the same primitive.
+    pub vertex_data: Arc<VertexData<V>>,
     pub pbr: PBR,
+    /// set for skinned primitives, drawn with the skinned pbr pipeline.
+    pub skin: Option<SkinnedMesh>,
+    /// set for primitives with morph targets, always set for skinned primitives since the
+    /// skinned pipeline blends morph targets as well.
+    pub morph: Option<MorphMesh>,
     pub uniform_buffers: Vec<Buffer>,
     pub uniform_buffers_memory: Vec<DeviceMemory>,
+    /// an `InstanceData` for every entry of `instances`.
+    pub instance_buffers: GrowableBuffers,
     pub descriptor_sets: Vec<DescriptorSet>,
     pub instances: Vec<ObjectId>,
+    /// instances at the front of the instance buffer inside the view frustum, only these are
+    /// drawn by the main pass while the shadow pass draws every instance.
+    pub visible: usize,
+    pub pin: RenderPin,
+}
+
+/// the optional gpu data that moves the vertices of a primitive.
+#[derive(Debug, Default)]
+pub struct Deformation {
+    pub skin: Option<SkinnedMesh>,
+    pub morph: Option<MorphMesh>,
 }
 
 impl<V> RenderObject<V>
 where
     V: Vertex,
 {
+    /// creates the uniform and instance buffers and the descriptor sets of a primitive.
+    ///
+    /// # Safety
+    /// `device` must be created from `instance`, the buffers are freed by `destroy`.
     pub unsafe fn create_render_object(
         instance: &Instance,
         device: &Device,
         data: &mut AppData,
-        vertex_data: VertexData<V>,
+        vertex_data: Arc<VertexData<V>>,
         pbr: PBR,
+        deformation: Deformation,
         sun: &mut Sun,
-    ) -> Result<RenderObject<V>, OneOf<(String, anyhow::Error)>> {
+    ) -> anyhow::Result<RenderObject<V>> {
+        let Deformation { skin, morph } = deformation;
         let mut uniform_buffers = vec![];
         let mut uniform_buffers_memory = vec![];
         (unsafe {
@@ -138,20 +477,99 @@ where
                 &mut uniform_buffers,
                 &mut uniform_buffers_memory,
             )
-            .map_err(OneOf::new)
         })?;
+        let instance_buffers =
+            unsafe { GrowableBuffers::create(instance, device, data, instance_buffer_size(1)) }?;
         let mut object = Self {
             vertex_data,
             pbr,
+            skin,
+            morph,
             uniform_buffers,
             uniform_buffers_memory,
+            instance_buffers,
             descriptor_sets: vec![],
             instances: Default::default(),
+            visible: 0,
+            pin: RenderPin::default(),
         };
-        (unsafe {
-            create_pbr_descriptor_sets::<V, PbrUniform>(device, data, sun, &mut object)
-                .map_err(|e| OneOf::new(e))
-        })?;
+        (unsafe { create_pbr_descriptor_sets::<V, PbrUniform>(device, data, sun, &mut object) })?;
         Ok(object)
     }
+
+    /// writes the instance data of a frame. the instance, joint and morph weight buffers of
+    /// `image_index` too small for the instances are grown first and the descriptor set of that
+    /// image is written again, the joints and weights are written by the caller.
+    ///
+    /// # Safety
+    /// the frame that last rendered `image_index` must have finished, the buffers and descriptor
+    /// sets of the other images are left alone.
+    pub unsafe fn update_instances(
+        &mut self,
+        instance: &Instance,
+        device: &Device,
+        data: &AppData,
+        sun: &mut Sun,
+        image_index: usize,
+        instances: &[InstanceData],
+    ) -> anyhow::Result<()> {
+        let count = instances.len();
+        let mut grown = unsafe {
+            self.instance_buffers.reserve(
+                instance,
+                device,
+                data,
+                image_index,
+                instance_buffer_size(count),
+            )
+        }?;
+        if let Some(skin) = &mut self.skin {
+            let size = SkinnedMesh::joint_buffer_size(skin.joint_count, count);
+            grown |= unsafe {
+                skin.joint_buffers
+                    .reserve(instance, device, data, image_index, size)
+            }?;
+        }
+        if let Some(morph) = &mut self.morph {
+            let size = MorphMesh::weight_buffer_size(morph.targets.target_count(), count);
+            grown |= unsafe {
+                morph
+                    .weight_buffers
+                    .reserve(instance, device, data, image_index, size)
+            }?;
+        }
+        if grown {
+            self.init_descriptor(device, data, sun, image_index);
+        }
+        unsafe {
+            self.instance_buffers.write(device, image_index, |mapped| {
+                memcpy(instances.as_ptr(), mapped.cast(), count);
+            })
+        }
+    }
+
+    /// frees the buffers owned by this render object. the descriptor sets go with their pool,
+    /// the shared vertex data and textures are left to the `AssetRegistry`.
+    /// # Safety
+    /// the device must be idle, or no frame in flight may still use the buffers, see
+    /// `AssetRegistry::retire`.
+    pub unsafe fn destroy(&self, device: &Device) {
+        unsafe {
+            for (buffer, memory) in self
+                .uniform_buffers
+                .iter()
+                .zip(&self.uniform_buffers_memory)
+            {
+                device.destroy_buffer(*buffer, None);
+                device.free_memory(*memory, None);
+            }
+            self.instance_buffers.destroy(device);
+            if let Some(skin) = &self.skin {
+                skin.destroy(device);
+            }
+            if let Some(morph) = &self.morph {
+                morph.destroy(device);
+            }
+        }
+    }
 }
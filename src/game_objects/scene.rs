@@ -1,128 +1,249 @@
+use crate::asset_manager::bevy_load::AssetLoads;
+use crate::asset_manager::error::AssetError;
+use crate::asset_manager::hot_reload::HotReload;
+use crate::asset_manager::registry::{AssetRegistry, Garbage};
+use crate::game_objects::animation::{AnimationPlayer, Skin};
+use crate::game_objects::bounds::{CullStats, Frustum};
+use crate::game_objects::bvh::Bvh;
 use crate::game_objects::camera::Camera;
+use crate::game_objects::light::Light;
 use crate::game_objects::skybox::SkyBox;
+use crate::game_objects::slabs::ParaSlab;
+use crate::vulkan::MAX_FRAMES_IN_FLIGHT;
 use crate::vulkan::input_state::InputState;
-use crate::vulkan::uniform_buffer_object::OrthographicLight;
+use crate::vulkan::shadow_map::CascadeSettings;
+use crate::vulkan::uniform_buffer_object::{InstanceData, OrthographicLight, UniformBuffer};
 use crate::vulkan::vertexbuffer_util::VertexPbr;
+use crate::winit_app::winit_render_app::AppData;
 use bevy::ecs::resource::Resource;
 use glam::Mat4;
-use slab::{IntoIter, Iter, IterMut, Slab};
+use log::error;
 use vulkanalia::vk::{self};
+use vulkanalia::{Device, Instance};
 
-use std::marker::PhantomData;
+use std::collections::HashSet;
 
-use crate::game_objects::render_object::{ObjectId, RenderId, RenderObject};
+use crate::game_objects::render_object::{MaterialOverride, ObjectId, RenderId, RenderObject};
 use crate::game_objects::transform::Transform;
 
-pub trait IsId: Copy {
-    fn get_id(&self) -> usize;
-    fn new(u: usize) -> Self;
-}
-impl IsId for RenderId {
-    fn get_id(&self) -> usize {
-        self.0
-    }
-
-    fn new(u: usize) -> Self {
-        Self(u)
-    }
-}
-impl IsId for ObjectId {
-    fn get_id(&self) -> usize {
-        self.0
-    }
-
-    fn new(u: usize) -> Self {
-        Self(u)
-    }
-}
-
-#[derive(Debug)]
-pub struct ParaSlab<Id, O>
-where
-    Id: IsId,
-{
-    slab: Slab<O>,
-    phantom: PhantomData<Id>,
-}
-
-impl<Id, O> Iterator for ParaSlab<Id, O>
-where
-    Id: IsId,
-{
-    type Item = O;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        todo!()
-    }
-}
-
-impl<Id, O> ParaSlab<Id, O>
-where
-    Id: IsId,
-{
-    pub fn new() -> Self {
-        ParaSlab {
-            slab: Slab::new(),
-            phantom: PhantomData,
-        }
-    }
-    pub fn iter(&self) -> Iter<'_, O> {
-        self.slab.iter()
-    }
-    pub fn into_iter(self) -> IntoIter<O> {
-        self.slab.into_iter()
-    }
-    pub fn iter_mut(&mut self) -> IterMut<'_, O> {
-        self.slab.iter_mut()
-    }
-    pub fn insert(&mut self, o: O) -> Id {
-        Id::new(self.slab.insert(o))
-    }
-    pub fn get(&self, id: Id) -> Option<&O> {
-        self.slab.get(id.get_id())
-    }
-    pub fn get_mut(&mut self, id: Id) -> Option<&mut O> {
-        self.slab.get_mut(id.get_id())
-    }
-    pub fn remove(&mut self, id: Id) -> O {
-        self.slab.remove(id.get_id())
-    }
-}
 pub type RenderSlab = ParaSlab<RenderId, RenderObject<VertexPbr>>;
 pub type ObjectSlab = ParaSlab<ObjectId, GameObject>;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct GameObject {
     pub name: String,
     pub transform: Transform,
     pub parent: Option<ObjectId>,
     pub children: Vec<ObjectId>,
     pub render_objects: Vec<RenderId>,
+    pub skin: Option<Skin>,
+    pub animation: Option<AnimationPlayer>,
+    /// weights of the morph targets of every render object of this instance.
+    pub morph_weights: Vec<f32>,
+    pub light: Option<Light>,
+    pub material_override: MaterialOverride,
 }
-///returns the global transform of the object in matrix form
 impl GameObject {
+    pub fn new(
+        name: impl Into<String>,
+        transform: Transform,
+        render_objects: Vec<RenderId>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            transform,
+            parent: None,
+            children: vec![],
+            render_objects,
+            skin: None,
+            animation: None,
+            morph_weights: vec![],
+            light: None,
+            material_override: MaterialOverride::default(),
+        }
+    }
+    ///starts the named animation clip, returns false if the object has no such clip.
+    pub fn play_animation(&mut self, name: &str) -> bool {
+        self.animation
+            .as_mut()
+            .is_some_and(|player| player.play_by_name(name))
+    }
+    pub fn stop_animation(&mut self) {
+        if let Some(player) = &mut self.animation {
+            player.stop();
+        }
+    }
+    ///returns the global transform of the object in matrix form
+    /// a parent that was removed is treated like no parent.
     pub fn global_matrix(&self, scene: &Scene) -> Mat4 {
-        let Some(id) = self.parent else {
-            return self.transform.matrix();
-        };
-        let Some(parent) = scene.objects.get(id) else {
-            panic!("none empty parent id should always be valid")
-        };
-        parent.global_matrix(scene) * self.transform.matrix()
+        match self.parent.and_then(|id| scene.objects.get(id)) {
+            Some(parent) => parent.global_matrix(scene) * self.transform.matrix(),
+            None => self.transform.matrix(),
+        }
     }
 }
-#[derive(Debug, Resource)]
+#[derive(Debug, Default, Resource)]
 pub struct Scene {
     pub(crate) camera: Camera,
     pub render_objects: RenderSlab,
     pub objects: ObjectSlab,
     pub skybox: Option<SkyBox>,
     pub sun: Sun,
-    pub selected_object: ObjectId,
+    pub selected_object: Option<ObjectId>,
+    /// assets that failed to load, shown in the gui until dismissed.
+    pub asset_errors: Vec<AssetError>,
+    pub assets: AssetRegistry,
+    /// files being read in the background, uploaded by `bevy_load::upload_read_assets`.
+    pub loads: AssetLoads,
+    /// watches the loaded files and queues a reload when they change.
+    pub hot_reload: HotReload,
+    /// draw and cull counts of the last frame, shown in the gui.
+    pub cull_stats: CullStats,
+    /// world bounds of every object, refit once per frame by `update`.
+    pub bvh: Bvh,
+    /// objects whose world bounds changed since the last `refit_bvh`, see `mark_moved`.
+    pub(crate) moved: HashSet<ObjectId>,
 }
 impl Scene {
+    pub fn report_asset_error(&mut self, error: AssetError) {
+        error!("{error}");
+        self.asset_errors.push(error);
+    }
+
     pub fn update(&mut self, delta: f32, input: &InputState) {
         self.camera.update(delta, input);
+        self.update_animations(delta);
+        self.refit_bvh();
+        self.retire_released();
+    }
+
+    /// retires the render objects without instances that no prefab pins anymore.
+    pub fn retire_released(&mut self) {
+        let released: Vec<RenderId> = self
+            .render_objects
+            .iter()
+            .filter(|(_, render)| render.instances.is_empty() && !render.pin.is_held())
+            .map(|(id, _)| id)
+            .collect();
+        for id in released {
+            if let Some(render) = self.render_objects.remove(id) {
+                self.assets.retire(
+                    Garbage::RenderObject(Box::new(render)),
+                    MAX_FRAMES_IN_FLIGHT,
+                );
+            }
+        }
+    }
+
+    ///advances every animation player and writes the sampled poses into the targeted objects.
+    pub fn update_animations(&mut self, delta: f32) {
+        let mut poses = vec![];
+        for (_, object) in self.objects.iter_mut() {
+            if let Some(player) = &mut object.animation {
+                player.advance(delta);
+                poses.extend(player.pose());
+            }
+        }
+        for (target, pose) in poses {
+            if let Some(object) = self.objects.get_mut(target) {
+                pose.apply(object);
+                self.moved.insert(target);
+            }
+        }
+    }
+
+    ///joint matrices of every skinned instance, instances without a skin get an empty list.
+    pub fn joint_matrices(&self, instances: &[ObjectId]) -> Vec<Vec<Mat4>> {
+        instances
+            .iter()
+            .map(|id| match self.objects.get(*id) {
+                Some(
+                    object @ GameObject {
+                        skin: Some(skin), ..
+                    },
+                ) => skin.joint_matrices(self, object),
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    ///model matrix and material factors of every instance, missing instances get the identity.
+    pub fn instance_data(&self, instances: &[ObjectId]) -> Vec<InstanceData> {
+        instances
+            .iter()
+            .map(|id| match self.objects.get(*id) {
+                Some(object) => object
+                    .material_override
+                    .instance(object.global_matrix(self)),
+                None => MaterialOverride::default().instance(Mat4::IDENTITY),
+            })
+            .collect()
+    }
+
+    ///writes the material, instances, joints and morph weights of every render object for the
+    ///frame of a swapchain image. instances in `frustum` are packed first, skinned and morphed
+    ///objects are never culled since they move out of the bounds of their vertices.
+    pub fn update_render_objects(
+        &mut self,
+        instance: &Instance,
+        device: &Device,
+        data: &AppData,
+        image_index: usize,
+        frustum: &Frustum,
+    ) -> anyhow::Result<()> {
+        self.cull_stats = CullStats::default();
+        let ids: Vec<RenderId> = self.render_objects.ids();
+        for id in ids {
+            let Some(object) = self.render_objects.get(id) else {
+                continue;
+            };
+            let (ids, visible) = if object.skin.is_some() || object.morph.is_some() {
+                (object.instances.clone(), object.instances.len())
+            } else {
+                self.cull_instances(&object.vertex_data.bounds, &object.instances, frustum)
+            };
+            let instances = self.instance_data(&ids);
+            let joints = self.joint_matrices(&ids);
+            let weights = self.morph_weights(&ids);
+            self.cull_stats.draw_calls += usize::from(visible > 0);
+            self.cull_stats.drawn += visible;
+            self.cull_stats.culled += ids.len() - visible;
+            let Some(object) = self.render_objects.get_mut(id) else {
+                continue;
+            };
+            object.visible = visible;
+            unsafe {
+                let ubo = object.pbr.uniform();
+                ubo.map_memory(device, object.uniform_buffers_memory[image_index])?;
+                object.update_instances(
+                    instance,
+                    device,
+                    data,
+                    &mut self.sun,
+                    image_index,
+                    &instances,
+                )?;
+                if let Some(skin) = &object.skin {
+                    skin.update_joints(device, image_index, &joints)?;
+                }
+                if let Some(morph) = &object.morph {
+                    morph.update_weights(device, image_index, &weights)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    ///morph weights of every instance, missing instances get no weights.
+    pub fn morph_weights(&self, instances: &[ObjectId]) -> Vec<Vec<f32>> {
+        instances
+            .iter()
+            .map(|id| {
+                self.objects
+                    .get(*id)
+                    .map_or(vec![], |object| object.morph_weights.clone())
+            })
+            .collect()
     }
 
     pub fn insert_instance(&mut self, object: GameObject) -> Option<ObjectId> {
@@ -132,29 +253,72 @@ impl Scene {
             let render_object = self.render_objects.get_mut(render_object_id)?;
             render_object.instances.push(instance_id.clone());
         }
+        self.moved.insert(instance_id);
         Some(instance_id)
     }
 
+    /// adds a render object to an existing object, false when either of them is missing.
+    pub fn attach_render_object(&mut self, id: ObjectId, render_id: RenderId) -> bool {
+        let (Some(object), Some(render_object)) = (
+            self.objects.get_mut(id),
+            self.render_objects.get_mut(render_id),
+        ) else {
+            return false;
+        };
+        if !object.render_objects.contains(&render_id) {
+            object.render_objects.push(render_id);
+            render_object.instances.push(id);
+            self.moved.insert(id);
+        }
+        true
+    }
+
     pub fn insert_from_transform(
         &mut self,
         transform: Transform,
         render_object_ids: &Vec<RenderId>,
     ) -> Option<ObjectId> {
-        self.insert_instance(GameObject {
-            name: "no name".into(),
+        self.insert_instance(GameObject::new(
+            "no name",
             transform,
-            children: vec![],
-            render_objects: render_object_ids.clone(),
-            parent: None,
-        })
+            render_object_ids.clone(),
+        ))
     }
+    /// removes a single object, its children become roots where they stand. see
+    /// `remove_subtree` to remove them with it.
     pub fn remove_instance(&mut self, id: ObjectId) -> Option<GameObject> {
-        let object = self.objects.remove(id);
+        let children = self.objects.get(id)?.children.clone();
+        for child in children {
+            let _ = self.detach(child, true);
+        }
+        let _ = self.detach(id, false);
+        self.remove_linked(id)
+    }
+
+    /// removes an object without touching the objects it is linked to. render objects go with
+    /// their last instance unless a prefab pins them, they are retired so `release_unused` can
+    /// drop their meshes and textures once no frame in flight draws them.
+    pub(crate) fn remove_linked(&mut self, id: ObjectId) -> Option<GameObject> {
+        let object = self.objects.remove(id)?;
+        self.bvh.remove(id);
         for render_id in &object.render_objects {
             // remove object reference from render_object
-            let render = self.render_objects.get_mut(*render_id)?;
-            let index = render.instances.iter().position(|i| i.0 == id.0).unwrap();
-            let _ind = render.instances.swap_remove(index);
+            let Some(render) = self.render_objects.get_mut(*render_id) else {
+                continue;
+            };
+            render.instances.retain(|instance| *instance != id);
+            if render.instances.is_empty()
+                && !render.pin.is_held()
+                && let Some(render) = self.render_objects.remove(*render_id)
+            {
+                self.assets.retire(
+                    Garbage::RenderObject(Box::new(render)),
+                    MAX_FRAMES_IN_FLIGHT,
+                );
+            }
+        }
+        if self.selected_object == Some(id) {
+            self.selected_object = None;
         }
         Some(object)
     }
@@ -164,6 +328,7 @@ impl Scene {
             instance.transform.position += transform.position;
             instance.transform.rotation *= transform.rotation;
             instance.transform.scale *= transform.scale;
+            self.moved.insert(id);
             Ok(())
         } else {
             Err("no object found".to_string())
@@ -171,22 +336,10 @@ impl Scene {
     }
 }
 
-impl Default for Scene {
-    fn default() -> Self {
-        Self {
-            camera: Camera::default(),
-            render_objects: ParaSlab::new(),
-            objects: ParaSlab::new(),
-            skybox: None,
-            sun: Sun::default(),
-            selected_object: ObjectId(0),
-        }
-    }
-}
-
 #[derive(Clone, Debug, Default)]
 pub struct Sun {
     pub omnidirectional_light: OrthographicLight,
+    pub cascades: CascadeSettings,
     pub buffer: Vec<vk::Buffer>,
     pub memory: Vec<vk::DeviceMemory>,
 }
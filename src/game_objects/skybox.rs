@@ -1,10 +1,15 @@
-use std::{fs::File, io::BufReader};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::{fs::File, io::BufReader, path::Path};
 
 use vulkanalia::{
     Device, Instance,
     vk::{self, DescriptorSet, DeviceV1_0, HasBuilder},
 };
 
+use crate::asset_manager::error::{AssetError, AssetLocation};
+use crate::asset_manager::load_skybox::{self, HdrCubemap};
+use crate::game_objects::environment::RADIANCE_SIZE;
 use crate::vulkan::{image_util::TextureData, uniform_buffer_object::GlobalUniform};
 use crate::winit_app::winit_render_app::AppData;
 
@@ -53,6 +58,72 @@ impl SkyBox {
 pub struct SkyBox {
     pub texture_data: TextureData,
     pub descriptor_sets: Vec<DescriptorSet>,
+    /// linear copy of the sky at most `RADIANCE_SIZE` wide, the image based lighting is made
+    /// from it.
+    pub radiance: HdrCubemap,
+    /// the files the sky was loaded from, saved with the scene.
+    pub source: SkyBoxSource,
+}
+
+/// the image files of a `SkyBox`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SkyBoxSource {
+    /// six png faces, see `SkyBox::load`.
+    Faces {
+        up: PathBuf,
+        down: PathBuf,
+        left: PathBuf,
+        right: PathBuf,
+        back: PathBuf,
+        front: PathBuf,
+    },
+    /// a radiance .hdr or openexr .exr image, see `SkyBox::load_hdr`.
+    Hdr(PathBuf),
+}
+impl SkyBoxSource {
+    /// every file of the sky.
+    pub fn paths_mut(&mut self) -> Vec<&mut PathBuf> {
+        match self {
+            Self::Faces {
+                up,
+                down,
+                left,
+                right,
+                back,
+                front,
+            } => vec![up, down, left, right, back, front],
+            Self::Hdr(path) => vec![path],
+        }
+    }
+    /// reads and uploads the sky again.
+    pub fn load(
+        &self,
+        instance: &Instance,
+        device: &Device,
+        data: &mut AppData,
+    ) -> anyhow::Result<SkyBox> {
+        match self {
+            Self::Faces {
+                up,
+                down,
+                left,
+                right,
+                back,
+                front,
+            } => SkyBox::load(
+                instance,
+                device,
+                data,
+                &up.to_string_lossy(),
+                &down.to_string_lossy(),
+                &left.to_string_lossy(),
+                &right.to_string_lossy(),
+                &back.to_string_lossy(),
+                &front.to_string_lossy(),
+            ),
+            Self::Hdr(path) => Ok(SkyBox::load_hdr(instance, device, data, path)?),
+        }
+    }
 }
 impl SkyBox {
     pub fn load(
@@ -91,13 +162,57 @@ impl SkyBox {
         println!("pixel sizes: {:?}", pixels.len());
         println!("x: {:?}, y: {:?}", x, y);
 
+        let radiance = HdrCubemap::from_rgba8_srgb(&pixels, x).downsample_to(RADIANCE_SIZE);
         let texture_data = unsafe {
-            TextureData::create_cubemap_from_data(instance, device, data, pixels, (x, y))
+            TextureData::create_cubemap_from_data(
+                instance,
+                device,
+                data,
+                pixels,
+                (x, y),
+                vk::Format::R8G8B8A8_SRGB,
+            )
         }?;
 
         return Ok(Self {
             texture_data,
             descriptor_sets: vec![],
+            radiance,
+            source: SkyBoxSource::Faces {
+                up: up.into(),
+                down: down.into(),
+                left: left.into(),
+                right: right.into(),
+                back: back.into(),
+                front: front.into(),
+            },
         });
     }
+    /// loads a single radiance .hdr or openexr .exr image, an equirectangular panorama or a horizontal or
+    /// vertical cross, into a float cube map.
+    pub fn load_hdr(
+        instance: &Instance,
+        device: &Device,
+        data: &mut AppData,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, AssetError> {
+        let cubemap = load_skybox::read_environment(&path)?;
+        let texture_data = unsafe {
+            TextureData::create_cubemap_from_data(
+                instance,
+                device,
+                data,
+                cubemap.to_rgba16f(),
+                (cubemap.size, cubemap.size),
+                vk::Format::R16G16B16A16_SFLOAT,
+            )
+        }
+        .map_err(|e| AssetError::upload(&AssetLocation::new(&path), e))?;
+        Ok(Self {
+            texture_data,
+            descriptor_sets: vec![],
+            radiance: cubemap.downsample_to(RADIANCE_SIZE),
+            source: SkyBoxSource::Hdr(path.as_ref().to_path_buf()),
+        })
+    }
 }
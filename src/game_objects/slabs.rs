@@ -1,181 +1,152 @@
-use slab::{IntoIter, Iter, IterMut, Slab};
+use slab::Slab;
 use std::marker::PhantomData;
 
-use crate::{
-    game_objects::{
-        game_objects::{GameObject, ObjectId},
-        render_object::RenderObject,
-        transform::Transform,
-    },
-    vulkan::vertexbuffer_util::Vertex,
-};
+use crate::game_objects::render_object::{ObjectId, RenderId};
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct PbrId(pub usize);
-
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct AnimatedPbrId(pub usize);
-
-pub trait IsId: Copy + PartialEq + Eq {
-    fn get_id(&self) -> usize;
-    fn new(u: usize) -> Self;
+pub trait IsId: Copy + PartialEq {
+    fn index(&self) -> usize;
+    fn generation(&self) -> u32;
+    fn from_parts(index: usize, generation: u32) -> Self;
 }
-pub trait RenderId: IsId {}
-impl IsId for AnimatedPbrId {
-    fn get_id(&self) -> usize {
-        self.0
-    }
-
-    fn new(u: usize) -> Self {
-        Self(u)
+impl IsId for RenderId {
+    fn index(&self) -> usize {
+        self.index
     }
-}
-impl IsId for PbrId {
-    fn get_id(&self) -> usize {
-        self.0
+    fn generation(&self) -> u32 {
+        self.generation
     }
-
-    fn new(u: usize) -> Self {
-        Self(u)
+    fn from_parts(index: usize, generation: u32) -> Self {
+        Self::new(index, generation)
     }
 }
 impl IsId for ObjectId {
-    fn get_id(&self) -> usize {
-        self.0
+    fn index(&self) -> usize {
+        self.index
     }
-
-    fn new(u: usize) -> Self {
-        Self(u)
+    fn generation(&self) -> u32 {
+        self.generation
+    }
+    fn from_parts(index: usize, generation: u32) -> Self {
+        Self::new(index, generation)
     }
 }
 
+/// a slab handing out generational ids. removing a value bumps the generation of its slot, so
+/// ids of removed values fail to resolve instead of reaching whatever is inserted there next.
 #[derive(Debug)]
 pub struct ParaSlab<Id, O>
 where
     Id: IsId,
 {
     slab: Slab<O>,
+    /// the generation of every slot the slab has used, occupied or not.
+    generations: Vec<u32>,
     phantom: PhantomData<Id>,
 }
 
-impl<Id, O> ParaSlab<Id, O>
+impl<Id, O> IntoIterator for ParaSlab<Id, O>
 where
     Id: IsId,
 {
-    pub fn new() -> Self {
-        ParaSlab {
-            slab: Slab::new(),
+    type Item = (Id, O);
+    type IntoIter = IntoIter<Id, O>;
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            values: self.slab.into_iter(),
+            generations: self.generations,
             phantom: PhantomData,
         }
     }
-    pub fn iter(&self) -> Iter<'_, O> {
-        self.slab.iter()
-    }
-    pub fn into_iter(self) -> IntoIter<O> {
-        self.slab.into_iter()
-    }
-    pub fn iter_mut(&mut self) -> IterMut<'_, O> {
-        self.slab.iter_mut()
-    }
-    pub fn insert(&mut self, o: O) -> Id {
-        Id::new(self.slab.insert(o))
-    }
-    pub fn get(&self, id: Id) -> Option<&O> {
-        self.slab.get(id.get_id())
-    }
-    pub fn get_mut(&mut self, id: Id) -> Option<&mut O> {
-        self.slab.get_mut(id.get_id())
-    }
-    pub fn remove(&mut self, id: Id) -> O {
-        self.slab.remove(id.get_id())
-    }
 }
 
-pub type RenderSlab<R, V>
-where
-    R: RenderId,
-= ParaSlab<R, RenderObject<V>>;
-pub type ObjectSlab<Oid, Rid, V> = ParaSlab<Oid, GameObject<Oid, Rid, V>>;
-
-pub fn insert_instance<Oid, Rid, V>(
-    renders: &mut RenderSlab<Oid, Rid, V>,
-    objects: &mut ObjectSlab<Oid, Rid, V>,
-    object: GameObject<Oid, Rid, V>,
-) -> Option<Oid>
+/// the values of a `ParaSlab` with their ids, in slot order.
+#[derive(Debug)]
+pub struct IntoIter<Id, O> {
+    values: slab::IntoIter<O>,
+    generations: Vec<u32>,
+    phantom: PhantomData<Id>,
+}
+impl<Id, O> Iterator for IntoIter<Id, O>
 where
-    Oid: IsId,
-    Rid: RenderId,
-    V: Vertex,
+    Id: IsId,
 {
-    let rids = object.render_objects.clone();
-    let instance_id = objects.insert(object);
-    for render_object_id in rids {
-        let render_object = renders.get_mut(render_object_id)?;
-        render_object.instances.push(instance_id.clone());
+    type Item = (Id, O);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, o) = self.values.next()?;
+        Some((Id::from_parts(index, self.generations[index]), o))
     }
-    Some(instance_id)
 }
 
-pub fn insert_from_transform<Oid, Rid, V>(
-    renders: &mut RenderSlab<Oid, Rid, V>,
-    objects: &mut ObjectSlab<Oid, Rid, V>,
-    transform: Transform,
-    render_object_ids: &Vec<Rid>,
-) -> Option<Oid>
+impl<Id, O> Default for ParaSlab<Id, O>
 where
-    Oid: IsId,
-    Rid: RenderId,
-    V: Vertex,
-{
-    insert_instance(
-        renders,
-        objects,
-        GameObject {
-            name: "no name".into(),
-            transform,
-            children: vec![],
-            render_objects: render_object_ids.clone(),
-            parent: None,
-            phantom_render: PhantomData,
-        },
-    )
-}
-pub fn remove_instance<Oid, Rid, V>(
-    objects: &mut ObjectSlab<Oid, Rid, V>,
-    renders: &mut RenderSlab<Oid, Rid, V>,
-    id: Oid,
-) -> Option<GameObject<Oid, Rid, V>>
-where
-    Oid: IsId,
-    Rid: RenderId,
-    V: Vertex,
+    Id: IsId,
 {
-    let object = objects.remove(id);
-    for render_id in &object.render_objects {
-        let render = renders.get_mut(*render_id)?;
-
-        let index = render.instances.iter().position(|i| *i == id).unwrap();
-        let _ind = render.instances.swap_remove(index);
+    fn default() -> Self {
+        Self::new()
     }
-    Some(object)
 }
 
-pub fn transform_object<Oid, Rid, V>(
-    objects: &mut ObjectSlab<Oid, Rid, V>,
-    id: Oid,
-    transform: Transform,
-) -> Result<(), String>
+impl<Id, O> ParaSlab<Id, O>
 where
-    Oid: IsId,
-    Rid: RenderId,
-    V: Vertex,
+    Id: IsId,
 {
-    if let Some(instance) = objects.get_mut(id) {
-        instance.transform.position += transform.position;
-        instance.transform.rotation *= transform.rotation;
-        instance.transform.scale *= transform.scale;
-        Ok(())
-    } else {
-        Err("no object found".to_string())
+    pub fn new() -> Self {
+        ParaSlab {
+            slab: Slab::new(),
+            generations: vec![],
+            phantom: PhantomData,
+        }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (Id, &O)> {
+        let generations = &self.generations;
+        self.slab
+            .iter()
+            .map(|(index, o)| (Id::from_parts(index, generations[index]), o))
+    }
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Id, &mut O)> {
+        let generations = &self.generations;
+        self.slab
+            .iter_mut()
+            .map(|(index, o)| (Id::from_parts(index, generations[index]), o))
+    }
+    /// ids of every value, in slot order.
+    pub fn ids(&self) -> Vec<Id> {
+        self.iter().map(|(id, _)| id).collect()
+    }
+    pub fn len(&self) -> usize {
+        self.slab.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.slab.is_empty()
+    }
+    pub fn insert(&mut self, o: O) -> Id {
+        let index = self.slab.insert(o);
+        if index == self.generations.len() {
+            self.generations.push(0);
+        }
+        Id::from_parts(index, self.generations[index])
+    }
+    pub fn contains(&self, id: Id) -> bool {
+        self.generations.get(id.index()) == Some(&id.generation()) && self.slab.contains(id.index())
+    }
+    pub fn get(&self, id: Id) -> Option<&O> {
+        if !self.contains(id) {
+            return None;
+        }
+        self.slab.get(id.index())
+    }
+    pub fn get_mut(&mut self, id: Id) -> Option<&mut O> {
+        if !self.contains(id) {
+            return None;
+        }
+        self.slab.get_mut(id.index())
+    }
+    /// the removed value, `None` when `id` was removed already.
+    pub fn remove(&mut self, id: Id) -> Option<O> {
+        if !self.contains(id) {
+            return None;
+        }
+        self.generations[id.index()] = self.generations[id.index()].wrapping_add(1);
+        Some(self.slab.remove(id.index()))
     }
 }
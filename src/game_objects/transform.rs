@@ -1,8 +1,9 @@
 use bevy::ecs::component::Component;
 use glam::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
 use std::{f32::consts::PI, ops::Mul};
 
-#[derive(Debug, PartialEq, Clone, Component)]
+#[derive(Debug, PartialEq, Clone, Component, Serialize, Deserialize)]
 pub struct Transform {
     pub position: Vec3,
     pub scale: Vec3,
@@ -57,6 +58,16 @@ impl Transform {
         }
     }
 
+    /// the transform of an affine `matrix`, shears are lost.
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        let (scale, rotation, position) = matrix.to_scale_rotation_translation();
+        Self {
+            position,
+            scale,
+            rotation,
+        }
+    }
+
     pub fn matrix(&self) -> Mat4 {
         Mat4::from_translation(self.position)
             * Mat4::from_quat(self.rotation)
@@ -24,7 +24,7 @@ use winit::window;
 use crate::winit_app::winit_render_app::AppData;
 use crate::{
     bevy_app::render::VulkanApp,
-    game_objects::scene::Scene,
+    game_objects::{bounds::CullStats, picking::cursor_ndc, scene::Scene},
     gui::{
         gui, menu,
         objects::{self, selected_object},
@@ -32,6 +32,7 @@ use crate::{
     vulkan::{
         image_util::TextureData,
         input_state::InputState,
+        shadow_map::{CascadeSettings, MAX_CASCADES, SplitScheme},
         uniform_buffer_object::GlobalUniform,
         vertexbuffer_util::{VertexData, VertexGui},
     },
@@ -196,6 +197,35 @@ impl Gui {
             self.enabled = !self.enabled;
         }
     }
+    /// origin and size of the 3d viewport in physical pixels, the rect of the paint callback
+    /// or the whole window without the gui.
+    pub fn viewport(&self, data: &AppData) -> (Vec2, Vec2) {
+        if !self.enabled {
+            return (Vec2::ZERO, full_viewport(data));
+        }
+        let ppp = self.egui_state.egui_ctx().pixels_per_point();
+        let Rect { min, max } = self.callback;
+        let min = Vec2::new(min.x, min.y) * ppp;
+        (min, Vec2::new(max.x, max.y) * ppp - min)
+    }
+    /// selects the object under the cursor when the left mouse button goes down in the 3d
+    /// viewport, clicks on the gui are left to egui.
+    pub fn pick_object(&self, scene: &mut Scene, data: &AppData, input: &mut InputState) {
+        if !input.mouse_left.is_entered()
+            || (self.enabled && self.egui_state.egui_ctx().wants_pointer_input())
+        {
+            return;
+        }
+        let (min, size) = self.viewport(data);
+        let Some(ndc) = cursor_ndc(input.mouse_position, min, size) else {
+            return;
+        };
+        let proj = scene.camera.projection_matrix(data, self);
+        let ray = scene.camera.ray_through(proj, ndc);
+        if let Some((id, _)) = scene.pick(&ray) {
+            scene.selected_object = Some(id);
+        }
+    }
     /// there must be an available winit_window to query. otherwise a panic will occur
     pub fn get_window_and_ctx(
         event_loop: &winit::event_loop::ActiveEventLoop,
@@ -605,6 +635,10 @@ pub fn show(data: &mut AppData, scene: &mut Scene, ctx: &egui::Context, ui: &mut
                         .range(0.0..=1.0),
                 )
             });
+            ui.separator();
+            shadow_settings(&mut scene.sun.cascades, ui);
+            ui.separator();
+            cull_stats(&scene.cull_stats, ui);
         });
 
     egui::TopBottomPanel::bottom("bottom panel")
@@ -613,8 +647,116 @@ pub fn show(data: &mut AppData, scene: &mut Scene, ctx: &egui::Context, ui: &mut
         .show(ctx, |ui| {
             selected_object(scene, ctx, ui);
         });
+    asset_errors(scene, ctx);
+    asset_loads(scene, ctx);
     paint_callback(ctx, ui)
 }
+
+/// the size of the swapchain images, the viewport when the gui is hidden.
+pub fn full_viewport(data: &AppData) -> Vec2 {
+    Vec2::new(
+        data.swapchain_extent.width as f32,
+        data.swapchain_extent.height as f32,
+    )
+}
+
+/// instances drawn and culled by the view frustum in the last frame.
+pub fn cull_stats(stats: &CullStats, ui: &mut Ui) {
+    ui.label("Culling");
+    ui.label(format!("Draw calls: {}", stats.draw_calls));
+    ui.label(format!("Drawn: {}", stats.drawn));
+    ui.label(format!("Culled: {}", stats.culled));
+}
+
+/// the cascades of the sun.
+pub fn shadow_settings(settings: &mut CascadeSettings, ui: &mut Ui) {
+    ui.label("Shadows");
+    ui.horizontal(|ui| {
+        ui.label("Cascades");
+        ui.add(egui::DragValue::new(&mut settings.count).range(1..=MAX_CASCADES))
+    });
+    ui.horizontal(|ui| {
+        ui.label("Splits");
+        egui::ComboBox::from_id_salt("cascade splits")
+            .selected_text(match settings.scheme {
+                SplitScheme::Uniform => "uniform",
+                SplitScheme::Logarithmic => "logarithmic",
+                SplitScheme::Practical(_) => "practical",
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut settings.scheme, SplitScheme::Uniform, "uniform");
+                ui.selectable_value(
+                    &mut settings.scheme,
+                    SplitScheme::Logarithmic,
+                    "logarithmic",
+                );
+                let practical = matches!(settings.scheme, SplitScheme::Practical(_));
+                if ui.selectable_label(practical, "practical").clicked() && !practical {
+                    settings.scheme = SplitScheme::Practical(0.8);
+                }
+            });
+    });
+    if let SplitScheme::Practical(lambda) = &mut settings.scheme {
+        ui.horizontal(|ui| {
+            ui.label("Logarithmic weight");
+            ui.add(egui::DragValue::new(lambda).speed(0.01).range(0.0..=1.0))
+        });
+    }
+    ui.horizontal(|ui| {
+        ui.label("Distance");
+        ui.add(egui::DragValue::new(&mut settings.distance).range(1.0..=f32::MAX))
+    });
+    ui.horizontal(|ui| {
+        ui.label("Blend");
+        ui.add(
+            egui::DragValue::new(&mut settings.blend)
+                .speed(0.01)
+                .range(0.0..=0.5),
+        )
+    });
+    ui.checkbox(&mut settings.debug, "Tint cascades");
+}
+
+/// lists the assets that failed to load until the user dismisses them.
+pub fn asset_errors(scene: &mut Scene, ctx: &egui::Context) {
+    if scene.asset_errors.is_empty() {
+        return;
+    }
+    let mut open = true;
+    egui::Window::new("Asset errors")
+        .open(&mut open)
+        .default_width(400.0)
+        .show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for error in &scene.asset_errors {
+                    ui.colored_label(egui::Color32::LIGHT_RED, error.to_string());
+                    ui.separator();
+                }
+            });
+        });
+    if !open {
+        scene.asset_errors.clear();
+    }
+}
+/// progress of the files still being read in the background.
+pub fn asset_loads(scene: &Scene, ctx: &egui::Context) {
+    if scene.loads.is_idle() {
+        return;
+    }
+    egui::Window::new("Loading")
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+        .resizable(false)
+        .collapsible(false)
+        .show(ctx, |ui| {
+            ui.add(egui::ProgressBar::new(scene.loads.progress()).show_percentage());
+            for path in scene.loads.pending() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(path.display().to_string());
+                });
+            }
+        });
+}
 pub fn filled_triangle(
     ui: &egui::Ui,
     rect: egui::Rect,
@@ -1,23 +1,28 @@
 use egui::{Context, DragValue, RichText, ScrollArea, Ui};
 
-use crate::game_objects::{render_object::ObjectId, scene::Scene};
+use crate::game_objects::{
+    animation::AnimationPlayer,
+    light::{Light, LightKind},
+    render_object::ObjectId,
+    scene::Scene,
+};
 
-pub fn show_objects(scene: &Scene, ctx: &Context, ui: &mut Ui) -> ObjectId {
+pub fn show_objects(scene: &Scene, ctx: &Context, ui: &mut Ui) -> Option<ObjectId> {
     let mut selected_object = scene.selected_object;
     // selected_object(scene, ctx, ui);
     ScrollArea::vertical().show(ui, |ui| {
         for (i, object) in scene.objects.iter() {
             if object.parent.is_none() {
                 if ui.button(object.name.clone()).clicked() {
-                    selected_object = ObjectId(i);
+                    selected_object = Some(i);
                 };
-                egui::CollapsingHeader::new(i.to_string()).show(ui, |ui| {
+                egui::CollapsingHeader::new(i.index.to_string()).show(ui, |ui| {
                     for i in &object.children {
                         if ui
                             .button(scene.objects.get(*i).unwrap().name.clone())
                             .clicked()
                         {
-                            selected_object = *i;
+                            selected_object = Some(*i);
                         };
                     }
                 });
@@ -28,9 +33,13 @@ pub fn show_objects(scene: &Scene, ctx: &Context, ui: &mut Ui) -> ObjectId {
 }
 
 pub fn selected_object(scene: &mut Scene, ctx: &Context, ui: &mut Ui) {
-    let Some(object) = scene.objects.get_mut(scene.selected_object) else {
+    let Some((id, object)) = scene
+        .selected_object
+        .and_then(|id| Some((id, scene.objects.get_mut(id)?)))
+    else {
         return;
     };
+    let before = object.transform.clone();
     let pos = &mut object.transform.position;
     let scale = &mut object.transform.scale;
     let mut rotation = object.transform.rotation.to_euler(glam::EulerRot::XYZ);
@@ -73,5 +82,116 @@ pub fn selected_object(scene: &mut Scene, ctx: &Context, ui: &mut Ui) {
                 glam::Quat::from_euler(glam::EulerRot::XYZ, rotation.0, rotation.1, rotation.2);
         };
     });
+    ui.label(RichText::new("tint"));
+    ui.horizontal(|ui| {
+        let tint = &mut object.material_override;
+        ui.label("base");
+        let mut base = tint.base.to_array();
+        if ui.color_edit_button_rgba_unmultiplied(&mut base).changed() {
+            tint.base = base.into();
+        }
+        ui.label("emissive");
+        let mut emissive = tint.emissive.to_array();
+        if ui.color_edit_button_rgb(&mut emissive).changed() {
+            tint.emissive = emissive.into();
+        }
+    });
+    if let Some(light) = &mut object.light {
+        light_settings(light, ui);
+    }
+    if let Some(player) = &mut object.animation {
+        animation_settings(player, ui);
+    }
+    if object.transform != before {
+        scene.mark_moved(id);
+    }
     ui.separator();
 }
+
+fn light_settings(light: &mut Light, ui: &mut Ui) {
+    ui.label(RichText::new("light"));
+    ui.horizontal(|ui| {
+        ui.label("color");
+        let mut color = light.color.to_array();
+        if ui.color_edit_button_rgb(&mut color).changed() {
+            light.color = color.into();
+        }
+        ui.label("intensity");
+        ui.add(
+            DragValue::new(&mut light.intensity)
+                .speed(0.1)
+                .range(0.0..=f32::MAX),
+        );
+    });
+    if let Some(range) = &mut light.range {
+        ui.horizontal(|ui| {
+            ui.label("range");
+            ui.add(DragValue::new(range).speed(0.1).range(0.0..=f32::MAX));
+        });
+    }
+    if let LightKind::Spot {
+        inner_cone,
+        outer_cone,
+    } = &mut light.kind
+    {
+        ui.horizontal(|ui| {
+            ui.label("inner cone");
+            ui.add(
+                DragValue::new(inner_cone)
+                    .speed(0.01)
+                    .range(0.0..=*outer_cone),
+            );
+            ui.label("outer cone");
+            ui.add(
+                DragValue::new(outer_cone)
+                    .speed(0.01)
+                    .range(0.0..=std::f32::consts::FRAC_PI_2),
+            );
+        });
+    }
+}
+
+fn animation_settings(player: &mut AnimationPlayer, ui: &mut Ui) {
+    ui.label(RichText::new("animation"));
+    let active = player.active_clip().map(|clip| clip.name.clone());
+    let mut play = None;
+    egui::ComboBox::from_label("clip")
+        .selected_text(active.as_deref().unwrap_or("none"))
+        .show_ui(ui, |ui| {
+            for (index, name) in player.clip_names().enumerate() {
+                if ui
+                    .selectable_label(player.active == Some(index), name)
+                    .clicked()
+                {
+                    play = Some(index);
+                }
+            }
+        });
+    if let Some(index) = play {
+        player.play(index);
+    }
+    ui.horizontal(|ui| {
+        if player.paused {
+            if ui.button("resume").clicked() {
+                player.resume();
+            }
+        } else if ui.button("pause").clicked() {
+            player.pause();
+        }
+        if ui.button("stop").clicked() {
+            player.stop();
+        }
+        ui.checkbox(&mut player.looping, "looping");
+        ui.label("speed");
+        ui.add(DragValue::new(&mut player.speed).speed(0.05));
+    });
+    if let Some(duration) = player.active_clip().map(|clip| clip.duration) {
+        let mut time = player.time;
+        if ui
+            .add(egui::Slider::new(&mut time, 0.0..=duration).text("time"))
+            .changed()
+        {
+            player.seek(time);
+        }
+    }
+}
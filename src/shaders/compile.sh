@@ -1,5 +1,11 @@
 /usr/local/bin/glslc glsl/pbr.vert -o spv/pbr_vert.spv
 /usr/local/bin/glslc glsl/pbr.frag -o spv/pbr_frag.spv
+/usr/local/bin/glslc glsl/pbr_skinned.vert -o spv/pbr_skinned_vert.spv
+/usr/local/bin/glslc glsl/pbr_morph.vert -o spv/pbr_morph_vert.spv
+
+/usr/local/bin/glslc glsl/shadow.vert -o spv/shadow_vert.spv
+/usr/local/bin/glslc glsl/shadow_skinned.vert -o spv/shadow_skinned_vert.spv
+/usr/local/bin/glslc glsl/shadow_morph.vert -o spv/shadow_morph_vert.spv
 
 /usr/local/bin/glslc glsl/skybox.vert -o spv/skybox_vert.spv
 /usr/local/bin/glslc glsl/skybox.frag -o spv/skybox_frag.spv
@@ -8,4 +14,7 @@
 /usr/local/bin/glslc glsl/pos_col_tex.frag -o spv/pos_col_tex_frag.spv
 
 /usr/local/bin/glslc glsl/gui.vert -o spv/gui_vert.spv
-/usr/local/bin/glslc glsl/gui.frag -o spv/gui_frag.spv
\ No newline at end of file
+/usr/local/bin/glslc glsl/gui.frag -o spv/gui_frag.spv
+
+# the shader tests compare these with the sources to catch binaries left stale
+cksum glsl/* > spv/checksums
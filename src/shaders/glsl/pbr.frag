@@ -4,6 +4,10 @@
 layout(binding = 0) uniform Sun {
     vec4 dir;
     vec4 color;
+    //world to shadow map clip space of every cascade
+    mat4 cascades[4];
+    //x cascade count, y blended fraction of each cascade, z tints the cascades when 1
+    vec4 cascade_info;
 } sun;
 layout (binding = 1) uniform Global {
     mat4 view;
@@ -13,33 +17,259 @@ layout (binding = 1) uniform Global {
 } global_ubo;
 
 layout(binding = 2) uniform UniformBufferObject {
-    mat4 model[10];
     vec4 base;
+    vec4 emissive;
+    float normal_scale;
+    float occlusion_strength;
+    float metallic;
+    float roughness;
 } ubo;
 
 layout(binding = 3) uniform sampler2D texSampler;
+layout(binding = 4) uniform sampler2D normalSampler;
+//g = roughness, b = metallic
+layout(binding = 5) uniform sampler2D metallicRoughnessSampler;
+layout(binding = 6) uniform sampler2D occlusionSampler;
+layout(binding = 7) uniform sampler2D emissiveSampler;
+//bindings 8 to 10 are storage buffers of the vertex stage
+layout(binding = 11) uniform samplerCube irradianceMap;
+layout(binding = 12) uniform samplerCube prefilteredMap;
+//x scales f0, y is added to it
+layout(binding = 13) uniform sampler2D brdfLut;
+//depth of the sun with a layer per cascade, the sampler compares against it
+layout(binding = 14) uniform sampler2DArrayShadow shadowMap;
+
+struct PointLight {
+    //w is the range, 0 for an unlimited light
+    vec4 position;
+    vec4 direction;
+    //color times intensity
+    vec4 color;
+    //x scales and y offsets the cosine to the spot direction
+    vec4 cone;
+};
+layout(std430, binding = 15) readonly buffer Lights {
+    uvec4 header;
+    PointLight lights[];
+} light_buffer;
+
+//matches light_clusters::CLUSTER_DIMENSIONS
+const uint CLUSTER_COUNT = 16 * 9 * 24;
+//tiles are uniform on the screen, slices logarithmic in view space depth
+layout(std430, binding = 16) readonly buffer Clusters {
+    mat4 view;
+    mat4 proj;
+    uvec4 dimensions;
+    //near, far, slice count over log(far / near)
+    vec4 depth;
+    //offset into indices and light count of every cluster
+    uvec2 ranges[CLUSTER_COUNT];
+    uint indices[];
+} clusters;
 
 layout(location = 0) in vec3 fragNormal;
 layout(location = 1) in vec2 fragTexCoord;
+layout(location = 2) in vec3 fragWorldPos;
+//material factors of the instance, multiplied with the factors of the material
+layout(location = 3) flat in vec4 fragBase;
+layout(location = 4) flat in vec3 fragEmissive;
 
 layout(location = 0) out vec4 outColor;
 
+//builds a tangent frame from screen space derivatives since the meshes carry no tangents.
+vec3 perturbNormal(vec3 normal) {
+    vec3 tangentNormal = texture(normalSampler, fragTexCoord).xyz * 2.0 - 1.0;
+    tangentNormal.xy *= ubo.normal_scale;
+    vec3 dp1 = dFdx(fragWorldPos);
+    vec3 dp2 = dFdy(fragWorldPos);
+    vec2 duv1 = dFdx(fragTexCoord);
+    vec2 duv2 = dFdy(fragTexCoord);
+    vec3 dp2perp = cross(dp2, normal);
+    vec3 dp1perp = cross(normal, dp1);
+    vec3 t = dp2perp * duv1.x + dp1perp * duv2.x;
+    vec3 b = dp2perp * duv1.y + dp1perp * duv2.y;
+    float invmax = inversesqrt(max(dot(t, t), dot(b, b)));
+    if (isinf(invmax) || isnan(invmax)) {
+        return normal;
+    }
+    mat3 tbn = mat3(t * invmax, b * invmax, normal);
+    return normalize(tbn * tangentNormal);
+}
+
+const float PI = 3.14159265359;
+
+//trowbridge-reitz ggx normal distribution
+float distributionGGX(vec3 n, vec3 h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float nDotH = max(dot(n, h), 0.0);
+    float denom = nDotH * nDotH * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denom * denom);
+}
+
+float geometrySchlickGGX(float nDotV, float roughness) {
+    float r = roughness + 1.0;
+    float k = (r * r) / 8.0;
+    return nDotV / (nDotV * (1.0 - k) + k);
+}
+
+//smith geometry term, shadowing from both the light and the view direction
+float geometrySmith(vec3 n, vec3 v, vec3 l, float roughness) {
+    return geometrySchlickGGX(max(dot(n, v), 0.0), roughness)
+         * geometrySchlickGGX(max(dot(n, l), 0.0), roughness);
+}
+
+vec3 fresnelSchlick(float cosTheta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+//fresnel of the whole environment, rough surfaces reflect less at grazing angles
+vec3 fresnelSchlickRoughness(float cosTheta, vec3 f0, float roughness) {
+    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+//3x3 taps of the filtered comparison in one cascade. the position is pushed along the normal
+//by a texel first so grazing surfaces do not shadow themselves
+float cascadeVisibility(int cascade, vec3 normal, float slope) {
+    mat4 viewProj = sun.cascades[cascade];
+    vec2 texel = 1.0 / vec2(textureSize(shadowMap, 0).xy);
+    //world space size of a texel, clip space spans two units across the map
+    float scale = length(vec3(viewProj[0][0], viewProj[1][0], viewProj[2][0]));
+    vec3 position = fragWorldPos + normal * 2.0 * texel.x / scale * slope;
+    vec4 clip = viewProj * vec4(position, 1.0);
+    vec3 coord = clip.xyz / clip.w;
+    if (coord.z > 1.0) {
+        return 1.0;
+    }
+    vec2 uv = coord.xy * 0.5 + 0.5;
+    float lit = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            lit += texture(shadowMap, vec4(uv + vec2(x, y) * texel, float(cascade), coord.z));
+        }
+    }
+    return lit / 9.0;
+}
+
+//how far inside the map of a cascade the fragment is, 1 in the center and 0 at the border
+float cascadeCoverage(int cascade) {
+    vec4 clip = sun.cascades[cascade] * vec4(fragWorldPos, 1.0);
+    vec2 ndc = clip.xy / clip.w;
+    return 1.0 - max(abs(ndc.x), abs(ndc.y));
+}
+
+//fraction of the sun reaching the fragment from the finest cascade holding it, faded into the
+//next cascade towards its border. `cascade` is the one used, the cascade count outside of all
+float sunVisibility(vec3 normal, vec3 lightDir, out int cascade) {
+    int count = int(sun.cascade_info.x);
+    float blend = sun.cascade_info.y;
+    float slope = 1.0 - max(dot(normal, lightDir), 0.0);
+    for (cascade = 0; cascade < count; cascade++) {
+        float coverage = cascadeCoverage(cascade);
+        if (coverage <= 0.0) {
+            continue;
+        }
+        float visibility = cascadeVisibility(cascade, normal, slope);
+        if (coverage < blend && cascade + 1 < count) {
+            float next = cascadeVisibility(cascade + 1, normal, slope);
+            visibility = mix(next, visibility, coverage / blend);
+        }
+        return visibility;
+    }
+    return 1.0;
+}
+
+//tints of the cascade debug view
+const vec3 CASCADE_COLORS[4] = vec3[](
+    vec3(1.0, 0.4, 0.4),
+    vec3(0.4, 1.0, 0.4),
+    vec3(0.4, 0.4, 1.0),
+    vec3(1.0, 1.0, 0.4)
+);
+
+//cook-torrance brdf times the incoming radiance for a single light
+vec3 cookTorrance(vec3 n, vec3 v, vec3 l, vec3 radiance, vec3 albedo, float metallic, float roughness) {
+    vec3 h = normalize(v + l);
+    vec3 f0 = mix(vec3(0.04), albedo, metallic);
+    float ndf = distributionGGX(n, h, roughness);
+    float g = geometrySmith(n, v, l, roughness);
+    vec3 f = fresnelSchlick(max(dot(h, v), 0.0), f0);
+
+    float nDotL = max(dot(n, l), 0.0);
+    vec3 specular = (ndf * g * f) / (4.0 * max(dot(n, v), 0.0) * nDotL + 0.0001);
+    vec3 kd = (vec3(1.0) - f) * (1.0 - metallic);
+    return (kd * albedo / PI + specular) * radiance * nDotL;
+}
+
+//radiance of a point or spot light reaching the fragment from the direction `l`.
+//inverse square falloff, windowed to zero at the range like KHR_lights_punctual
+vec3 pointRadiance(PointLight light, out vec3 l) {
+    vec3 toLight = light.position.xyz - fragWorldPos;
+    float distance2 = max(dot(toLight, toLight), 0.0001);
+    l = toLight * inversesqrt(distance2);
+    float attenuation = 1.0 / distance2;
+    float range = light.position.w;
+    if (range > 0.0) {
+        float ratio = distance2 / (range * range);
+        float window = clamp(1.0 - ratio * ratio, 0.0, 1.0);
+        attenuation *= window * window;
+    }
+    float cone = clamp(dot(light.direction.xyz, -l) * light.cone.x + light.cone.y, 0.0, 1.0);
+    return light.color.rgb * attenuation * cone * cone;
+}
+
+//the cluster holding the fragment, clamped like light_clusters::ClusterGrid::cluster_of
+uint clusterIndex() {
+    vec4 view = clusters.view * vec4(fragWorldPos, 1.0);
+    vec4 clip = clusters.proj * view;
+    uvec3 dimensions = clusters.dimensions.xyz;
+    vec2 tile = clamp((clip.xy / clip.w * 0.5 + 0.5) * vec2(dimensions.xy), vec2(0.0),
+        vec2(dimensions.xy) - 1.0);
+    float slice = clamp(log(-view.z / clusters.depth.x) * clusters.depth.z, 0.0,
+        float(dimensions.z) - 1.0);
+    uvec3 cluster = uvec3(tile, slice);
+    return cluster.x + dimensions.x * (cluster.y + dimensions.y * cluster.z);
+}
 
 void main() {
+    vec3 cameraPos = global_ubo.view[3].xyz;
+    vec3 normal = perturbNormal(normalize(fragNormal));
+    vec3 viewDir = normalize(cameraPos - fragWorldPos);
+    vec3 lightDir = normalize(sun.dir.xyz);
+
+    vec4 baseColor = ubo.base * fragBase * texture(texSampler, fragTexCoord);
+    vec4 metallicRoughness = texture(metallicRoughnessSampler, fragTexCoord);
+    float metallic = clamp(ubo.metallic * metallicRoughness.b, 0.0, 1.0);
+    //keep a minimum roughness so the ggx lobe never collapses to a point
+    float roughness = clamp(ubo.roughness * metallicRoughness.g, 0.04, 1.0);
+    vec3 albedo = baseColor.rgb;
+
+    int cascade;
+    float visibility = sunVisibility(normalize(fragNormal), lightDir, cascade);
+    vec3 color = cookTorrance(normal, viewDir, lightDir, sun.color.rgb, albedo, metallic, roughness)
+        * visibility;
+    uvec2 range = clusters.ranges[clusterIndex()];
+    for (uint i = range.x; i < range.x + range.y; i++) {
+        vec3 l;
+        vec3 radiance = pointRadiance(light_buffer.lights[clusters.indices[i]], l);
+        color += cookTorrance(normal, viewDir, l, radiance, albedo, metallic, roughness);
+    }
 
-    vec4 surfacePos = global_ubo.view*inverse(global_ubo.proj)*gl_FragCoord;
-    vec3 cameraDir = normalize((global_ubo.view*vec4(0.0,0.0,0.0,1.0)).xyz );
-    vec3 normal = normalize(fragNormal);
-    vec3 lightDir   = sun.dir.xyz;
-    vec3 viewDir    = normalize(-global_ubo.view*vec4(0.0,0.0,0.0,1.0) - surfacePos).xyz;
-    vec3 halfwayDir = normalize(lightDir + viewDir);
-    float spec = pow(max(dot(normal, halfwayDir), 0.0), 10.0);
-    vec3 specular = sun.color.xyz * spec;
-    vec4 color = ubo.base*texture(texSampler, fragTexCoord);
-    float lambertian = pow((dot(normal,sun.dir.xyz)*0.5 +0.5),2.0);
-    color *= lambertian;
-    color += vec4(specular,0.0);
-    outColor = color;
-    //outColor = vec4(global_ubo.x,global_ubo.y,0.0,1.0);
-   //outColor = vec4(1000.0);
+    float occlusion = mix(1.0, texture(occlusionSampler, fragTexCoord).r, ubo.occlusion_strength);
+    //image based lighting, split sum approximation of the specular part
+    vec3 f0 = mix(vec3(0.04), albedo, metallic);
+    float nDotV = max(dot(normal, viewDir), 0.0);
+    vec3 f = fresnelSchlickRoughness(nDotV, f0, roughness);
+    vec3 kd = (vec3(1.0) - f) * (1.0 - metallic);
+    vec3 diffuse = texture(irradianceMap, normal).rgb * albedo;
+    float lod = roughness * float(textureQueryLevels(prefilteredMap) - 1);
+    vec3 prefiltered = textureLod(prefilteredMap, reflect(-viewDir, normal), lod).rgb;
+    vec2 brdf = texture(brdfLut, vec2(nDotV, roughness)).rg;
+    vec3 specular = prefiltered * (f * brdf.x + brdf.y);
+    color += (kd * diffuse + specular) * occlusion;
+    color += ubo.emissive.rgb * fragEmissive * texture(emissiveSampler, fragTexCoord).rgb;
+    if (sun.cascade_info.z > 0.5 && cascade < int(sun.cascade_info.x)) {
+        color *= CASCADE_COLORS[cascade];
+    }
+    outColor = vec4(color, baseColor.a);
 }
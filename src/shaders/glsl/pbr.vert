@@ -6,10 +6,16 @@ layout(binding=1)uniform Global{
     int y;
 }global_ubo;
 
-layout(binding=2)uniform UniformBufferObject{
-    mat4 model[10];
+//model and normal matrix and material factors of every instance
+struct Instance{
+    mat4 model;
+    mat4 normal;
     vec4 base;
-}ubo;
+    vec4 emissive;
+};
+layout(std430,binding=17)readonly buffer Instances{
+    Instance instances[];
+}instance_buffer;
 
 layout(location=0)in vec3 inPosition;
 layout(location=1)in vec3 inNormal;
@@ -17,14 +23,21 @@ layout(location=2)in vec2 inTexCoord;
 
 layout(location=0)out vec3 fragNormal;
 layout(location=1)out vec2 fragTexCoord;
+layout(location=2)out vec3 fragWorldPos;
+layout(location=3)flat out vec4 fragBase;
+layout(location=4)flat out vec3 fragEmissive;
 
 layout(push_constant)uniform constants{
     mat4 proj_inv_view;
 }PushConstants;
 
 void main(){
-    mat4 mat=PushConstants.proj_inv_view*ubo.model[gl_InstanceIndex];
+    Instance instance=instance_buffer.instances[gl_InstanceIndex];
+    mat4 mat=PushConstants.proj_inv_view*instance.model;
     gl_Position=global_ubo.proj*mat*vec4(inPosition,1.);
-    fragNormal=(inverse(transpose(ubo.model[gl_InstanceIndex]))*vec4(inNormal,0.)).xyz;
+    fragNormal=(instance.normal*vec4(inNormal,0.)).xyz;
     fragTexCoord=inTexCoord;
+    fragBase=instance.base;
+    fragEmissive=instance.emissive.rgb;
+    fragWorldPos=(instance.model*vec4(inPosition,1.)).xyz;
 }
@@ -0,0 +1,78 @@
+#version 450
+layout(binding=1)uniform Global{
+    mat4 view;
+    mat4 proj;
+    int x;
+    int y;
+}global_ubo;
+
+//model and normal matrix and material factors of every instance
+struct Instance{
+    mat4 model;
+    mat4 normal;
+    vec4 base;
+    vec4 emissive;
+};
+layout(std430,binding=17)readonly buffer Instances{
+    Instance instances[];
+}instance_buffer;
+
+//header[0] holds the joint count, the joints of instance i start at i*header[0]
+layout(std430,binding=8)readonly buffer Joints{
+    uvec4 header;
+    mat4 joints[];
+}joint_buffer;
+
+//position and normal delta of every vertex of every morph target
+layout(std430,binding=9)readonly buffer MorphDeltas{
+    vec4 deltas[];
+}morph_deltas;
+
+//header[0] holds the target count, header[1] the vertex count
+layout(std430,binding=10)readonly buffer MorphWeights{
+    uvec4 header;
+    float weights[];
+}morph_weights;
+
+layout(location=0)in vec3 inPosition;
+layout(location=1)in vec3 inNormal;
+layout(location=2)in vec2 inTexCoord;
+layout(location=3)in uvec4 inJoints;
+layout(location=4)in vec4 inWeights;
+
+layout(location=0)out vec3 fragNormal;
+layout(location=1)out vec2 fragTexCoord;
+layout(location=2)out vec3 fragWorldPos;
+layout(location=3)flat out vec4 fragBase;
+layout(location=4)flat out vec3 fragEmissive;
+
+layout(push_constant)uniform constants{
+    mat4 proj_inv_view;
+}PushConstants;
+
+void main(){
+    Instance instance=instance_buffer.instances[gl_InstanceIndex];
+    vec3 position=inPosition;
+    vec3 normal=inNormal;
+    uint targets=morph_weights.header[0];
+    uint vertexCount=morph_weights.header[1];
+    for(uint t=0;t<targets;t++){
+        float weight=morph_weights.weights[uint(gl_InstanceIndex)*targets+t];
+        uint delta=2*(t*vertexCount+uint(gl_VertexIndex));
+        position+=weight*morph_deltas.deltas[delta].xyz;
+        normal+=weight*morph_deltas.deltas[delta+1].xyz;
+    }
+    uint first=uint(gl_InstanceIndex)*joint_buffer.header[0];
+    mat4 skin=inWeights.x*joint_buffer.joints[first+inJoints.x]
+        +inWeights.y*joint_buffer.joints[first+inJoints.y]
+        +inWeights.z*joint_buffer.joints[first+inJoints.z]
+        +inWeights.w*joint_buffer.joints[first+inJoints.w];
+    mat4 model=instance.model*skin;
+    mat4 mat=PushConstants.proj_inv_view*model;
+    gl_Position=global_ubo.proj*mat*vec4(position,1.);
+    fragNormal=(inverse(transpose(model))*vec4(normal,0.)).xyz;
+    fragTexCoord=inTexCoord;
+    fragBase=instance.base;
+    fragEmissive=instance.emissive.rgb;
+    fragWorldPos=(model*vec4(position,1.)).xyz;
+}
@@ -0,0 +1,23 @@
+#version 450
+//model and normal matrix and material factors of every instance
+struct Instance{
+    mat4 model;
+    mat4 normal;
+    vec4 base;
+    vec4 emissive;
+};
+layout(std430,binding=17)readonly buffer Instances{
+    Instance instances[];
+}instance_buffer;
+
+layout(location=0)in vec3 inPosition;
+
+//world to shadow map clip space of the cascade being drawn
+layout(push_constant)uniform constants{
+    mat4 view_proj;
+}cascade;
+
+void main(){
+    Instance instance=instance_buffer.instances[gl_InstanceIndex];
+    gl_Position=cascade.view_proj*instance.model*vec4(inPosition,1.);
+}
@@ -0,0 +1,54 @@
+#version 450
+//model and normal matrix and material factors of every instance
+struct Instance{
+    mat4 model;
+    mat4 normal;
+    vec4 base;
+    vec4 emissive;
+};
+layout(std430,binding=17)readonly buffer Instances{
+    Instance instances[];
+}instance_buffer;
+
+//header[0] holds the joint count, the joints of instance i start at i*header[0]
+layout(std430,binding=8)readonly buffer Joints{
+    uvec4 header;
+    mat4 joints[];
+}joint_buffer;
+
+//position and normal delta of every vertex of every morph target
+layout(std430,binding=9)readonly buffer MorphDeltas{
+    vec4 deltas[];
+}morph_deltas;
+
+//header[0] holds the target count, header[1] the vertex count
+layout(std430,binding=10)readonly buffer MorphWeights{
+    uvec4 header;
+    float weights[];
+}morph_weights;
+
+layout(location=0)in vec3 inPosition;
+layout(location=3)in uvec4 inJoints;
+layout(location=4)in vec4 inWeights;
+
+//world to shadow map clip space of the cascade being drawn
+layout(push_constant)uniform constants{
+    mat4 view_proj;
+}cascade;
+
+void main(){
+    Instance instance=instance_buffer.instances[gl_InstanceIndex];
+    vec3 position=inPosition;
+    uint targets=morph_weights.header[0];
+    uint vertexCount=morph_weights.header[1];
+    for(uint t=0;t<targets;t++){
+        float weight=morph_weights.weights[uint(gl_InstanceIndex)*targets+t];
+        position+=weight*morph_deltas.deltas[2*(t*vertexCount+uint(gl_VertexIndex))].xyz;
+    }
+    uint first=uint(gl_InstanceIndex)*joint_buffer.header[0];
+    mat4 skin=inWeights.x*joint_buffer.joints[first+inJoints.x]
+        +inWeights.y*joint_buffer.joints[first+inJoints.y]
+        +inWeights.z*joint_buffer.joints[first+inJoints.z]
+        +inWeights.w*joint_buffer.joints[first+inJoints.w];
+    gl_Position=cascade.view_proj*instance.model*skin*vec4(position,1.);
+}
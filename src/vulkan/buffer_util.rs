@@ -101,3 +101,106 @@ pub unsafe fn end_single_time_commands(
 
     Ok(())
 }
+
+/// host visible storage buffers, one for every swapchain image, replaced by larger ones when
+/// their contents outgrow them. every image grows its own buffer, the others may still be read
+/// by frames in flight.
+#[derive(Clone, Debug, Default)]
+pub struct GrowableBuffers {
+    pub buffers: Vec<vk::Buffer>,
+    pub memory: Vec<vk::DeviceMemory>,
+    /// bytes of the buffer of every swapchain image.
+    pub sizes: Vec<vk::DeviceSize>,
+}
+impl GrowableBuffers {
+    pub unsafe fn create(
+        instance: &Instance,
+        device: &Device,
+        data: &AppData,
+        size: vk::DeviceSize,
+    ) -> Result<Self> {
+        let mut buffers = Self::default();
+        for _ in 0..data.swapchain_images.len() {
+            let (buffer, memory) = create_storage_buffer(instance, device, data, size.max(1))?;
+            buffers.buffers.push(buffer);
+            buffers.memory.push(memory);
+            buffers.sizes.push(size.max(1));
+        }
+        Ok(buffers)
+    }
+
+    /// makes the buffer of `image_index` hold at least `size` bytes, see `grown_size`. returns
+    /// true when it was replaced and the descriptor set of that image has to be written again.
+    ///
+    /// # Safety
+    /// the old buffer is destroyed right away, the frame that last rendered `image_index`
+    /// must have finished.
+    pub unsafe fn reserve(
+        &mut self,
+        instance: &Instance,
+        device: &Device,
+        data: &AppData,
+        image_index: usize,
+        size: vk::DeviceSize,
+    ) -> Result<bool> {
+        if size <= self.sizes[image_index] {
+            return Ok(false);
+        }
+        let grown = grown_size(self.sizes[image_index], size);
+        let (buffer, memory) = create_storage_buffer(instance, device, data, grown)?;
+        device.destroy_buffer(self.buffers[image_index], None);
+        device.free_memory(self.memory[image_index], None);
+        self.buffers[image_index] = buffer;
+        self.memory[image_index] = memory;
+        self.sizes[image_index] = grown;
+        Ok(true)
+    }
+
+    /// maps the buffer of a swapchain image for `write`.
+    pub unsafe fn write(
+        &self,
+        device: &Device,
+        image_index: usize,
+        write: impl FnOnce(*mut u8),
+    ) -> Result<()> {
+        let memory = self.memory[image_index];
+        let size = self.sizes[image_index];
+        let mapped = device.map_memory(memory, 0, size, vk::MemoryMapFlags::empty())?;
+        write(mapped.cast());
+        device.unmap_memory(memory);
+        Ok(())
+    }
+
+    pub unsafe fn destroy(&self, device: &Device) {
+        for (buffer, memory) in self.buffers.iter().zip(&self.memory) {
+            device.destroy_buffer(*buffer, None);
+            device.free_memory(*memory, None);
+        }
+    }
+}
+
+unsafe fn create_storage_buffer(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    size: vk::DeviceSize,
+) -> Result<(vk::Buffer, vk::DeviceMemory)> {
+    create_buffer(
+        instance,
+        device,
+        data,
+        size,
+        vk::BufferUsageFlags::STORAGE_BUFFER,
+        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+    )
+}
+
+/// `size` doubled until it holds `needed` bytes, so a steadily growing buffer is only
+/// replaced a logarithmic number of times.
+pub fn grown_size(size: vk::DeviceSize, needed: vk::DeviceSize) -> vk::DeviceSize {
+    let mut size = size.max(1);
+    while size < needed {
+        size *= 2;
+    }
+    size
+}
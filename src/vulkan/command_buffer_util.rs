@@ -1,17 +1,17 @@
 #![allow(unsafe_op_in_unsafe_fn, clippy::missing_safety_doc)]
 use crate::game_objects::scene::Scene;
-use crate::gui::gui::Gui;
+use crate::gui::gui::{Gui, full_viewport};
+use crate::vulkan::shadow_map::SHADOW_MAP_SIZE;
 use crate::winit_app::winit_render_app::AppData;
 use egui::Rect;
+use glam::Vec2;
 use vulkanalia::vk::{DeviceV1_0, HasBuilder};
 use vulkanalia::{Device, vk};
-use winit::window::Window;
 
 pub unsafe fn create_command_buffers(
     device: &Device,
     scene: &mut Scene,
     data: &mut AppData,
-    window: &Window,
     gui: Option<&Gui>,
 ) -> anyhow::Result<()> {
     for i in 0..data.framebuffers.len() {
@@ -22,7 +22,7 @@ pub unsafe fn create_command_buffers(
             .command_buffer_count(1);
         command_center.command_buffers = device.allocate_command_buffers(&allocate_info)?;
         assert_eq!(command_center.command_buffers.len(), 1);
-        create_command_buffer(device, scene, data, window, gui, i)?;
+        create_command_buffer(device, scene, data, gui, i)?;
     }
     Ok(())
 }
@@ -31,7 +31,6 @@ pub unsafe fn create_command_buffer(
     device: &Device,
     scene: &mut Scene,
     data: &mut AppData,
-    window: &Window,
     gui: Option<&Gui>,
     i: usize,
 ) -> anyhow::Result<()> {
@@ -44,6 +43,7 @@ pub unsafe fn create_command_buffer(
         .inheritance_info(&inheritance); // Optional.
 
     device.begin_command_buffer(*command_buffer, &info)?;
+    record_shadow_pass(device, *command_buffer, scene, data, i);
 
     let render_area = vk::Rect2D::builder()
         .offset(vk::Offset2D::default())
@@ -151,40 +151,48 @@ pub unsafe fn create_command_buffer(
         vk::PipelineBindPoint::GRAPHICS,
         data.pbr_pipeline,
     );
-    let viewports = if let Some(gui) = gui
-        && gui.enabled
-    {
-        let Rect { min, max } = gui.callback;
-
-        let ppp = gui.egui_state.egui_ctx().pixels_per_point();
-        let x = ppp * min.x;
-        let y = ppp * min.y;
-        let w = max.x * ppp - x;
-        let h = max.y * ppp - y;
-        &[vk::Viewport::builder()
-            .x(x)
-            .y(y)
-            .width(w)
-            .height(h)
-            .min_depth(0.0)
-            .max_depth(1.0)]
-    } else {
-        &[vk::Viewport::builder()
-            .x(0.0)
-            .y(0.0)
-            .width(data.swapchain_extent.width as f32)
-            .height(data.swapchain_extent.height as f32)
-            .min_depth(0.0)
-            .max_depth(1.0)]
+    let (min, size) = match gui {
+        Some(gui) => gui.viewport(data),
+        None => (Vec2::ZERO, full_viewport(data)),
     };
+    let viewports = &[vk::Viewport::builder()
+        .x(min.x)
+        .y(min.y)
+        .width(size.x)
+        .height(size.y)
+        .min_depth(0.0)
+        .max_depth(1.0)];
     device.cmd_set_viewport(*command_buffer, 0, viewports);
     for (_, object) in scene.render_objects.iter() {
-        device.cmd_bind_vertex_buffers(
-            *command_buffer,
-            0,
-            &[object.vertex_data.vertex_buffer],
-            &[0],
-        );
+        if object.visible == 0 {
+            continue;
+        }
+        if let Some(skin) = &object.skin {
+            device.cmd_bind_pipeline(
+                *command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                data.skinned_pbr_pipeline,
+            );
+            device.cmd_bind_vertex_buffers(
+                *command_buffer,
+                0,
+                &[object.vertex_data.vertex_buffer, skin.vertex_buffer],
+                &[0, 0],
+            );
+        } else {
+            let pipeline = if object.morph.is_some() {
+                data.morph_pbr_pipeline
+            } else {
+                data.pbr_pipeline
+            };
+            device.cmd_bind_pipeline(*command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline);
+            device.cmd_bind_vertex_buffers(
+                *command_buffer,
+                0,
+                &[object.vertex_data.vertex_buffer],
+                &[0],
+            );
+        }
         device.cmd_bind_index_buffer(
             *command_buffer,
             object.vertex_data.index_buffer,
@@ -212,7 +220,7 @@ pub unsafe fn create_command_buffer(
         device.cmd_draw_indexed(
             *command_buffer,
             object.vertex_data.indices.len()/*INDICES.len()*/ as u32,
-            object.instances.len() as u32,
+            object.visible as u32,
             0,
             0,
             0,
@@ -243,3 +251,110 @@ pub unsafe fn create_command_buffer(
 
     Ok(())
 }
+
+/// draws every render object into each cascade of the depth map of the sun, the main render
+/// pass samples it. layers without a cascade are only cleared, every layer has to be readable.
+unsafe fn record_shadow_pass(
+    device: &Device,
+    command_buffer: vk::CommandBuffer,
+    scene: &Scene,
+    data: &AppData,
+    i: usize,
+) {
+    let render_area = vk::Rect2D::builder()
+        .offset(vk::Offset2D::default())
+        .extent(vk::Extent2D {
+            width: SHADOW_MAP_SIZE,
+            height: SHADOW_MAP_SIZE,
+        });
+    let clear_values = &[vk::ClearValue {
+        depth_stencil: vk::ClearDepthStencilValue {
+            depth: 1.0,
+            stencil: 0,
+        },
+    }];
+    let cascade_count = scene.sun.cascades.cascade_count();
+    for (cascade, framebuffer) in data.shadow_map.framebuffers.iter().enumerate() {
+        let info = vk::RenderPassBeginInfo::builder()
+            .render_pass(data.shadow_map.render_pass)
+            .framebuffer(*framebuffer)
+            .render_area(render_area)
+            .clear_values(clear_values);
+        device.cmd_begin_render_pass(command_buffer, &info, vk::SubpassContents::INLINE);
+        if cascade < cascade_count {
+            let view_proj: [u8; 64] = std::mem::transmute(
+                scene.sun.omnidirectional_light.cascades[cascade].to_cols_array(),
+            );
+            record_shadow_casters(device, command_buffer, scene, data, i, &view_proj);
+        }
+        device.cmd_end_render_pass(command_buffer);
+    }
+}
+
+/// the shadow pipelines take the light matrix of the cascade as their push constant.
+unsafe fn record_shadow_casters(
+    device: &Device,
+    command_buffer: vk::CommandBuffer,
+    scene: &Scene,
+    data: &AppData,
+    i: usize,
+    view_proj: &[u8],
+) {
+    for (_, object) in scene.render_objects.iter() {
+        if let Some(skin) = &object.skin {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                data.shadow_map.skinned_pipeline,
+            );
+            device.cmd_bind_vertex_buffers(
+                command_buffer,
+                0,
+                &[object.vertex_data.vertex_buffer, skin.vertex_buffer],
+                &[0, 0],
+            );
+        } else {
+            let pipeline = if object.morph.is_some() {
+                data.shadow_map.morph_pipeline
+            } else {
+                data.shadow_map.pipeline
+            };
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline);
+            device.cmd_bind_vertex_buffers(
+                command_buffer,
+                0,
+                &[object.vertex_data.vertex_buffer],
+                &[0],
+            );
+        }
+        device.cmd_bind_index_buffer(
+            command_buffer,
+            object.vertex_data.index_buffer,
+            0,
+            vk::IndexType::UINT32,
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            data.pbr_pipeline_layout,
+            0,
+            &[object.descriptor_sets[i]],
+            &[],
+        );
+        device.cmd_push_constants(
+            command_buffer,
+            data.pbr_pipeline_layout,
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            0,
+            view_proj,
+        );
+        device.cmd_draw_indexed(
+            command_buffer,
+            object.vertex_data.indices.len() as u32,
+            object.instances.len() as u32,
+            0,
+            0,
+            0,
+        );
+    }
+}
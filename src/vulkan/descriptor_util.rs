@@ -1,8 +1,14 @@
 #![allow(unsafe_op_in_unsafe_fn, clippy::missing_safety_doc)]
 
-use crate::game_objects::render_object::{RenderObject, Renderable};
+use crate::game_objects::light::light_buffer_size;
+use crate::game_objects::render_object::{
+    CLUSTER_BINDING, ENVIRONMENT_BINDING, ENVIRONMENT_TEXTURE_COUNT, INSTANCE_BINDING,
+    JOINT_BINDING, LIGHT_BINDING, MORPH_DELTA_BINDING, MORPH_WEIGHT_BINDING, PBR_TEXTURE_COUNT,
+    RenderObject, Renderable, SHADOW_BINDING,
+};
 use crate::game_objects::scene::{Scene, Sun};
 use crate::vulkan::buffer_util::create_buffer;
+use crate::vulkan::light_clusters::cluster_buffer_size;
 use crate::vulkan::uniform_buffer_object::{GlobalUniform, OrthographicLight, UniformBuffer};
 use crate::vulkan::vertexbuffer_util::Vertex;
 use crate::winit_app::winit_render_app::AppData;
@@ -67,15 +73,72 @@ pub unsafe fn pbr_descriptor_set_layout(device: &Device, data: &mut AppData) ->
         .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
         .descriptor_count(1)
         .stage_flags(vk::ShaderStageFlags::all());
-    //main color texture
-    let sampler_binding = vk::DescriptorSetLayoutBinding::builder()
-        .binding(3)
-        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-        .descriptor_count(1)
-        .stage_flags(vk::ShaderStageFlags::FRAGMENT);
-
-    let bindings = &[camera, ortho_light, object_binding, sampler_binding];
-    let info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(bindings);
+    let mut bindings = vec![camera, ortho_light, object_binding];
+    //base color, normal, metallic-roughness, occlusion and emissive textures
+    for i in 0..PBR_TEXTURE_COUNT as u32 {
+        bindings.push(
+            vk::DescriptorSetLayoutBinding::builder()
+                .binding(3 + i)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::FRAGMENT),
+        );
+    }
+    //model matrices and material factors of every instance
+    bindings.push(
+        vk::DescriptorSetLayoutBinding::builder()
+            .binding(INSTANCE_BINDING)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::VERTEX),
+    );
+    //joint matrices, only written and read for skinned objects
+    bindings.push(
+        vk::DescriptorSetLayoutBinding::builder()
+            .binding(JOINT_BINDING)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::VERTEX),
+    );
+    //morph target deltas and weights, only written and read for morphed objects
+    for binding in [MORPH_DELTA_BINDING, MORPH_WEIGHT_BINDING] {
+        bindings.push(
+            vk::DescriptorSetLayoutBinding::builder()
+                .binding(binding)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::VERTEX),
+        );
+    }
+    //irradiance, prefiltered specular and brdf lookup table of the image based lighting
+    for i in 0..ENVIRONMENT_TEXTURE_COUNT as u32 {
+        bindings.push(
+            vk::DescriptorSetLayoutBinding::builder()
+                .binding(ENVIRONMENT_BINDING + i)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::FRAGMENT),
+        );
+    }
+    //depth map of the sun, compared against in the sampler
+    bindings.push(
+        vk::DescriptorSetLayoutBinding::builder()
+            .binding(SHADOW_BINDING)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT),
+    );
+    //point and spot lights and the lights of every cluster of the view frustum
+    for binding in [LIGHT_BINDING, CLUSTER_BINDING] {
+        bindings.push(
+            vk::DescriptorSetLayoutBinding::builder()
+                .binding(binding)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::FRAGMENT),
+        );
+    }
+    let info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings);
     data.pbr_descriptor_set_layout = device.create_descriptor_set_layout(&info, None)?;
 
     Ok(())
@@ -132,6 +195,38 @@ pub unsafe fn create_global_buffers(
         data.global_buffer_memory.push(new_uniform_buffer_memory);
     }
 
+    data.light_buffer.clear();
+    data.light_buffer_memory.clear();
+
+    for _ in 0..data.swapchain_images.len() {
+        let (new_storage_buffer, new_storage_buffer_memory) = create_buffer(
+            instance,
+            device,
+            data,
+            light_buffer_size(),
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+        data.light_buffer.push(new_storage_buffer);
+        data.light_buffer_memory.push(new_storage_buffer_memory);
+    }
+
+    data.cluster_buffer.clear();
+    data.cluster_buffer_memory.clear();
+
+    for _ in 0..data.swapchain_images.len() {
+        let (new_storage_buffer, new_storage_buffer_memory) = create_buffer(
+            instance,
+            device,
+            data,
+            cluster_buffer_size(),
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+        data.cluster_buffer.push(new_storage_buffer);
+        data.cluster_buffer_memory.push(new_storage_buffer_memory);
+    }
+
     scene.sun.buffer.clear();
     scene.sun.memory.clear();
 
@@ -166,9 +261,18 @@ pub unsafe fn create_descriptor_pool(
 
     let sampler_size = vk::DescriptorPoolSize::builder()
         .type_(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-        .descriptor_count(data.swapchain_images.len() as u32 * max_objects + GLOBAL_SAMPLERS);
+        .descriptor_count(
+            data.swapchain_images.len() as u32
+                * max_objects
+                * (PBR_TEXTURE_COUNT + ENVIRONMENT_TEXTURE_COUNT + 1) as u32
+                + GLOBAL_SAMPLERS,
+        );
+
+    let storage_size = vk::DescriptorPoolSize::builder()
+        .type_(vk::DescriptorType::STORAGE_BUFFER)
+        .descriptor_count(data.swapchain_images.len() as u32 * max_objects * 6);
 
-    let pool_sizes = &[ubo_size, sampler_size];
+    let pool_sizes = &[ubo_size, sampler_size, storage_size];
     let info = vk::DescriptorPoolCreateInfo::builder()
         .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET) //?
         .pool_sizes(pool_sizes)
@@ -12,7 +12,7 @@ use std::path::PathBuf;
 use vulkanalia::vk::{DeviceV1_0, HasBuilder, InstanceV1_0};
 use vulkanalia::{Device, Instance, vk};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct TextureData {
     pub mip_levels: u32,
     pub image: vk::Image,
@@ -106,7 +106,15 @@ impl TextureData {
     ) -> Result<TextureData> {
         let (mip_levels, image, image_memory) =
             Self::load_texture_from_path_buf(instance, device, data, image_path)?;
-        Self::create_texture(device, data, mip_levels, image, image_memory, 1)
+        Self::create_texture(
+            device,
+            data,
+            mip_levels,
+            image,
+            image_memory,
+            1,
+            vk::Format::R8G8B8A8_SRGB,
+        )
     }
     pub unsafe fn create_texture_from_data(
         instance: &Instance,
@@ -115,9 +123,36 @@ impl TextureData {
         pixels: Vec<u8>,
         size: (u32, u32),
     ) -> Result<TextureData> {
-        let (mip_levels, image, image_memory) =
-            Self::create_texture_image(instance, device, data, pixels, (size.0, size.1, 1))?;
-        Self::create_texture(device, data, mip_levels, image, image_memory, 1)
+        let format = vk::Format::R8G8B8A8_SRGB;
+        let (mip_levels, image, image_memory) = Self::create_texture_image(
+            instance,
+            device,
+            data,
+            pixels,
+            (size.0, size.1, 1),
+            format,
+        )?;
+        Self::create_texture(device, data, mip_levels, image, image_memory, 1, format)
+    }
+    /// same as `create_texture_from_data` but the pixels are not color data and are sampled
+    /// without srgb conversion, e.g. normal, metallic-roughness and occlusion maps.
+    pub unsafe fn create_linear_texture_from_data(
+        instance: &Instance,
+        device: &Device,
+        data: &mut AppData,
+        pixels: Vec<u8>,
+        size: (u32, u32),
+    ) -> Result<TextureData> {
+        let format = vk::Format::R8G8B8A8_UNORM;
+        let (mip_levels, image, image_memory) = Self::create_texture_image(
+            instance,
+            device,
+            data,
+            pixels,
+            (size.0, size.1, 1),
+            format,
+        )?;
+        Self::create_texture(device, data, mip_levels, image, image_memory, 1, format)
     }
     pub unsafe fn create_gui_texture(
         instance: &Instance,
@@ -126,20 +161,90 @@ impl TextureData {
         pixels: Vec<u8>,
         size: (u32, u32),
     ) -> Result<TextureData> {
-        let (mip_levels, image, image_memory) =
-            Self::create_texture_image(instance, device, data, pixels, (size.0, size.1, 1))?;
-        Self::create_texture(device, data, mip_levels, image, image_memory, 1)
+        let format = vk::Format::R8G8B8A8_SRGB;
+        let (mip_levels, image, image_memory) = Self::create_texture_image(
+            instance,
+            device,
+            data,
+            pixels,
+            (size.0, size.1, 1),
+            format,
+        )?;
+        Self::create_texture(device, data, mip_levels, image, image_memory, 1, format)
     }
+    /// `pixels` holds the six faces one after the other, in the order +x, -x, +y, -y, +z, -z.
+    /// `format` is `R8G8B8A8_SRGB` for png faces and `R16G16B16A16_SFLOAT` for hdr skies.
     pub unsafe fn create_cubemap_from_data(
         instance: &Instance,
         device: &Device,
         data: &mut AppData,
         pixels: Vec<u8>,
         size: (u32, u32),
+        format: vk::Format,
     ) -> Result<TextureData> {
-        let (mip_levels, image, image_memory) =
-            Self::create_cubemap_texture_image(instance, device, data, pixels, (size.0, size.1))?;
-        Self::create_cubemap_texture(device, data, mip_levels, image, image_memory)
+        let (mip_levels, image, image_memory) = Self::create_cubemap_texture_image(
+            instance,
+            device,
+            data,
+            pixels,
+            (size.0, size.1),
+            format,
+        )?;
+        Self::create_cubemap_texture(device, data, mip_levels, image, image_memory, format)
+    }
+    /// a cube map with precomputed mip levels. `levels[mip]` holds the six faces of that level
+    /// like `create_cubemap_from_data`, the faces halve in size from `size` on.
+    pub unsafe fn create_cubemap_from_levels(
+        instance: &Instance,
+        device: &Device,
+        data: &mut AppData,
+        levels: &[Vec<u8>],
+        size: u32,
+        format: vk::Format,
+    ) -> Result<TextureData> {
+        let (image, image_memory) =
+            create_image_from_levels(instance, device, data, levels, (size, size), 6, format)?;
+        Self::create_cubemap_texture(
+            device,
+            data,
+            levels.len() as u32,
+            image,
+            image_memory,
+            format,
+        )
+    }
+    /// a texture without mip levels sampled with clamped coordinates, e.g. the brdf lookup
+    /// table.
+    pub unsafe fn create_lookup_texture(
+        instance: &Instance,
+        device: &Device,
+        data: &mut AppData,
+        pixels: Vec<u8>,
+        size: (u32, u32),
+        format: vk::Format,
+    ) -> Result<TextureData> {
+        let (image, image_memory) =
+            create_image_from_levels(instance, device, data, &[pixels], size, 1, format)?;
+        let image_view = Self::create_texture_image_view(image, 1, device, data, 1, format)?;
+        let info = vk::SamplerCreateInfo::builder()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .border_color(vk::BorderColor::INT_OPAQUE_BLACK)
+            .unnormalized_coordinates(false)
+            .compare_op(vk::CompareOp::ALWAYS)
+            .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+            .max_lod(0.0);
+        let sampler = device.create_sampler(&info, None)?;
+        Ok(Self {
+            mip_levels: 1,
+            image,
+            image_memory,
+            image_view,
+            sampler,
+        })
     }
     pub unsafe fn create_texture(
         device: &Device,
@@ -148,8 +253,10 @@ impl TextureData {
         image: vk::Image,
         image_memory: vk::DeviceMemory,
         depth: u32,
+        format: vk::Format,
     ) -> Result<TextureData> {
-        let image_view = Self::create_texture_image_view(image, mip_levels, device, data, depth)?;
+        let image_view =
+            Self::create_texture_image_view(image, mip_levels, device, data, depth, format)?;
         let sampler = Self::create_texture_sampler(mip_levels, device)?;
         Ok(Self {
             mip_levels,
@@ -178,7 +285,16 @@ impl TextureData {
         reader.next_frame(&mut pixels)?;
 
         let (width, height) = reader.info().size();
-        unsafe { Self::create_texture_image(instance, device, data, pixels, (width, height, 1)) }
+        unsafe {
+            Self::create_texture_image(
+                instance,
+                device,
+                data,
+                pixels,
+                (width, height, 1),
+                vk::Format::R8G8B8A8_SRGB,
+            )
+        }
     }
 
     pub unsafe fn create_texture_image(
@@ -187,6 +303,7 @@ impl TextureData {
         data: &mut AppData,
         pixels: Vec<u8>,
         size: (u32, u32, u32),
+        format: vk::Format,
     ) -> Result<(u32, vk::Image, vk::DeviceMemory)> {
         let (width, height, depth) = size;
         let mip_levels = (width.max(height) as f32).log2().floor() as u32 + 1;
@@ -218,7 +335,7 @@ impl TextureData {
             depth,
             mip_levels,
             vk::SampleCountFlags::_1,
-            vk::Format::R8G8B8A8_SRGB,
+            format,
             vk::ImageTiling::OPTIMAL,
             vk::ImageUsageFlags::SAMPLED
                 | vk::ImageUsageFlags::TRANSFER_DST
@@ -232,7 +349,7 @@ impl TextureData {
             device,
             data,
             texture_image,
-            vk::Format::R8G8B8A8_SRGB,
+            format,
             vk::ImageLayout::UNDEFINED,
             vk::ImageLayout::TRANSFER_DST_OPTIMAL,
             mip_levels,
@@ -257,7 +374,7 @@ impl TextureData {
             device,
             data,
             texture_image,
-            vk::Format::R8G8B8A8_SRGB,
+            format,
             width,
             height,
             1,
@@ -272,11 +389,12 @@ impl TextureData {
         device: &Device,
         _data: &mut AppData,
         depth: u32,
+        format: vk::Format,
     ) -> Result<vk::ImageView> {
         create_image_view(
             device,
             texture_image,
-            vk::Format::R8G8B8A8_SRGB,
+            format,
             vk::ImageAspectFlags::COLOR,
             mip_levels,
             depth,
@@ -309,6 +427,7 @@ impl TextureData {
         data: &mut AppData,
         pixels: Vec<u8>,
         size: (u32, u32),
+        format: vk::Format,
     ) -> Result<(u32, vk::Image, vk::DeviceMemory)> {
         let (width, height) = size;
         const DEPTH: u32 = 6;
@@ -347,7 +466,7 @@ impl TextureData {
             })
             .mip_levels(mip_levels)
             .array_layers(6)
-            .format(vk::Format::R8G8B8A8_SRGB)
+            .format(format)
             .tiling(vk::ImageTiling::OPTIMAL)
             .initial_layout(vk::ImageLayout::UNDEFINED)
             .usage(usage)
@@ -378,7 +497,7 @@ impl TextureData {
             device,
             data,
             texture_image,
-            vk::Format::R8G8B8A8_SRGB,
+            format,
             vk::ImageLayout::UNDEFINED,
             vk::ImageLayout::TRANSFER_DST_OPTIMAL,
             mip_levels,
@@ -403,7 +522,7 @@ impl TextureData {
             device,
             data,
             texture_image,
-            vk::Format::R8G8B8A8_SRGB,
+            format,
             width,
             height,
             6,
@@ -418,8 +537,9 @@ impl TextureData {
         mip_levels: u32,
         image: vk::Image,
         image_memory: vk::DeviceMemory,
+        format: vk::Format,
     ) -> Result<TextureData> {
-        let image_view = Self::create_cubemap_image_view(image, mip_levels, &device, data)?;
+        let image_view = Self::create_cubemap_image_view(image, mip_levels, &device, data, format)?;
         let sampler = Self::create_cubemap_sampler(mip_levels, device)?;
         Ok(Self {
             mip_levels,
@@ -435,6 +555,7 @@ impl TextureData {
         mip_levels: u32,
         device: &&Device,
         _data: &mut AppData,
+        format: vk::Format,
     ) -> Result<vk::ImageView> {
         let subresource_range = vk::ImageSubresourceRange::builder()
             .aspect_mask(vk::ImageAspectFlags::COLOR)
@@ -445,7 +566,7 @@ impl TextureData {
         let info = vk::ImageViewCreateInfo::builder()
             .image(image)
             .view_type(vk::ImageViewType::CUBE)
-            .format(vk::Format::R8G8B8A8_SRGB)
+            .format(format)
             .subresource_range(subresource_range);
 
         Ok(device.create_image_view(&info, None)?)
@@ -522,6 +643,129 @@ pub unsafe fn create_image(
     Ok((image, image_memory))
 }
 
+/// uploads every mip level as is instead of generating them. `levels[mip]` holds all `layers`
+/// of that level, a cube map has six.
+pub unsafe fn create_image_from_levels(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    levels: &[Vec<u8>],
+    size: (u32, u32),
+    layers: u32,
+    format: vk::Format,
+) -> Result<(vk::Image, vk::DeviceMemory)> {
+    let (width, height) = size;
+    let mip_levels = levels.len() as u32;
+    let bytes: u64 = levels.iter().map(|level| level.len() as u64).sum();
+    let (staging_buffer, staging_buffer_memory) = create_buffer(
+        instance,
+        device,
+        data,
+        bytes,
+        vk::BufferUsageFlags::TRANSFER_SRC,
+        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+    )?;
+    let memory = device.map_memory(staging_buffer_memory, 0, bytes, vk::MemoryMapFlags::empty())?;
+    let mut offsets = vec![];
+    let mut offset = 0;
+    for level in levels {
+        memcpy(level.as_ptr(), memory.cast::<u8>().add(offset), level.len());
+        offsets.push(offset as u64);
+        offset += level.len();
+    }
+    device.unmap_memory(staging_buffer_memory);
+
+    let flags = if layers == 6 {
+        vk::ImageCreateFlags::CUBE_COMPATIBLE
+    } else {
+        vk::ImageCreateFlags::empty()
+    };
+    let info = vk::ImageCreateInfo::builder()
+        .flags(flags)
+        .image_type(vk::ImageType::_2D)
+        .extent(vk::Extent3D {
+            width,
+            height,
+            depth: 1,
+        })
+        .mip_levels(mip_levels)
+        .array_layers(layers)
+        .format(format)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .usage(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST)
+        .samples(vk::SampleCountFlags::_1)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE);
+    let image = device.create_image(&info, None)?;
+    let requirements = device.get_image_memory_requirements(image);
+    let info = vk::MemoryAllocateInfo::builder()
+        .allocation_size(requirements.size)
+        .memory_type_index(get_memory_type_index(
+            instance,
+            data,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            requirements,
+        )?);
+    let image_memory = device.allocate_memory(&info, None)?;
+    device.bind_image_memory(image, image_memory, 0)?;
+
+    transition_image_layout(
+        device,
+        data,
+        image,
+        format,
+        vk::ImageLayout::UNDEFINED,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        mip_levels,
+        layers,
+    )?;
+    let regions: Vec<_> = offsets
+        .iter()
+        .enumerate()
+        .map(|(mip, offset)| {
+            let subresource = vk::ImageSubresourceLayers::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .mip_level(mip as u32)
+                .base_array_layer(0)
+                .layer_count(layers);
+            vk::BufferImageCopy::builder()
+                .buffer_offset(*offset)
+                .buffer_row_length(0)
+                .buffer_image_height(0)
+                .image_subresource(subresource)
+                .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+                .image_extent(vk::Extent3D {
+                    width: (width >> mip).max(1),
+                    height: (height >> mip).max(1),
+                    depth: 1,
+                })
+        })
+        .collect();
+    let command_buffer = begin_single_time_commands(device, data)?;
+    device.cmd_copy_buffer_to_image(
+        command_buffer,
+        staging_buffer,
+        image,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        &regions,
+    );
+    end_single_time_commands(device, data, command_buffer)?;
+    device.destroy_buffer(staging_buffer, None);
+    device.free_memory(staging_buffer_memory, None);
+
+    transition_image_layout(
+        device,
+        data,
+        image,
+        format,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        mip_levels,
+        layers,
+    )?;
+    Ok((image, image_memory))
+}
+
 pub unsafe fn transition_image_layout(
     device: &Device,
     data: &AppData,
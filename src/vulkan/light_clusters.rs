@@ -0,0 +1,227 @@
+#![allow(clippy::missing_safety_doc)]
+use std::ptr::copy_nonoverlapping as memcpy;
+
+use glam::{Mat4, UVec2, UVec3, UVec4, Vec2, Vec3, Vec4, Vec4Swizzles};
+use vulkanalia::{
+    Device,
+    vk::{self, DeviceMemory, DeviceV1_0},
+};
+
+use crate::game_objects::camera::Camera;
+use crate::vulkan::uniform_buffer_object::PointLight;
+
+/// tiles across and down the screen and slices in depth, matches `pbr.frag`.
+pub const CLUSTER_DIMENSIONS: UVec3 = UVec3::new(16, 9, 24);
+pub const CLUSTER_COUNT: usize =
+    (CLUSTER_DIMENSIONS.x * CLUSTER_DIMENSIONS.y * CLUSTER_DIMENSIONS.z) as usize;
+/// light indices of all clusters together, the lights past it are left out of their clusters.
+pub const MAX_CLUSTER_INDICES: usize = CLUSTER_COUNT * 32;
+
+/// the view frustum cut into uniform tiles on the screen and logarithmic slices in depth.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClusterGrid {
+    /// world to view space.
+    pub view: Mat4,
+    /// the projection of the camera, tiles are uniform in its normalized device coordinates.
+    pub proj: Mat4,
+    pub near: f32,
+    pub far: f32,
+}
+
+/// the header of the cluster buffer, followed by the offset and light count of every cluster
+/// and the light indices they point into.
+#[repr(C)]
+#[derive(Clone, Debug)]
+pub struct ClusterHeader {
+    pub view: Mat4,
+    pub proj: Mat4,
+    /// x, y and z hold `CLUSTER_DIMENSIONS`.
+    pub dimensions: UVec4,
+    /// near and far plane, then the slice count over the log of their ratio.
+    pub depth: Vec4,
+}
+
+/// the lights of every cluster, `ranges[i]` holds the offset into `indices` and the light count
+/// of cluster `i`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LightClusters {
+    pub ranges: Vec<UVec2>,
+    pub indices: Vec<u32>,
+}
+impl LightClusters {
+    /// the lights of a cluster by their index in the light buffer.
+    pub fn lights(&self, cluster: usize) -> &[u32] {
+        let range = self.ranges[cluster];
+        &self.indices[range.x as usize..(range.x + range.y) as usize]
+    }
+}
+
+impl ClusterGrid {
+    pub fn new(camera: &Camera, proj: Mat4) -> Self {
+        Self {
+            view: camera.transform.matrix().inverse(),
+            proj,
+            near: camera.near_field,
+            far: camera.far_field.max(camera.near_field * 2.0),
+        }
+    }
+
+    /// view space depth at which `slice` starts, the slice count is the far plane.
+    pub fn slice_depth(&self, slice: u32) -> f32 {
+        if slice == 0 {
+            return self.near;
+        } else if slice >= CLUSTER_DIMENSIONS.z {
+            return self.far;
+        }
+        let t = slice as f32 / CLUSTER_DIMENSIONS.z as f32;
+        self.near * (self.far / self.near).powf(t)
+    }
+
+    pub fn index(cluster: UVec3) -> usize {
+        (cluster.x + CLUSTER_DIMENSIONS.x * (cluster.y + CLUSTER_DIMENSIONS.y * cluster.z)) as usize
+    }
+
+    /// the cluster a world space point is shaded with, points outside of the frustum are
+    /// clamped into the nearest cluster like the shader does.
+    pub fn cluster_of(&self, point: Vec3) -> UVec3 {
+        let view = self.view.transform_point3(point);
+        let clip = self.proj * view.extend(1.0);
+        let ndc = clip.xy() / clip.w;
+        let dimensions = CLUSTER_DIMENSIONS.truncate().as_vec2();
+        let tile = ((ndc * 0.5 + 0.5) * dimensions)
+            .clamp(Vec2::ZERO, dimensions - 1.0)
+            .as_uvec2();
+        let slice = ((-view.z / self.near).ln() * self.slice_scale())
+            .clamp(0.0, CLUSTER_DIMENSIONS.z as f32 - 1.0) as u32;
+        tile.extend(slice)
+    }
+
+    /// view space bounding box of a cluster.
+    pub fn bounds(&self, cluster: UVec3) -> (Vec3, Vec3) {
+        let rays = self.tile_rays(cluster.x, cluster.y);
+        let near = self.slice_depth(cluster.z);
+        let far = self.slice_depth(cluster.z + 1);
+        let mut min = Vec3::MAX;
+        let mut max = Vec3::MIN;
+        for ray in rays {
+            for depth in [near, far] {
+                min = min.min(ray * depth);
+                max = max.max(ray * depth);
+            }
+        }
+        (min, max)
+    }
+
+    pub fn header(&self) -> ClusterHeader {
+        ClusterHeader {
+            view: self.view,
+            proj: self.proj,
+            dimensions: CLUSTER_DIMENSIONS.extend(0),
+            depth: Vec4::new(self.near, self.far, self.slice_scale(), 0.0),
+        }
+    }
+
+    fn slice_scale(&self) -> f32 {
+        CLUSTER_DIMENSIONS.z as f32 / (self.far / self.near).ln()
+    }
+
+    /// view space directions through the corners of a tile, scaled to a depth of 1.
+    fn tile_rays(&self, x: u32, y: u32) -> [Vec3; 4] {
+        let inverse = self.proj.inverse();
+        let dimensions = CLUSTER_DIMENSIONS.truncate().as_vec2();
+        [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)].map(|(x, y)| {
+            let ndc = Vec2::new(x as f32, y as f32) / dimensions * 2.0 - 1.0;
+            let point = inverse.project_point3(ndc.extend(0.5));
+            point / -point.z
+        })
+    }
+}
+
+/// the lights touching each cluster of `grid`. a light reaches as far as its range, lights
+/// without a range are in every cluster. spot lights are treated as points of the same range.
+pub fn assign_lights(grid: &ClusterGrid, lights: &[PointLight]) -> LightClusters {
+    let mut bounds = Vec::with_capacity(CLUSTER_COUNT);
+    for z in 0..CLUSTER_DIMENSIONS.z {
+        for y in 0..CLUSTER_DIMENSIONS.y {
+            for x in 0..CLUSTER_DIMENSIONS.x {
+                bounds.push(grid.bounds(UVec3::new(x, y, z)));
+            }
+        }
+    }
+    let slices: Vec<f32> = (0..=CLUSTER_DIMENSIONS.z)
+        .map(|slice| grid.slice_depth(slice))
+        .collect();
+    let tiles = (CLUSTER_DIMENSIONS.x * CLUSTER_DIMENSIONS.y) as usize;
+    let mut clusters: Vec<Vec<u32>> = vec![vec![]; CLUSTER_COUNT];
+    for (index, light) in lights.iter().enumerate() {
+        let range = light.position.w;
+        if range <= 0.0 {
+            clusters
+                .iter_mut()
+                .for_each(|lights| lights.push(index as u32));
+            continue;
+        }
+        let center = grid.view.transform_point3(light.position.truncate());
+        let (closest, farthest) = (-center.z - range, -center.z + range);
+        // only the slices overlapping the depth of the sphere are tested
+        let first = slices[1..].partition_point(|depth| *depth < closest);
+        let last =
+            slices[..CLUSTER_DIMENSIONS.z as usize].partition_point(|depth| *depth <= farthest);
+        for slice in first..last {
+            for cluster in slice * tiles..(slice + 1) * tiles {
+                let (min, max) = bounds[cluster];
+                if center.clamp(min, max).distance_squared(center) <= range * range {
+                    clusters[cluster].push(index as u32);
+                }
+            }
+        }
+    }
+    let mut assigned = LightClusters {
+        ranges: Vec::with_capacity(CLUSTER_COUNT),
+        indices: vec![],
+    };
+    for lights in clusters {
+        let offset = assigned.indices.len();
+        let count = lights.len().min(MAX_CLUSTER_INDICES - offset);
+        assigned.indices.extend_from_slice(&lights[..count]);
+        assigned
+            .ranges
+            .push(UVec2::new(offset as u32, count as u32));
+    }
+    assigned
+}
+
+/// the header, `CLUSTER_COUNT` ranges and `MAX_CLUSTER_INDICES` indices.
+pub fn cluster_buffer_size() -> u64 {
+    (size_of::<ClusterHeader>()
+        + size_of::<UVec2>() * CLUSTER_COUNT
+        + size_of::<u32>() * MAX_CLUSTER_INDICES) as u64
+}
+
+/// writes the clusters of a frame into its cluster buffer, see `cluster_buffer_size`.
+pub unsafe fn update_clusters(
+    device: &Device,
+    memory: DeviceMemory,
+    grid: &ClusterGrid,
+    clusters: &LightClusters,
+) -> anyhow::Result<()> {
+    let mapped = unsafe {
+        device.map_memory(
+            memory,
+            0,
+            cluster_buffer_size(),
+            vk::MemoryMapFlags::empty(),
+        )
+    }?;
+    let header = grid.header();
+    unsafe {
+        memcpy(&header, mapped.cast(), 1);
+        let ranges: *mut UVec2 = mapped.cast::<u8>().add(size_of::<ClusterHeader>()).cast();
+        memcpy(clusters.ranges.as_ptr(), ranges, CLUSTER_COUNT);
+        let indices: *mut u32 = ranges.add(CLUSTER_COUNT).cast();
+        let count = clusters.indices.len().min(MAX_CLUSTER_INDICES);
+        memcpy(clusters.indices.as_ptr(), indices, count);
+        device.unmap_memory(memory);
+    }
+    Ok(())
+}
@@ -10,6 +10,7 @@ pub mod framebuffer_util;
 pub mod image_util;
 pub mod input_state;
 pub mod instance_util;
+pub mod light_clusters;
 pub mod memory;
 pub mod pipeline_util;
 pub mod queue_family_indices;
@@ -1,20 +1,76 @@
 #![allow(unsafe_op_in_unsafe_fn)]
 use crate::vulkan::shader_module_util::create_shader_module;
 use crate::vulkan::uniform_buffer_object::PbrPushConstant;
-use crate::vulkan::vertexbuffer_util::{Vertex, VertexGui, VertexPbr};
+use crate::vulkan::vertexbuffer_util::{Vertex, VertexGui, VertexPbr, VertexSkin};
 use crate::winit_app::winit_render_app::AppData;
 use vulkanalia::vk::{DeviceV1_0, Handle, HasBuilder};
 use vulkanalia::{Device, vk};
 
+/// creates the pbr pipeline layout and the static, morphed and skinned pbr pipelines.
+/// the pipelines share the layout and the fragment shader.
 pub unsafe fn create_pbr_pipeline(
     device: &Device,
     data: &mut AppData,
     subpass_order: u32,
 ) -> std::result::Result<(), anyhow::Error> {
+    let push_range = vk::PushConstantRange::builder()
+        .offset(0)
+        .size(size_of::<PbrPushConstant>() as u32)
+        .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)
+        .build();
+    let set_layouts = &[data.pbr_descriptor_set_layout];
+    let push_ranges = [push_range];
+    let mut layout_info = vk::PipelineLayoutCreateInfo::builder()
+        .set_layouts(set_layouts)
+        .push_constant_ranges(&push_ranges);
+    layout_info.push_constant_range_count = 1;
+    let pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
+    data.pbr_pipeline_layout = pipeline_layout;
+
+    data.pbr_pipeline = pbr_pipeline_variant(
+        device,
+        data,
+        "src/shaders/spv/pbr_vert.spv",
+        &[<VertexPbr>::binding_description()],
+        &<VertexPbr>::attribute_descriptions(),
+        subpass_order,
+    )?;
+    data.morph_pbr_pipeline = pbr_pipeline_variant(
+        device,
+        data,
+        "src/shaders/spv/pbr_morph_vert.spv",
+        &[<VertexPbr>::binding_description()],
+        &<VertexPbr>::attribute_descriptions(),
+        subpass_order,
+    )?;
+    let mut skinned_attributes = <VertexPbr>::attribute_descriptions();
+    skinned_attributes.extend(VertexSkin::attribute_descriptions());
+    data.skinned_pbr_pipeline = pbr_pipeline_variant(
+        device,
+        data,
+        "src/shaders/spv/pbr_skinned_vert.spv",
+        &[
+            <VertexPbr>::binding_description(),
+            VertexSkin::binding_description(),
+        ],
+        &skinned_attributes,
+        subpass_order,
+    )?;
+    Ok(())
+}
+
+unsafe fn pbr_pipeline_variant(
+    device: &Device,
+    data: &AppData,
+    vert_path: &str,
+    binding_descriptions: &[vk::VertexInputBindingDescription],
+    attribute_descriptions: &[vk::VertexInputAttributeDescription],
+    subpass_order: u32,
+) -> anyhow::Result<vk::Pipeline> {
     let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
         .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
         .build();
-    let vert = std::fs::read("src/shaders/spv/pbr_vert.spv").unwrap();
+    let vert = std::fs::read(vert_path).unwrap();
     let frag = std::fs::read("src/shaders/spv/pbr_frag.spv").unwrap();
 
     let vert_shader_module = create_shader_module(device, &vert[..])?;
@@ -24,22 +80,15 @@ pub unsafe fn create_pbr_pipeline(
         .stage(vk::ShaderStageFlags::VERTEX)
         .module(vert_shader_module)
         .name(b"main\0");
-    let push_range = vk::PushConstantRange::builder()
-        .offset(0)
-        .size(size_of::<PbrPushConstant>() as u32)
-        .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)
-        .build();
     //specialization_info for shader constants!!
     let frag_stage = vk::PipelineShaderStageCreateInfo::builder()
         .stage(vk::ShaderStageFlags::FRAGMENT)
         .module(frag_shader_module)
         .name(b"main\0");
 
-    let binding_descriptions = &[<VertexPbr>::binding_description()];
-    let attribute_descriptions = <VertexPbr>::attribute_descriptions();
     let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
         .vertex_binding_descriptions(binding_descriptions)
-        .vertex_attribute_descriptions(&attribute_descriptions);
+        .vertex_attribute_descriptions(attribute_descriptions);
 
     let scissor = vk::Rect2D::builder()
         .offset(vk::Offset2D { x: 0, y: 0 })
@@ -99,14 +148,6 @@ pub unsafe fn create_pbr_pipeline(
     let dynamic_state =
         vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(dynamic_states);
 
-    let set_layouts = &[data.pbr_descriptor_set_layout];
-    let push_ranges = [push_range];
-    let mut layout_info = vk::PipelineLayoutCreateInfo::builder()
-        .set_layouts(set_layouts)
-        .push_constant_ranges(&push_ranges);
-    layout_info.push_constant_range_count = 1;
-    let pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
-
     let stages = &[vert_stage, frag_stage];
     let info = vk::GraphicsPipelineCreateInfo::builder()
         .stages(stages)
@@ -118,7 +159,7 @@ pub unsafe fn create_pbr_pipeline(
         .depth_stencil_state(&depth_stencil_state)
         .dynamic_state(&dynamic_state)
         .color_blend_state(&color_blend_state)
-        .layout(pipeline_layout)
+        .layout(data.pbr_pipeline_layout)
         .render_pass(data.render_pass)
         .subpass(subpass_order);
 
@@ -128,9 +169,7 @@ pub unsafe fn create_pbr_pipeline(
 
     device.destroy_shader_module(vert_shader_module, None);
     device.destroy_shader_module(frag_shader_module, None);
-    data.pbr_pipeline_layout = pipeline_layout;
-    data.pbr_pipeline = pipeline;
-    Ok(())
+    Ok(pipeline)
 }
 
 pub unsafe fn skybox_pipeline(
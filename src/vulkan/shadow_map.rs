@@ -1,168 +1,465 @@
 #![allow(unsafe_op_in_unsafe_fn, clippy::missing_safety_doc)]
+use glam::{Mat4, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
 use vulkanalia::{
     Device, Instance,
-    vk::{self, DeviceV1_0, Handle, HasBuilder, ImageView},
+    vk::{self, DeviceV1_0, Handle, HasBuilder},
 };
 
 use crate::{
+    game_objects::{camera::Camera, scene::Sun},
     vulkan::{
-        framebuffer_util::get_depth_format,
-        image_util::{TextureData, create_image, create_image_view},
+        buffer_util::get_memory_type_index,
         shader_module_util::create_shader_module,
+        vertexbuffer_util::{Vertex, VertexPbr, VertexSkin},
     },
     winit_app::winit_render_app::AppData,
 };
 
-#[derive(Clone, Debug)]
+/// width and height of every cascade of the depth map of the sun.
+pub const SHADOW_MAP_SIZE: u32 = 2048;
+pub const SHADOW_MAP_FORMAT: vk::Format = vk::Format::D32_SFLOAT;
+/// layers of the depth map, matches the size of `OrthographicLight::cascades`.
+pub const MAX_CASCADES: usize = 4;
+/// casters between the sun and the covered frustum are drawn up to this far outside of it.
+pub const CASTER_DISTANCE: f32 = 100.0;
+
+/// how the camera frustum is cut into cascades.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SplitScheme {
+    /// cascades of the same depth.
+    Uniform,
+    /// every cascade is the same factor deeper than the one before.
+    Logarithmic,
+    /// blends the logarithmic splits into the uniform ones, 0 is uniform and 1 logarithmic.
+    Practical(f32),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CascadeSettings {
+    /// clamped to `1..=MAX_CASCADES`.
+    pub count: usize,
+    pub scheme: SplitScheme,
+    /// the cascades cover the camera frustum up to this distance, nothing further away casts
+    /// or receives shadows.
+    pub distance: f32,
+    /// fraction of a cascade at the border of its map that fades into the next cascade.
+    pub blend: f32,
+    /// tints every cascade in its own color.
+    pub debug: bool,
+}
+impl Default for CascadeSettings {
+    fn default() -> Self {
+        Self {
+            count: MAX_CASCADES,
+            scheme: SplitScheme::Practical(0.8),
+            distance: 500.0,
+            blend: 0.1,
+            debug: false,
+        }
+    }
+}
+impl CascadeSettings {
+    pub fn cascade_count(&self) -> usize {
+        self.count.clamp(1, MAX_CASCADES)
+    }
+}
+
+/// the depth map of the sun with a layer per cascade, and the depth-only pass rendering into
+/// it. the pass runs before the main render pass, which samples the map at `SHADOW_BINDING`.
+#[derive(Clone, Debug, Default)]
 pub struct ShadowMap {
     pub image: vk::Image,
     pub image_memory: vk::DeviceMemory,
+    /// every layer, as sampled by the fragment shader.
     pub image_view: vk::ImageView,
+    /// compares against the map and filters the results, the fragment shader gets the
+    /// fraction of lit texels.
     pub sampler: vk::Sampler,
-    pub descriptor_set_layout: vk::DescriptorSetLayout,
-    pub descriptor_set: vk::DescriptorSet,
-    pub framebuffer: vk::Framebuffer,
+    pub render_pass: vk::RenderPass,
+    /// a view and framebuffer per cascade.
+    pub layer_views: Vec<vk::ImageView>,
+    pub framebuffers: Vec<vk::Framebuffer>,
+    pub pipeline: vk::Pipeline,
+    pub skinned_pipeline: vk::Pipeline,
+    pub morph_pipeline: vk::Pipeline,
 }
 
 impl ShadowMap {
-    pub unsafe fn new(
+    /// creates the map and its render pass. the pipelines use the pbr pipeline layout and are
+    /// created with it by `shadow_map_pipelines`.
+    pub unsafe fn create(
         instance: &Instance,
-        data: &AppData,
         device: &Device,
+        data: &AppData,
     ) -> anyhow::Result<Self> {
-        // Image + Image Memory
-
-        let format = get_depth_format(instance, data)?;
-
-        let (depth_image, depth_image_memory) = create_image(
-            instance,
-            device,
-            data,
-            data.swapchain_extent.width,
-            data.swapchain_extent.height,
-            1,
-            1,
-            data.msaa_samples,
-            format,
-            vk::ImageTiling::OPTIMAL,
-            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
-            vk::MemoryPropertyFlags::DEVICE_LOCAL,
-        )?;
-
-        // Image View
-
-        let depth_image_view = create_image_view(
-            device,
-            data.depth_image,
-            format,
-            vk::ImageAspectFlags::DEPTH,
-            1,
-            1,
-        )?;
-        let sampler = TextureData::create_texture_sampler(1, device)?;
-        let descriptor_set_layout = descriptor_set_layout(device)?;
-        let descriptor_set = descriptor_set(
-            descriptor_set_layout,
-            data.descriptor_pool,
-            device,
-            depth_image_view,
+        let info = vk::ImageCreateInfo::builder()
+            .image_type(vk::ImageType::_2D)
+            .extent(vk::Extent3D {
+                width: SHADOW_MAP_SIZE,
+                height: SHADOW_MAP_SIZE,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(MAX_CASCADES as u32)
+            .format(SHADOW_MAP_FORMAT)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED)
+            .samples(vk::SampleCountFlags::_1)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        let image = device.create_image(&info, None)?;
+        let requirements = device.get_image_memory_requirements(image);
+        let info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(requirements.size)
+            .memory_type_index(get_memory_type_index(
+                instance,
+                data,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                requirements,
+            )?);
+        let image_memory = device.allocate_memory(&info, None)?;
+        device.bind_image_memory(image, image_memory, 0)?;
+
+        let image_view = layer_view(device, image, 0, MAX_CASCADES as u32)?;
+        let sampler = shadow_map_sampler(device)?;
+        let render_pass = shadow_map_render_pass(device)?;
+        let mut layer_views = vec![];
+        let mut framebuffers = vec![];
+        for layer in 0..MAX_CASCADES as u32 {
+            let view = layer_view(device, image, layer, 1)?;
+            let attachments = &[view];
+            let info = vk::FramebufferCreateInfo::builder()
+                .render_pass(render_pass)
+                .attachments(attachments)
+                .width(SHADOW_MAP_SIZE)
+                .height(SHADOW_MAP_SIZE)
+                .layers(1);
+            framebuffers.push(device.create_framebuffer(&info, None)?);
+            layer_views.push(view);
+        }
+        Ok(Self {
+            image,
+            image_memory,
+            image_view,
             sampler,
-        )?;
-        let attachments = [depth_image_view];
-        let create_info = vk::FramebufferCreateInfo::builder()
-            .render_pass(data.render_pass)
-            .attachments(&attachments)
-            .width(data.swapchain_extent.width)
-            .height(data.swapchain_extent.height)
-            .layers(1);
-
-        let framebuffer = device.create_framebuffer(&create_info, None)?;
-        Ok(ShadowMap {
-            image: depth_image,
-            image_memory: depth_image_memory,
-            image_view: depth_image_view,
-            sampler,
-            descriptor_set_layout,
-            descriptor_set,
-            framebuffer,
+            render_pass,
+            layer_views,
+            framebuffers,
+            ..Default::default()
+        })
+    }
+
+    pub unsafe fn destroy_pipelines(&self, device: &Device) {
+        device.destroy_pipeline(self.pipeline, None);
+        device.destroy_pipeline(self.skinned_pipeline, None);
+        device.destroy_pipeline(self.morph_pipeline, None);
+    }
+
+    pub unsafe fn destroy(&self, device: &Device) {
+        for (framebuffer, view) in self.framebuffers.iter().zip(&self.layer_views) {
+            device.destroy_framebuffer(*framebuffer, None);
+            device.destroy_image_view(*view, None);
+        }
+        device.destroy_render_pass(self.render_pass, None);
+        device.destroy_sampler(self.sampler, None);
+        device.destroy_image_view(self.image_view, None);
+        device.destroy_image(self.image, None);
+        device.free_memory(self.image_memory, None);
+    }
+}
+
+/// the depths at which the camera frustum between `near` and `far` is cut, `count + 1` values
+/// from `near` to `far`. `near` has to be positive for the logarithmic splits.
+pub fn cascade_splits(near: f32, far: f32, count: usize, scheme: SplitScheme) -> Vec<f32> {
+    let count = count.max(1);
+    let near = near.max(f32::MIN_POSITIVE);
+    let lambda = match scheme {
+        SplitScheme::Uniform => 0.0,
+        SplitScheme::Logarithmic => 1.0,
+        SplitScheme::Practical(lambda) => lambda.clamp(0.0, 1.0),
+    };
+    (0..=count)
+        .map(|i| {
+            // the ends are exact, the powers would round them
+            if i == 0 {
+                return near;
+            } else if i == count {
+                return far;
+            }
+            let t = i as f32 / count as f32;
+            let uniform = near + (far - near) * t;
+            // in log space, `far / near` overflows for tiny near planes
+            let logarithmic = (near.ln() + (far.ln() - near.ln()) * t).exp();
+            lambda * logarithmic + (1.0 - lambda) * uniform
         })
+        .collect()
+}
+
+/// the world space corners of the part of a camera frustum between `near` and `far`, near
+/// plane first. `camera` transforms from camera to world space.
+pub fn frustum_corners(camera: Mat4, fov_y: f32, aspect: f32, near: f32, far: f32) -> [Vec3; 8] {
+    let inverse = camera * Mat4::perspective_rh(fov_y, aspect, near, far).inverse();
+    let mut corners = [Vec3::ZERO; 8];
+    for (i, corner) in corners.iter_mut().enumerate() {
+        let z = (i / 4) as f32;
+        let (x, y) = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)][i % 4];
+        *corner = inverse.project_point3(Vec3::new(x, y, z));
     }
+    corners
 }
-// To be used by other shaders
-pub unsafe fn descriptor_set_layout(device: &Device) -> anyhow::Result<vk::DescriptorSetLayout> {
-    let sampler_binding = vk::DescriptorSetLayoutBinding::builder()
-        .binding(0)
-        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-        .descriptor_count(1)
-        .stage_flags(vk::ShaderStageFlags::FRAGMENT);
-
-    let bindings = &[sampler_binding];
-    let info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(bindings);
-    Ok(device.create_descriptor_set_layout(&info, None)?)
+
+/// an orthographic projection along `direction`, pointing towards the light, that holds the
+/// sphere around `corners`. the sphere keeps the size of the map texels constant while the
+/// camera turns, and the projection is moved in whole texels of a `resolution` sized map so
+/// the edges of the shadows stay put while the camera moves.
+pub fn light_view_proj(direction: Vec3, corners: &[Vec3; 8], resolution: u32) -> Mat4 {
+    let direction = direction.try_normalize().unwrap_or(Vec3::Y);
+    let center = corners.iter().sum::<Vec3>() / corners.len() as f32;
+    let radius = corners
+        .iter()
+        .map(|corner| corner.distance(center))
+        .fold(0.0, f32::max);
+    // rounded so float noise does not change the texel size, and a texel larger than the
+    // sphere on each side so the snapping below cannot move a corner off the map
+    let radius = (radius * 16.0).ceil() / 16.0;
+    let radius = radius * resolution as f32 / (resolution as f32 - 2.0);
+    let up = if direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    let view = Mat4::look_to_rh(center, -direction, up);
+    let proj = Mat4::orthographic_rh(
+        -radius,
+        radius,
+        -radius,
+        radius,
+        -radius - CASTER_DISTANCE,
+        radius,
+    );
+    let view_proj = proj * view;
+    let texel = 2.0 / resolution as f32;
+    let origin = view_proj.project_point3(Vec3::ZERO).truncate();
+    let offset = (origin / texel).round() * texel - origin;
+    Mat4::from_translation(offset.extend(0.0)) * view_proj
 }
-pub unsafe fn descriptor_set(
-    layout: vk::DescriptorSetLayout,
-    pool: vk::DescriptorPool,
-    device: &Device,
-    image_view: ImageView,
-    sampler: vk::Sampler,
-) -> anyhow::Result<vk::DescriptorSet> {
-    let layouts = vec![layout];
-
-    let info = vk::DescriptorSetAllocateInfo::builder()
-        .descriptor_pool(pool)
-        .set_layouts(&layouts);
-    let descriptor_sets = device.allocate_descriptor_sets(&info)?;
-    let info = vk::DescriptorImageInfo::builder()
-        .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-        .image_view(image_view)
-        .sampler(sampler);
-
-    let image_info = &[info];
-    let sampler_write = vk::WriteDescriptorSet::builder()
-        .dst_set(descriptor_sets[0])
-        .dst_binding(1)
-        .dst_array_element(0)
-        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-        .image_info(image_info);
-
-    unsafe { device.update_descriptor_sets(&[sampler_write], &[] as &[vk::CopyDescriptorSet]) };
-    Ok(descriptor_sets[0])
+
+/// the projection of every cascade, nearest first, for a light pointing along `direction`.
+pub fn cascade_view_projs(
+    direction: Vec3,
+    camera: &Camera,
+    aspect: f32,
+    settings: &CascadeSettings,
+) -> Vec<Mat4> {
+    let far = settings
+        .distance
+        .min(camera.far_field)
+        .max(2.0 * camera.near_field);
+    let splits = cascade_splits(
+        camera.near_field,
+        far,
+        settings.cascade_count(),
+        settings.scheme,
+    );
+    splits
+        .windows(2)
+        .map(|split| {
+            let corners = frustum_corners(
+                camera.transform.matrix(),
+                camera.fov.to_radians(),
+                aspect,
+                split[0],
+                split[1],
+            );
+            light_view_proj(direction, &corners, SHADOW_MAP_SIZE)
+        })
+        .collect()
+}
+
+/// fits the cascades of `sun` to the camera frustum and stores them in its uniform.
+pub fn update_cascades(sun: &mut Sun, camera: &Camera, aspect: f32) {
+    let light = &mut sun.omnidirectional_light;
+    let view_projs = cascade_view_projs(light.direction.truncate(), camera, aspect, &sun.cascades);
+    light.cascades = [Mat4::IDENTITY; MAX_CASCADES];
+    light.cascades[..view_projs.len()].copy_from_slice(&view_projs);
+    light.cascade_info = Vec4::new(
+        view_projs.len() as f32,
+        sun.cascades.blend.clamp(0.0, 1.0),
+        if sun.cascades.debug { 1.0 } else { 0.0 },
+        0.0,
+    );
 }
 
-pub unsafe fn shadow_map_pipeline(
+unsafe fn layer_view(
     device: &Device,
-    data: &mut AppData,
-    subpass_order: u32,
-) -> std::result::Result<(), anyhow::Error> {
-    let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
-        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
-        .build();
-    let vert = std::fs::read("src/shaders/spv/shadow.spv").unwrap();
-    let frag = std::fs::read("src/shaders/spv/shadow.spv").unwrap();
+    image: vk::Image,
+    base_layer: u32,
+    layers: u32,
+) -> anyhow::Result<vk::ImageView> {
+    let subresource_range = vk::ImageSubresourceRange::builder()
+        .aspect_mask(vk::ImageAspectFlags::DEPTH)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(base_layer)
+        .layer_count(layers);
+    let view_type = if layers == 1 {
+        vk::ImageViewType::_2D
+    } else {
+        vk::ImageViewType::_2D_ARRAY
+    };
+    let info = vk::ImageViewCreateInfo::builder()
+        .image(image)
+        .view_type(view_type)
+        .format(SHADOW_MAP_FORMAT)
+        .subresource_range(subresource_range);
+    Ok(device.create_image_view(&info, None)?)
+}
+
+unsafe fn shadow_map_sampler(device: &Device) -> anyhow::Result<vk::Sampler> {
+    // everything outside of the map is lit
+    let info = vk::SamplerCreateInfo::builder()
+        .mag_filter(vk::Filter::LINEAR)
+        .min_filter(vk::Filter::LINEAR)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+        .border_color(vk::BorderColor::FLOAT_OPAQUE_WHITE)
+        .unnormalized_coordinates(false)
+        .compare_enable(true)
+        .compare_op(vk::CompareOp::LESS_OR_EQUAL)
+        .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+        .min_lod(0.0)
+        .max_lod(0.0);
+    Ok(device.create_sampler(&info, None)?)
+}
+
+unsafe fn shadow_map_render_pass(device: &Device) -> anyhow::Result<vk::RenderPass> {
+    let depth_stencil_attachment = vk::AttachmentDescription::builder()
+        .format(SHADOW_MAP_FORMAT)
+        .samples(vk::SampleCountFlags::_1)
+        .load_op(vk::AttachmentLoadOp::CLEAR)
+        .store_op(vk::AttachmentStoreOp::STORE)
+        .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+        .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .final_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+
+    let depth_stencil_attachment_ref = vk::AttachmentReference::builder()
+        .attachment(0)
+        .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
+
+    let subpass = vk::SubpassDescription::builder()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .depth_stencil_attachment(&depth_stencil_attachment_ref);
+
+    // the previous frame is done reading the map before it is cleared
+    let before = vk::SubpassDependency::builder()
+        .src_subpass(vk::SUBPASS_EXTERNAL)
+        .dst_subpass(0)
+        .src_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+        .dst_stage_mask(
+            vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+        )
+        .src_access_mask(vk::AccessFlags::SHADER_READ)
+        .dst_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE);
+    // and the main pass reads it once it is written
+    let after = vk::SubpassDependency::builder()
+        .src_subpass(0)
+        .dst_subpass(vk::SUBPASS_EXTERNAL)
+        .src_stage_mask(vk::PipelineStageFlags::LATE_FRAGMENT_TESTS)
+        .dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+        .src_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+        .dst_access_mask(vk::AccessFlags::SHADER_READ);
+
+    let attachments = &[depth_stencil_attachment];
+    let subpasses = &[subpass];
+    let dependencies = &[before, after];
+    let info = vk::RenderPassCreateInfo::builder()
+        .attachments(attachments)
+        .subpasses(subpasses)
+        .dependencies(dependencies);
 
+    Ok(device.create_render_pass(&info, None)?)
+}
+
+/// creates the static, morphed and skinned depth-only pipelines. they share the pbr pipeline
+/// layout, so the descriptor sets of the render objects are bound as they are.
+pub unsafe fn shadow_map_pipelines(device: &Device, data: &mut AppData) -> anyhow::Result<()> {
+    data.shadow_map.pipeline = shadow_pipeline_variant(
+        device,
+        data,
+        "src/shaders/spv/shadow_vert.spv",
+        &[<VertexPbr>::binding_description()],
+        &<VertexPbr>::attribute_descriptions(),
+    )?;
+    data.shadow_map.morph_pipeline = shadow_pipeline_variant(
+        device,
+        data,
+        "src/shaders/spv/shadow_morph_vert.spv",
+        &[<VertexPbr>::binding_description()],
+        &<VertexPbr>::attribute_descriptions(),
+    )?;
+    let mut skinned_attributes = <VertexPbr>::attribute_descriptions();
+    skinned_attributes.extend(VertexSkin::attribute_descriptions());
+    data.shadow_map.skinned_pipeline = shadow_pipeline_variant(
+        device,
+        data,
+        "src/shaders/spv/shadow_skinned_vert.spv",
+        &[
+            <VertexPbr>::binding_description(),
+            VertexSkin::binding_description(),
+        ],
+        &skinned_attributes,
+    )?;
+    Ok(())
+}
+
+unsafe fn shadow_pipeline_variant(
+    device: &Device,
+    data: &AppData,
+    vert_path: &str,
+    binding_descriptions: &[vk::VertexInputBindingDescription],
+    attribute_descriptions: &[vk::VertexInputAttributeDescription],
+) -> anyhow::Result<vk::Pipeline> {
+    let vert = std::fs::read(vert_path).unwrap();
     let vert_shader_module = create_shader_module(device, &vert[..])?;
-    let frag_shader_module = create_shader_module(device, &frag[..])?;
 
+    // depth only, there is no fragment stage
     let vert_stage = vk::PipelineShaderStageCreateInfo::builder()
         .stage(vk::ShaderStageFlags::VERTEX)
         .module(vert_shader_module)
         .name(b"main\0");
-    //specialization_info for shader constants!!
-    let frag_stage = vk::PipelineShaderStageCreateInfo::builder()
-        .stage(vk::ShaderStageFlags::FRAGMENT)
-        .module(frag_shader_module)
-        .name(b"main\0");
 
+    let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
+        .vertex_binding_descriptions(binding_descriptions)
+        .vertex_attribute_descriptions(attribute_descriptions);
+
+    let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+        .build();
+
+    let viewport = vk::Viewport::builder()
+        .x(0.0)
+        .y(0.0)
+        .width(SHADOW_MAP_SIZE as f32)
+        .height(SHADOW_MAP_SIZE as f32)
+        .min_depth(0.0)
+        .max_depth(1.0);
     let scissor = vk::Rect2D::builder()
         .offset(vk::Offset2D { x: 0, y: 0 })
-        .extent(data.swapchain_extent);
-
+        .extent(vk::Extent2D {
+            width: SHADOW_MAP_SIZE,
+            height: SHADOW_MAP_SIZE,
+        });
+    let viewports = &[viewport];
     let scissors = &[scissor];
     let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
-        .viewport_count(1)
+        .viewports(viewports)
         .scissors(scissors);
 
+    // the bias keeps lit surfaces from shadowing themselves, steeper ones need more
     let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
         .depth_clamp_enable(false)
         .rasterizer_discard_enable(false)
@@ -170,107 +467,37 @@ pub unsafe fn shadow_map_pipeline(
         .line_width(1.0)
         .cull_mode(vk::CullModeFlags::empty())
         .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
-        .depth_bias_enable(false);
+        .depth_bias_enable(true)
+        .depth_bias_constant_factor(1.25)
+        .depth_bias_slope_factor(1.75);
 
     let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
-        // Enable sample shading in the pipeline.
-        .sample_shading_enable(true)
-        // Minimum fraction for sample shading; closer to one is smoother.
-        .min_sample_shading(0.2)
-        .rasterization_samples(data.msaa_samples);
+        .rasterization_samples(vk::SampleCountFlags::_1);
 
     let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
         .depth_test_enable(true)
         .depth_write_enable(true)
-        .depth_compare_op(vk::CompareOp::LESS)
+        .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
         .depth_bounds_test_enable(false)
-        .min_depth_bounds(0.0) // Optional.
-        .max_depth_bounds(1.0) // Optional.
         .stencil_test_enable(false);
-    //.front(/* vk::StencilOpState */) // Optional.
-    //  .back(/* vk::StencilOpState */); // Optional.
-
-    let dynamic_states = &[vk::DynamicState::VIEWPORT, vk::DynamicState::LINE_WIDTH];
-
-    let dynamic_state =
-        vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(dynamic_states);
-
-    let set_layouts = &[data.pbr_descriptor_set_layout];
-    let mut layout_info = vk::PipelineLayoutCreateInfo::builder().set_layouts(set_layouts);
-    layout_info.push_constant_range_count = 1;
-    let pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
 
-    let stages = &[vert_stage, frag_stage];
+    let stages = &[vert_stage];
     let info = vk::GraphicsPipelineCreateInfo::builder()
         .stages(stages)
+        .vertex_input_state(&vertex_input_state)
         .input_assembly_state(&input_assembly_state)
         .viewport_state(&viewport_state)
         .rasterization_state(&rasterization_state)
         .multisample_state(&multisample_state)
         .depth_stencil_state(&depth_stencil_state)
-        .dynamic_state(&dynamic_state)
-        .layout(pipeline_layout)
-        .render_pass(data.render_pass)
-        .subpass(subpass_order);
+        .layout(data.pbr_pipeline_layout)
+        .render_pass(data.shadow_map.render_pass)
+        .subpass(0);
 
     let pipeline = device
         .create_graphics_pipelines(vk::PipelineCache::null(), &[info], None)?
         .0[0];
 
     device.destroy_shader_module(vert_shader_module, None);
-    device.destroy_shader_module(frag_shader_module, None);
-    data.pbr_pipeline_layout = pipeline_layout;
-    data.pbr_pipeline = pipeline;
-    Ok(())
-}
-
-pub unsafe fn shadow_map_render_pass(
-    instance: &Instance,
-    device: &Device,
-    data: &mut AppData,
-) -> anyhow::Result<()> {
-    // Attachments
-
-    let depth_stencil_attachment = vk::AttachmentDescription::builder()
-        .format(unsafe { get_depth_format(instance, data) }?)
-        .samples(data.msaa_samples)
-        .load_op(vk::AttachmentLoadOp::CLEAR)
-        .store_op(vk::AttachmentStoreOp::STORE)
-        .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
-        .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
-        .initial_layout(vk::ImageLayout::UNDEFINED)
-        .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
-
-    // Subpasses
-
-    let depth_stencil_attachment_ref = vk::AttachmentReference::builder()
-        .attachment(0)
-        .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
-
-    let subpass_description = vk::SubpassDescription::builder()
-        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
-        .depth_stencil_attachment(&depth_stencil_attachment_ref);
-    // Dependencies
-
-    // Create
-
-    let attachments = &[depth_stencil_attachment];
-    let dependency = vk::SubpassDependency::builder()
-        .src_subpass(vk::SUBPASS_EXTERNAL) // main
-        .dst_subpass(0) //skybox
-        .src_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
-        .dst_stage_mask(vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS)
-        .src_access_mask(vk::AccessFlags::empty())
-        .dst_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE);
-
-    let subpasses = &[subpass_description];
-    let dependencies = &[dependency];
-    let info = vk::RenderPassCreateInfo::builder()
-        .attachments(attachments)
-        .subpasses(subpasses)
-        .dependencies(dependencies);
-
-    data.render_pass = unsafe { device.create_render_pass(&info, None) }?;
-
-    Ok(())
+    Ok(pipeline)
 }
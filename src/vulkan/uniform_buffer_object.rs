@@ -10,6 +10,7 @@ use vulkanalia::{
 };
 
 use crate::vulkan::descriptor_util::create_uniform_buffers;
+use crate::vulkan::shadow_map::MAX_CASCADES;
 use crate::winit_app::winit_render_app::AppData;
 
 #[repr(C)]
@@ -47,28 +48,60 @@ impl UniformBuffer for OrthographicLight {}
 #[repr(C)]
 #[derive(Debug, Clone)]
 pub struct PbrUniform {
-    pub model: [Mat4; 10],
     pub base: Vec4,
+    pub emissive: Vec4,
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+    pub metallic: f32,
+    pub roughness: f32,
 }
 
+/// an instance in the instance buffer of a render object, indexed by `gl_InstanceIndex`.
 #[repr(C)]
-#[derive(Debug, Clone)]
-pub struct PointLight {
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstanceData {
     pub model: Mat4,
+    /// inverse transpose of `model`, transforms normals.
+    pub normal: Mat4,
+    /// multiplied onto the base color and emissive factor of the material.
+    pub base: Vec4,
+    pub emissive: Vec4,
+}
+
+/// a point or spot light in the light buffer, see `Light::uniform`.
+#[repr(C)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PointLight {
+    /// w holds the range, 0 for an unlimited light.
+    pub position: Vec4,
+    /// the direction a spot light shines in.
+    pub direction: Vec4,
+    /// color times intensity.
     pub color: Vec4,
+    /// x scales and y offsets the cosine of the angle to the spot direction, the result
+    /// clamped to 0..1 fades the cone out.
+    pub cone: Vec4,
 }
 
 #[repr(C)]
 #[derive(Debug, Clone)]
 pub struct OrthographicLight {
+    /// points towards the light.
     pub direction: Vec4,
     pub color: Vec4,
+    /// world to shadow map clip space of every cascade, see `shadow_map::update_cascades`.
+    pub cascades: [Mat4; MAX_CASCADES],
+    /// x holds the cascade count, y the blended fraction of each cascade and z is 1 to tint
+    /// the cascades.
+    pub cascade_info: Vec4,
 }
 impl Default for OrthographicLight {
     fn default() -> Self {
         Self {
             direction: vec4(-3.0, 1.0, -1.0, 0.0).normalize(),
             color: Vec4::ONE,
+            cascades: [Mat4::IDENTITY; MAX_CASCADES],
+            cascade_info: Vec4::X,
         }
     }
 }
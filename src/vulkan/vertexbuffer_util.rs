@@ -2,9 +2,10 @@
 use anyhow::Result;
 use std::mem::size_of;
 
+use crate::game_objects::bounds::Bounds;
 use crate::vulkan::buffer_util::{copy_buffer, create_buffer};
 use crate::winit_app::winit_render_app::AppData;
-use glam::{U8Vec4, Vec2, Vec3, vec3};
+use glam::{U8Vec4, UVec4, Vec2, Vec3, Vec4, vec3};
 use std::ptr::copy_nonoverlapping as memcpy;
 use varlen_macro::define_varlen;
 use vulkanalia::vk::{DeviceV1_0, HasBuilder};
@@ -76,6 +77,8 @@ where
     pub index_buffer: vk::Buffer,
     pub index_buffer_memory: vk::DeviceMemory,
     pub mem_map: Option<VertexStagingMap>,
+    /// around the positions of `vertices`, used for frustum culling.
+    pub bounds: Bounds,
 }
 
 impl<V> VertexData<V>
@@ -94,6 +97,7 @@ where
             unsafe { Self::create_vertex_buffer(instance, device, data, &vertices, has_map) }?;
         let (index_buffer, index_buffer_memory, index_mem_map) =
             unsafe { Self::create_index_buffer(instance, device, data, &indices, has_map) }?;
+        let bounds = Self::bounds_of(&vertices);
         match (vertex_mem_map, index_mem_map) {
             (Some(vertex), Some(index)) => Ok(VertexData {
                 vertices,
@@ -103,6 +107,7 @@ where
                 index_buffer,
                 index_buffer_memory,
                 mem_map: Some(VertexStagingMap { vertex, index }),
+                bounds,
             }),
             (None, None) => Ok(VertexData {
                 vertices,
@@ -112,10 +117,28 @@ where
                 index_buffer,
                 index_buffer_memory,
                 mem_map: None,
+                bounds,
             }),
             _ => panic!("undefined match"),
         }
     }
+    /// the bounds of the vertex positions, empty for vertices without one.
+    pub fn bounds_of(vertices: &[V]) -> Bounds {
+        let positions: Vec<Vec3> = vertices.iter().filter_map(Vertex::position).collect();
+        Bounds::from_points(&positions)
+    }
+    pub unsafe fn destroy(&self, device: &Device) {
+        device.destroy_buffer(self.vertex_buffer, None);
+        device.free_memory(self.vertex_buffer_memory, None);
+        device.destroy_buffer(self.index_buffer, None);
+        device.free_memory(self.index_buffer_memory, None);
+        if let Some(staging_map) = &self.mem_map {
+            device.destroy_buffer(staging_map.vertex.staging_buffer, None);
+            device.free_memory(staging_map.vertex.staging_memory, None);
+            device.destroy_buffer(staging_map.index.staging_buffer, None);
+            device.free_memory(staging_map.index.staging_memory, None);
+        }
+    }
     pub unsafe fn update_vertex_data(
         &mut self,
         instance: &Instance,
@@ -124,6 +147,7 @@ where
         vertices: Vec<V>,
         indices: Vec<u32>,
     ) -> Result<()> {
+        self.bounds = Self::bounds_of(&vertices);
         if vertices.len() > self.vertices.len() || indices.len() > self.indices.len() {
             device.destroy_buffer(self.vertex_buffer, None);
             device.destroy_buffer(self.index_buffer, None);
@@ -162,8 +186,8 @@ where
             }
             return Ok(());
         }
-        self.update_vertex_buffer(instance, device, data, vertices)?;
-        self.update_index_buffer(instance, device, data, indices)?;
+        self.update_vertex_buffer(device, data, vertices)?;
+        self.update_index_buffer(device, data, indices)?;
 
         Ok(())
     }
@@ -220,12 +244,11 @@ where
     }
     pub unsafe fn update_vertex_buffer(
         &mut self,
-        instance: &Instance,
         device: &Device,
         data: &mut AppData,
         vertices: Vec<V>,
     ) -> Result<()> {
-        let Some(VertexStagingMap { vertex, index }) = &self.mem_map else {
+        let Some(VertexStagingMap { vertex, .. }) = &self.mem_map else {
             panic!("updating vertex buffer for unmapped memory is still undefined")
         };
 
@@ -250,10 +273,10 @@ where
         instance: &Instance,
         device: &Device,
         data: &mut AppData,
-        indices: &Vec<u32>,
+        indices: &[u32],
         has_map: bool,
     ) -> Result<(vk::Buffer, vk::DeviceMemory, Option<StagingMap>)> {
-        let size = (size_of::<u32>() * indices.len()) as u64;
+        let size = std::mem::size_of_val(indices) as u64;
 
         let (staging_buffer, staging_buffer_memory) = create_buffer(
             instance,
@@ -298,7 +321,6 @@ where
     }
     pub unsafe fn update_index_buffer(
         &mut self,
-        instance: &Instance,
         device: &Device,
         data: &mut AppData,
         indices: Vec<u32>,
@@ -432,6 +454,10 @@ pub trait Vertex: Sized {
             .build()
     }
     fn attribute_descriptions() -> Vec<vk::VertexInputAttributeDescription>;
+    /// the position of the vertex in the space of its mesh, if it has one.
+    fn position(&self) -> Option<Vec3> {
+        None
+    }
 }
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
@@ -449,6 +475,9 @@ pub struct VertexGui {
     pub color: U8Vec4,
 }
 impl Vertex for VertexGui {
+    fn position(&self) -> Option<Vec3> {
+        Some(self.pos.extend(0.0))
+    }
     fn attribute_descriptions() -> Vec<vk::VertexInputAttributeDescription> {
         let pos = vk::VertexInputAttributeDescription::builder()
             .binding(0)
@@ -483,6 +512,9 @@ impl VertexPbr {
     }
 }
 impl Vertex for VertexPbr {
+    fn position(&self) -> Option<Vec3> {
+        Some(self.pos)
+    }
     fn attribute_descriptions() -> Vec<vk::VertexInputAttributeDescription> {
         let pos = vk::VertexInputAttributeDescription::builder()
             .binding(0)
@@ -506,6 +538,39 @@ impl Vertex for VertexPbr {
     }
 }
 
+/// joint indices and weights of a skinned vertex, bound as a second vertex buffer
+/// next to the `VertexPbr` buffer of the same primitive.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct VertexSkin {
+    pub joints: UVec4,
+    pub weights: Vec4,
+}
+impl Vertex for VertexSkin {
+    fn binding_description() -> vk::VertexInputBindingDescription {
+        vk::VertexInputBindingDescription::builder()
+            .binding(1)
+            .stride(size_of::<Self>() as u32)
+            .input_rate(vk::VertexInputRate::VERTEX)
+            .build()
+    }
+    fn attribute_descriptions() -> Vec<vk::VertexInputAttributeDescription> {
+        let joints = vk::VertexInputAttributeDescription::builder()
+            .binding(1)
+            .location(3)
+            .format(vk::Format::R32G32B32A32_UINT)
+            .offset(0)
+            .build();
+        let weights = vk::VertexInputAttributeDescription::builder()
+            .binding(1)
+            .location(4)
+            .format(vk::Format::R32G32B32A32_SFLOAT)
+            .offset(size_of::<UVec4>() as u32)
+            .build();
+        vec![joints, weights]
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct SimpleVertex {
@@ -513,6 +578,9 @@ pub struct SimpleVertex {
 }
 
 impl Vertex for SimpleVertex {
+    fn position(&self) -> Option<Vec3> {
+        Some(self.pos)
+    }
     fn attribute_descriptions() -> Vec<vk::VertexInputAttributeDescription> {
         vec![
             vk::VertexInputAttributeDescription::builder()
@@ -5,6 +5,8 @@
     clippy::unnecessary_wraps,
     unsafe_op_in_unsafe_fn
 )]
+use crate::asset_manager::bevy_load::upload_read_assets;
+use crate::asset_manager::hot_reload::queue_changed_files;
 use crate::gui::gui::Gui;
 use crate::vulkan::input_state::InputState;
 use crate::winit_app::winit_render_app::App;
@@ -152,6 +154,7 @@ impl ApplicationHandler for WinitWrapper {
         let response = gui.egui_state.on_window_event(window, &event);
         self.input_state.read_event(&event);
         gui.set_enabled(&mut self.input_state);
+        gui.pick_object(&mut app.scene, &app.data, &mut self.input_state);
         app.scene.update(dt, &self.input_state);
         self.input_state.reset_mouse_delta();
         match event {
@@ -184,6 +187,9 @@ impl ApplicationHandler for WinitWrapper {
                 event_loop.exit();
             }
             WindowEvent::RedrawRequested => {
+                // once per frame like the bevy app, not for every input event
+                queue_changed_files(&mut app.scene);
+                upload_read_assets(&app.instance, &app.device, &mut app.data, &mut app.scene);
                 let window = &self.window.as_ref().unwrap();
                 if gui.enabled {
                     gui.run_egui(&mut app.data, &mut app.scene, window);
@@ -1,5 +1,8 @@
 #![allow(unsafe_op_in_unsafe_fn, clippy::missing_safety_doc)]
-use crate::asset_manager;
+use crate::asset_manager::bevy_load::AssetSource;
+use crate::game_objects::bounds::Frustum;
+use crate::game_objects::environment::{Environment, update_environment};
+use crate::game_objects::light::update_lights;
 use crate::game_objects::skybox::SkyBox;
 use crate::gui::gui::{Gui, create_gui_descriptor_sets};
 use crate::vulkan::command_buffer_util::{create_command_buffer, create_command_buffers};
@@ -14,8 +17,10 @@ use crate::vulkan::descriptor_util::{
 use crate::vulkan::device_util::{create_logical_device, pick_physical_device};
 use crate::vulkan::framebuffer_util::{create_depth_objects, create_framebuffers};
 use crate::vulkan::instance_util::create_instance;
+use crate::vulkan::light_clusters::{ClusterGrid, assign_lights, update_clusters};
 use crate::vulkan::pipeline_util::{create_pbr_pipeline, gui_pipeline, skybox_pipeline};
 use crate::vulkan::render_pass_util::create_render_pass;
+use crate::vulkan::shadow_map::{ShadowMap, shadow_map_pipelines, update_cascades};
 use crate::vulkan::swapchain_util::{create_swapchain, create_swapchain_image_views};
 use crate::vulkan::sync_util::create_sync_objects;
 use crate::vulkan::uniform_buffer_object::{
@@ -40,7 +45,6 @@ use winit::window::Window;
 use crate::game_objects::render_object::ObjectId;
 use crate::game_objects::scene::Scene;
 use crate::vulkan::color_objects::create_color_objects;
-use glam::Mat4;
 use std::ptr::copy_nonoverlapping as memcpy;
 
 /// Our Vulkan app.
@@ -103,6 +107,8 @@ pub struct AppData {
     pub skybox_descriptor_set_layout: vk::DescriptorSetLayout,
     pub pbr_pipeline_layout: vk::PipelineLayout,
     pub pbr_pipeline: vk::Pipeline,
+    pub skinned_pbr_pipeline: vk::Pipeline,
+    pub morph_pbr_pipeline: vk::Pipeline,
     pub pbr_push_contant: PbrPushConstant,
     pub skybox_pipeline_layout: vk::PipelineLayout,
     pub skybox_pipeline: vk::Pipeline,
@@ -111,6 +117,12 @@ pub struct AppData {
     pub gui_pipeline: vk::Pipeline,
     pub global_buffer: Vec<vk::Buffer>,
     pub global_buffer_memory: Vec<vk::DeviceMemory>,
+    /// point and spot lights of every frame, see `light::update_lights`.
+    pub light_buffer: Vec<vk::Buffer>,
+    pub light_buffer_memory: Vec<vk::DeviceMemory>,
+    /// the lights of every cluster of the view frustum, see `light_clusters::assign_lights`.
+    pub cluster_buffer: Vec<vk::Buffer>,
+    pub cluster_buffer_memory: Vec<vk::DeviceMemory>,
     pub framebuffers: Vec<vk::Framebuffer>,
 
     pub command_centers: Vec<CommandCenter>,
@@ -133,6 +145,10 @@ pub struct AppData {
     pub color_image: vk::Image,
     pub color_image_memory: vk::DeviceMemory,
     pub color_image_view: vk::ImageView,
+
+    /// image based lighting of the skybox, see `environment::update_environment`.
+    pub environment: Environment,
+    pub shadow_map: ShadowMap,
 }
 impl App {
     /// Creates our Vulkan app.
@@ -156,6 +172,8 @@ impl App {
         gui_pipeline(&device, &mut data, 0)?;
         create_pbr_pipeline(&device, &mut data, 1)?;
         skybox_pipeline(&device, &mut data, 2)?;
+        data.shadow_map = ShadowMap::create(&instance, &device, &data)?;
+        shadow_map_pipelines(&device, &mut data)?;
         create_color_objects(&instance, &device, &mut data)?;
         create_depth_objects(&instance, &device, &mut data)?;
         create_framebuffers(&device, &mut data)?;
@@ -163,10 +181,11 @@ impl App {
         create_transient_command_pool(&instance, &device, &mut data)?;
         create_descriptor_pool(&device, &mut data, 30)?;
 
-        create_command_buffers(&device, &mut scene, &mut data, window, None)?;
+        create_command_buffers(&device, &mut scene, &mut data, None)?;
         create_sync_objects(&device, &mut data)?;
 
         create_global_buffers(&instance, &device, &mut data, &mut scene)?;
+        update_environment(&instance, &device, &mut data, &mut scene)?;
         let app = Self {
             entry,
             instance,
@@ -182,26 +201,28 @@ impl App {
         Ok(app)
     }
 
-    pub fn add_object(&mut self, path: impl AsRef<Path>) -> Result<Vec<ObjectId>, ()> {
-        let object_id = match asset_manager::load::scene(
-            &self.instance,
-            &self.device,
-            &mut self.data,
-            &mut self.scene,
-            path.as_ref(),
-        ) {
-            Ok(object) => object,
-            Err(e) => panic!("you fucked up {:?}", e),
-        };
-        Ok(object_id)
+    /// loads a gltf or obj file, a broken file is reported to the gui and adds nothing.
+    pub fn add_object(&mut self, path: impl AsRef<Path>) -> Vec<ObjectId> {
+        let path = path.as_ref();
+        AssetSource::read(path)
+            .and_then(|source| {
+                source.upload(
+                    &self.instance,
+                    &self.device,
+                    &mut self.data,
+                    &mut self.scene,
+                )
+            })
+            .unwrap_or_else(|error| {
+                self.scene.report_asset_error(error);
+                vec![]
+            })
     }
 
     pub fn add_objects(&mut self, paths: &[&str]) -> Vec<ObjectId> {
         let mut game_object_ids = vec![];
         for path in paths {
-            if let Ok(gs) = self.add_object(path) {
-                gs.into_iter().for_each(|g| game_object_ids.push(g));
-            }
+            game_object_ids.extend(self.add_object(path));
         }
         game_object_ids
     }
@@ -215,6 +236,10 @@ impl App {
 
         println!("process start");
         self.device.device_wait_idle()?;
+        // retired objects hold descriptor sets of the pool that is about to be destroyed
+        self.scene
+            .assets
+            .destroy_garbage(&self.device, self.data.descriptor_pool);
         self.destroy_swapchain();
         create_swapchain(window, &self.instance, &self.device, &mut self.data)?;
         create_swapchain_image_views(&self.device, &mut self.data)?;
@@ -223,6 +248,7 @@ impl App {
         create_descriptor_pool(&self.device, &mut self.data, 30)?;
         skybox_pipeline(&self.device, &mut self.data, 2)?;
         create_pbr_pipeline(&self.device, &mut self.data, 1)?;
+        shadow_map_pipelines(&self.device, &mut self.data)?;
         gui_pipeline(&self.device, &mut self.data, 0)?;
 
         create_color_objects(&self.instance, &self.device, &mut self.data)?;
@@ -261,13 +287,7 @@ impl App {
             }
         }
 
-        create_command_buffers(
-            &self.device,
-            &mut self.scene,
-            &mut self.data,
-            window,
-            Some(gui),
-        )?;
+        create_command_buffers(&self.device, &mut self.scene, &mut self.data, Some(gui))?;
         self.data.recreated = true;
         Ok(())
     }
@@ -286,18 +306,27 @@ impl App {
         self.data.pbr_push_contant = PbrPushConstant {
             proj_inv_view: view.inverse(),
         };
-        for (_i, object) in self.scene.render_objects.iter() {
-            let mut model = [Mat4::default(); 10];
-            for (index, instance_index) in object.instances.iter().enumerate() {
-                let instance = self.scene.objects.get(*instance_index).unwrap();
-                model[index] = instance.global_matrix(&self.scene);
-            }
-            let ubo = PbrUniform {
-                model,
-                base: object.pbr.base,
-            };
-            ubo.map_memory(&self.device, object.uniform_buffers_memory[image_index])?;
-        }
+        let frustum = Frustum::new(&self.scene.camera, proj);
+        self.scene.update_render_objects(
+            &self.instance,
+            &self.device,
+            &self.data,
+            image_index,
+            &frustum,
+        )?;
+        let lights = self.scene.lights();
+        update_lights(
+            &self.device,
+            self.data.light_buffer_memory[image_index],
+            &lights,
+        )?;
+        let grid = ClusterGrid::new(&self.scene.camera, proj);
+        update_clusters(
+            &self.device,
+            self.data.cluster_buffer_memory[image_index],
+            &grid,
+            &assign_lights(&grid, &lights),
+        )?;
         if self.scene.skybox.is_some() {
             let scale = window.scale_factor() as f32;
             let ubo = GlobalUniform {
@@ -308,6 +337,8 @@ impl App {
             };
             ubo.map_memory(&self.device, self.data.global_buffer_memory[image_index])?;
         }
+        let aspect = self.scene.camera.aspect(&self.data, gui);
+        update_cascades(&mut self.scene.sun, &self.scene.camera, aspect);
         let memory = unsafe {
             self.device.map_memory(
                 self.scene.sun.memory[image_index],
@@ -326,6 +357,17 @@ impl App {
     pub unsafe fn render(&mut self, window: &Window, gui: &mut Gui) -> anyhow::Result<()> {
         self.device
             .wait_for_fences(&[self.data.in_flight_fences[self.frame]], true, u64::MAX)?;
+        self.scene
+            .assets
+            .collect_garbage(&self.device, self.data.descriptor_pool);
+        // textures and meshes of the render objects destroyed above
+        self.scene.assets.release_unused(&self.device);
+        update_environment(
+            &self.instance,
+            &self.device,
+            &mut self.data,
+            &mut self.scene,
+        )?;
 
         let result = self.device.acquire_next_image_khr(
             self.data.swapchain,
@@ -386,7 +428,6 @@ impl App {
             &self.device,
             &mut self.scene,
             &mut self.data,
-            window,
             Some(gui),
             image_index,
         )?;
@@ -437,6 +478,9 @@ impl App {
     /// Destroys our Vulkan app.
     pub unsafe fn destroy(&mut self, gui: &mut Gui) {
         self.device.device_wait_idle().unwrap();
+        self.scene
+            .assets
+            .destroy_garbage(&self.device, self.data.descriptor_pool);
         self.destroy_swapchain();
         self.device
             .destroy_descriptor_set_layout(self.data.gui_descriptor_layout, None);
@@ -449,15 +493,13 @@ impl App {
             self.device.free_memory(data.image_memory, None);
         }
         for (_i, object) in self.scene.render_objects.iter() {
-            self.device
-                .destroy_sampler(object.pbr.texture_data.sampler, None);
-            self.device
-                .destroy_image_view(object.pbr.texture_data.image_view, None);
-
-            self.device
-                .destroy_image(object.pbr.texture_data.image, None);
-            self.device
-                .free_memory(object.pbr.texture_data.image_memory, None);
+            if let Some(skin) = &object.skin {
+                skin.destroy(&self.device);
+            }
+            if let Some(morph) = &object.morph {
+                morph.destroy(&self.device);
+            }
+            object.instance_buffers.destroy(&self.device);
         }
 
         self.device
@@ -498,19 +540,12 @@ impl App {
                 }
             }
         }
-        for (_i, object) in self.scene.render_objects.iter() {
-            self.device
-                .free_memory(object.vertex_data.vertex_buffer_memory, None);
-            self.device
-                .destroy_buffer(object.vertex_data.vertex_buffer, None);
-            self.device
-                .free_memory(object.vertex_data.index_buffer_memory, None);
-            self.device
-                .destroy_buffer(object.vertex_data.index_buffer, None);
-        }
+        self.scene.assets.destroy(&self.device);
         if let Some(skybox) = &self.scene.skybox {
             skybox.texture_data.destroy_image(&self.device);
         }
+        self.data.environment.destroy(&self.device);
+        self.data.shadow_map.destroy(&self.device);
         for center in &self.data.command_centers {
             self.device.destroy_command_pool(center.command_pool, None);
         }
@@ -557,6 +592,22 @@ impl App {
             .global_buffer_memory
             .iter()
             .for_each(|m| self.device.free_memory(*m, None));
+        self.data
+            .light_buffer
+            .iter()
+            .for_each(|b| self.device.destroy_buffer(*b, None));
+        self.data
+            .light_buffer_memory
+            .iter()
+            .for_each(|m| self.device.free_memory(*m, None));
+        self.data
+            .cluster_buffer
+            .iter()
+            .for_each(|b| self.device.destroy_buffer(*b, None));
+        self.data
+            .cluster_buffer_memory
+            .iter()
+            .for_each(|m| self.device.free_memory(*m, None));
         self.scene
             .sun
             .buffer
@@ -585,6 +636,11 @@ impl App {
                 .free_command_buffers(center.command_pool, &center.command_buffers);
         }
         self.device.destroy_pipeline(self.data.pbr_pipeline, None);
+        self.device
+            .destroy_pipeline(self.data.skinned_pbr_pipeline, None);
+        self.device
+            .destroy_pipeline(self.data.morph_pbr_pipeline, None);
+        self.data.shadow_map.destroy_pipelines(&self.device);
         self.device
             .destroy_pipeline_layout(self.data.pbr_pipeline_layout, None);
         self.device
@@ -0,0 +1,262 @@
+use VulcanEngine_0::game_objects::animation::{
+    AnimationClip, AnimationPlayer, Channel, Interpolation, Pose, Property, Sampler, Skin,
+};
+use VulcanEngine_0::game_objects::render_object::ObjectId;
+use VulcanEngine_0::game_objects::scene::{GameObject, Scene};
+use VulcanEngine_0::game_objects::transform::Transform;
+use glam::{Mat4, Quat, Vec3, vec3};
+use std::f32::consts::FRAC_PI_2;
+
+const EPSILON: f32 = 1e-5;
+
+fn assert_vec3_eq(a: Vec3, b: Vec3) {
+    assert!(a.abs_diff_eq(b, EPSILON), "{a} != {b}");
+}
+
+fn identity() -> Transform {
+    Transform::new(Vec3::ZERO, Vec3::ONE, Quat::IDENTITY)
+}
+
+#[test]
+fn step_holds_previous_keyframe() {
+    let sampler = Sampler::new(
+        Interpolation::Step,
+        vec![0.0, 1.0, 2.0],
+        vec![Vec3::ZERO, Vec3::ONE, Vec3::splat(2.0)],
+    );
+    assert_vec3_eq(sampler.sample(0.0), Vec3::ZERO);
+    assert_vec3_eq(sampler.sample(0.99), Vec3::ZERO);
+    assert_vec3_eq(sampler.sample(1.0), Vec3::ONE);
+    assert_vec3_eq(sampler.sample(1.5), Vec3::ONE);
+    assert_vec3_eq(sampler.sample(2.0), Vec3::splat(2.0));
+}
+
+#[test]
+fn linear_interpolates_and_clamps() {
+    let sampler = Sampler::new(
+        Interpolation::Linear,
+        vec![1.0, 3.0],
+        vec![Vec3::ZERO, vec3(4.0, 0.0, -2.0)],
+    );
+    assert_vec3_eq(sampler.sample(-1.0), Vec3::ZERO);
+    assert_vec3_eq(sampler.sample(2.0), vec3(2.0, 0.0, -1.0));
+    assert_vec3_eq(sampler.sample(2.5), vec3(3.0, 0.0, -1.5));
+    assert_vec3_eq(sampler.sample(10.0), vec3(4.0, 0.0, -2.0));
+    assert_eq!(sampler.duration(), 3.0);
+}
+
+#[test]
+fn linear_rotation_uses_slerp() {
+    let end = Quat::from_rotation_y(FRAC_PI_2);
+    let sampler = Sampler::new(
+        Interpolation::Linear,
+        vec![0.0, 1.0],
+        vec![Quat::IDENTITY, end],
+    );
+    let half = sampler.sample(0.5);
+    assert!(half.abs_diff_eq(Quat::from_rotation_y(FRAC_PI_2 / 2.0), EPSILON));
+    assert!((half.length() - 1.0).abs() < EPSILON);
+}
+
+#[test]
+fn cubic_spline_matches_hermite_basis() {
+    // in-tangent, value, out-tangent per keyframe
+    let sampler = Sampler::new(
+        Interpolation::CubicSpline,
+        vec![0.0, 2.0],
+        vec![
+            Vec3::ZERO,
+            Vec3::ZERO,
+            Vec3::X,
+            Vec3::X,
+            Vec3::splat(1.0),
+            Vec3::ZERO,
+        ],
+    );
+    // keyframe values are hit exactly
+    assert_vec3_eq(sampler.sample(0.0), Vec3::ZERO);
+    assert_vec3_eq(sampler.sample(2.0), Vec3::ONE);
+    // s = 0.5: h00 = 0.5, h10 = 0.125, h01 = 0.5, h11 = -0.125, tangents scaled by 2
+    let expected = Vec3::ONE * 0.5 + Vec3::X * (2.0 * 0.125) + Vec3::X * (2.0 * -0.125);
+    assert_vec3_eq(sampler.sample(1.0), expected);
+}
+
+#[test]
+#[should_panic]
+fn cubic_spline_requires_three_values_per_key() {
+    Sampler::new(
+        Interpolation::CubicSpline,
+        vec![0.0, 1.0],
+        vec![Vec3::ZERO, Vec3::ONE],
+    );
+}
+
+#[test]
+fn player_wraps_and_poses_targets() {
+    let mut scene = Scene::default();
+    let target = scene
+        .insert_instance(GameObject::new("target", identity(), vec![]))
+        .unwrap();
+    let clip = AnimationClip::new(
+        "slide",
+        vec![Channel {
+            target,
+            property: Property::Translation(Sampler::new(
+                Interpolation::Linear,
+                vec![0.0, 2.0],
+                vec![Vec3::ZERO, vec3(2.0, 0.0, 0.0)],
+            )),
+        }],
+    );
+    assert_eq!(clip.duration, 2.0);
+
+    let mut player = AnimationPlayer::new(vec![clip]);
+    assert!(player.pose().is_empty());
+    assert!(!player.play_by_name("missing"));
+    assert!(player.play_by_name("slide"));
+    player.advance(2.5);
+    assert!((player.time - 0.5).abs() < EPSILON);
+    let pose = player.pose();
+    assert_eq!(pose.len(), 1);
+    assert_eq!(pose[0].1, Pose::Translation(vec3(0.5, 0.0, 0.0)));
+
+    let root = scene
+        .insert_instance(GameObject::new("root", identity(), vec![]))
+        .unwrap();
+    scene.objects.get_mut(root).unwrap().animation = Some(player);
+    scene.update_animations(1.0);
+    let position = scene.objects.get(target).unwrap().transform.position;
+    assert_vec3_eq(position, vec3(1.5, 0.0, 0.0));
+
+    scene.objects.get_mut(root).unwrap().stop_animation();
+    scene.update_animations(1.0);
+    let position = scene.objects.get(target).unwrap().transform.position;
+    assert_vec3_eq(position, vec3(1.5, 0.0, 0.0));
+}
+
+#[test]
+fn skin_is_identity_in_bind_pose() {
+    let mut scene = Scene::default();
+    let joint_transform = Transform::new(vec3(0.0, 1.0, 0.0), Vec3::ONE, Quat::IDENTITY);
+    let joint = scene
+        .insert_instance(GameObject::new("joint", joint_transform.clone(), vec![]))
+        .unwrap();
+    let mesh = scene
+        .insert_instance(GameObject::new("mesh", identity(), vec![]))
+        .unwrap();
+    let skin = Skin {
+        joints: vec![joint],
+        inverse_bind_matrices: vec![joint_transform.matrix().inverse()],
+    };
+    scene.objects.get_mut(mesh).unwrap().skin = Some(skin);
+
+    let matrices = scene.joint_matrices(&[mesh]);
+    assert!(matrices[0][0].abs_diff_eq(Mat4::IDENTITY, EPSILON));
+
+    scene.objects.get_mut(joint).unwrap().transform.position = vec3(0.0, 3.0, 0.0);
+    let matrices = scene.joint_matrices(&[mesh]);
+    assert!(matrices[0][0].abs_diff_eq(Mat4::from_translation(vec3(0.0, 2.0, 0.0)), EPSILON));
+}
+
+fn slide_player(target: ObjectId) -> AnimationPlayer {
+    let clip = AnimationClip::new(
+        "slide",
+        vec![Channel {
+            target,
+            property: Property::Translation(Sampler::new(
+                Interpolation::Linear,
+                vec![0.0, 4.0],
+                vec![Vec3::ZERO, vec3(4.0, 0.0, 0.0)],
+            )),
+        }],
+    );
+    let mut player = AnimationPlayer::new(vec![clip]);
+    assert!(player.play(0));
+    player
+}
+
+fn animated_scene() -> (Scene, ObjectId) {
+    let mut scene = Scene::default();
+    let target = scene
+        .insert_instance(GameObject::new("platform", identity(), vec![]))
+        .unwrap();
+    scene.objects.get_mut(target).unwrap().animation = Some(slide_player(target));
+    (scene, target)
+}
+
+fn position(scene: &Scene, id: ObjectId) -> Vec3 {
+    scene.objects.get(id).unwrap().transform.position
+}
+
+fn player(scene: &mut Scene, id: ObjectId) -> &mut AnimationPlayer {
+    scene
+        .objects
+        .get_mut(id)
+        .unwrap()
+        .animation
+        .as_mut()
+        .unwrap()
+}
+
+#[test]
+fn non_looping_clip_holds_last_pose() {
+    let (mut scene, target) = animated_scene();
+    player(&mut scene, target).set_looping(false);
+    scene.update_animations(3.0);
+    assert!(player(&mut scene, target).is_playing());
+    scene.update_animations(3.0);
+    assert_vec3_eq(position(&scene, target), vec3(4.0, 0.0, 0.0));
+    assert!(player(&mut scene, target).is_finished());
+    assert!(!player(&mut scene, target).is_playing());
+}
+
+#[test]
+fn looping_clip_wraps() {
+    let (mut scene, target) = animated_scene();
+    scene.update_animations(5.0);
+    assert_vec3_eq(position(&scene, target), vec3(1.0, 0.0, 0.0));
+    // many small steps end up in the same place as one big one
+    for _ in 0..40 {
+        scene.update_animations(0.25);
+    }
+    assert_vec3_eq(position(&scene, target), vec3(3.0, 0.0, 0.0));
+}
+
+#[test]
+fn paused_player_does_not_advance() {
+    let (mut scene, target) = animated_scene();
+    scene.update_animations(1.0);
+    player(&mut scene, target).pause();
+    scene.update_animations(2.0);
+    assert_vec3_eq(position(&scene, target), vec3(1.0, 0.0, 0.0));
+    player(&mut scene, target).resume();
+    scene.update_animations(2.0);
+    assert_vec3_eq(position(&scene, target), vec3(3.0, 0.0, 0.0));
+}
+
+#[test]
+fn speed_scales_and_reverses_playback() {
+    let (mut scene, target) = animated_scene();
+    player(&mut scene, target).set_speed(2.0);
+    scene.update_animations(1.0);
+    assert_vec3_eq(position(&scene, target), vec3(2.0, 0.0, 0.0));
+    player(&mut scene, target).set_speed(-0.5);
+    scene.update_animations(2.0);
+    assert_vec3_eq(position(&scene, target), vec3(1.0, 0.0, 0.0));
+    // backwards past the start wraps to the end of the clip
+    scene.update_animations(4.0);
+    assert_vec3_eq(position(&scene, target), vec3(3.0, 0.0, 0.0));
+}
+
+#[test]
+fn seek_moves_the_playhead_within_the_clip() {
+    let (mut scene, target) = animated_scene();
+    player(&mut scene, target).pause();
+    player(&mut scene, target).seek(2.5);
+    scene.update_animations(1.0);
+    assert_vec3_eq(position(&scene, target), vec3(2.5, 0.0, 0.0));
+    player(&mut scene, target).seek(10.0);
+    assert_eq!(player(&mut scene, target).time, 4.0);
+    player(&mut scene, target).seek(-1.0);
+    assert_eq!(player(&mut scene, target).time, 0.0);
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use VulcanEngine_0::asset_manager::error::{AssetError, AssetLocation, ParseError};
+use VulcanEngine_0::asset_manager::load::AccessorError;
+use VulcanEngine_0::asset_manager::load_obj;
+use common::fixture;
+
+#[test]
+fn missing_gltf_is_an_io_error() {
+    let path = fixture("does_not_exist.gltf");
+    let location = AssetLocation::new(&path);
+    let error = AssetError::gltf(&location, gltf::import(&path).unwrap_err());
+    assert!(matches!(error, AssetError::Io { .. }));
+    assert_eq!(error.location().path, path);
+}
+
+#[test]
+fn malformed_gltf_is_a_parse_error() {
+    let path = fixture("broken.gltf");
+    let location = AssetLocation::new(&path);
+    let error = AssetError::gltf(&location, gltf::import(&path).unwrap_err());
+    assert!(matches!(
+        error,
+        AssetError::Parse {
+            source: ParseError::Gltf(_),
+            ..
+        }
+    ));
+}
+
+#[test]
+fn missing_obj_is_an_io_error() {
+    let path = fixture("does_not_exist.obj");
+    let error = load_obj::read(&path).unwrap_err();
+    assert!(matches!(error, AssetError::Io { .. }));
+    assert_eq!(error.location(), &AssetLocation::new(&path));
+}
+
+#[test]
+fn unsupported_primitive_mode_is_unsupported() {
+    let location = AssetLocation::new("level.gltf");
+    let error = AssetError::accessor(&location, AccessorError::Mode(gltf::mesh::Mode::Points));
+    assert!(matches!(error, AssetError::Unsupported { .. }));
+    let error = AssetError::accessor(&location, AccessorError::OutOfBounds { accessor: 3 });
+    assert!(matches!(
+        error,
+        AssetError::Parse {
+            source: ParseError::Accessor(_),
+            ..
+        }
+    ));
+}
+
+#[test]
+fn message_names_file_node_and_mesh() {
+    let location = AssetLocation::new("assets/level.gltf")
+        .node("door")
+        .mesh("hinge");
+    let error = AssetError::unsupported(&location, "texture format R16G16B16");
+    assert_eq!(
+        error.to_string(),
+        "assets/level.gltf, node \"door\", mesh \"hinge\": unsupported format: texture format R16G16B16"
+    );
+}
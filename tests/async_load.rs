@@ -0,0 +1,107 @@
+mod common;
+
+use VulcanEngine_0::asset_manager::bevy_load::{
+    AssetLoads, AssetSource, LoadId, LoadState, ReadAsset,
+};
+use VulcanEngine_0::asset_manager::error::AssetError;
+use common::fixture;
+use std::time::{Duration, Instant};
+
+/// polls until every worker is done, the way the upload system does once per frame.
+fn read_all(loads: &mut AssetLoads) -> Vec<ReadAsset> {
+    let start = Instant::now();
+    let mut read = vec![];
+    while !loads.is_idle() {
+        assert!(start.elapsed() < Duration::from_secs(10), "loading hangs");
+        read.extend(loads.take_read());
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    read
+}
+
+#[test]
+fn gltf_is_read_off_the_calling_thread() {
+    let mut loads = AssetLoads::default();
+    let id = loads.load(fixture("interleaved.gltf"), None);
+    assert_eq!(loads.state(id), Some(&LoadState::Reading));
+    let read = read_all(&mut loads);
+    assert_eq!(read.len(), 1);
+    assert_eq!(read[0].id, id);
+    let Ok(AssetSource::Gltf(source)) = &read[0].source else {
+        panic!("expected a gltf source, got {:?}", read[0].source);
+    };
+    assert_eq!(source.document.meshes().count(), 1);
+    assert_eq!(source.location.path, fixture("interleaved.gltf"));
+}
+
+#[test]
+fn read_errors_are_returned_with_the_load() {
+    let mut loads = AssetLoads::default();
+    let broken = loads.load(fixture("broken.gltf"), None);
+    let missing = loads.load(fixture("missing.gltf"), None);
+    // textures that can not be read fall back to the default, the model still loads
+    let missing_texture = loads.load(fixture("unreadable_textures.obj"), None);
+    let read = read_all(&mut loads);
+    assert_eq!(read.len(), 3);
+    for asset in &read {
+        match asset.source {
+            Err(AssetError::Parse { .. }) => assert_eq!(asset.id, broken),
+            Err(AssetError::Io { ref location, .. }) => {
+                assert_eq!(asset.id, missing);
+                assert_eq!(location.path, fixture("missing.gltf"));
+            }
+            Ok(AssetSource::Obj(_)) => assert_eq!(asset.id, missing_texture),
+            ref other => panic!("unexpected result {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn progress_counts_loads_since_the_queue_was_empty() {
+    let mut loads = AssetLoads::default();
+    assert!(loads.is_idle());
+    assert_eq!(loads.progress(), 1.0);
+    let ids: Vec<LoadId> = ["interleaved.gltf", "sparse.gltf", "strip_fan.gltf"]
+        .into_iter()
+        .map(|name| loads.load(fixture(name), None))
+        .collect();
+    // workers may already be done, but nothing is handed out before `take_read`
+    assert_eq!(loads.pending().count(), 3);
+    assert_eq!(loads.progress(), 0.0);
+    assert_eq!(read_all(&mut loads).len(), 3);
+    assert_eq!(loads.progress(), 1.0);
+    assert_ne!(ids[0], ids[1]);
+    assert_ne!(ids[1], ids[2]);
+}
+
+#[test]
+fn finished_states_are_handed_out_once() {
+    let mut loads = AssetLoads::default();
+    let id = loads.load(fixture("sparse.gltf"), None);
+    assert_eq!(loads.take_state(id), Some(LoadState::Reading));
+    let read = read_all(&mut loads);
+    loads.finish(read[0].id, LoadState::Failed("no device".to_string()));
+    assert_eq!(
+        loads.state(id),
+        Some(&LoadState::Failed("no device".to_string()))
+    );
+    assert_eq!(
+        loads.take_state(id),
+        Some(LoadState::Failed("no device".to_string()))
+    );
+    assert_eq!(loads.state(id), None);
+    assert_eq!(loads.state(LoadId(id.0 + 1)), None);
+}
+
+#[test]
+fn reloads_are_forgotten_once_read() {
+    let mut loads = AssetLoads::default();
+    let id = loads.reload(fixture("sparse.gltf"));
+    assert_eq!(loads.state(id), Some(&LoadState::Reading));
+    let read = read_all(&mut loads);
+    assert!(read[0].reload);
+    assert_eq!(loads.state(id), None);
+    // hot reload keeps no id, so the outcome is not kept either
+    loads.finish(id, LoadState::Loaded(vec![]));
+    assert_eq!(loads.state(id), None);
+}
@@ -0,0 +1,200 @@
+mod common;
+
+use VulcanEngine_0::game_objects::bounds::{Aabb, BoundingSphere, Bounds, Frustum};
+use VulcanEngine_0::game_objects::camera::Camera;
+use VulcanEngine_0::game_objects::render_object::ObjectId;
+use VulcanEngine_0::game_objects::scene::{GameObject, Scene};
+use VulcanEngine_0::game_objects::transform::Transform;
+use VulcanEngine_0::vulkan::CORRECTION;
+use common::{camera, numbers};
+use glam::{Mat4, Quat, Vec3};
+
+const EPSILON: f32 = 1e-4;
+
+fn proj(camera: &Camera) -> Mat4 {
+    CORRECTION
+        * Mat4::perspective_rh(
+            camera.fov.to_radians(),
+            16.0 / 9.0,
+            camera.near_field,
+            camera.far_field,
+        )
+}
+
+/// a world space point at a view space position of `camera`.
+fn world(camera: &Camera, view: Vec3) -> Vec3 {
+    camera.transform.matrix().transform_point3(view)
+}
+
+fn corners(aabb: &Aabb) -> [Vec3; 8] {
+    [0, 1, 2, 3, 4, 5, 6, 7].map(|i| {
+        Vec3::new(
+            if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+            if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+            if i & 4 == 0 { aabb.min.z } else { aabb.max.z },
+        )
+    })
+}
+
+fn sphere_at(center: Vec3) -> BoundingSphere {
+    BoundingSphere {
+        center,
+        radius: 0.0,
+    }
+}
+
+#[test]
+fn bounds_enclose_their_points() {
+    let mut random = numbers(3);
+    let points: Vec<Vec3> = (0..100)
+        .map(|_| Vec3::new(random() * 4.0 - 1.0, random() * 2.0, random() - 3.0))
+        .collect();
+    let bounds = Bounds::from_points(&points);
+    for point in &points {
+        assert!(point.cmpge(bounds.aabb.min).all() && point.cmple(bounds.aabb.max).all());
+        assert!(point.distance(bounds.sphere.center) <= bounds.sphere.radius + EPSILON);
+    }
+    assert_eq!(bounds.sphere.center, bounds.aabb.center());
+    assert_eq!(Bounds::from_points(&[]), Bounds::default());
+}
+
+#[test]
+fn transformed_bounds_enclose_transformed_points() {
+    let mut random = numbers(5);
+    let aabb = Aabb {
+        min: Vec3::new(-1.0, 0.0, -2.0),
+        max: Vec3::new(2.0, 1.0, 0.5),
+    };
+    let sphere = Bounds::from_points(&corners(&aabb)).sphere;
+    for _ in 0..50 {
+        let matrix = Mat4::from_scale_rotation_translation(
+            Vec3::new(
+                random() * 3.0 + 0.1,
+                random() * 3.0 + 0.1,
+                random() * 3.0 + 0.1,
+            ),
+            Quat::from_euler(
+                glam::EulerRot::XYZ,
+                random() * 6.0,
+                random() * 6.0,
+                random(),
+            ),
+            Vec3::new(random(), random(), random()) * 20.0 - 10.0,
+        );
+        let moved = aabb.transform(matrix);
+        let moved_sphere = sphere.transform(matrix);
+        for corner in corners(&aabb) {
+            let corner = matrix.transform_point3(corner);
+            let slack = Vec3::splat(EPSILON * 10.0);
+            assert!(corner.cmpge(moved.min - slack).all() && corner.cmple(moved.max + slack).all());
+            assert!(corner.distance(moved_sphere.center) <= moved_sphere.radius + EPSILON * 10.0);
+        }
+    }
+    // without a rotation the box stays tight
+    let matrix = Mat4::from_scale_rotation_translation(Vec3::splat(2.0), Quat::IDENTITY, Vec3::X);
+    let moved = aabb.transform(matrix);
+    assert!((moved.min - Vec3::new(-1.0, 0.0, -4.0)).length() < EPSILON);
+    assert!((moved.max - Vec3::new(5.0, 2.0, 1.0)).length() < EPSILON);
+}
+
+#[test]
+fn frustum_matches_the_projection() {
+    let camera = camera();
+    let proj = proj(&camera);
+    let frustum = Frustum::new(&camera, proj);
+    let view = camera.transform.matrix().inverse();
+    let mut random = numbers(7);
+    let (mut inside, mut outside) = (0, 0);
+    for _ in 0..2000 {
+        let view_point = Vec3::new(
+            random() * 400.0 - 200.0,
+            random() * 400.0 - 200.0,
+            random() * 500.0 - 250.0,
+        );
+        let point = world(&camera, view_point);
+        let depth = -view.transform_point3(point).z;
+        let ndc = proj.project_point3(view.transform_point3(point));
+        let margin = 0.01;
+        if depth > camera.near_field
+            && depth < camera.far_field - margin
+            && ndc.x.abs() < 1.0 - margin
+            && ndc.y.abs() < 1.0 - margin
+        {
+            inside += 1;
+            assert!(frustum.intersects_sphere(&sphere_at(point)), "{view_point}");
+        } else if depth < 0.0
+            || depth > camera.far_field + margin
+            || ndc.x.abs() > 1.0 + margin
+            || ndc.y.abs() > 1.0 + margin
+        {
+            outside += 1;
+            assert!(
+                !frustum.intersects_sphere(&sphere_at(point)),
+                "{view_point}"
+            );
+        }
+    }
+    assert!(inside > 50 && outside > 500, "{inside} {outside}");
+}
+
+#[test]
+fn volumes_touching_the_frustum_are_kept() {
+    let camera = camera();
+    let frustum = Frustum::new(&camera, proj(&camera));
+    // behind the camera, but reaching in front of it
+    let sphere = BoundingSphere {
+        center: world(&camera, Vec3::new(0.0, 0.0, 2.0)),
+        radius: 3.0,
+    };
+    assert!(frustum.intersects_sphere(&sphere));
+    assert!(!frustum.intersects_sphere(&BoundingSphere {
+        radius: 1.0,
+        ..sphere
+    }));
+
+    // a box around the camera
+    let around = Aabb {
+        min: camera.transform.position - 1.0,
+        max: camera.transform.position + 1.0,
+    };
+    assert!(frustum.intersects_aabb(&around));
+    // a box far past the far plane
+    let far = world(&camera, Vec3::new(0.0, 0.0, -400.0));
+    assert!(!frustum.intersects_aabb(&Aabb {
+        min: far - 1.0,
+        max: far + 1.0,
+    }));
+}
+
+#[test]
+fn visible_instances_are_packed_first() {
+    let camera = camera();
+    let frustum = Frustum::new(&camera, proj(&camera));
+    let bounds = Bounds::from_points(&[Vec3::splat(-0.5), Vec3::splat(0.5)]);
+    let mut scene = Scene::default();
+    let mut place = |view: Vec3| {
+        scene
+            .insert_instance(GameObject::new(
+                "cube",
+                Transform::new(world(&camera, view), Vec3::ONE, Quat::IDENTITY),
+                vec![],
+            ))
+            .unwrap()
+    };
+    let behind = place(Vec3::new(0.0, 0.0, 10.0));
+    let front = place(Vec3::new(0.0, 0.0, -10.0));
+    let left = place(Vec3::new(-500.0, 0.0, -10.0));
+    let edge = place(Vec3::new(0.0, 0.0, -camera.far_field - 0.4));
+    let missing = ObjectId::new(999, 0);
+
+    let instances = [behind, front, left, edge, missing];
+    let (ordered, visible) = scene.cull_instances(&bounds, &instances, &frustum);
+    assert_eq!(visible, 2);
+    assert_eq!(ordered, vec![front, edge, behind, left, missing]);
+
+    // scaled up the cube behind the camera reaches into the view
+    scene.objects.get_mut(behind).unwrap().transform.scale = Vec3::splat(30.0);
+    let (ordered, visible) = scene.cull_instances(&bounds, &instances, &frustum);
+    assert_eq!(visible, 3);
+    assert_eq!(ordered[..3], [behind, front, edge]);
+}
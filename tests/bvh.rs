@@ -0,0 +1,288 @@
+mod common;
+
+use std::collections::HashMap;
+
+use VulcanEngine_0::game_objects::bounds::{Aabb, Frustum, Ray};
+use VulcanEngine_0::game_objects::bvh::{BVH_MARGIN, Bvh};
+use VulcanEngine_0::game_objects::camera::Camera;
+use VulcanEngine_0::game_objects::render_object::ObjectId;
+use VulcanEngine_0::game_objects::scene::{GameObject, Scene};
+use VulcanEngine_0::game_objects::transform::Transform;
+use VulcanEngine_0::vulkan::CORRECTION;
+use common::numbers;
+use glam::{Mat4, Quat, Vec3};
+
+fn random_vec(random: &mut impl FnMut() -> f32, scale: f32) -> Vec3 {
+    (Vec3::new(random(), random(), random()) * 2.0 - 1.0) * scale
+}
+
+fn random_box(random: &mut impl FnMut() -> f32) -> Aabb {
+    let center = random_vec(random, 50.0);
+    let half = Vec3::new(random(), random(), random()) * 3.0;
+    Aabb {
+        min: center - half,
+        max: center + half,
+    }
+}
+
+fn sorted(mut ids: Vec<ObjectId>) -> Vec<ObjectId> {
+    ids.sort_by_key(|id| id.index);
+    ids
+}
+
+/// compares every query of `bvh` against testing each box of `boxes`.
+fn check(bvh: &Bvh, boxes: &HashMap<ObjectId, Aabb>, random: &mut impl FnMut() -> f32) {
+    assert_eq!(bvh.len(), boxes.len());
+    for (id, aabb) in boxes {
+        assert_eq!(bvh.bounds(*id), Some(*aabb));
+    }
+    let brute = |test: &dyn Fn(&Aabb) -> bool| {
+        sorted(
+            boxes
+                .iter()
+                .filter(|(_, aabb)| test(aabb))
+                .map(|(id, _)| *id)
+                .collect(),
+        )
+    };
+
+    let query = random_box(random).expand(10.0);
+    assert_eq!(
+        sorted(bvh.query_aabb(&query)),
+        brute(&|aabb| aabb.overlaps(&query))
+    );
+
+    let mut camera = Camera::default();
+    camera.transform.position = random_vec(random, 60.0);
+    camera.transform.rotation = Quat::from_euler(
+        glam::EulerRot::XYZ,
+        random() * 6.0,
+        random() * 6.0,
+        random() * 6.0,
+    );
+    camera.far_field = 80.0;
+    let proj = CORRECTION * Mat4::perspective_rh(1.0, 1.5, camera.near_field, camera.far_field);
+    let frustum = Frustum::new(&camera, proj);
+    assert_eq!(
+        sorted(bvh.query_frustum(&frustum)),
+        brute(&|aabb| frustum.intersects_aabb(aabb))
+    );
+
+    let ray = Ray {
+        origin: random_vec(random, 70.0),
+        direction: random_vec(random, 1.0).normalize_or(Vec3::X),
+    };
+    let max_distance = random() * 150.0;
+    let hits = bvh.ray_hits(&ray, max_distance);
+    assert!(hits.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+    assert_eq!(
+        sorted(hits.iter().map(|(id, _)| *id).collect()),
+        brute(&|aabb| aabb.ray_distance(&ray, max_distance).is_some())
+    );
+    let first = boxes
+        .values()
+        .filter_map(|aabb| aabb.ray_distance(&ray, max_distance))
+        .fold(None, |best: Option<f32>, distance| {
+            Some(best.map_or(distance, |best| best.min(distance)))
+        });
+    assert_eq!(bvh.ray_cast(&ray, max_distance).map(|hit| hit.1), first);
+    assert_eq!(hits.first().map(|hit| hit.1), first);
+
+    let point = random_vec(random, 70.0);
+    let nearest = boxes
+        .values()
+        .map(|aabb| aabb.distance_squared(point).sqrt())
+        .fold(None, |best: Option<f32>, distance| {
+            Some(best.map_or(distance, |best| best.min(distance)))
+        });
+    let found = bvh.nearest(point);
+    assert_eq!(found.map(|hit| hit.1), nearest);
+    if let Some((id, distance)) = found {
+        assert_eq!(boxes[&id].distance_squared(point).sqrt(), distance);
+    }
+}
+
+#[test]
+fn queries_match_brute_force() {
+    for seed in 0..8 {
+        let mut random = numbers(seed);
+        let mut bvh = Bvh::default();
+        let mut boxes = HashMap::new();
+        let mut next = 0;
+        check(&bvh, &boxes, &mut random);
+        for step in 0..600 {
+            let ids: Vec<ObjectId> = boxes.keys().copied().collect();
+            let choice = random();
+            if ids.is_empty() || choice < 0.4 {
+                let aabb = random_box(&mut random);
+                bvh.insert(ObjectId::new(next, 0), aabb);
+                boxes.insert(ObjectId::new(next, 0), aabb);
+                next += 1;
+            } else if choice < 0.85 {
+                // small moves stay in the grown box, large ones move the leaf
+                let id = ids[(random() * ids.len() as f32) as usize % ids.len()];
+                let scale = if random() < 0.5 { BVH_MARGIN } else { 20.0 };
+                let offset = random_vec(&mut random, scale);
+                let aabb = Aabb {
+                    min: boxes[&id].min + offset,
+                    max: boxes[&id].max + offset,
+                };
+                bvh.update(id, aabb);
+                boxes.insert(id, aabb);
+            } else {
+                let id = ids[(random() * ids.len() as f32) as usize % ids.len()];
+                assert!(bvh.remove(id));
+                assert!(!bvh.remove(id));
+                boxes.remove(&id);
+            }
+            if step % 20 == 0 {
+                check(&bvh, &boxes, &mut random);
+            }
+        }
+        check(&bvh, &boxes, &mut random);
+    }
+}
+
+#[test]
+fn small_moves_only_refit() {
+    let mut bvh = Bvh::default();
+    let aabb = Aabb {
+        min: Vec3::ZERO,
+        max: Vec3::ONE,
+    };
+    assert!(bvh.update(ObjectId::new(0, 0), aabb));
+    let nudged = Aabb {
+        min: aabb.min + BVH_MARGIN * 0.5,
+        max: aabb.max + BVH_MARGIN * 0.5,
+    };
+    assert!(!bvh.update(ObjectId::new(0, 0), nudged));
+    assert_eq!(bvh.bounds(ObjectId::new(0, 0)), Some(nudged));
+    // queries use the exact box, not the grown one
+    let beside = Aabb {
+        min: Vec3::new(-1.0, 0.0, 0.0),
+        max: Vec3::new(-BVH_MARGIN * 0.1, 1.0, 1.0),
+    };
+    assert!(bvh.query_aabb(&beside).is_empty());
+    let moved = Aabb {
+        min: aabb.min + 5.0,
+        max: aabb.max + 5.0,
+    };
+    assert!(bvh.update(ObjectId::new(0, 0), moved));
+    assert_eq!(bvh.query_aabb(&moved), vec![ObjectId::new(0, 0)]);
+}
+
+#[test]
+fn grids_stay_shallow() {
+    let mut bvh = Bvh::default();
+    let mut id = 0;
+    for x in 0..32 {
+        for z in 0..32 {
+            let min = Vec3::new(x as f32, 0.0, z as f32);
+            bvh.insert(
+                ObjectId::new(id, 0),
+                Aabb {
+                    min,
+                    max: min + 0.9,
+                },
+            );
+            id += 1;
+        }
+    }
+    assert_eq!(bvh.len(), 1024);
+    assert!(bvh.depth() <= 16, "{}", bvh.depth());
+}
+
+#[test]
+fn scene_bvh_follows_objects() {
+    let mut scene = Scene::default();
+    let parent = scene
+        .insert_instance(GameObject::new(
+            "parent",
+            Transform::new(Vec3::new(10.0, 0.0, 0.0), Vec3::ONE, Quat::IDENTITY),
+            vec![],
+        ))
+        .unwrap();
+    let mut child = GameObject::new(
+        "child",
+        Transform::new(Vec3::new(0.0, 2.0, 0.0), Vec3::ONE, Quat::IDENTITY),
+        vec![],
+    );
+    child.parent = Some(parent);
+    let child = scene.insert_instance(child).unwrap();
+    scene.objects.get_mut(parent).unwrap().children.push(child);
+    scene.refit_bvh();
+    assert_eq!(scene.bvh.len(), 2);
+    // objects without meshes are points at their origin
+    let point = Vec3::new(10.0, 2.0, 0.0);
+    assert_eq!(
+        scene.bvh.bounds(child),
+        Some(Aabb {
+            min: point,
+            max: point
+        })
+    );
+    assert_eq!(
+        scene.bvh.nearest(Vec3::new(10.0, 3.0, 0.0)),
+        Some((child, 1.0))
+    );
+
+    // moving the parent moves the child, once the move is marked
+    scene.objects.get_mut(parent).unwrap().transform.position = Vec3::new(-10.0, 0.0, 0.0);
+    scene.refit_bvh();
+    assert_eq!(scene.bvh.bounds(child).unwrap().min, point);
+    scene.mark_moved(parent);
+    scene.refit_bvh();
+    assert_eq!(scene.world_bounds(child), scene.bvh.bounds(child));
+    assert_eq!(
+        scene.bvh.bounds(child).unwrap().min,
+        Vec3::new(-10.0, 2.0, 0.0)
+    );
+
+    scene.remove_instance(child);
+    assert!(!scene.bvh.contains(child));
+    assert_eq!(scene.bvh.len(), 1);
+}
+
+#[test]
+fn scene_refits_match_brute_force() {
+    for seed in 0..4 {
+        let mut random = numbers(seed);
+        let mut scene = Scene::default();
+        for step in 0..300 {
+            let ids = scene.objects.ids();
+            let pick = |random: &mut dyn FnMut() -> f32| {
+                ids[(random() * ids.len() as f32) as usize % ids.len()]
+            };
+            let choice = random();
+            if ids.is_empty() || choice < 0.3 {
+                let transform =
+                    Transform::new(random_vec(&mut random, 20.0), Vec3::ONE, Quat::IDENTITY);
+                let id = scene
+                    .insert_instance(GameObject::new("object", transform, vec![]))
+                    .unwrap();
+                if !ids.is_empty() && random() < 0.5 {
+                    let _ = scene.reparent(id, pick(&mut random), false);
+                }
+            } else if choice < 0.8 {
+                let offset = random_vec(&mut random, 5.0);
+                let rotation = Quat::from_rotation_y(random() * 6.0);
+                let id = pick(&mut random);
+                scene
+                    .transform_object(id, Transform::new(offset, Vec3::ONE, rotation))
+                    .unwrap();
+            } else if choice < 0.9 {
+                let (child, parent) = (pick(&mut random), pick(&mut random));
+                let _ = scene.reparent(child, parent, random() < 0.5);
+            } else {
+                scene.remove_subtree(pick(&mut random));
+            }
+            if step % 10 == 0 {
+                scene.refit_bvh();
+                assert_eq!(scene.bvh.len(), scene.objects.len());
+                for id in scene.objects.ids() {
+                    assert_eq!(scene.bvh.bounds(id), scene.world_bounds(id));
+                }
+            }
+        }
+    }
+}
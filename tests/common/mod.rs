@@ -0,0 +1,88 @@
+//! helpers shared by the integration tests, every test crate uses a different part of them.
+#![allow(dead_code)]
+
+use VulcanEngine_0::game_objects::camera::Camera;
+use VulcanEngine_0::game_objects::render_object::{PBR, RenderObject};
+use VulcanEngine_0::vulkan::buffer_util::GrowableBuffers;
+use VulcanEngine_0::vulkan::image_util::TextureData;
+use VulcanEngine_0::vulkan::vertexbuffer_util::{VertexData, VertexPbr};
+use glam::{Vec3, Vec4};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// deterministic numbers in 0..1.
+pub fn numbers(seed: u64) -> impl FnMut() -> f32 {
+    let mut state = seed;
+    move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// a file of `tests/fixtures`.
+pub fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// a fresh directory per test, tests run in parallel.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vulcan_{}_{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+pub fn assert_close(a: Vec3, b: Vec3, epsilon: f32) {
+    assert!((a - b).abs().max_element() < epsilon, "{a} != {b}");
+}
+
+pub fn camera() -> Camera {
+    let mut camera = Camera::default();
+    camera.transform.position = Vec3::new(3.0, -1.0, 2.0);
+    camera.far_field = 200.0;
+    camera
+}
+
+/// a render object that is not on the gpu, enough to track its instances.
+pub fn render_object() -> RenderObject<VertexPbr> {
+    let texture = Arc::new(TextureData::default());
+    RenderObject {
+        vertex_data: Arc::new(VertexData {
+            vertices: vec![],
+            indices: vec![],
+            vertex_buffer: Default::default(),
+            vertex_buffer_memory: Default::default(),
+            index_buffer: Default::default(),
+            index_buffer_memory: Default::default(),
+            mem_map: None,
+            bounds: Default::default(),
+        }),
+        pbr: PBR {
+            texture_data: texture.clone(),
+            base: Vec4::ONE,
+            normal_texture: texture.clone(),
+            normal_scale: 1.0,
+            metallic_roughness_texture: texture.clone(),
+            metallic: 1.0,
+            roughness: 1.0,
+            occlusion_texture: texture.clone(),
+            occlusion_strength: 1.0,
+            emissive_texture: texture,
+            emissive: Vec3::ZERO,
+        },
+        skin: None,
+        morph: None,
+        uniform_buffers: vec![],
+        uniform_buffers_memory: vec![],
+        instance_buffers: GrowableBuffers::default(),
+        descriptor_sets: vec![],
+        instances: vec![],
+        visible: 0,
+        pin: Default::default(),
+    }
+}
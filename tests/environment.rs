@@ -0,0 +1,171 @@
+mod common;
+
+use VulcanEngine_0::asset_manager::load_skybox::{HdrCubemap, face_direction, face_uv};
+use VulcanEngine_0::game_objects::environment::{
+    Environment, brdf_lut, irradiance, prefilter, texel_solid_angle,
+};
+use VulcanEngine_0::game_objects::skybox::{SkyBox, SkyBoxSource};
+use VulcanEngine_0::vulkan::image_util::TextureData;
+use common::assert_close;
+use glam::{Vec2, Vec3};
+use std::f32::consts::PI;
+use std::path::PathBuf;
+use vulkanalia::vk::{self, Handle};
+
+const POS_X: usize = 0;
+const POS_Y: usize = 2;
+const NEG_Y: usize = 3;
+const POS_Z: usize = 4;
+
+/// bright above the horizon, black below.
+fn upper_hemisphere(size: u32, radiance: Vec3) -> HdrCubemap {
+    let mut cubemap = HdrCubemap::uniform(size, Vec3::ZERO);
+    for (face, pixels) in cubemap.faces.iter_mut().enumerate() {
+        for y in 0..size {
+            for x in 0..size {
+                if face_direction(face, size, x, y).y > 0.0 {
+                    pixels.set(x, y, radiance);
+                }
+            }
+        }
+    }
+    cubemap
+}
+
+#[test]
+fn texels_cover_the_whole_sphere() {
+    for size in [1, 4, 16] {
+        let mut total = 0.0;
+        for y in 0..size {
+            for x in 0..size {
+                total += texel_solid_angle(size, x, y);
+            }
+        }
+        assert!((6.0 * total - 4.0 * PI).abs() < 1e-4, "{size}: {total}");
+    }
+}
+
+#[test]
+fn directions_find_their_texel_again() {
+    let size = 8;
+    for face in 0..6 {
+        for (x, y) in [(0, 0), (3, 5), (7, 7)] {
+            let (found, uv) = face_uv(face_direction(face, size, x, y));
+            assert_eq!(found, face);
+            let texel = uv * size as f32 - Vec2::new(x as f32, y as f32);
+            assert!((texel - Vec2::splat(0.5)).length() < 1e-4, "{face} {x} {y}");
+        }
+    }
+}
+
+#[test]
+fn uniform_skies_keep_their_radiance() {
+    let radiance = Vec3::new(2.0, 1.0, 0.5);
+    let sky = HdrCubemap::uniform(32, radiance);
+    let irradiance = irradiance(&sky, 4);
+    for face in &irradiance.faces {
+        for color in &face.pixels {
+            assert_close(*color, radiance, 0.01);
+        }
+    }
+    let levels = prefilter(&sky, 16, 4, 32);
+    assert_eq!(
+        levels.iter().map(|level| level.size).collect::<Vec<_>>(),
+        [16, 8, 4, 2]
+    );
+    for level in &levels {
+        for color in level.faces.iter().flat_map(|face| &face.pixels) {
+            assert_close(*color, radiance, 0.01);
+        }
+    }
+}
+
+#[test]
+fn irradiance_follows_the_cosine_law() {
+    // a sky of radiance l over the upper hemisphere gives l facing up, l / 2 facing the
+    // horizon and nothing facing down
+    let sky = upper_hemisphere(16, Vec3::ONE);
+    let irradiance = irradiance(&sky, 8);
+    let center = |face: usize| {
+        let pixels = &irradiance.faces[face];
+        (pixels.get(3, 3) + pixels.get(4, 4)) / 2.0
+    };
+    assert_close(center(POS_Y), Vec3::ONE, 0.03);
+    assert_close(center(NEG_Y), Vec3::ZERO, 0.03);
+    assert_close(center(POS_X), Vec3::splat(0.5), 0.03);
+    assert_close(center(POS_Z), Vec3::splat(0.5), 0.03);
+}
+
+#[test]
+fn rough_levels_blur_the_sky() {
+    let sky = upper_hemisphere(32, Vec3::ONE);
+    let levels = prefilter(&sky, 16, 5, 64);
+    // the first level is the mirror reflection, a sharp horizon
+    let sharp = &levels[0].faces[POS_Z];
+    assert_close(sharp.get(8, 6), Vec3::ONE, 1e-4);
+    assert_close(sharp.get(8, 9), Vec3::ZERO, 1e-4);
+    // rougher levels let the sky leak below the horizon, the last one is a texel per face
+    let rough = &levels[3];
+    let below = rough.sample(Vec3::new(0.0, -0.3, 1.0));
+    let above = rough.sample(Vec3::new(0.0, 0.3, 1.0));
+    assert!(below.x > 0.05, "{below}");
+    assert!(above.x < 0.95, "{above}");
+    assert!(above.x > below.x);
+}
+
+#[test]
+fn brdf_lut_matches_the_split_sum() {
+    let size = 16;
+    let lut = brdf_lut(size, 128);
+    assert_eq!(lut.len(), (size * size) as usize);
+    let at = |n_dot_v: usize, roughness: usize| lut[roughness * size as usize + n_dot_v];
+    for texel in &lut {
+        assert!(texel.min_element() >= 0.0);
+        assert!(texel.x + texel.y <= 1.0 + 1e-3, "{texel}");
+    }
+    // smooth surfaces seen head on reflect f0
+    let smooth = at(size as usize - 1, 0);
+    assert!(smooth.x > 0.95 && smooth.y < 0.02, "{smooth}");
+    // fresnel reflects most at grazing angles
+    assert!(at(0, 0).y > 0.5, "{}", at(0, 0));
+    // rough surfaces lose energy
+    let rough = at(size as usize - 1, size as usize - 1);
+    assert!(rough.x + rough.y < smooth.x + smooth.y);
+}
+
+#[test]
+fn environment_follows_the_skybox() {
+    let mut environment = Environment::default();
+    // nothing was generated yet, not even the uniform environment
+    assert!(!environment.is_current(None));
+    environment.source = Some(vk::Image::null());
+    assert!(environment.is_current(None));
+    let skybox = SkyBox {
+        texture_data: TextureData {
+            image: vk::Image::from_raw(1),
+            ..Default::default()
+        },
+        descriptor_sets: vec![],
+        radiance: HdrCubemap::uniform(1, Vec3::ONE),
+        source: SkyBoxSource::Hdr(PathBuf::new()),
+    };
+    assert!(!environment.is_current(Some(&skybox)));
+    environment.source = Some(skybox.texture_data.image);
+    assert!(environment.is_current(Some(&skybox)));
+    // removing the skybox goes back to the uniform environment
+    assert!(!environment.is_current(None));
+}
+
+#[test]
+fn srgb_faces_are_linearized() {
+    let mut pixels = vec![];
+    for value in [0u8, 188, 255, 255, 128, 0] {
+        pixels.extend([value, value, value, 255]);
+    }
+    let cubemap = HdrCubemap::from_rgba8_srgb(&pixels, 1);
+    assert_eq!(cubemap.faces.len(), 6);
+    assert_close(cubemap.faces[0].get(0, 0), Vec3::ZERO, 1e-4);
+    assert_close(cubemap.faces[1].get(0, 0), Vec3::splat(0.5), 0.01);
+    assert_close(cubemap.faces[2].get(0, 0), Vec3::ONE, 1e-4);
+    assert_close(cubemap.faces[4].get(0, 0), Vec3::splat(0.216), 0.01);
+}
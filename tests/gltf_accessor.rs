@@ -0,0 +1,226 @@
+mod common;
+
+use VulcanEngine_0::asset_manager::error::{AssetError, AssetLocation, ParseError};
+use VulcanEngine_0::asset_manager::load::{self, AccessorError};
+use VulcanEngine_0::game_objects::animation::Property;
+use VulcanEngine_0::game_objects::render_object::ObjectId;
+use common::fixture;
+use glam::{Vec2, Vec3};
+use std::collections::HashMap;
+
+const EPSILON: f32 = 1e-5;
+
+fn import(name: &str) -> (gltf::Document, Vec<gltf::buffer::Data>) {
+    let (document, buffers, _images) = gltf::import(fixture(name)).unwrap();
+    (document, buffers)
+}
+
+fn primitive(document: &gltf::Document, index: usize) -> gltf::Primitive<'_> {
+    document
+        .meshes()
+        .next()
+        .unwrap()
+        .primitives()
+        .nth(index)
+        .unwrap()
+}
+
+#[test]
+fn reads_interleaved_buffer_view() {
+    let (document, buffers) = import("interleaved.gltf");
+    let (vertices, indices) = load::read_primitive(&primitive(&document, 0), &buffers).unwrap();
+    assert_eq!(indices, vec![0, 1, 2]);
+    assert_eq!(vertices.len(), 3);
+    assert_eq!(vertices[1].pos, Vec3::X);
+    assert_eq!(vertices[2].pos, Vec3::Y);
+    assert!(vertices.iter().all(|v| v.normal == Vec3::Z));
+    // normalized u16 texture coordinates
+    assert_eq!(vertices[0].tex_coord, Vec2::ZERO);
+    assert_eq!(vertices[1].tex_coord, Vec2::X);
+    assert_eq!(vertices[2].tex_coord, Vec2::Y);
+}
+
+#[test]
+fn reads_padded_matrix_columns() {
+    let (document, buffers) = import("interleaved.gltf");
+    let matrix = document.accessors().nth(4).unwrap();
+    let values = load::read_floats::<4>(&matrix, &buffers).unwrap();
+    assert_eq!(values, vec![[1.0, 2.0, 3.0, 4.0]]);
+}
+
+#[test]
+fn non_indexed_primitive_gets_sequential_indices_and_normals() {
+    let (document, buffers) = import("non_indexed.gltf");
+    let (vertices, indices) = load::read_primitive(&primitive(&document, 0), &buffers).unwrap();
+    assert_eq!(indices, (0..6).collect::<Vec<u32>>());
+    for vertex in &vertices {
+        assert!((vertex.normal - Vec3::Y).length() < EPSILON);
+    }
+    // normalized u8 texture coordinates with a 4 byte stride
+    assert_eq!(vertices[1].tex_coord, Vec2::Y);
+    assert_eq!(vertices[5].tex_coord, Vec2::ONE);
+}
+
+#[test]
+fn applies_sparse_substitution() {
+    let (document, buffers) = import("sparse.gltf");
+    let (vertices, indices) = load::read_primitive(&primitive(&document, 0), &buffers).unwrap();
+    assert_eq!(indices, vec![0, 1, 2]);
+    assert_eq!(vertices[0].pos, Vec3::ZERO);
+    assert_eq!(vertices[1].pos, Vec3::X);
+    assert_eq!(vertices[2].pos, Vec3::splat(5.0));
+}
+
+#[test]
+fn sparse_accessor_without_view_starts_at_zero() {
+    let (document, buffers) = import("sparse.gltf");
+    let morph = document.accessors().nth(1).unwrap();
+    let deltas = load::read_floats::<3>(&morph, &buffers).unwrap();
+    assert_eq!(deltas, vec![[0.0; 3], [0.0, 1.0, 0.0], [0.0; 3]]);
+}
+
+#[test]
+fn unrolls_strips_and_fans() {
+    let (document, buffers) = import("strip_fan.gltf");
+    let (_, strip) = load::read_primitive(&primitive(&document, 0), &buffers).unwrap();
+    assert_eq!(strip, vec![0, 1, 2, 2, 1, 3]);
+    let (_, fan) = load::read_primitive(&primitive(&document, 1), &buffers).unwrap();
+    assert_eq!(fan, vec![0, 1, 2, 0, 2, 3]);
+}
+
+#[test]
+fn rejects_line_primitives() {
+    let (document, buffers) = import("strip_fan.gltf");
+    let result = load::read_primitive(&primitive(&document, 2), &buffers);
+    assert!(matches!(
+        result,
+        Err(AccessorError::Mode(gltf::mesh::Mode::Lines))
+    ));
+}
+
+#[test]
+fn rejects_wrong_dimensions_and_component_types() {
+    let (document, buffers) = import("interleaved.gltf");
+    let positions = document.accessors().next().unwrap();
+    assert!(matches!(
+        load::read_floats::<2>(&positions, &buffers),
+        Err(AccessorError::Dimensions {
+            expected: 2,
+            found: 3,
+            ..
+        })
+    ));
+    assert!(matches!(
+        load::read_integers::<3>(&positions, &buffers),
+        Err(AccessorError::ComponentType { .. })
+    ));
+}
+
+#[test]
+fn reports_reads_past_the_view() {
+    let (document, buffers) = import("out_of_bounds.gltf");
+    let result = load::read_primitive(&primitive(&document, 0), &buffers);
+    assert!(matches!(
+        result,
+        Err(AccessorError::OutOfBounds { accessor: 0 })
+    ));
+}
+
+#[test]
+fn reads_joints_and_weights_of_every_vertex() {
+    let (document, buffers) = import("skin.gltf");
+    let skin = load::read_skin(&primitive(&document, 0), &buffers, 3, 2)
+        .unwrap()
+        .unwrap();
+    assert_eq!(skin.len(), 3);
+    assert_eq!(skin[1].joints, glam::UVec4::new(1, 0, 0, 0));
+    assert!(skin.iter().all(|v| v.weights == glam::Vec4::X));
+    // primitives without joints and weights are not skinned
+    let unskinned = load::read_skin(&primitive(&document, 3), &buffers, 3, 2).unwrap();
+    assert!(unskinned.is_none());
+}
+
+#[test]
+fn rejects_joints_outside_the_skin() {
+    let (document, buffers) = import("skin.gltf");
+    let result = load::read_skin(&primitive(&document, 1), &buffers, 3, 2);
+    assert!(matches!(
+        result,
+        Err(AccessorError::JointOutOfRange {
+            joint: 2,
+            joint_count: 2
+        })
+    ));
+}
+
+#[test]
+fn rejects_weights_missing_vertices() {
+    let (document, buffers) = import("skin.gltf");
+    let result = load::read_skin(&primitive(&document, 2), &buffers, 3, 2);
+    assert!(matches!(
+        result,
+        Err(AccessorError::AttributeCount {
+            semantic: "WEIGHTS_0",
+            expected: 3,
+            found: 2
+        })
+    ));
+}
+
+#[test]
+fn reads_morph_deltas_of_every_vertex() {
+    let (document, buffers) = import("sparse.gltf");
+    let targets = load::read_morph_targets(&primitive(&document, 0), &buffers, 3).unwrap();
+    assert_eq!(targets.target_count(), 1);
+    assert_eq!(targets.position_deltas[0][1], Vec3::Y);
+    // the target has no normals, so they do not move
+    assert_eq!(targets.normal_deltas[0], vec![Vec3::ZERO; 3]);
+}
+
+#[test]
+fn rejects_morph_targets_missing_vertices() {
+    let (document, buffers) = import("short_morph.gltf");
+    let result = load::read_morph_targets(&primitive(&document, 0), &buffers, 3);
+    assert!(matches!(
+        result,
+        Err(AccessorError::AttributeCount {
+            semantic: "POSITION",
+            expected: 3,
+            found: 2
+        })
+    ));
+}
+
+#[test]
+fn animation_outputs_read_sparse_accessors() {
+    let (document, buffers) = import("sparse_animation.gltf");
+    let animation = document.animations().next().unwrap();
+    let nodes = HashMap::from([(0, ObjectId::new(0, 0))]);
+    let location = AssetLocation::new(fixture("sparse_animation.gltf"));
+    let clip = load::load_animation(&animation, &buffers, &nodes, &location).unwrap();
+    assert_eq!(clip.channels.len(), 2);
+    let Property::Translation(translation) = &clip.channels[0].property else {
+        panic!("the first channel moves the node");
+    };
+    assert_eq!(translation.times, vec![0.0, 1.0]);
+    assert_eq!(
+        translation.values,
+        vec![Vec3::ZERO, Vec3::new(2.0, 3.0, 4.0)]
+    );
+    let Property::Weights(weights) = &clip.channels[1].property else {
+        panic!("the second channel sets the morph weights");
+    };
+    let values: Vec<Vec<f32>> = weights.iter().map(|w| w.values.clone()).collect();
+    assert_eq!(values, vec![vec![0.0, 0.0], vec![0.0, 0.5]]);
+
+    // channels of nodes outside the scene are left out, missing buffers are errors
+    let clip = load::load_animation(&animation, &buffers, &HashMap::new(), &location).unwrap();
+    assert!(clip.channels.is_empty());
+    assert!(matches!(
+        load::load_animation(&animation, &[], &nodes, &location),
+        Err(AssetError::Parse {
+            source: ParseError::Accessor(AccessorError::MissingBuffer { .. }),
+            ..
+        })
+    ));
+}
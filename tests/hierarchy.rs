@@ -0,0 +1,274 @@
+mod common;
+
+use VulcanEngine_0::asset_manager::hot_reload::{self, LoadedFile};
+use VulcanEngine_0::asset_manager::registry::TextureKey;
+use VulcanEngine_0::game_objects::hierarchy::HierarchyError;
+use VulcanEngine_0::game_objects::render_object::{ObjectId, RenderId};
+use VulcanEngine_0::game_objects::scene::{GameObject, Scene};
+use VulcanEngine_0::game_objects::transform::Transform;
+use VulcanEngine_0::vulkan::image_util::TextureData;
+use common::render_object;
+use glam::{Mat4, Quat, Vec3};
+
+const EPSILON: f32 = 1e-4;
+
+fn transform(x: f32) -> Transform {
+    Transform::new(
+        Vec3::new(x, 1.0, 0.0),
+        Vec3::splat(1.0 + x * 0.5),
+        Quat::from_rotation_y(x),
+    )
+}
+
+/// `parent` gets a new child with `mesh`.
+fn add(scene: &mut Scene, name: &str, parent: Option<ObjectId>, mesh: RenderId) -> ObjectId {
+    let mut object = GameObject::new(name, transform(parent.map_or(0.0, |_| 1.0)), vec![mesh]);
+    object.parent = parent;
+    let id = scene.insert_instance(object).unwrap();
+    if let Some(parent) = parent {
+        scene.objects.get_mut(parent).unwrap().children.push(id);
+    }
+    id
+}
+
+/// every link of the scene points back at its object.
+fn assert_consistent(scene: &Scene) {
+    for (id, object) in scene.objects.iter() {
+        if let Some(parent) = object.parent {
+            assert!(scene.objects.get(parent).unwrap().children.contains(&id));
+        }
+        for child in &object.children {
+            assert_eq!(scene.objects.get(*child).unwrap().parent, Some(id));
+        }
+        for render_id in &object.render_objects {
+            let instances = &scene.render_objects.get(*render_id).unwrap().instances;
+            assert_eq!(instances.iter().filter(|i| **i == id).count(), 1);
+        }
+    }
+    for (render_id, render_object) in scene.render_objects.iter() {
+        for instance in &render_object.instances {
+            let object = scene.objects.get(*instance).unwrap();
+            assert!(object.render_objects.contains(&render_id));
+        }
+    }
+}
+
+fn assert_matrix_eq(a: Mat4, b: Mat4) {
+    assert!(a.abs_diff_eq(b, EPSILON), "{a} {b}");
+}
+
+/// root -> (arm -> hand, leg), all sharing one mesh, and a separate tree.
+fn tree() -> (Scene, RenderId, [ObjectId; 5]) {
+    let mut scene = Scene::default();
+    let mesh = scene.render_objects.insert(render_object());
+    let root = add(&mut scene, "root", None, mesh);
+    let arm = add(&mut scene, "arm", Some(root), mesh);
+    let hand = add(&mut scene, "hand", Some(arm), mesh);
+    let leg = add(&mut scene, "leg", Some(root), mesh);
+    let other = add(&mut scene, "other", None, mesh);
+    (scene, mesh, [root, arm, hand, leg, other])
+}
+
+#[test]
+fn subtrees_are_removed_with_their_links() {
+    let (mut scene, mesh, [root, arm, hand, leg, other]) = tree();
+    assert_consistent(&scene);
+    assert_eq!(scene.subtree(root), vec![root, arm, hand, leg]);
+    scene.selected_object = Some(hand);
+    scene.refit_bvh();
+    assert_eq!(scene.bvh.len(), 5);
+
+    let removed = scene.remove_subtree(arm);
+    let names: Vec<&str> = removed.iter().map(|object| object.name.as_str()).collect();
+    assert_eq!(names, vec!["arm", "hand"]);
+    assert_eq!(removed[0].parent, None);
+    assert!(scene.objects.get(arm).is_none() && scene.objects.get(hand).is_none());
+    assert_eq!(scene.objects.get(root).unwrap().children, vec![leg]);
+    assert_eq!(
+        scene.render_objects.get(mesh).unwrap().instances,
+        vec![root, leg, other]
+    );
+    assert_eq!(scene.selected_object, None);
+    assert!(!scene.bvh.contains(arm) && !scene.bvh.contains(hand));
+    assert_eq!(scene.bvh.len(), 3);
+    assert_consistent(&scene);
+
+    assert!(scene.remove_subtree(arm).is_empty());
+    assert_eq!(scene.remove_subtree(root).len(), 2);
+    assert_eq!(scene.objects.len(), 1);
+    assert_eq!(scene.bvh.objects().collect::<Vec<_>>(), vec![other]);
+    assert_eq!(
+        scene.render_objects.get(mesh).unwrap().instances,
+        vec![other]
+    );
+    assert_consistent(&scene);
+}
+
+#[test]
+fn removed_parents_leave_their_children_in_place() {
+    let (mut scene, mesh, [root, arm, hand, leg, _]) = tree();
+    let world = |scene: &Scene, id| scene.objects.get(id).unwrap().global_matrix(scene);
+    let before = world(&scene, hand);
+
+    let removed = scene.remove_instance(arm).unwrap();
+    assert_eq!(removed.parent, None);
+    assert!(removed.children.is_empty());
+    assert_eq!(scene.objects.get(root).unwrap().children, vec![leg]);
+    assert_eq!(scene.objects.get(hand).unwrap().parent, None);
+    assert_matrix_eq(world(&scene, hand), before);
+    assert!(
+        !scene
+            .render_objects
+            .get(mesh)
+            .unwrap()
+            .instances
+            .contains(&arm)
+    );
+    assert_consistent(&scene);
+
+    // a dangling parent no longer panics, the object is treated as a root
+    let mut orphan = GameObject::new("orphan", transform(2.0), vec![]);
+    orphan.parent = Some(arm);
+    let orphan = scene.insert_instance(orphan).unwrap();
+    assert_matrix_eq(world(&scene, orphan), transform(2.0).matrix());
+}
+
+#[test]
+fn reparenting_moves_between_children_lists() {
+    let (mut scene, _, [root, arm, hand, leg, other]) = tree();
+    let world = |scene: &Scene, id| scene.objects.get(id).unwrap().global_matrix(scene);
+
+    // keeping the world transform leaves the hand where it is
+    let before = world(&scene, hand);
+    scene.reparent(hand, other, true).unwrap();
+    assert_eq!(scene.objects.get(hand).unwrap().parent, Some(other));
+    assert!(scene.objects.get(arm).unwrap().children.is_empty());
+    assert_eq!(scene.objects.get(other).unwrap().children, vec![hand]);
+    assert_matrix_eq(world(&scene, hand), before);
+    assert_consistent(&scene);
+
+    // otherwise the local transform is kept and the hand moves with its new parent
+    let local = scene.objects.get(leg).unwrap().transform.clone();
+    scene.reparent(leg, hand, false).unwrap();
+    assert_eq!(scene.objects.get(leg).unwrap().transform, local);
+    assert_matrix_eq(world(&scene, leg), world(&scene, hand) * local.matrix());
+    assert_eq!(scene.objects.get(root).unwrap().children, vec![arm]);
+    assert_consistent(&scene);
+
+    // reparenting to the same parent keeps a single link
+    scene.reparent(leg, hand, true).unwrap();
+    assert_eq!(scene.objects.get(hand).unwrap().children, vec![leg]);
+    assert_consistent(&scene);
+
+    let before = world(&scene, hand);
+    scene.detach(hand, true).unwrap();
+    assert_eq!(scene.objects.get(hand).unwrap().parent, None);
+    assert!(scene.objects.get(other).unwrap().children.is_empty());
+    assert_matrix_eq(world(&scene, hand), before);
+    // detaching a root changes nothing
+    scene.detach(hand, false).unwrap();
+    assert_matrix_eq(world(&scene, hand), before);
+    assert_consistent(&scene);
+}
+
+#[test]
+fn cycles_are_refused() {
+    let (mut scene, _, [root, arm, hand, leg, _]) = tree();
+    assert!(scene.is_descendant(hand, root));
+    assert!(!scene.is_descendant(leg, arm));
+    assert_eq!(
+        scene.reparent(root, hand, true),
+        Err(HierarchyError::Cycle {
+            child: root,
+            parent: hand
+        })
+    );
+    assert_eq!(
+        scene.reparent(arm, arm, false),
+        Err(HierarchyError::Cycle {
+            child: arm,
+            parent: arm
+        })
+    );
+    // nothing changed
+    assert_eq!(scene.objects.get(root).unwrap().parent, None);
+    assert_eq!(scene.objects.get(root).unwrap().children, vec![arm, leg]);
+    assert_consistent(&scene);
+
+    // moving the arm under the leg is fine, the leg is not below the arm
+    scene.reparent(arm, leg, false).unwrap();
+    assert!(scene.is_descendant(hand, leg));
+    assert_eq!(
+        scene.reparent(leg, hand, false),
+        Err(HierarchyError::Cycle {
+            child: leg,
+            parent: hand
+        })
+    );
+    assert_consistent(&scene);
+
+    let removed = scene.remove_subtree(hand).pop().unwrap();
+    assert_eq!(removed.name, "hand");
+    assert_eq!(
+        scene.reparent(leg, hand, false),
+        Err(HierarchyError::Missing(hand))
+    );
+    assert_eq!(scene.detach(hand, true), Err(HierarchyError::Missing(hand)));
+    // hierarchies that already loop do not hang the check
+    scene.objects.get_mut(root).unwrap().parent = Some(leg);
+    assert!(!scene.is_descendant(root, hand));
+}
+
+#[test]
+fn failed_loads_leave_nothing_behind() {
+    let (mut scene, mesh, [root, arm, hand, leg, other]) = tree();
+    // a load that failed after its first node and the mesh of the next one
+    let partial = scene.render_objects.insert(render_object());
+    let mut loaded = LoadedFile::default();
+    for (node, id) in [root, arm, hand, leg].into_iter().enumerate() {
+        loaded.nodes.insert(node, id);
+    }
+    loaded.primitives.insert((4, 0), partial);
+
+    hot_reload::discard(&mut scene, loaded, 2);
+    assert_eq!(scene.objects.ids(), vec![other]);
+    assert_eq!(
+        scene.render_objects.get(mesh).unwrap().instances,
+        vec![other]
+    );
+    assert!(scene.render_objects.get(partial).is_none());
+    // retired until the frames in flight are done with it
+    assert_eq!(scene.assets.garbage.len(), 1);
+    assert_eq!(scene.assets.garbage[0].0, 2);
+    assert_consistent(&scene);
+}
+
+#[test]
+fn render_objects_go_with_their_last_instance() {
+    let (mut scene, mesh, [root, _, _, _, other]) = tree();
+    let key = TextureKey::image("painted.png", 0, false);
+    let texture = scene
+        .assets
+        .textures
+        .get_or_try_insert(key.clone(), || Ok::<_, ()>(TextureData::default()))
+        .unwrap();
+    let mut painted = render_object();
+    painted.pbr.texture_data = texture;
+    let painted = scene.render_objects.insert(painted);
+    let statue = add(&mut scene, "statue", None, painted);
+    drop(scene.remove_subtree(root));
+    assert!(scene.render_objects.get(mesh).is_some());
+    assert!(scene.assets.garbage.is_empty());
+
+    scene.remove_instance(statue);
+    assert!(scene.render_objects.get(painted).is_none());
+    assert_eq!(scene.assets.garbage.len(), 1);
+    // the cache still holds the texture until the retired render object is destroyed
+    assert!(scene.assets.textures.take_unused().is_empty());
+    scene.assets.garbage.clear();
+    assert_eq!(scene.assets.textures.take_unused().len(), 1);
+    assert!(scene.assets.textures.is_empty());
+
+    scene.remove_instance(other);
+    assert!(scene.render_objects.get(mesh).is_none());
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use VulcanEngine_0::asset_manager::hot_reload::{FileWatcher, HotReload, LoadedFile};
+use VulcanEngine_0::game_objects::render_object::{ObjectId, RenderId};
+use common::temp_dir;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+const SETTLE: Duration = Duration::from_millis(300);
+
+/// sets the modification time explicitly, file systems with coarse timestamps would
+/// otherwise hide writes that happen in quick succession.
+fn touch(path: &PathBuf, seconds: u64) {
+    let file = File::options()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap();
+    file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+        .unwrap();
+}
+
+#[test]
+fn changes_are_reported_once_the_file_settles() {
+    let dir = temp_dir("settle");
+    let path = dir.join("model.glb");
+    touch(&path, 1_000);
+    let mut watcher = FileWatcher::new(SETTLE);
+    watcher.watch(&path);
+    let start = Instant::now();
+    assert!(watcher.poll(start).is_empty());
+
+    touch(&path, 2_000);
+    assert!(watcher.poll(start).is_empty());
+    // still being written
+    touch(&path, 3_000);
+    assert!(watcher.poll(start + SETTLE).is_empty());
+    assert!(watcher.poll(start + SETTLE + SETTLE / 2).is_empty());
+    let changed = watcher.poll(start + 2 * SETTLE);
+    assert_eq!(changed, vec![path.canonicalize().unwrap()]);
+    // reported only once
+    assert!(watcher.poll(start + 10 * SETTLE).is_empty());
+}
+
+#[test]
+fn deleted_files_count_as_changed() {
+    let dir = temp_dir("deleted");
+    let path = dir.join("model.obj");
+    touch(&path, 1_000);
+    let mut watcher = FileWatcher::new(SETTLE);
+    watcher.watch(&path);
+    let canonical = path.canonicalize().unwrap();
+    fs::remove_file(&path).unwrap();
+    let start = Instant::now();
+    assert!(watcher.poll(start).is_empty());
+    assert_eq!(watcher.poll(start + SETTLE), vec![canonical]);
+}
+
+#[test]
+fn unwatched_files_are_not_polled() {
+    let dir = temp_dir("unwatch");
+    let path = dir.join("model.glb");
+    touch(&path, 1_000);
+    let mut watcher = FileWatcher::new(SETTLE);
+    watcher.watch(&path);
+    assert!(watcher.is_watched(dir.join("./model.glb")));
+    watcher.unwatch(&path);
+    touch(&path, 2_000);
+    let start = Instant::now();
+    assert!(watcher.poll(start).is_empty());
+    assert!(watcher.poll(start + SETTLE).is_empty());
+}
+
+#[test]
+fn dependencies_reload_the_files_that_use_them() {
+    let dir = temp_dir("dependencies");
+    let gltf = dir.join("scene.gltf");
+    let buffer = dir.join("scene.bin");
+    let texture = dir.join("shared.png");
+    let obj = dir.join("prop.obj");
+    for path in [&gltf, &buffer, &texture, &obj] {
+        touch(path, 1_000);
+    }
+    let mut hot_reload = HotReload::default();
+    hot_reload.enabled = true;
+    hot_reload.watcher.settle = Duration::ZERO;
+    let loaded = LoadedFile::default();
+    hot_reload.track(
+        &gltf,
+        loaded.clone(),
+        [gltf.clone(), buffer.clone(), texture.clone()],
+    );
+    hot_reload.track(&obj, loaded, [obj.clone(), texture.clone()]);
+
+    touch(&buffer, 2_000);
+    let start = Instant::now();
+    assert!(hot_reload.changed_files(start).is_empty());
+    assert_eq!(
+        hot_reload.changed_files(start + hot_reload.interval),
+        vec![gltf.canonicalize().unwrap()]
+    );
+
+    touch(&texture, 2_000);
+    assert!(
+        hot_reload
+            .changed_files(start + 2 * hot_reload.interval)
+            .is_empty()
+    );
+    let mut changed = hot_reload.changed_files(start + 3 * hot_reload.interval);
+    changed.sort();
+    let mut expected = vec![gltf.canonicalize().unwrap(), obj.canonicalize().unwrap()];
+    expected.sort();
+    assert_eq!(changed, expected);
+}
+
+#[test]
+fn polling_is_throttled_and_can_be_disabled() {
+    let dir = temp_dir("throttle");
+    let path = dir.join("model.glb");
+    touch(&path, 1_000);
+    let mut hot_reload = HotReload::default();
+    hot_reload.enabled = true;
+    hot_reload.watcher.settle = Duration::ZERO;
+    hot_reload.track(&path, LoadedFile::default(), [path.clone()]);
+    let start = Instant::now();
+    touch(&path, 2_000);
+    assert!(hot_reload.changed_files(start).is_empty());
+    // too early for the next poll, the settled change is not seen yet
+    assert!(
+        hot_reload
+            .changed_files(start + hot_reload.interval / 2)
+            .is_empty()
+    );
+    hot_reload.enabled = false;
+    assert!(
+        hot_reload
+            .changed_files(start + hot_reload.interval)
+            .is_empty()
+    );
+    hot_reload.enabled = true;
+    assert_eq!(
+        hot_reload.changed_files(start + hot_reload.interval).len(),
+        1
+    );
+}
+
+#[test]
+fn every_load_of_a_file_is_tracked() {
+    let dir = temp_dir("loads");
+    let path = dir.join("model.glb");
+    touch(&path, 1_000);
+    let mut hot_reload = HotReload::default();
+    for i in 0..2 {
+        let mut loaded = LoadedFile::default();
+        loaded.nodes.insert(0, ObjectId::new(i, 0));
+        loaded.primitives.insert((0, 0), RenderId::new(i, 0));
+        hot_reload.track(&path, loaded, [path.clone()]);
+    }
+    assert_eq!(hot_reload.loads(dir.join("./model.glb")).len(), 2);
+    let loads = hot_reload.take_loads(&path);
+    assert_eq!(loads[1].nodes[&0], ObjectId::new(1, 0));
+    assert!(hot_reload.loads(&path).is_empty());
+}
@@ -0,0 +1,90 @@
+use VulcanEngine_0::game_objects::render_object::{
+    MaterialOverride, ObjectId, instance_buffer_size,
+};
+use VulcanEngine_0::game_objects::scene::{GameObject, Scene};
+use VulcanEngine_0::game_objects::transform::Transform;
+use VulcanEngine_0::vulkan::buffer_util::grown_size;
+use VulcanEngine_0::vulkan::uniform_buffer_object::InstanceData;
+use glam::{Mat4, Quat, Vec3, Vec4};
+
+const EPSILON: f32 = 1e-5;
+
+#[test]
+fn buffers_grow_by_doubling() {
+    assert_eq!(grown_size(64, 64), 64);
+    assert_eq!(grown_size(64, 65), 128);
+    assert_eq!(grown_size(64, 1000), 1024);
+    // an empty buffer still grows
+    assert_eq!(grown_size(0, 3), 4);
+
+    let size = instance_buffer_size(1);
+    assert_eq!(size, size_of::<InstanceData>() as u64);
+    assert_eq!(instance_buffer_size(0), size);
+    assert_eq!(instance_buffer_size(5000), size * 5000);
+    let mut grown = size;
+    let mut replaced = 0;
+    for count in 1..=5000 {
+        let needed = instance_buffer_size(count);
+        if needed > grown {
+            grown = grown_size(grown, needed);
+            replaced += 1;
+        }
+        assert!(grown >= needed);
+    }
+    assert!(replaced <= 13, "{replaced}");
+}
+
+#[test]
+fn normal_matrix_keeps_normals_perpendicular() {
+    let model = Mat4::from_scale_rotation_translation(
+        Vec3::new(1.0, 4.0, 0.5),
+        Quat::from_rotation_z(0.7),
+        Vec3::new(3.0, -2.0, 1.0),
+    );
+    let instance = MaterialOverride::default().instance(model);
+    assert_eq!(instance.model, model);
+    assert_eq!(instance.base, Vec4::ONE);
+    assert_eq!(instance.emissive, Vec4::ONE);
+    // a tangent and normal of a slanted plane stay perpendicular after the transform
+    let tangent = Vec3::new(1.0, -1.0, 0.0);
+    let normal = Vec3::new(1.0, 1.0, 0.0);
+    let tangent = model.transform_vector3(tangent);
+    let normal = instance.normal.transform_vector3(normal);
+    assert!(
+        tangent.dot(normal).abs() < EPSILON,
+        "{}",
+        tangent.dot(normal)
+    );
+}
+
+#[test]
+fn scene_instances_are_not_limited() {
+    let mut scene = Scene::default();
+    let ids: Vec<ObjectId> = (0..50)
+        .map(|i| {
+            let mut object = GameObject::new(
+                "cube",
+                Transform::new(Vec3::new(i as f32, 0.0, 0.0), Vec3::ONE, Quat::IDENTITY),
+                vec![],
+            );
+            object.material_override.base = Vec4::new(i as f32 / 50.0, 1.0, 1.0, 1.0);
+            object.material_override.emissive = Vec3::splat(2.0);
+            scene.insert_instance(object).unwrap()
+        })
+        .collect();
+    let mut instances = ids.clone();
+    // a removed instance is drawn at the origin without a tint
+    instances.push(ObjectId::new(999, 0));
+
+    let data = scene.instance_data(&instances);
+    assert_eq!(data.len(), 51);
+    for (i, instance) in data[..50].iter().enumerate() {
+        assert_eq!(instance.model.w_axis.x, i as f32);
+        assert_eq!(instance.base.x, i as f32 / 50.0);
+        assert_eq!(instance.emissive, Vec4::new(2.0, 2.0, 2.0, 1.0));
+    }
+    assert_eq!(
+        data[50],
+        MaterialOverride::default().instance(Mat4::IDENTITY)
+    );
+}
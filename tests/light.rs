@@ -0,0 +1,110 @@
+use VulcanEngine_0::asset_manager::load;
+use VulcanEngine_0::game_objects::light::{Light, LightKind};
+use VulcanEngine_0::game_objects::scene::{GameObject, Scene};
+use VulcanEngine_0::game_objects::transform::Transform;
+use glam::{Mat4, Quat, Vec3, Vec4};
+use std::f32::consts::FRAC_PI_2;
+use std::path::Path;
+
+const EPSILON: f32 = 1e-5;
+
+/// the cone factor of the shader for a direction at `angle` from the spot axis.
+fn cone(light: &Light, angle: f32) -> f32 {
+    let cone = light.uniform(Mat4::IDENTITY).cone;
+    (angle.cos() * cone.x + cone.y).clamp(0.0, 1.0)
+}
+
+#[test]
+fn reads_punctual_lights_of_nodes() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lights.gltf");
+    let (document, _buffers, _images) = gltf::import(path).unwrap();
+    let lights: Vec<Option<Light>> = document
+        .nodes()
+        .map(|node| load::read_light(&node))
+        .collect();
+
+    let point = lights[0].as_ref().unwrap();
+    assert_eq!(point.kind, LightKind::Point);
+    assert_eq!(point.color, Vec3::new(1.0, 0.5, 0.25));
+    assert_eq!(point.intensity, 20.0);
+    assert_eq!(point.range, Some(8.0));
+
+    let spot = lights[1].as_ref().unwrap();
+    assert_eq!(
+        spot.kind,
+        LightKind::Spot {
+            inner_cone: 0.2,
+            outer_cone: 0.6
+        }
+    );
+    assert_eq!(spot.color, Vec3::ONE);
+    assert_eq!(spot.range, None);
+
+    // the sun is the only directional light
+    assert!(lights[2].is_none());
+    assert!(lights[3].is_none());
+}
+
+#[test]
+fn point_lights_shine_from_their_object() {
+    let mut light = Light::point(Vec3::new(1.0, 0.5, 0.0), 4.0);
+    light.range = Some(10.0);
+    let matrix = Mat4::from_scale_rotation_translation(
+        Vec3::splat(3.0),
+        Quat::from_rotation_y(1.0),
+        Vec3::new(1.0, 2.0, 3.0),
+    );
+    let uniform = light.uniform(matrix);
+    assert_eq!(uniform.position, Vec4::new(1.0, 2.0, 3.0, 10.0));
+    assert_eq!(uniform.color, Vec4::new(4.0, 2.0, 0.0, 1.0));
+    // a point light is never faded by the cone
+    assert_eq!(cone(&light, 0.0), 1.0);
+    assert_eq!(cone(&light, 3.0), 1.0);
+    // unlimited lights have no range
+    assert_eq!(Light::point(Vec3::ONE, 1.0).uniform(matrix).position.w, 0.0);
+}
+
+#[test]
+fn spot_lights_fade_between_their_cones() {
+    let light = Light::spot(Vec3::ONE, 1.0, 0.3, 0.7);
+    assert!((cone(&light, 0.0) - 1.0).abs() < EPSILON);
+    assert!((cone(&light, 0.3) - 1.0).abs() < 1e-4);
+    assert!(cone(&light, 0.7).abs() < 1e-4);
+    assert_eq!(cone(&light, 1.5), 0.0);
+    let half = cone(&light, 0.5);
+    assert!(0.0 < half && half < 1.0, "{half}");
+
+    // equal cones are a hard edge instead of a division by zero
+    let hard = Light::spot(Vec3::ONE, 1.0, 0.5, 0.5);
+    assert!(hard.uniform(Mat4::IDENTITY).cone.is_finite());
+    assert_eq!(cone(&hard, 0.4), 1.0);
+    assert_eq!(cone(&hard, 0.6), 0.0);
+
+    // spots shine along -z of their object
+    let turned = light.uniform(Mat4::from_rotation_y(FRAC_PI_2));
+    assert!((turned.direction - Vec4::new(-1.0, 0.0, 0.0, 0.0)).length() < EPSILON);
+}
+
+#[test]
+fn scene_lights_follow_their_parents() {
+    let mut scene = Scene::default();
+    let parent = scene
+        .insert_instance(GameObject::new(
+            "parent",
+            Transform::new(Vec3::new(10.0, 0.0, 0.0), Vec3::ONE, Quat::IDENTITY),
+            vec![],
+        ))
+        .unwrap();
+    let mut lamp = GameObject::new(
+        "lamp",
+        Transform::new(Vec3::new(0.0, 2.0, 0.0), Vec3::ONE, Quat::IDENTITY),
+        vec![],
+    );
+    lamp.parent = Some(parent);
+    lamp.light = Some(Light::point(Vec3::ONE, 1.0));
+    scene.insert_instance(lamp).unwrap();
+
+    let lights = scene.lights();
+    assert_eq!(lights.len(), 1);
+    assert_eq!(lights[0].position.truncate(), Vec3::new(10.0, 2.0, 0.0));
+}
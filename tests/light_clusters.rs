@@ -0,0 +1,175 @@
+mod common;
+
+use VulcanEngine_0::game_objects::camera::Camera;
+use VulcanEngine_0::game_objects::light::Light;
+use VulcanEngine_0::vulkan::CORRECTION;
+use VulcanEngine_0::vulkan::light_clusters::{
+    CLUSTER_COUNT, CLUSTER_DIMENSIONS, ClusterGrid, LightClusters, MAX_CLUSTER_INDICES,
+    assign_lights,
+};
+use VulcanEngine_0::vulkan::uniform_buffer_object::PointLight;
+use common::{camera, numbers};
+use glam::{Mat4, UVec2, Vec3};
+
+fn grid(camera: &Camera) -> ClusterGrid {
+    let proj = CORRECTION
+        * Mat4::perspective_rh(
+            camera.fov.to_radians(),
+            16.0 / 9.0,
+            camera.near_field,
+            camera.far_field,
+        );
+    ClusterGrid::new(camera, proj)
+}
+
+/// a point light at a view space position.
+fn light(camera: &Camera, view: Vec3, range: f32) -> PointLight {
+    let mut light = Light::point(Vec3::ONE, 1.0);
+    light.range = Some(range);
+    let world = camera.transform.matrix().transform_point3(view);
+    light.uniform(Mat4::from_translation(world))
+}
+
+/// whether a world space point is drawn by the camera of `grid`.
+fn visible(grid: &ClusterGrid, point: Vec3) -> bool {
+    let view = grid.view.transform_point3(point);
+    let ndc = grid.proj.project_point3(view);
+    -view.z > grid.near && -view.z < grid.far && ndc.x.abs() < 1.0 && ndc.y.abs() < 1.0
+}
+
+#[test]
+fn slices_grow_by_a_constant_factor() {
+    let grid = grid(&camera());
+    assert_eq!(grid.slice_depth(0), grid.near);
+    assert_eq!(grid.slice_depth(CLUSTER_DIMENSIONS.z), grid.far);
+    let ratio = grid.slice_depth(1) / grid.slice_depth(0);
+    for slice in 1..CLUSTER_DIMENSIONS.z {
+        let next = grid.slice_depth(slice + 1) / grid.slice_depth(slice);
+        assert!((next - ratio).abs() < 1e-3, "{slice}");
+    }
+}
+
+#[test]
+fn points_lie_in_the_bounds_of_their_cluster() {
+    let camera = camera();
+    let grid = grid(&camera);
+    let mut random = numbers(7);
+    let mut tested = 0;
+    while tested < 500 {
+        let view = Vec3::new(random() * 2.0 - 1.0, random() * 2.0 - 1.0, -1.0)
+            * grid.near
+            * (grid.far / grid.near).powf(random());
+        let point = camera.transform.matrix().transform_point3(view);
+        if !visible(&grid, point) {
+            continue;
+        }
+        tested += 1;
+        let (min, max) = grid.bounds(grid.cluster_of(point));
+        let slack = view.length() * 1e-4;
+        assert!(
+            view.cmpge(min - slack).all() && view.cmple(max + slack).all(),
+            "{view} {min} {max}"
+        );
+    }
+}
+
+#[test]
+fn lights_are_in_every_cluster_they_reach() {
+    let camera = camera();
+    let grid = grid(&camera);
+    let mut random = numbers(11);
+    let lights: Vec<PointLight> = (0..40)
+        .map(|_| {
+            let view = Vec3::new(
+                random() * 40.0 - 20.0,
+                random() * 24.0 - 12.0,
+                -random() * 60.0,
+            );
+            light(&camera, view, 0.5 + random() * 6.0)
+        })
+        .collect();
+    let clusters = assign_lights(&grid, &lights);
+    let mut tested = 0;
+    for (index, light) in lights.iter().enumerate() {
+        let range = light.position.w;
+        for _ in 0..200 {
+            let offset = Vec3::new(random(), random(), random()) * 2.0 - 1.0;
+            let point = light.position.truncate() + offset.clamp_length_max(1.0) * range * 0.99;
+            if !visible(&grid, point) {
+                continue;
+            }
+            tested += 1;
+            let cluster = ClusterGrid::index(grid.cluster_of(point));
+            assert!(
+                clusters.lights(cluster).contains(&(index as u32)),
+                "light {index} misses {point}"
+            );
+        }
+    }
+    assert!(tested > 1000, "{tested}");
+}
+
+#[test]
+fn lights_out_of_view_are_culled() {
+    let camera = camera();
+    let grid = grid(&camera);
+    let lights = [
+        // behind the camera
+        light(&camera, Vec3::new(0.0, 0.0, 5.0), 2.0),
+        // past the far plane
+        light(&camera, Vec3::new(0.0, 0.0, -300.0), 20.0),
+        // far off to the side
+        light(&camera, Vec3::new(500.0, 0.0, -10.0), 5.0),
+        // in front
+        light(&camera, Vec3::new(0.0, 0.0, -10.0), 1.0),
+    ];
+    let clusters = assign_lights(&grid, &lights);
+    assert!(clusters.indices.iter().all(|index| *index == 3));
+    let lit = (0..CLUSTER_COUNT)
+        .filter(|cluster| !clusters.lights(*cluster).is_empty())
+        .count();
+    // a small light only reaches a few clusters
+    assert!(lit > 0 && lit < 40, "{lit}");
+}
+
+#[test]
+fn unlimited_lights_are_everywhere() {
+    let camera = camera();
+    let grid = grid(&camera);
+    let mut lights = vec![light(&camera, Vec3::new(0.0, 0.0, 5.0), 0.0)];
+    lights.push(light(&camera, Vec3::new(0.0, 0.0, -10.0), 1.0));
+    let clusters = assign_lights(&grid, &lights);
+    for cluster in 0..CLUSTER_COUNT {
+        assert_eq!(clusters.lights(cluster).first(), Some(&0));
+    }
+}
+
+#[test]
+fn ranges_are_contiguous() {
+    let camera = camera();
+    let grid = grid(&camera);
+    let check = |clusters: &LightClusters| {
+        assert_eq!(clusters.ranges.len(), CLUSTER_COUNT);
+        let mut offset = 0;
+        for range in &clusters.ranges {
+            assert_eq!(range.x, offset);
+            offset += range.y;
+        }
+        assert_eq!(offset as usize, clusters.indices.len());
+    };
+    check(&assign_lights(&grid, &[]));
+    assert!(
+        assign_lights(&grid, &[])
+            .ranges
+            .iter()
+            .all(|range| *range == UVec2::ZERO)
+    );
+
+    // more unlimited lights than the index budget holds
+    let lights = vec![light(&camera, Vec3::ZERO, 0.0); 40];
+    let clusters = assign_lights(&grid, &lights);
+    check(&clusters);
+    assert_eq!(clusters.indices.len(), MAX_CLUSTER_INDICES);
+    assert_eq!(clusters.lights(0).len(), 40);
+    assert!(clusters.lights(CLUSTER_COUNT - 1).is_empty());
+}
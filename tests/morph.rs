@@ -0,0 +1,101 @@
+use VulcanEngine_0::game_objects::animation::{
+    AnimationClip, AnimationPlayer, Channel, Interpolation, Property, Sampler,
+};
+use VulcanEngine_0::game_objects::morph::MorphTargets;
+use VulcanEngine_0::game_objects::scene::{GameObject, Scene};
+use VulcanEngine_0::game_objects::transform::Transform;
+use VulcanEngine_0::vulkan::vertexbuffer_util::VertexPbr;
+use glam::{Quat, Vec2, Vec3, Vec4, vec3};
+
+const EPSILON: f32 = 1e-5;
+
+fn triangle() -> Vec<VertexPbr> {
+    vec![
+        VertexPbr::new(Vec3::ZERO, Vec3::Z, Vec2::ZERO),
+        VertexPbr::new(Vec3::X, Vec3::Z, Vec2::X),
+        VertexPbr::new(Vec3::Y, Vec3::Z, Vec2::Y),
+    ]
+}
+
+fn targets() -> MorphTargets {
+    MorphTargets {
+        vertex_count: 3,
+        position_deltas: vec![
+            vec![Vec3::ZERO, Vec3::X, Vec3::ZERO],
+            vec![Vec3::Z, Vec3::Z, Vec3::Z],
+        ],
+        normal_deltas: vec![vec![Vec3::ZERO; 3], vec![Vec3::X, Vec3::X, Vec3::X]],
+    }
+}
+
+#[test]
+fn zero_weights_keep_the_base_mesh() {
+    let blended = targets().blend(&triangle(), &[0.0, 0.0]);
+    for (a, b) in blended.iter().zip(triangle()) {
+        assert!(a.pos.abs_diff_eq(b.pos, EPSILON));
+        assert!(a.normal.abs_diff_eq(b.normal, EPSILON));
+        assert_eq!(a.tex_coord, b.tex_coord);
+    }
+}
+
+#[test]
+fn weights_blend_deltas_linearly() {
+    let blended = targets().blend(&triangle(), &[0.5, 2.0]);
+    assert!(blended[0].pos.abs_diff_eq(vec3(0.0, 0.0, 2.0), EPSILON));
+    assert!(blended[1].pos.abs_diff_eq(vec3(1.5, 0.0, 2.0), EPSILON));
+    assert!(blended[2].pos.abs_diff_eq(vec3(0.0, 1.0, 2.0), EPSILON));
+    // normals are renormalized after blending
+    let normal = vec3(2.0, 0.0, 1.0).normalize();
+    assert!(blended[0].normal.abs_diff_eq(normal, EPSILON));
+}
+
+#[test]
+fn missing_weights_count_as_zero() {
+    let blended = targets().blend(&triangle(), &[1.0]);
+    assert!(blended[1].pos.abs_diff_eq(vec3(2.0, 0.0, 0.0), EPSILON));
+    assert!(blended[1].normal.abs_diff_eq(Vec3::Z, EPSILON));
+}
+
+#[test]
+fn gpu_deltas_interleave_positions_and_normals() {
+    let deltas = targets().gpu_deltas();
+    assert_eq!(deltas.len(), 2 * 3 * 2);
+    assert_eq!(deltas[2], Vec4::new(1.0, 0.0, 0.0, 0.0));
+    assert_eq!(deltas[6], Vec4::new(0.0, 0.0, 1.0, 0.0));
+    assert_eq!(deltas[7], Vec4::new(1.0, 0.0, 0.0, 0.0));
+}
+
+#[test]
+fn weight_channels_drive_instance_weights() {
+    let mut scene = Scene::default();
+    let transform = Transform::new(Vec3::ZERO, Vec3::ONE, Quat::IDENTITY);
+    let mut face = GameObject::new("face", transform, vec![]);
+    face.morph_weights = vec![0.0, 0.0];
+    let face = scene.insert_instance(face).unwrap();
+    let samplers = vec![
+        Sampler::new(Interpolation::Linear, vec![0.0, 1.0], vec![0.0, 1.0]),
+        Sampler::new(Interpolation::Step, vec![0.0, 1.0], vec![1.0, 0.0]),
+    ];
+    let clip = AnimationClip::new(
+        "smile",
+        vec![Channel {
+            target: face,
+            property: Property::Weights(samplers),
+        }],
+    );
+    let mut player = AnimationPlayer::new(vec![clip]);
+    player.set_looping(false);
+    assert!(player.play(0));
+    scene.objects.get_mut(face).unwrap().animation = Some(player);
+
+    scene.update_animations(0.25);
+    let weights = &scene.morph_weights(&[face])[0];
+    assert!((weights[0] - 0.25).abs() < EPSILON);
+    assert_eq!(weights[1], 1.0);
+
+    scene.update_animations(1.0);
+    assert_eq!(scene.morph_weights(&[face])[0], vec![1.0, 0.0]);
+
+    let blended = targets().blend(&triangle(), &scene.morph_weights(&[face])[0]);
+    assert!(blended[1].pos.abs_diff_eq(vec3(2.0, 0.0, 0.0), EPSILON));
+}
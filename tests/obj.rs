@@ -0,0 +1,117 @@
+mod common;
+
+use VulcanEngine_0::asset_manager::load_obj::{self, ObjMaterial};
+use VulcanEngine_0::vulkan::vertexbuffer_util::VertexPbr;
+use common::fixture;
+use glam::{Vec2, Vec3, Vec4};
+
+const EPSILON: f32 = 1e-5;
+
+#[test]
+fn reads_quad_and_generates_normals() {
+    let meshes = load_obj::read(fixture("quad.obj")).unwrap();
+    assert_eq!(meshes.len(), 1);
+    let quad = &meshes[0];
+    assert_eq!(quad.name, "quad");
+    assert_eq!(quad.vertices.len(), 4);
+    assert_eq!(quad.indices.len(), 6);
+    for vertex in &quad.vertices {
+        assert!((vertex.normal - Vec3::Y).length() < EPSILON);
+    }
+}
+
+#[test]
+fn flips_texture_coordinates() {
+    let quad = &load_obj::read(fixture("quad.obj")).unwrap()[0];
+    let origin = quad
+        .vertices
+        .iter()
+        .find(|vertex| vertex.pos == Vec3::ZERO)
+        .unwrap();
+    assert_eq!(origin.tex_coord, Vec2::new(0.0, 1.0));
+}
+
+#[test]
+fn maps_mtl_onto_pbr() {
+    let quad = &load_obj::read(fixture("quad.obj")).unwrap()[0];
+    let material = &quad.material;
+    assert_eq!(material.base, Vec4::new(0.5, 0.25, 1.0, 0.5));
+    assert_eq!(material.metallic, 0.75);
+    // a phong exponent of zero is a completely rough surface
+    assert_eq!(material.roughness, 1.0);
+    assert_eq!(material.emissive, Vec3::new(1.0, 2.0, 3.0));
+    assert_eq!(material.diffuse_texture, Some(fixture("quad.png")));
+    assert_eq!(material.normal_texture, None);
+}
+
+#[test]
+fn bare_mesh_gets_normals_and_default_material() {
+    let mesh = tobj::Mesh {
+        positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+        indices: vec![0, 1, 2],
+        ..Default::default()
+    };
+    let (vertices, indices) = load_obj::mesh_vertices(&mesh).unwrap();
+    assert_eq!(indices, vec![0, 1, 2]);
+    assert!(
+        vertices
+            .iter()
+            .all(|v| (v.normal - Vec3::Z).length() < EPSILON)
+    );
+    assert_eq!(ObjMaterial::default().base, Vec4::ONE);
+}
+
+#[test]
+fn rejects_out_of_range_index() {
+    let mesh = tobj::Mesh {
+        positions: vec![0.0; 6],
+        indices: vec![0, 1, 2],
+        ..Default::default()
+    };
+    assert!(load_obj::mesh_vertices(&mesh).is_err());
+}
+
+#[test]
+fn generated_normals_are_area_weighted() {
+    // a large face pointing up and a small one pointing along x share vertex 0
+    let mut vertices = vec![
+        VertexPbr::new(Vec3::ZERO, Vec3::ZERO, Vec2::ZERO),
+        VertexPbr::new(Vec3::new(0.0, 0.0, 10.0), Vec3::ZERO, Vec2::ZERO),
+        VertexPbr::new(Vec3::new(10.0, 0.0, 0.0), Vec3::ZERO, Vec2::ZERO),
+        VertexPbr::new(Vec3::new(0.0, 1.0, 0.0), Vec3::ZERO, Vec2::ZERO),
+        VertexPbr::new(Vec3::new(0.0, 0.0, 1.0), Vec3::ZERO, Vec2::ZERO),
+    ];
+    load_obj::generate_normals(&mut vertices, &[0, 1, 2, 0, 3, 4]);
+    let shared = vertices[0].normal;
+    assert!((shared.length() - 1.0).abs() < EPSILON);
+    assert!(shared.y > 0.99);
+    assert!((vertices[3].normal - Vec3::X).length() < EPSILON);
+}
+
+#[test]
+fn decodes_the_diffuse_texture() {
+    let source = load_obj::read_source(fixture("quad.obj")).unwrap();
+    let (pixels, size) = &source.textures[&fixture("quad.png")];
+    assert_eq!(*size, (2, 2));
+    // rgb is widened to rgba, red green on top and blue white below
+    assert_eq!(&pixels[..8], &[255, 0, 0, 255, 0, 255, 0, 255]);
+    assert_eq!(&pixels[8..], &[0, 0, 255, 255, 255, 255, 255, 255]);
+    let material = &source.meshes[0].material;
+    assert_eq!(material.diffuse_texture, Some(fixture("quad.png")));
+}
+
+#[test]
+fn unreadable_textures_fall_back_to_the_default() {
+    let source = load_obj::read_source(fixture("unreadable_textures.obj")).unwrap();
+    assert_eq!(source.meshes.len(), 2);
+    // neither the missing file nor the mtl posing as a texture is kept
+    for mesh in &source.meshes {
+        assert_eq!(mesh.material.diffuse_texture, None, "{}", mesh.name);
+    }
+    // readable textures of the same material are still used
+    assert_eq!(
+        source.meshes[1].material.normal_texture,
+        Some(fixture("quad.png"))
+    );
+    assert_eq!(source.textures.len(), 1);
+}
@@ -0,0 +1,179 @@
+mod common;
+
+use VulcanEngine_0::game_objects::bounds::Ray;
+use VulcanEngine_0::game_objects::camera::Camera;
+use VulcanEngine_0::game_objects::picking::{cursor_ndc, ray_mesh, ray_triangle};
+use VulcanEngine_0::vulkan::CORRECTION;
+use VulcanEngine_0::vulkan::vertexbuffer_util::VertexPbr;
+use common::numbers;
+use glam::{Mat4, Quat, Vec2, Vec3};
+
+const EPSILON: f32 = 1e-4;
+
+const TRIANGLE: [Vec3; 3] = [
+    Vec3::new(0.0, 0.0, 0.0),
+    Vec3::new(2.0, 0.0, 0.0),
+    Vec3::new(0.0, 2.0, 0.0),
+];
+
+fn ray(origin: Vec3, direction: Vec3) -> Ray {
+    Ray { origin, direction }
+}
+
+/// a unit cube around the origin.
+fn cube() -> (Vec<VertexPbr>, Vec<u32>) {
+    let vertices = (0..8)
+        .map(|i| VertexPbr {
+            pos: Vec3::new(
+                if i & 1 == 0 { -0.5 } else { 0.5 },
+                if i & 2 == 0 { -0.5 } else { 0.5 },
+                if i & 4 == 0 { -0.5 } else { 0.5 },
+            ),
+            normal: Vec3::ZERO,
+            tex_coord: Vec2::ZERO,
+        })
+        .collect();
+    let indices = vec![
+        0, 1, 3, 0, 3, 2, // -z
+        4, 7, 5, 4, 6, 7, // +z
+        0, 4, 5, 0, 5, 1, // -y
+        2, 3, 7, 2, 7, 6, // +y
+        0, 2, 6, 0, 6, 4, // -x
+        1, 5, 7, 1, 7, 3, // +x
+    ];
+    (vertices, indices)
+}
+
+#[test]
+fn rays_hit_triangles_from_both_sides() {
+    let down = ray(Vec3::new(0.5, 0.5, 3.0), Vec3::NEG_Z);
+    assert!((ray_triangle(&down, TRIANGLE).unwrap() - 3.0).abs() < EPSILON);
+    let up = ray(Vec3::new(0.5, 0.5, -1.0), Vec3::Z * 2.0);
+    // distances are in multiples of the direction
+    assert!((ray_triangle(&up, TRIANGLE).unwrap() - 0.5).abs() < EPSILON);
+
+    // outside of the triangle, past its hypotenuse
+    assert_eq!(
+        ray_triangle(&ray(Vec3::new(1.5, 1.5, 3.0), Vec3::NEG_Z), TRIANGLE),
+        None
+    );
+    // pointing away
+    assert_eq!(
+        ray_triangle(&ray(Vec3::new(0.5, 0.5, 3.0), Vec3::Z), TRIANGLE),
+        None
+    );
+    // parallel to the plane of the triangle
+    assert_eq!(
+        ray_triangle(&ray(Vec3::new(-1.0, 0.5, 0.0), Vec3::X), TRIANGLE),
+        None
+    );
+    // degenerate triangles are never hit
+    let line = [Vec3::ZERO, Vec3::X, Vec3::X * 2.0];
+    assert_eq!(ray_triangle(&down, line), None);
+}
+
+#[test]
+fn hits_lie_on_the_triangle() {
+    let mut random = numbers(3);
+    let mut hits = 0;
+    for _ in 0..500 {
+        let origin = Vec3::new(random(), random(), random()) * 6.0 - 3.0;
+        let target = Vec3::new(random() * 2.5, random() * 2.5, 0.0);
+        let direction = target - origin;
+        let inside = target.x + target.y <= 2.0;
+        let Some(distance) = ray_triangle(&ray(origin, direction), TRIANGLE) else {
+            // misses are either outside of the triangle or close to its edge
+            assert!(!inside || target.x.min(target.y).min(2.0 - target.x - target.y) < 1e-3);
+            continue;
+        };
+        hits += 1;
+        assert!(inside, "{target}");
+        let point = origin + direction * distance;
+        assert!((point - target).length() < 1e-3, "{point} {target}");
+    }
+    assert!(hits > 100, "{hits}");
+}
+
+#[test]
+fn meshes_are_hit_where_they_are_placed() {
+    let (vertices, indices) = cube();
+    let matrix = Mat4::from_scale_rotation_translation(
+        Vec3::new(2.0, 1.0, 4.0),
+        Quat::from_rotation_y(0.3),
+        Vec3::new(0.0, 0.0, -10.0),
+    );
+    let forward = ray(Vec3::ZERO, Vec3::NEG_Z);
+    let distance = ray_mesh(&forward, matrix, &vertices, &indices).unwrap();
+    // the front face of the cube, turned a little around y
+    let front = matrix.transform_point3(Vec3::new(0.0, 0.0, 0.5));
+    assert!(distance > 7.0 && distance < 8.5, "{distance}");
+    assert!(distance < 10.0 - (front.z + 10.0).abs() + 0.5);
+    // the closest of the front and back face
+    let inside = ray(Vec3::new(0.0, 0.0, -10.0), Vec3::Y);
+    assert!((ray_mesh(&inside, matrix, &vertices, &indices).unwrap() - 0.5).abs() < EPSILON);
+    let beside = ray(Vec3::new(5.0, 0.0, 0.0), Vec3::NEG_Z);
+    assert_eq!(ray_mesh(&beside, matrix, &vertices, &indices), None);
+    assert_eq!(
+        ray_mesh(&forward, Mat4::from_scale(Vec3::ZERO), &vertices, &indices),
+        None
+    );
+}
+
+#[test]
+fn cursors_map_into_the_viewport() {
+    let min = Vec2::new(200.0, 50.0);
+    let size = Vec2::new(800.0, 600.0);
+    assert_eq!(cursor_ndc(min, min, size), Some(Vec2::NEG_ONE));
+    assert_eq!(cursor_ndc(min + size * 0.5, min, size), Some(Vec2::ZERO));
+    assert_eq!(
+        cursor_ndc(min + size * 0.75, min, size),
+        Some(Vec2::splat(0.5))
+    );
+    assert_eq!(cursor_ndc(Vec2::new(100.0, 300.0), min, size), None);
+    assert_eq!(cursor_ndc(min + size, min, size), None);
+    assert_eq!(cursor_ndc(min, min, Vec2::ZERO), None);
+}
+
+#[test]
+fn rays_pass_through_the_projected_point() {
+    let mut camera = Camera::default();
+    camera.transform.position = Vec3::new(3.0, -1.0, 2.0);
+    camera.transform.rotation = Quat::from_euler(glam::EulerRot::XYZ, 0.4, -1.2, 0.1);
+    let proj = CORRECTION
+        * Mat4::perspective_rh(
+            camera.fov.to_radians(),
+            16.0 / 9.0,
+            camera.near_field,
+            camera.far_field,
+        );
+    let view_proj = proj * camera.transform.matrix().inverse();
+    let forward = camera.transform.rotation * Vec3::NEG_Z;
+
+    // the center of the screen looks straight ahead
+    let center = camera.ray_through(proj, Vec2::ZERO);
+    assert!((center.direction - forward).length() < EPSILON);
+    assert!(
+        (center.origin - (camera.transform.position + forward * camera.near_field)).length()
+            < EPSILON
+    );
+
+    let mut random = numbers(5);
+    for _ in 0..200 {
+        let view =
+            Vec3::new(random() * 2.0 - 1.0, random() * 2.0 - 1.0, -1.0) * (1.0 + random() * 50.0);
+        let point = camera.transform.matrix().transform_point3(view);
+        let ndc = view_proj.project_point3(point);
+        let ray = camera.ray_through(proj, ndc.truncate());
+        assert!((ray.direction.length() - 1.0).abs() < EPSILON);
+        // the ray starts on the near plane and passes through the point
+        let near = (ray.origin - camera.transform.position).dot(forward);
+        assert!((near - camera.near_field).abs() < EPSILON, "{near}");
+        let along = (point - ray.origin).dot(ray.direction);
+        let closest = ray.origin + ray.direction * along;
+        assert!(along > 0.0);
+        assert!(
+            (closest - point).length() < 1e-3 * along,
+            "{closest} {point}"
+        );
+    }
+}
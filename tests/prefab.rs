@@ -0,0 +1,303 @@
+mod common;
+
+use VulcanEngine_0::asset_manager::bevy_load::AssetBinding;
+use VulcanEngine_0::asset_manager::hot_reload::LoadedFile;
+use VulcanEngine_0::asset_manager::registry;
+use VulcanEngine_0::bevy_app::scene::{NodeRef, Scene};
+use VulcanEngine_0::game_objects::animation::{
+    AnimationClip, AnimationPlayer, Channel, Interpolation, Property, Sampler, Skin,
+};
+use VulcanEngine_0::game_objects::prefab::Prefab;
+use VulcanEngine_0::game_objects::render_object::{MaterialOverride, ObjectId, RenderId};
+use VulcanEngine_0::game_objects::scene::{self, GameObject};
+use VulcanEngine_0::game_objects::transform::Transform;
+use common::{render_object, temp_dir};
+use glam::{Mat4, Quat, Vec3, Vec4};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+fn at(position: Vec3) -> Transform {
+    Transform::new(position, Vec3::ONE, Quat::IDENTITY)
+}
+
+/// a robot loaded from `dir/robot.glb`: a body with an arm, the body is skinned to the arm and
+/// waves it. a stray object is inserted before it so its ids do not start at 0. nothing is on
+/// the gpu.
+fn robot_scene(dir: &Path) -> (scene::Scene, ObjectId, ObjectId) {
+    let model = dir.join("robot.glb");
+    fs::write(&model, "").unwrap();
+    let mut scene = scene::Scene::default();
+    scene
+        .objects
+        .insert(GameObject::new("stray", Transform::default(), vec![]));
+    let body = scene.objects.insert(GameObject::new(
+        "body",
+        at(Vec3::new(1.0, 0.0, 0.0)),
+        vec![RenderId::new(0, 0), RenderId::new(1, 0)],
+    ));
+    let mut arm = GameObject::new("arm", at(Vec3::Y), vec![RenderId::new(2, 0)]);
+    arm.parent = Some(body);
+    let arm = scene.objects.insert(arm);
+    let wave = Channel {
+        target: arm,
+        property: Property::Rotation(Sampler::new(
+            Interpolation::Linear,
+            vec![0.0, 1.0],
+            vec![Quat::IDENTITY, Quat::from_rotation_z(1.0)],
+        )),
+    };
+    let object = scene.objects.get_mut(body).unwrap();
+    object.children.push(arm);
+    object.skin = Some(Skin {
+        joints: vec![arm],
+        inverse_bind_matrices: vec![Mat4::IDENTITY],
+    });
+    object.animation = Some(AnimationPlayer::new(vec![AnimationClip::new(
+        "wave",
+        vec![wave],
+    )]));
+    scene.hot_reload.track(
+        &model,
+        LoadedFile {
+            nodes: HashMap::from([(0, body), (1, arm)]),
+            primitives: HashMap::from([
+                ((0, 0), RenderId::new(0, 0)),
+                ((0, 1), RenderId::new(1, 0)),
+                ((1, 0), RenderId::new(2, 0)),
+            ]),
+        },
+        [],
+    );
+    (scene, body, arm)
+}
+
+#[test]
+fn copies_share_render_objects() {
+    let dir = temp_dir("share");
+    let (mut scene, body, arm) = robot_scene(&dir);
+    let prefab = Prefab::capture(&scene, "robot", &[body]).unwrap();
+    assert_eq!(prefab.name, "robot");
+    assert_eq!(prefab.len(), 2);
+    // parents come before their children
+    assert_eq!(prefab.objects[0].0, body);
+    assert_eq!(prefab.objects[1].0, arm);
+    assert_eq!(prefab.loads.len(), 1);
+    assert!(Prefab::capture(&scene, "missing", &[ObjectId::new(99, 0)]).is_none());
+
+    let copies: Vec<ObjectId> = (0..3)
+        .map(|i| {
+            let roots = scene.instantiate(&prefab, at(Vec3::Z * i as f32));
+            assert_eq!(roots.len(), 1);
+            roots[0]
+        })
+        .collect();
+    assert_eq!(scene.objects.iter().count(), 3 + 2 * 3);
+    for copy in &copies {
+        let object = scene.objects.get(*copy).unwrap();
+        assert_eq!(object.name, "body");
+        assert_eq!(
+            object.render_objects,
+            vec![RenderId::new(0, 0), RenderId::new(1, 0)]
+        );
+        let child = scene.objects.get(object.children[0]).unwrap();
+        assert_eq!(child.render_objects, vec![RenderId::new(2, 0)]);
+    }
+    // the original is left alone
+    assert_eq!(scene.objects.get(body).unwrap().children, vec![arm]);
+    // every copy is tracked as a load of the file
+    let model = dir.join("robot.glb");
+    let loads = scene.hot_reload.loads(&model);
+    assert_eq!(loads.len(), 4);
+    for (index, copy) in copies.iter().enumerate() {
+        assert_eq!(loads[index + 1].nodes[&0], *copy);
+        assert_eq!(loads[index + 1].primitives, loads[0].primitives);
+        assert_eq!(
+            scene.hot_reload.node_source(*copy),
+            Some((model.as_path(), index + 1, 0))
+        );
+    }
+}
+
+#[test]
+fn copies_refer_to_their_own_objects() {
+    let dir = temp_dir("remap");
+    let (mut scene, body, arm) = robot_scene(&dir);
+    let prefab = Prefab::capture(&scene, "robot", &[body]).unwrap();
+    let root = scene.instantiate(&prefab, Transform::default())[0];
+    let copy = scene.objects.get(root).unwrap();
+    assert_eq!(copy.parent, None);
+    let copied_arm = copy.children[0];
+    assert_ne!(copied_arm, arm);
+    assert_eq!(copy.skin.as_ref().unwrap().joints, vec![copied_arm]);
+    let clip = &copy.animation.as_ref().unwrap().clips[0];
+    assert_eq!(clip.channels[0].target, copied_arm);
+    assert_eq!(scene.objects.get(copied_arm).unwrap().parent, Some(root));
+
+    // capturing a child detaches it from its parent, references outside of the prefab are kept
+    let prefab = Prefab::capture(&scene, "arm", &[arm]).unwrap();
+    assert_eq!(prefab.len(), 1);
+    assert_eq!(prefab.objects[0].1.parent, None);
+    assert_eq!(prefab.loads[0].1.nodes, HashMap::from([(1, arm)]));
+    let arms = scene.instantiate(&prefab, Transform::default());
+    assert_eq!(scene.objects.get(arms[0]).unwrap().parent, None);
+    let prefab = Prefab::capture(&scene, "two", &[body, root]).unwrap();
+    assert_eq!(prefab.roots, vec![body, root]);
+    assert_eq!(prefab.len(), 4);
+}
+
+#[test]
+fn roots_are_placed_by_the_transform() {
+    let dir = temp_dir("place");
+    let (mut scene, body, arm) = robot_scene(&dir);
+    let prefab = Prefab::capture(&scene, "robot", &[body]).unwrap();
+    let place = Transform::new(
+        Vec3::new(0.0, 0.0, 5.0),
+        Vec3::splat(2.0),
+        Quat::from_rotation_y(0.5),
+    );
+    let root = scene.instantiate(&prefab, place.clone())[0];
+    let copy = scene.objects.get(root).unwrap();
+    assert_eq!(copy.transform.position, Vec3::new(1.0, 0.0, 5.0));
+    assert_eq!(copy.transform.scale, Vec3::splat(2.0));
+    assert_eq!(copy.transform.rotation, place.rotation);
+    // children move with their root
+    let copied_arm = copy.children[0];
+    assert_eq!(
+        scene.objects.get(copied_arm).unwrap().transform,
+        scene.objects.get(arm).unwrap().transform
+    );
+}
+
+#[test]
+fn tints_survive_saving() {
+    let dir = temp_dir("tint");
+    let (mut scene, body, _) = robot_scene(&dir);
+    let prefab = Prefab::capture(&scene, "robot", &[body]).unwrap();
+    let first = scene.instantiate(&prefab, at(Vec3::X * 4.0))[0];
+    let second = scene.instantiate(&prefab, at(Vec3::X * 8.0))[0];
+    let red = MaterialOverride {
+        base: Vec4::new(1.0, 0.0, 0.0, 1.0),
+        ..Default::default()
+    };
+    scene.set_material_override(second, red);
+    let second_arm = scene.objects.get(second).unwrap().children[0];
+    assert_eq!(
+        scene.objects.get(second_arm).unwrap().material_override,
+        red
+    );
+    assert_eq!(
+        scene.objects.get(first).unwrap().material_override,
+        MaterialOverride::default()
+    );
+
+    let saved = Scene::capture(&scene);
+    let saved = Scene::from_ron(&saved.to_ron().unwrap()).unwrap();
+    let model = dir.join("robot.glb");
+    let copy = saved
+        .objects
+        .iter()
+        .position(|object| object.transform.position == Vec3::new(9.0, 0.0, 0.0))
+        .unwrap();
+    assert_eq!(saved.objects[copy].material_override, red);
+    assert_eq!(
+        saved.objects[copy].node,
+        Some(NodeRef {
+            path: model.clone(),
+            node: 0,
+            copy: 2
+        })
+    );
+
+    let mut restored = scene::Scene::default();
+    let ids = saved.restore(&mut restored).unwrap();
+    let object = restored.objects.get(ids[copy]).unwrap();
+    assert_eq!(object.material_override, red);
+    assert_eq!(object.transform.position, Vec3::new(9.0, 0.0, 0.0));
+    assert_eq!(
+        restored
+            .objects
+            .get(object.children[0])
+            .unwrap()
+            .material_override,
+        red
+    );
+    // the copies come back as loads of their own once the file is read
+    let bindings = saved.bindings(&ids);
+    assert_eq!(bindings.len(), 1);
+    assert!(bindings[0].1.contains(&AssetBinding {
+        object: ids[copy],
+        node: 0,
+        primitive: None,
+        copy: 2
+    }));
+    assert!(bindings[0].1.contains(&AssetBinding {
+        object: ids[copy],
+        node: 0,
+        primitive: Some(1),
+        copy: 0
+    }));
+}
+
+#[test]
+fn prefabs_keep_their_render_objects() {
+    let mut scene = scene::Scene::default();
+    let mesh = scene.render_objects.insert(render_object());
+    let original = scene
+        .insert_instance(GameObject::new("crate", Transform::default(), vec![mesh]))
+        .unwrap();
+    let prefab = Prefab::capture(&scene, "crate", &[original]).unwrap();
+    assert_eq!(prefab.pins.len(), 1);
+
+    // the last instance is gone but the prefab still holds the mesh
+    scene.remove_instance(original).unwrap();
+    scene.retire_released();
+    assert!(scene.render_objects.get(mesh).is_some());
+
+    // copies are found by the bvh before the next refit
+    let copy = scene.instantiate(&prefab, at(Vec3::X * 3.0))[0];
+    assert_eq!(
+        scene.render_objects.get(mesh).unwrap().instances,
+        vec![copy]
+    );
+    assert!(scene.bvh.contains(copy));
+    assert_eq!(scene.bvh.bounds(copy), scene.world_bounds(copy));
+
+    scene.remove_instance(copy).unwrap();
+    assert!(scene.render_objects.get(mesh).is_some());
+    drop(prefab);
+    scene.retire_released();
+    assert!(scene.render_objects.get(mesh).is_none());
+}
+
+#[test]
+fn copies_reload_with_the_files_they_read() {
+    let dir = temp_dir("dependencies");
+    let model = dir.join("robot.gltf");
+    let buffer = dir.join("robot.bin");
+    fs::write(&model, "").unwrap();
+    fs::write(&buffer, "").unwrap();
+    let mut scene = scene::Scene::default();
+    let body = scene
+        .objects
+        .insert(GameObject::new("body", Transform::default(), vec![]));
+    scene.hot_reload.track(
+        &model,
+        LoadedFile {
+            nodes: HashMap::from([(0, body)]),
+            primitives: HashMap::new(),
+        },
+        [model.clone(), buffer.clone()],
+    );
+    let prefab = Prefab::capture(&scene, "robot", &[body]).unwrap();
+
+    // a scene that never loaded the file learns about it from the prefab
+    let mut other = scene::Scene::default();
+    other.instantiate(&prefab, Transform::default());
+    let mut dependencies = other.hot_reload.dependencies(&model);
+    dependencies.sort();
+    let mut expected = vec![registry::source(&model), registry::source(&buffer)];
+    expected.sort();
+    assert_eq!(dependencies, expected);
+    assert_eq!(other.hot_reload.loads(&model).len(), 1);
+}
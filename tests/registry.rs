@@ -0,0 +1,108 @@
+mod common;
+
+use VulcanEngine_0::asset_manager::registry::{Cache, MeshKey, TextureKey};
+use common::fixture;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+#[test]
+fn same_key_is_created_once_and_shared() {
+    let mut cache: Cache<MeshKey, Vec<u32>> = Cache::default();
+    let mut created = 0;
+    let mut load = |cache: &mut Cache<MeshKey, Vec<u32>>| {
+        cache
+            .get_or_try_insert(MeshKey::new("level.glb", 2, 0), || {
+                created += 1;
+                Ok::<_, Infallible>(vec![0, 1, 2])
+            })
+            .unwrap()
+    };
+    let first = load(&mut cache);
+    let second = load(&mut cache);
+    assert!(Arc::ptr_eq(&first, &second));
+    assert_eq!(created, 1);
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn failed_creation_is_not_cached() {
+    let mut cache: Cache<MeshKey, u32> = Cache::default();
+    let key = MeshKey::new("level.glb", 0, 0);
+    assert!(
+        cache
+            .get_or_try_insert(key.clone(), || Err("broken"))
+            .is_err()
+    );
+    assert!(cache.is_empty());
+    assert_eq!(*cache.get_or_try_insert(key, || Ok::<_, ()>(7)).unwrap(), 7);
+}
+
+#[test]
+fn only_unreferenced_entries_are_released() {
+    let mut cache: Cache<TextureKey, &str> = Cache::default();
+    let solid = TextureKey::Solid {
+        color: [255; 4],
+        linear: false,
+    };
+    let kept = cache
+        .get_or_try_insert(solid.clone(), || Ok::<_, ()>("white"))
+        .unwrap();
+    let dropped = cache
+        .get_or_try_insert(TextureKey::image("a.png", 0, false), || Ok::<_, ()>("a"))
+        .unwrap();
+    drop(dropped);
+    let released = cache.take_unused();
+    assert_eq!(released.len(), 1);
+    assert_eq!(*released[0], "a");
+    assert!(cache.get(&solid).is_some());
+    drop(kept);
+    assert_eq!(cache.take_unused().len(), 1);
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn srgb_and_linear_uploads_are_different_textures() {
+    assert_ne!(
+        TextureKey::image("a.png", 0, false),
+        TextureKey::image("a.png", 0, true)
+    );
+}
+
+#[test]
+fn relative_paths_to_the_same_file_share_a_key() {
+    let direct = fixture("quad.obj");
+    let detour = fixture("../fixtures/./quad.obj");
+    assert_eq!(MeshKey::new(direct, 0, 0), MeshKey::new(detour, 0, 0));
+    assert_ne!(
+        MeshKey::new(fixture("quad.obj"), 0, 0),
+        MeshKey::new(fixture("quad.obj"), 1, 0)
+    );
+}
+
+#[test]
+fn entries_of_one_file_can_be_taken_out() {
+    let mut cache: Cache<TextureKey, u32> = Cache::default();
+    let keys = [
+        TextureKey::image(fixture("quad.obj"), 0, false),
+        TextureKey::image(fixture("quad.obj"), 1, true),
+        TextureKey::image(fixture("quad.mtl"), 0, false),
+        TextureKey::Solid {
+            color: [0; 4],
+            linear: true,
+        },
+    ];
+    for (value, key) in keys.iter().enumerate() {
+        cache
+            .get_or_try_insert(key.clone(), || Ok::<_, ()>(value as u32))
+            .unwrap();
+    }
+    let quad = fixture("quad.obj").canonicalize().unwrap();
+    let mut taken: Vec<u32> = cache
+        .take_where(|key, _| key.path() == Some(quad.as_path()))
+        .iter()
+        .map(|value| **value)
+        .collect();
+    taken.sort();
+    assert_eq!(taken, vec![0, 1]);
+    assert_eq!(cache.len(), 2);
+}
@@ -0,0 +1,286 @@
+mod common;
+
+use VulcanEngine_0::asset_manager::bevy_load::AssetBinding;
+use VulcanEngine_0::asset_manager::hot_reload::LoadedFile;
+use VulcanEngine_0::asset_manager::load_skybox::HdrCubemap;
+use VulcanEngine_0::bevy_app::scene::{NodeRef, PrimitiveRef, SavedObject, Scene, SceneError};
+use VulcanEngine_0::game_objects::light::Light;
+use VulcanEngine_0::game_objects::render_object::RenderId;
+use VulcanEngine_0::game_objects::scene::{self, GameObject};
+use VulcanEngine_0::game_objects::skybox::{SkyBox, SkyBoxSource};
+use VulcanEngine_0::game_objects::transform::Transform;
+use VulcanEngine_0::vulkan::image_util::TextureData;
+use VulcanEngine_0::vulkan::shadow_map::SplitScheme;
+use common::temp_dir;
+use glam::{Quat, Vec3, Vec4};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+fn transform(x: f32) -> Transform {
+    Transform::new(
+        Vec3::new(x, 1.0, -2.0),
+        Vec3::splat(0.5),
+        Quat::from_rotation_y(x),
+    )
+}
+
+/// a fox loaded from `dir/fox.glb` with a lamp under it, a second fox sharing its render
+/// objects and a skybox from `dir/sky.hdr`. nothing is on the gpu.
+fn fox_scene(dir: &Path) -> scene::Scene {
+    let model = dir.join("fox.glb");
+    fs::write(&model, "").unwrap();
+    let mut scene = scene::Scene::default();
+    let meshes = vec![RenderId::new(0, 0), RenderId::new(1, 0)];
+    let fox = scene
+        .objects
+        .insert(GameObject::new("fox", transform(1.0), meshes.clone()));
+    let mut lamp = GameObject::new("lamp", transform(2.0), vec![]);
+    lamp.parent = Some(fox);
+    lamp.light = Some(Light::spot(Vec3::new(1.0, 0.5, 0.2), 40.0, 0.2, 0.6));
+    lamp.material_override.base = Vec4::new(1.0, 0.0, 0.0, 0.5);
+    let lamp = scene.objects.insert(lamp);
+    scene.objects.get_mut(fox).unwrap().children.push(lamp);
+    let mut copy = GameObject::new("copy", transform(3.0), meshes);
+    copy.morph_weights = vec![0.25, 0.75];
+    scene.objects.insert(copy);
+    scene.hot_reload.track(
+        &model,
+        LoadedFile {
+            nodes: HashMap::from([(0, fox), (4, lamp)]),
+            primitives: HashMap::from([
+                ((0, 0), RenderId::new(0, 0)),
+                ((0, 1), RenderId::new(1, 0)),
+            ]),
+        },
+        [],
+    );
+    scene.skybox = Some(SkyBox {
+        texture_data: TextureData::default(),
+        descriptor_sets: vec![],
+        radiance: HdrCubemap::uniform(1, Vec3::ONE),
+        source: SkyBoxSource::Hdr(dir.join("sky.hdr")),
+    });
+    scene.sun.omnidirectional_light.direction = Vec4::new(0.0, 1.0, 0.0, 0.0);
+    scene.sun.omnidirectional_light.color = Vec4::new(1.0, 0.9, 0.8, 1.0);
+    scene.sun.cascades.count = 2;
+    scene.sun.cascades.scheme = SplitScheme::Logarithmic;
+    scene
+}
+
+#[test]
+fn captured_scenes_survive_ron() {
+    let dir = temp_dir("ron");
+    let scene = fox_scene(&dir);
+    let saved = Scene::capture(&scene);
+    let model = dir.join("fox.glb");
+    let primitives = vec![
+        PrimitiveRef {
+            path: model.clone(),
+            node: 0,
+            primitive: 0,
+        },
+        PrimitiveRef {
+            path: model.clone(),
+            node: 0,
+            primitive: 1,
+        },
+    ];
+    assert_eq!(saved.objects.len(), 3);
+    let [fox, lamp, copy] = &saved.objects[..] else {
+        unreachable!()
+    };
+    assert_eq!(fox.name, "fox");
+    assert_eq!(fox.transform, transform(1.0));
+    assert_eq!(fox.children, vec![1]);
+    assert_eq!(
+        fox.node,
+        Some(NodeRef {
+            path: model.clone(),
+            node: 0,
+            copy: 0
+        })
+    );
+    assert_eq!(fox.primitives, primitives);
+    assert_eq!(lamp.parent, Some(0));
+    assert_eq!(lamp.node.as_ref().map(|node| node.node), Some(4));
+    assert!(lamp.primitives.is_empty());
+    assert_eq!(lamp.light, scene.objects.iter().nth(1).unwrap().1.light);
+    assert_eq!(lamp.material_override.base, Vec4::new(1.0, 0.0, 0.0, 0.5));
+    // instances refer to the primitives of the object they were copied from
+    assert_eq!(copy.node, None);
+    assert_eq!(copy.primitives, primitives);
+    assert_eq!(copy.morph_weights, vec![0.25, 0.75]);
+    assert_eq!(saved.skybox, Some(SkyBoxSource::Hdr(dir.join("sky.hdr"))));
+    assert_eq!(saved.sun.direction, Vec3::Y);
+    assert_eq!(saved.sun.cascades.scheme, SplitScheme::Logarithmic);
+
+    let text = saved.to_ron().unwrap();
+    assert!(text.contains("\"lamp\""), "{text}");
+    assert_eq!(Scene::from_ron(&text).unwrap(), saved);
+    // missing fields take their defaults
+    let sparse = Scene::from_ron("(objects: [(name: \"empty\")])").unwrap();
+    assert_eq!(sparse.objects[0].transform, Transform::default());
+    assert_eq!(sparse.sun, Scene::default().sun);
+}
+
+#[test]
+fn restoring_rebuilds_the_scene() {
+    let dir = temp_dir("restore");
+    let scene = fox_scene(&dir);
+    let mut saved = Scene::capture(&scene);
+    saved.camera.fov = 70.0;
+    saved.camera.transform.position = Vec3::new(4.0, 5.0, 6.0);
+
+    let mut restored = scene::Scene::default();
+    restored
+        .objects
+        .insert(GameObject::new("stale", Transform::default(), vec![]));
+    let ids = saved.restore(&mut restored).unwrap();
+    assert_eq!(ids.len(), 3);
+    assert_eq!(restored.objects.iter().count(), 3);
+    assert!(
+        restored
+            .objects
+            .iter()
+            .all(|(_, object)| object.name != "stale")
+    );
+    let fox = restored.objects.get(ids[0]).unwrap();
+    assert_eq!(fox.children, vec![ids[1]]);
+    assert!(fox.render_objects.is_empty());
+    assert_eq!(restored.objects.get(ids[1]).unwrap().parent, Some(ids[0]));
+    assert_eq!(restored.selected_object, Some(ids[0]));
+    assert_eq!(restored.bvh.len(), 3);
+
+    // the primitives are attached once the file is read
+    let bindings = saved.bindings(&ids);
+    assert_eq!(bindings.len(), 1);
+    assert_eq!(bindings[0].0, dir.join("fox.glb"));
+    assert_eq!(
+        bindings[0].1,
+        vec![
+            AssetBinding {
+                object: ids[0],
+                node: 0,
+                primitive: None,
+                copy: 0
+            },
+            AssetBinding {
+                object: ids[0],
+                node: 0,
+                primitive: Some(0),
+                copy: 0
+            },
+            AssetBinding {
+                object: ids[0],
+                node: 0,
+                primitive: Some(1),
+                copy: 0
+            },
+            AssetBinding {
+                object: ids[1],
+                node: 4,
+                primitive: None,
+                copy: 0
+            },
+            AssetBinding {
+                object: ids[2],
+                node: 0,
+                primitive: Some(0),
+                copy: 0
+            },
+            AssetBinding {
+                object: ids[2],
+                node: 0,
+                primitive: Some(1),
+                copy: 0
+            },
+        ]
+    );
+    assert_eq!(
+        restored.loads.pending().collect::<Vec<_>>(),
+        vec![dir.join("fox.glb")]
+    );
+
+    // everything but the files, which are not attached yet, and the skybox, which needs
+    // the device, comes back the same
+    let mut again = Scene::capture(&restored);
+    assert_eq!(again.camera, saved.camera);
+    assert_eq!(again.sun, saved.sun);
+    assert_eq!(again.skybox, None);
+    again.skybox = saved.skybox.clone();
+    for (object, saved) in again.objects.iter_mut().zip(&saved.objects) {
+        object.node = saved.node.clone();
+        object.primitives = saved.primitives.clone();
+    }
+    assert_eq!(again, saved);
+}
+
+#[test]
+fn saved_files_use_relative_paths() {
+    let dir = temp_dir("files");
+    let mut scene = fox_scene(&dir);
+    let outside = temp_dir("files_outside").join("other.hdr");
+    scene.skybox.as_mut().unwrap().source = SkyBoxSource::Hdr(outside.clone());
+    let saved = Scene::capture(&scene);
+    let path = dir.join("level.ron");
+    saved.save(&path).unwrap();
+    let text = fs::read_to_string(&path).unwrap();
+    assert!(text.contains("\"fox.glb\""), "{text}");
+    // files outside of the directory of the scene keep their full path
+    assert!(text.contains(&*outside.to_string_lossy()), "{text}");
+    assert_eq!(Scene::load(&path).unwrap(), saved);
+
+    assert!(matches!(
+        Scene::load(dir.join("missing.ron")),
+        Err(SceneError::Io { .. })
+    ));
+    fs::write(&path, "(objects: [(name: 3)])").unwrap();
+    assert!(matches!(Scene::load(&path), Err(SceneError::Parse(_))));
+}
+
+#[test]
+fn broken_hierarchies_are_rejected() {
+    let object = |parent: Option<usize>, children: Vec<usize>| SavedObject {
+        parent,
+        children,
+        ..Default::default()
+    };
+    let scene = |objects| Scene {
+        objects,
+        ..Default::default()
+    };
+    assert!(
+        scene(vec![object(None, vec![1]), object(Some(0), vec![])])
+            .validate()
+            .is_ok()
+    );
+    // a child that does not know its parent
+    assert!(matches!(
+        scene(vec![object(None, vec![1]), object(None, vec![])]).validate(),
+        Err(SceneError::Link { object: 0, link: 1 })
+    ));
+    // a parent past the end
+    assert!(matches!(
+        scene(vec![object(Some(5), vec![])]).validate(),
+        Err(SceneError::Link { object: 0, link: 5 })
+    ));
+    let cycle = scene(vec![
+        object(Some(2), vec![1]),
+        object(Some(0), vec![2]),
+        object(Some(1), vec![0]),
+    ]);
+    assert!(matches!(cycle.validate(), Err(SceneError::Cycle { .. })));
+    assert!(matches!(
+        scene(vec![object(Some(0), vec![0])]).validate(),
+        Err(SceneError::Cycle { object: 0 })
+    ));
+
+    // a broken scene leaves the scene it is restored into alone
+    let mut restored = scene::Scene::default();
+    restored
+        .objects
+        .insert(GameObject::new("kept", Transform::default(), vec![]));
+    assert!(cycle.restore(&mut restored).is_err());
+    assert_eq!(restored.objects.iter().next().unwrap().1.name, "kept");
+}
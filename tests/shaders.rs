@@ -0,0 +1,372 @@
+use VulcanEngine_0::vulkan::light_clusters::{CLUSTER_COUNT, ClusterHeader};
+use VulcanEngine_0::vulkan::uniform_buffer_object::{
+    InstanceData, OrthographicLight, PbrUniform, PointLight,
+};
+use std::collections::HashMap;
+use std::mem::offset_of;
+use std::path::Path;
+
+// opcodes and decorations of the spir-v spec used below
+const OP_NAME: u32 = 5;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_VARIABLE: u32 = 59;
+const OP_LOAD: u32 = 61;
+const OP_ACCESS_CHAIN: u32 = 65;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_OFFSET: u32 = 35;
+const STORAGE_INPUT: u32 = 1;
+const STORAGE_OUTPUT: u32 = 3;
+const DIM_2D: u32 = 1;
+const DIM_CUBE: u32 = 3;
+
+/// the parts of a compiled shader the engine relies on.
+struct Shader {
+    /// opcode and operands of every instruction.
+    instructions: Vec<(u32, Vec<u32>)>,
+}
+impl Shader {
+    /// a shader of `src/shaders/spv` as the pipelines load it.
+    fn load(name: &str) -> Self {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/shaders/spv")
+            .join(name);
+        let bytes = std::fs::read(&path).unwrap();
+        let words: Vec<u32> = bytes
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+        assert_eq!(words[0], 0x0723_0203, "{path:?} is not spir-v");
+        let mut instructions = vec![];
+        let mut rest = &words[5..];
+        while let Some(first) = rest.first() {
+            let count = (first >> 16) as usize;
+            instructions.push((first & 0xffff, rest[1..count].to_vec()));
+            rest = &rest[count..];
+        }
+        Self { instructions }
+    }
+
+    fn operands(&self, opcode: u32) -> impl Iterator<Item = &[u32]> {
+        self.instructions
+            .iter()
+            .filter(move |(op, _)| *op == opcode)
+            .map(|(_, operands)| &operands[..])
+    }
+
+    /// the debug names of functions and variables, functions without their signature.
+    fn names(&self) -> Vec<String> {
+        self.operands(OP_NAME)
+            .map(|operands| {
+                let bytes: Vec<u8> = operands[1..].iter().flat_map(|w| w.to_le_bytes()).collect();
+                let end = bytes.iter().position(|b| *b == 0 || *b == b'(');
+                String::from_utf8_lossy(&bytes[..end.unwrap_or(bytes.len())]).into_owned()
+            })
+            .collect()
+    }
+
+    /// the value of `decoration` for every id decorated with it.
+    fn decorated(&self, decoration: u32) -> HashMap<u32, u32> {
+        self.operands(OP_DECORATE)
+            .filter(|operands| operands[1] == decoration)
+            .map(|operands| (operands[0], operands[2]))
+            .collect()
+    }
+
+    /// the variable of a descriptor binding.
+    fn binding(&self, binding: u32) -> u32 {
+        self.decorated(DECORATION_BINDING)
+            .into_iter()
+            .find(|(_, value)| *value == binding)
+            .unwrap_or_else(|| panic!("no binding {binding}"))
+            .0
+    }
+
+    /// the type a variable points to.
+    fn pointee(&self, variable: u32) -> u32 {
+        let pointer = self
+            .operands(OP_VARIABLE)
+            .find(|operands| operands[1] == variable)
+            .unwrap()[0];
+        self.operands(OP_TYPE_POINTER)
+            .find(|operands| operands[0] == pointer)
+            .unwrap()[2]
+    }
+
+    /// dimension, depth and arrayed operands of the image behind a combined image sampler.
+    fn image(&self, binding: u32) -> (u32, u32, u32) {
+        let sampled = self.pointee(self.binding(binding));
+        let image = self
+            .operands(OP_TYPE_SAMPLED_IMAGE)
+            .find(|operands| operands[0] == sampled)
+            .unwrap_or_else(|| panic!("binding {binding} is not an image"))[1];
+        let operands = self
+            .operands(OP_TYPE_IMAGE)
+            .find(|operands| operands[0] == image)
+            .unwrap();
+        (operands[2], operands[3], operands[4])
+    }
+
+    /// whether the shader reads a variable, directly or through an access chain.
+    fn reads(&self, variable: u32) -> bool {
+        self.operands(OP_LOAD)
+            .chain(self.operands(OP_ACCESS_CHAIN))
+            .any(|operands| operands[2] == variable)
+    }
+
+    /// the member offsets of a struct type.
+    fn member_offsets(&self, ty: u32) -> Vec<u32> {
+        let mut offsets: Vec<(u32, u32)> = self
+            .operands(OP_MEMBER_DECORATE)
+            .filter(|operands| operands[0] == ty && operands[2] == DECORATION_OFFSET)
+            .map(|operands| (operands[1], operands[3]))
+            .collect();
+        offsets.sort();
+        offsets.into_iter().map(|(_, offset)| offset).collect()
+    }
+
+    /// the member offsets of the block of a buffer binding.
+    fn offsets(&self, binding: u32) -> Vec<u32> {
+        self.member_offsets(self.pointee(self.binding(binding)))
+    }
+
+    /// the member offsets of the struct named `name` in a buffer, structs copied out of the
+    /// buffer are a second type without offsets.
+    fn struct_offsets(&self, name: &str) -> Vec<u32> {
+        self.operands(OP_NAME)
+            .zip(self.names())
+            .filter(|(_, found)| found == name)
+            .map(|(operands, _)| self.member_offsets(operands[0]))
+            .find(|offsets| !offsets.is_empty())
+            .unwrap_or_else(|| panic!("no struct {name} in a buffer"))
+    }
+
+    /// the strides of every array type, runtime arrays included.
+    fn array_strides(&self) -> Vec<u32> {
+        self.decorated(DECORATION_ARRAY_STRIDE)
+            .into_values()
+            .collect()
+    }
+
+    /// the locations of the inputs or outputs of the entry point.
+    fn locations(&self, storage: u32) -> Vec<u32> {
+        let locations = self.decorated(DECORATION_LOCATION);
+        let mut found: Vec<u32> = self
+            .operands(OP_VARIABLE)
+            .filter(|operands| operands[2] == storage)
+            .filter_map(|operands| locations.get(&operands[1]).copied())
+            .collect();
+        found.sort();
+        found
+    }
+}
+
+/// the crc printed by posix `cksum`.
+fn cksum(bytes: &[u8]) -> u32 {
+    let mut crc = 0u32;
+    let mut feed = |byte: u8| {
+        crc ^= (byte as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            };
+        }
+    };
+    bytes.iter().copied().for_each(&mut feed);
+    let mut length = bytes.len();
+    while length > 0 {
+        feed(length as u8);
+        length >>= 8;
+    }
+    !crc
+}
+
+#[test]
+fn sources_match_the_compiled_shaders() {
+    let shaders = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/shaders");
+    let checksums = std::fs::read_to_string(shaders.join("spv/checksums")).unwrap();
+    let checksums: HashMap<&str, (u32, usize)> = checksums
+        .lines()
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            (
+                fields[2],
+                (fields[0].parse().unwrap(), fields[1].parse().unwrap()),
+            )
+        })
+        .collect();
+    let mut sources = 0;
+    for entry in std::fs::read_dir(shaders.join("glsl")).unwrap() {
+        let path = entry.unwrap().path();
+        let name = format!("glsl/{}", path.file_name().unwrap().to_str().unwrap());
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(
+            checksums.get(name.as_str()),
+            Some(&(cksum(&bytes), bytes.len())),
+            "{name} changed since src/shaders/compile.sh last ran"
+        );
+        sources += 1;
+    }
+    assert_eq!(sources, checksums.len());
+}
+
+#[test]
+fn material_textures_are_sampled() {
+    let vertex = Shader::load("pbr_vert.spv");
+    let fragment = Shader::load("pbr_frag.spv");
+    // base color, normal, metallic-roughness, occlusion and emissive
+    for binding in 3..8 {
+        assert_eq!(fragment.image(binding), (DIM_2D, 0, 0), "binding {binding}");
+        assert!(
+            fragment.reads(fragment.binding(binding)),
+            "binding {binding}"
+        );
+    }
+    assert_eq!(
+        vertex.locations(STORAGE_OUTPUT),
+        fragment.locations(STORAGE_INPUT)
+    );
+}
+
+#[test]
+fn surfaces_are_lit_by_the_brdf() {
+    let names = Shader::load("pbr_frag.spv").names();
+    for function in [
+        "distributionGGX",
+        "geometrySmith",
+        "fresnelSchlick",
+        "cookTorrance",
+    ] {
+        assert!(names.iter().any(|name| name == function), "{function}");
+    }
+    // the blinn-phong terms the brdf replaced
+    for old in ["lambertian", "halfwayDir"] {
+        assert!(!names.iter().any(|name| name == old), "{old}");
+    }
+}
+
+#[test]
+fn environment_maps_light_the_surface() {
+    let fragment = Shader::load("pbr_frag.spv");
+    // irradiance, prefiltered radiance and the brdf lookup table
+    for (binding, dim) in [(11, DIM_CUBE), (12, DIM_CUBE), (13, DIM_2D)] {
+        assert_eq!(fragment.image(binding), (dim, 0, 0), "binding {binding}");
+        assert!(
+            fragment.reads(fragment.binding(binding)),
+            "binding {binding}"
+        );
+    }
+}
+
+#[test]
+fn shadows_are_sampled_with_comparison() {
+    let fragment = Shader::load("pbr_frag.spv");
+    // a depth comparison sampler over the layers of the cascades
+    assert_eq!(fragment.image(14), (DIM_2D, 1, 1));
+    assert!(fragment.reads(fragment.binding(14)));
+}
+
+#[test]
+fn cascades_are_chosen_and_blended() {
+    let fragment = Shader::load("pbr_frag.spv");
+    assert_eq!(
+        fragment.offsets(0),
+        [
+            offset_of!(OrthographicLight, direction),
+            offset_of!(OrthographicLight, color),
+            offset_of!(OrthographicLight, cascades),
+            offset_of!(OrthographicLight, cascade_info),
+        ]
+        .map(|offset| offset as u32)
+    );
+    let names = fragment.names();
+    for function in ["cascadeCoverage", "sunVisibility"] {
+        assert!(names.iter().any(|name| name == function), "{function}");
+    }
+}
+
+#[test]
+fn punctual_lights_are_read() {
+    let fragment = Shader::load("pbr_frag.spv");
+    // a uvec4 header holding the count, then the lights
+    assert_eq!(fragment.offsets(15), [0, 16]);
+    assert!(fragment.reads(fragment.binding(15)));
+    assert_eq!(
+        fragment.struct_offsets("PointLight"),
+        [
+            offset_of!(PointLight, position),
+            offset_of!(PointLight, direction),
+            offset_of!(PointLight, color),
+            offset_of!(PointLight, cone),
+        ]
+        .map(|offset| offset as u32)
+    );
+    assert!(
+        fragment
+            .array_strides()
+            .contains(&(size_of::<PointLight>() as u32))
+    );
+}
+
+#[test]
+fn light_clusters_are_read() {
+    let fragment = Shader::load("pbr_frag.spv");
+    let header = size_of::<ClusterHeader>();
+    // the header, the range of every cluster and the light indices
+    assert_eq!(
+        fragment.offsets(16),
+        [
+            offset_of!(ClusterHeader, view),
+            offset_of!(ClusterHeader, proj),
+            offset_of!(ClusterHeader, dimensions),
+            offset_of!(ClusterHeader, depth),
+            header,
+            header + size_of::<glam::UVec2>() * CLUSTER_COUNT,
+        ]
+        .map(|offset| offset as u32)
+    );
+    assert!(fragment.reads(fragment.binding(16)));
+}
+
+#[test]
+fn instances_come_from_the_instance_buffer() {
+    let fragment = Shader::load("pbr_frag.spv");
+    // binding 2 only holds the material factors, the model matrices moved out of it
+    assert_eq!(
+        fragment.offsets(2),
+        [
+            offset_of!(PbrUniform, base),
+            offset_of!(PbrUniform, emissive),
+            offset_of!(PbrUniform, normal_scale),
+            offset_of!(PbrUniform, occlusion_strength),
+            offset_of!(PbrUniform, metallic),
+            offset_of!(PbrUniform, roughness),
+        ]
+        .map(|offset| offset as u32)
+    );
+    for name in [
+        "pbr_vert.spv",
+        "pbr_skinned_vert.spv",
+        "pbr_morph_vert.spv",
+        "shadow_vert.spv",
+        "shadow_skinned_vert.spv",
+        "shadow_morph_vert.spv",
+    ] {
+        let vertex = Shader::load(name);
+        assert_eq!(vertex.offsets(17), [0], "{name}");
+        assert!(vertex.reads(vertex.binding(17)), "{name}");
+        assert!(
+            vertex
+                .array_strides()
+                .contains(&(size_of::<InstanceData>() as u32)),
+            "{name}"
+        );
+    }
+}
@@ -0,0 +1,246 @@
+mod common;
+
+use VulcanEngine_0::game_objects::camera::Camera;
+use VulcanEngine_0::game_objects::scene::Sun;
+use VulcanEngine_0::vulkan::shadow_map::{
+    CASTER_DISTANCE, CascadeSettings, MAX_CASCADES, SplitScheme, cascade_splits,
+    cascade_view_projs, frustum_corners, light_view_proj, update_cascades,
+};
+use common::assert_close;
+use glam::{Mat4, Quat, Vec3};
+use std::f32::consts::FRAC_PI_2;
+
+const EPSILON: f32 = 1e-4;
+const RESOLUTION: u32 = 1024;
+
+/// whether `point` lands on the map, between its near and far plane.
+fn on_map(view_proj: Mat4, point: Vec3) -> bool {
+    let ndc = view_proj.project_point3(point);
+    ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0 && (0.0..=1.0).contains(&ndc.z)
+}
+
+fn camera_matrix(position: Vec3, rotation: Quat) -> Mat4 {
+    Mat4::from_rotation_translation(rotation, position)
+}
+
+#[test]
+fn frustum_corners_span_the_slice() {
+    let corners = frustum_corners(Mat4::IDENTITY, FRAC_PI_2, 2.0, 1.0, 10.0);
+    for near in &corners[..4] {
+        assert!((near.z + 1.0).abs() < EPSILON);
+        assert!((near.x.abs() - 2.0).abs() < EPSILON, "{near}");
+        assert!((near.y.abs() - 1.0).abs() < EPSILON, "{near}");
+    }
+    for far in &corners[4..] {
+        assert!((far.z + 10.0).abs() < EPSILON * 10.0);
+        assert!((far.x.abs() - 20.0).abs() < EPSILON * 10.0, "{far}");
+        assert!((far.y.abs() - 10.0).abs() < EPSILON * 10.0, "{far}");
+    }
+    // the corners follow the camera
+    let moved = camera_matrix(Vec3::new(3.0, -2.0, 5.0), Quat::from_rotation_y(1.0));
+    let world = frustum_corners(moved, FRAC_PI_2, 2.0, 1.0, 10.0);
+    for (corner, moved_corner) in corners.iter().zip(&world) {
+        let expected = moved.transform_point3(*corner);
+        assert!((expected - *moved_corner).length() < 1e-3);
+    }
+}
+
+#[test]
+fn the_sun_sees_the_whole_slice() {
+    let directions = [
+        Vec3::new(-3.0, 1.0, -1.0),
+        Vec3::new(0.2, -1.0, 0.4),
+        Vec3::X,
+    ];
+    let cameras = [
+        camera_matrix(Vec3::ZERO, Quat::IDENTITY),
+        camera_matrix(
+            Vec3::new(40.0, 3.0, -12.0),
+            Quat::from_euler(glam::EulerRot::YXZ, 2.0, 0.3, 0.0),
+        ),
+    ];
+    for direction in directions {
+        for camera in cameras {
+            let corners = frustum_corners(camera, 0.8, 16.0 / 9.0, 0.1, 30.0);
+            let view_proj = light_view_proj(direction, &corners, RESOLUTION);
+            for corner in corners {
+                assert!(on_map(view_proj, corner), "{corner} {direction}");
+            }
+        }
+    }
+}
+
+#[test]
+fn casters_towards_the_sun_stay_on_the_map() {
+    let direction = Vec3::new(-3.0, 1.0, -1.0).normalize();
+    let corners = frustum_corners(Mat4::IDENTITY, 0.8, 1.0, 0.1, 20.0);
+    let view_proj = light_view_proj(direction, &corners, RESOLUTION);
+    for corner in corners {
+        let caster = corner + direction * CASTER_DISTANCE * 0.9;
+        assert!(on_map(view_proj, caster), "{caster}");
+        // closer to the sun is closer in the map
+        assert!(view_proj.project_point3(caster).z < view_proj.project_point3(corner).z);
+    }
+    // but not forever
+    let far_caster = corners[0] + direction * CASTER_DISTANCE * 3.0;
+    assert!(!on_map(view_proj, far_caster));
+}
+
+#[test]
+fn shadow_texels_do_not_swim() {
+    let direction = Vec3::new(0.3, 1.0, -0.5);
+    let texels = |camera: Mat4, point: Vec3| {
+        let corners = frustum_corners(camera, 0.8, 1.5, 0.1, 25.0);
+        let ndc = light_view_proj(direction, &corners, RESOLUTION).project_point3(point);
+        ndc.truncate() * RESOLUTION as f32 / 2.0
+    };
+    let point = Vec3::new(1.3, -0.7, -6.1);
+    let rotation = Quat::from_rotation_y(0.4);
+    let reference = texels(camera_matrix(Vec3::ZERO, rotation), point);
+    for step in [0.013, 0.37, 1.9] {
+        let moved = texels(camera_matrix(Vec3::new(step, 0.0, -step), rotation), point);
+        // the map moved by whole texels
+        let shift = moved - reference;
+        assert!(
+            (shift - shift.round()).abs().max_element() < 1e-2,
+            "{shift}"
+        );
+    }
+    // turning the camera keeps the texel size
+    let size = |rotation: Quat| {
+        let a = texels(camera_matrix(Vec3::ZERO, rotation), Vec3::ZERO);
+        let b = texels(camera_matrix(Vec3::ZERO, rotation), Vec3::X);
+        (a - b).length()
+    };
+    assert!((size(Quat::IDENTITY) - size(Quat::from_rotation_y(1.1))).abs() < 1e-2);
+}
+
+#[test]
+fn vertical_suns_have_a_valid_projection() {
+    let corners = frustum_corners(Mat4::IDENTITY, 0.8, 1.0, 0.1, 20.0);
+    for direction in [Vec3::Y, Vec3::NEG_Y, Vec3::ZERO] {
+        let view_proj = light_view_proj(direction, &corners, RESOLUTION);
+        assert!(view_proj.is_finite(), "{direction}");
+        for corner in corners {
+            assert!(on_map(view_proj, corner));
+        }
+    }
+    // straight down, the map is the ground plane
+    let view_proj = light_view_proj(Vec3::Y, &corners, RESOLUTION);
+    let a = view_proj.project_point3(Vec3::new(1.0, -3.0, -5.0));
+    let b = view_proj.project_point3(Vec3::new(1.0, -4.0, -5.0));
+    assert_close(a.truncate().extend(0.0), b.truncate().extend(0.0), EPSILON);
+    assert!(b.z > a.z);
+}
+
+#[test]
+fn cascades_cover_the_view_up_to_the_shadow_distance() {
+    let camera = Camera::default();
+    let settings = CascadeSettings::default();
+    let view_projs = cascade_view_projs(Vec3::new(-3.0, 1.0, -1.0), &camera, 16.0 / 9.0, &settings);
+    assert_eq!(view_projs.len(), settings.count);
+    let corners = frustum_corners(
+        camera.transform.matrix(),
+        camera.fov.to_radians(),
+        16.0 / 9.0,
+        1.0,
+        2.0,
+    );
+    let forward = ((corners[4] + corners[6]) / 2.0 - camera.transform.position).normalize();
+    let position = camera.transform.position;
+    let covered = |point: Vec3| view_projs.iter().any(|view_proj| on_map(*view_proj, point));
+    assert!(covered(position + forward));
+    assert!(covered(position + forward * settings.distance * 0.9));
+    assert!(!covered(position + forward * settings.distance * 5.0));
+    // the first cascade is the sharpest
+    assert!(on_map(view_projs[0], position + forward));
+    let texel = |view_proj: Mat4| 1.0 / view_proj.x_axis.truncate().length();
+    for pair in view_projs.windows(2) {
+        assert!(texel(pair[0]) < texel(pair[1]));
+    }
+}
+
+#[test]
+fn unused_cascades_are_left_out_of_the_uniform() {
+    let camera = Camera::default();
+    let mut sun = Sun::default();
+    sun.cascades.count = 2;
+    sun.cascades.blend = 3.0;
+    sun.cascades.debug = true;
+    update_cascades(&mut sun, &camera, 1.0);
+    let light = &sun.omnidirectional_light;
+    assert_eq!(light.cascade_info.x, 2.0);
+    assert_eq!(light.cascade_info.y, 1.0);
+    assert_eq!(light.cascade_info.z, 1.0);
+    assert_ne!(light.cascades[1], Mat4::IDENTITY);
+    assert_eq!(light.cascades[2..], [Mat4::IDENTITY; MAX_CASCADES - 2]);
+
+    sun.cascades.count = 0;
+    sun.cascades.debug = false;
+    update_cascades(&mut sun, &camera, 1.0);
+    assert_eq!(sun.omnidirectional_light.cascade_info.x, 1.0);
+    assert_eq!(sun.omnidirectional_light.cascade_info.z, 0.0);
+}
+
+#[test]
+fn splits_start_and_end_at_the_range() {
+    for scheme in [
+        SplitScheme::Uniform,
+        SplitScheme::Logarithmic,
+        SplitScheme::Practical(0.5),
+        SplitScheme::Practical(7.0),
+    ] {
+        for count in 1..=MAX_CASCADES {
+            let splits = cascade_splits(0.1, 1000.0, count, scheme);
+            assert_eq!(splits.len(), count + 1);
+            assert_eq!(splits[0], 0.1);
+            assert_eq!(splits[count], 1000.0);
+            assert!(
+                splits.windows(2).all(|pair| pair[0] < pair[1]),
+                "{splits:?}"
+            );
+        }
+    }
+    assert_eq!(
+        cascade_splits(1.0, 2.0, 0, SplitScheme::Uniform),
+        [1.0, 2.0]
+    );
+}
+
+#[test]
+fn uniform_splits_are_evenly_spaced() {
+    let splits = cascade_splits(2.0, 102.0, 4, SplitScheme::Uniform);
+    for (split, expected) in splits.iter().zip([2.0, 27.0, 52.0, 77.0, 102.0]) {
+        assert!((split - expected).abs() < EPSILON * 100.0, "{splits:?}");
+    }
+}
+
+#[test]
+fn logarithmic_splits_grow_by_a_constant_factor() {
+    let splits = cascade_splits(1.0, 10000.0, 4, SplitScheme::Logarithmic);
+    for pair in splits.windows(2) {
+        assert!((pair[1] / pair[0] - 10.0).abs() < 1e-3, "{splits:?}");
+    }
+    // a near plane at zero does not collapse the splits
+    let splits = cascade_splits(0.0, 100.0, 3, SplitScheme::Logarithmic);
+    assert!(splits.iter().all(|split| split.is_finite()));
+    assert_eq!(splits[3], 100.0);
+}
+
+#[test]
+fn practical_splits_lie_between_uniform_and_logarithmic() {
+    let uniform = cascade_splits(0.5, 500.0, 4, SplitScheme::Uniform);
+    let logarithmic = cascade_splits(0.5, 500.0, 4, SplitScheme::Logarithmic);
+    let practical = cascade_splits(0.5, 500.0, 4, SplitScheme::Practical(0.8));
+    for i in 1..4 {
+        assert!(logarithmic[i] < practical[i] && practical[i] < uniform[i]);
+    }
+    assert_eq!(
+        cascade_splits(0.5, 500.0, 4, SplitScheme::Practical(0.0)),
+        uniform
+    );
+    assert_eq!(
+        cascade_splits(0.5, 500.0, 4, SplitScheme::Practical(1.0)),
+        logarithmic
+    );
+}
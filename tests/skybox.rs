@@ -0,0 +1,206 @@
+mod common;
+
+use VulcanEngine_0::asset_manager::error::AssetError;
+use VulcanEngine_0::asset_manager::load_skybox::{
+    self, HdrCubemap, HdrImage, SkyLayout, equirectangular_uv, face_direction,
+};
+use common::{assert_close, temp_dir};
+use glam::{Vec2, Vec3};
+use image::codecs::hdr::HdrEncoder;
+use image::{Rgb, Rgb32FImage};
+use std::fs::File;
+use std::path::PathBuf;
+
+const EPSILON: f32 = 1e-3;
+
+const POS_X: usize = 0;
+const NEG_X: usize = 1;
+const POS_Y: usize = 2;
+const NEG_Y: usize = 3;
+const POS_Z: usize = 4;
+const NEG_Z: usize = 5;
+
+/// writes `image` as a radiance file in a directory of its own, tests run in parallel.
+fn write_hdr(name: &str, image: &HdrImage) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vulcan_skybox_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    let pixels: Vec<Rgb<f32>> = image.pixels.iter().map(|c| Rgb(c.to_array())).collect();
+    HdrEncoder::new(File::create(&path).unwrap())
+        .encode(&pixels, image.width as usize, image.height as usize)
+        .unwrap();
+    path
+}
+
+fn write_exr(name: &str, image: &HdrImage) -> PathBuf {
+    let path = temp_dir(name).join(name);
+    Rgb32FImage::from_fn(image.width, image.height, |x, y| {
+        Rgb(image.get(x, y).to_array())
+    })
+    .save(&path)
+    .unwrap();
+    path
+}
+
+fn filled(width: u32, height: u32, color: impl Fn(u32, u32) -> Vec3) -> HdrImage {
+    let mut image = HdrImage::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            image.set(x, y, color(x, y));
+        }
+    }
+    image
+}
+
+#[test]
+fn layouts_are_detected_from_the_aspect_ratio() {
+    assert_eq!(SkyLayout::detect(64, 32), Some(SkyLayout::Equirectangular));
+    assert_eq!(SkyLayout::detect(64, 48), Some(SkyLayout::HorizontalCross));
+    assert_eq!(SkyLayout::detect(48, 64), Some(SkyLayout::VerticalCross));
+    assert_eq!(SkyLayout::detect(64, 64), None);
+    assert_eq!(SkyLayout::detect(30, 22), None);
+}
+
+#[test]
+fn face_centers_map_to_the_panorama() {
+    let center =
+        |face| equirectangular_uv(face_direction(face, 2, 1, 1) + face_direction(face, 2, 0, 0));
+    assert!((center(POS_Z) - Vec2::new(0.5, 0.5)).length() < EPSILON);
+    assert!((center(POS_X) - Vec2::new(0.75, 0.5)).length() < EPSILON);
+    assert!((center(NEG_X) - Vec2::new(0.25, 0.5)).length() < EPSILON);
+    assert!(center(POS_Y).y < EPSILON);
+    assert!((center(NEG_Y).y - 1.0).abs() < EPSILON);
+    let back = center(NEG_Z);
+    assert!(back.x.abs() < EPSILON || (back.x - 1.0).abs() < EPSILON);
+    // the top row of a side face is towards +y
+    assert!(face_direction(POS_Z, 4, 1, 0).y > 0.0);
+    assert!(face_direction(POS_Z, 4, 3, 1).x > 0.0);
+}
+
+#[test]
+fn panoramas_keep_values_above_one() {
+    // bright sky, dim ground
+    let sky = Vec3::new(4.0, 2.0, 1.0);
+    let ground = Vec3::new(0.25, 0.125, 0.0625);
+    let image = filled(64, 32, |_, y| if y < 16 { sky } else { ground });
+    let cubemap = load_skybox::read_environment(write_hdr("halves.hdr", &image)).unwrap();
+    assert_eq!(cubemap.size, 16);
+    assert_eq!(cubemap.faces.len(), 6);
+    for color in &cubemap.faces[POS_Y].pixels {
+        assert_close(*color, sky, EPSILON);
+    }
+    for color in &cubemap.faces[NEG_Y].pixels {
+        assert_close(*color, ground, EPSILON);
+    }
+    let side = &cubemap.faces[POS_Z];
+    assert_close(side.get(8, 0), sky, EPSILON);
+    assert_close(side.get(8, 15), ground, EPSILON);
+}
+
+#[test]
+fn openexr_panoramas_read_like_radiance_ones() {
+    let image = filled(32, 16, |x, y| Vec3::new(x as f32, y as f32 * 0.5, 100.0));
+    let exr = load_skybox::read_exr(write_exr("gradient.exr", &image)).unwrap();
+    assert_eq!((exr.width, exr.height), (32, 16));
+    for (read, written) in exr.pixels.iter().zip(&image.pixels) {
+        assert_close(*read, *written, EPSILON);
+    }
+    let cubemap = load_skybox::read_environment(write_exr("panorama.exr", &image)).unwrap();
+    assert_eq!(cubemap.size, 8);
+}
+
+#[test]
+fn panoramas_wrap_around_the_horizon() {
+    // a color per quarter of the longitude, centered on the four side faces. the first one
+    // is split between both edges of the image
+    let quarters = [Vec3::X, Vec3::Y, Vec3::Z, Vec3::ONE];
+    let image = filled(64, 32, |x, _| quarters[((x + 8) / 16 % 4) as usize]);
+    let cubemap = HdrCubemap::from_equirectangular(&image, 8);
+    for (face, color) in [(NEG_X, Vec3::Y), (POS_Z, Vec3::Z), (POS_X, Vec3::ONE)] {
+        assert_close(cubemap.faces[face].get(4, 4), color, EPSILON);
+    }
+    // across the seam of the panorama
+    for x in 2..6 {
+        assert_close(cubemap.faces[NEG_Z].get(x, 4), Vec3::X, EPSILON);
+    }
+}
+
+#[test]
+fn horizontal_cross_cells_become_faces() {
+    // cell (column, row) gets the value column + 4 * row
+    let image = filled(16, 12, |x, y| Vec3::splat((x / 4 + 4 * (y / 4)) as f32));
+    let cubemap = load_skybox::read_environment(write_hdr("hcross.hdr", &image)).unwrap();
+    assert_eq!(cubemap.size, 4);
+    let expected = [6.0, 4.0, 1.0, 9.0, 5.0, 7.0];
+    for (face, value) in expected.into_iter().enumerate() {
+        for color in &cubemap.faces[face].pixels {
+            assert_close(*color, Vec3::splat(value), EPSILON);
+        }
+    }
+}
+
+#[test]
+fn vertical_cross_turns_the_back_face_around() {
+    let image = filled(12, 16, |x, y| {
+        if y >= 12 && (4..8).contains(&x) {
+            // the -z cell, numbered from its top left corner
+            Vec3::splat(((x - 4) + 4 * (y - 12)) as f32)
+        } else {
+            Vec3::splat(100.0 + (x / 4 + 3 * (y / 4)) as f32)
+        }
+    });
+    let cubemap = HdrCubemap::from_cross(&image, SkyLayout::VerticalCross).unwrap();
+    assert_eq!(cubemap.size, 4);
+    assert_close(cubemap.faces[POS_X].get(0, 0), Vec3::splat(105.0), EPSILON);
+    assert_close(cubemap.faces[NEG_X].get(0, 0), Vec3::splat(103.0), EPSILON);
+    assert_close(cubemap.faces[POS_Y].get(0, 0), Vec3::splat(101.0), EPSILON);
+    assert_close(cubemap.faces[NEG_Y].get(0, 0), Vec3::splat(107.0), EPSILON);
+    assert_close(cubemap.faces[POS_Z].get(0, 0), Vec3::splat(104.0), EPSILON);
+    let back = &cubemap.faces[NEG_Z];
+    assert_close(back.get(0, 0), Vec3::splat(15.0), EPSILON);
+    assert_close(back.get(3, 3), Vec3::splat(0.0), EPSILON);
+    assert_close(back.get(3, 0), Vec3::splat(12.0), EPSILON);
+    assert!(HdrCubemap::from_cross(&image, SkyLayout::HorizontalCross).is_none());
+}
+
+#[test]
+fn faces_upload_as_half_floats() {
+    let image = filled(8, 6, |_, _| Vec3::new(1.0, 2.0, 0.5));
+    let cubemap = HdrCubemap::from_cross(&image, SkyLayout::HorizontalCross).unwrap();
+    let bytes = cubemap.to_rgba16f();
+    assert_eq!(bytes.len(), 6 * 2 * 2 * 4 * 2);
+    let texel: Vec<u16> = bytes[..8]
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    assert_eq!(texel, [0x3c00, 0x4000, 0x3800, 0x3c00]);
+}
+
+#[test]
+fn unusable_skies_are_reported() {
+    let square = write_hdr("square.hdr", &HdrImage::new(8, 8));
+    let Err(AssetError::Parse { location, .. }) = load_skybox::read_environment(&square) else {
+        panic!("a square image is not a sky");
+    };
+    assert_eq!(location.path, square);
+    assert!(matches!(
+        load_skybox::read_environment("missing.exr"),
+        Err(AssetError::Io { .. })
+    ));
+    assert!(matches!(
+        load_skybox::read_environment("missing.hdr"),
+        Err(AssetError::Io { .. })
+    ));
+    let not_hdr = square.with_file_name("not_hdr.hdr");
+    std::fs::write(&not_hdr, b"P6\n1 1\n255\n...").unwrap();
+    assert!(matches!(
+        load_skybox::read_environment(&not_hdr),
+        Err(AssetError::Parse { .. })
+    ));
+    let not_exr = square.with_file_name("not_exr.exr");
+    std::fs::write(&not_exr, b"not an openexr image").unwrap();
+    assert!(matches!(
+        load_skybox::read_environment(&not_exr),
+        Err(AssetError::Parse { .. })
+    ));
+}
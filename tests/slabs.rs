@@ -0,0 +1,101 @@
+mod common;
+
+use VulcanEngine_0::game_objects::render_object::ObjectId;
+use VulcanEngine_0::game_objects::scene::{GameObject, ObjectSlab, Scene};
+use VulcanEngine_0::game_objects::slabs::ParaSlab;
+use VulcanEngine_0::game_objects::transform::Transform;
+use common::numbers;
+use glam::Vec3;
+use std::collections::HashMap;
+
+fn object(name: &str) -> GameObject {
+    GameObject::new(name, Transform::default(), vec![])
+}
+
+#[test]
+fn stale_ids_miss_reused_slots() {
+    let mut slab: ParaSlab<ObjectId, &str> = ParaSlab::new();
+    let first = slab.insert("first");
+    assert_eq!(first, ObjectId::new(0, 0));
+    assert_eq!(slab.remove(first), Some("first"));
+    assert_eq!(slab.remove(first), None);
+
+    // the slot is reused with the next generation
+    let second = slab.insert("second");
+    assert_eq!(second, ObjectId::new(0, 1));
+    assert!(!slab.contains(first));
+    assert_eq!(slab.get(first), None);
+    assert_eq!(slab.get_mut(first), None);
+    assert_eq!(slab.remove(first), None);
+    assert_eq!(slab.get(second), Some(&"second"));
+
+    // ids past the used slots, or from the future, miss too
+    assert_eq!(slab.get(ObjectId::new(7, 0)), None);
+    assert_eq!(slab.get(ObjectId::new(0, 2)), None);
+    let third = slab.insert("third");
+    assert_eq!(slab.len(), 2);
+    assert_eq!(slab.ids(), vec![second, third]);
+    assert_eq!(
+        slab.iter().collect::<Vec<_>>(),
+        vec![(second, &"second"), (third, &"third")]
+    );
+    let owned: Vec<(ObjectId, &str)> = slab.into_iter().collect();
+    assert_eq!(owned, vec![(second, "second"), (third, "third")]);
+}
+
+#[test]
+fn churn_never_resolves_removed_ids() {
+    let mut random = numbers(11);
+    let mut slab: ObjectSlab = ParaSlab::new();
+    let mut live: HashMap<ObjectId, String> = HashMap::new();
+    let mut removed: Vec<ObjectId> = vec![];
+    for step in 0..2000 {
+        let ids: Vec<ObjectId> = live.keys().copied().collect();
+        if ids.is_empty() || random() < 0.55 {
+            let name = format!("object {step}");
+            let id = slab.insert(object(&name));
+            assert!(!live.contains_key(&id) && !removed.contains(&id));
+            live.insert(id, name);
+        } else {
+            let id = ids[(random() * ids.len() as f32) as usize % ids.len()];
+            assert_eq!(slab.remove(id).unwrap().name, live[&id]);
+            live.remove(&id);
+            removed.push(id);
+        }
+    }
+    assert_eq!(slab.len(), live.len());
+    for (id, name) in &live {
+        assert_eq!(&slab.get(*id).unwrap().name, name);
+    }
+    assert!(removed.iter().all(|id| slab.get(*id).is_none()));
+    // slots were reused
+    assert!(removed.iter().any(|id| id.generation > 0));
+}
+
+#[test]
+fn removed_objects_stay_removed() {
+    let mut scene = Scene::default();
+    assert_eq!(scene.selected_object, None);
+    let old = scene.insert_instance(object("old")).unwrap();
+    scene.selected_object = Some(old);
+    scene.refit_bvh();
+    assert_eq!(scene.remove_instance(old).unwrap().name, "old");
+    assert_eq!(scene.selected_object, None);
+    assert!(!scene.bvh.contains(old));
+
+    let new = scene.insert_instance(object("new")).unwrap();
+    assert_eq!(new.index, old.index);
+    // the stale id neither reaches nor removes the object in its slot
+    assert!(scene.objects.get(old).is_none());
+    assert!(scene.remove_instance(old).is_none());
+    assert!(
+        scene
+            .transform_object(old, Transform::new(Vec3::X, Vec3::ONE, Default::default()))
+            .is_err()
+    );
+    assert_eq!(scene.objects.get(new).unwrap().name, "new");
+    assert_eq!(
+        scene.objects.get(new).unwrap().transform,
+        Transform::default()
+    );
+}